@@ -0,0 +1,186 @@
+//! Runs CPython's gc and this collector on the same workload: N lists joined
+//! into M rings, dropped so every list is cyclic garbage. Only the collection
+//! is timed. Criterion reports each side's throughput in objects collected per
+//! second, and each side prints its pause percentiles and the ratio of
+//! medians. Needs the `cpython-bench` feature and an embeddable libpython.
+
+use criterion::{BenchmarkId, Criterion, Throughput, black_box, criterion_group, criterion_main};
+use python_gc::PyObject;
+use python_gc::collector::Collector;
+use python_gc::object::ObjectData;
+use std::ffi::{CString, c_char, c_int, c_void};
+use std::time::{Duration, Instant};
+
+type RawObject = *mut c_void;
+
+unsafe extern "C" {
+    fn Py_InitializeEx(initsigs: c_int);
+    fn PyRun_SimpleString(command: *const c_char) -> c_int;
+    fn PyImport_ImportModule(name: *const c_char) -> RawObject;
+    fn PyObject_GetAttrString(obj: RawObject, name: *const c_char) -> RawObject;
+    fn PyObject_CallNoArgs(callable: RawObject) -> RawObject;
+    fn PyLong_AsSsize_t(obj: RawObject) -> isize;
+    fn Py_DecRef(obj: RawObject);
+}
+
+/// Defines `build(n, m)` in `__main__`. The interpreter's own objects are
+/// frozen first so `gc.collect()` scans the workload rather than startup
+/// garbage; automatic collection stays off so only timed calls collect.
+const SETUP: &str = "\
+import gc
+gc.disable()
+gc.collect()
+gc.freeze()
+
+def build(n, m):
+    size = n // m
+    for _ in range(m):
+        ring = [[] for _ in range(size)]
+        for i, node in enumerate(ring):
+            node.append(ring[i - 1])
+";
+
+const WORKLOADS: [(usize, usize); 3] = [(10_000, 10), (10_000, 1_000), (100_000, 1_000)];
+
+struct CPython {
+    collect: RawObject,
+}
+
+impl CPython {
+    fn start() -> Self {
+        let setup = CString::new(SETUP).unwrap();
+        unsafe {
+            Py_InitializeEx(0);
+            assert_eq!(PyRun_SimpleString(setup.as_ptr()), 0, "setup script failed");
+            let gc = PyImport_ImportModule(c"gc".as_ptr());
+            assert!(!gc.is_null(), "cannot import gc");
+            let collect = PyObject_GetAttrString(gc, c"collect".as_ptr());
+            assert!(!collect.is_null(), "gc.collect is missing");
+            Py_DecRef(gc);
+            Self { collect }
+        }
+    }
+
+    fn build(&self, objects: usize, cycles: usize) {
+        let command = CString::new(format!("build({objects}, {cycles})")).unwrap();
+        assert_eq!(unsafe { PyRun_SimpleString(command.as_ptr()) }, 0);
+    }
+
+    /// Run `gc.collect()` and return how many unreachable objects it found.
+    fn collect(&self) -> usize {
+        unsafe {
+            let result = PyObject_CallNoArgs(self.collect);
+            assert!(!result.is_null(), "gc.collect() raised");
+            let found = PyLong_AsSsize_t(result);
+            Py_DecRef(result);
+            found as usize
+        }
+    }
+}
+
+/// The same rings as `build` in [`SETUP`], tracked by a fresh collector. Each
+/// list's only reference comes from the one after it in its ring.
+fn build_rings(objects: usize, cycles: usize) -> Collector {
+    let size = objects / cycles;
+    let mut lists = Vec::with_capacity(size * cycles);
+    for ring in 0..cycles {
+        let nodes: Vec<PyObject> = (0..size)
+            .map(|index| {
+                let obj =
+                    PyObject::new(format!("ring{ring}_{index}"), ObjectData::List(Vec::new()));
+                obj.set_refcount(1);
+                obj
+            })
+            .collect();
+        for (index, node) in nodes.iter().enumerate() {
+            let previous = &nodes[(index + size - 1) % size];
+            if let ObjectData::List(items) = &mut *node.data_mut() {
+                items.push(previous.clone());
+            }
+        }
+        lists.extend(nodes);
+    }
+    let mut collector = Collector::new();
+    collector.track_objects_bulk(lists).unwrap();
+    collector
+}
+
+fn median(pauses: &mut [Duration]) -> Duration {
+    pauses.sort_unstable();
+    pauses[pauses.len() / 2]
+}
+
+fn report_pauses(name: &str, pauses: &mut [Duration]) {
+    if pauses.is_empty() {
+        return;
+    }
+    pauses.sort_unstable();
+    let at = |quantile: f64| pauses[((pauses.len() - 1) as f64 * quantile).round() as usize];
+    println!(
+        "{name}: pause p50 {:?}, p90 {:?}, p99 {:?}, max {:?} over {} collections",
+        at(0.5),
+        at(0.9),
+        at(0.99),
+        pauses[pauses.len() - 1],
+        pauses.len()
+    );
+}
+
+fn benchmark_against_cpython(c: &mut Criterion) {
+    let cpython = CPython::start();
+    let mut group = c.benchmark_group("CPython Comparison");
+    group.sample_size(20);
+
+    for (objects, cycles) in WORKLOADS {
+        let workload = format!("{objects}_objects_{cycles}_cycles");
+        group.throughput(Throughput::Elements(objects as u64));
+
+        let mut cpython_pauses = Vec::new();
+        group.bench_function(BenchmarkId::new("cpython_gc", &workload), |b| {
+            b.iter_custom(|iters| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    cpython.build(objects, cycles);
+                    let start = Instant::now();
+                    let found = black_box(cpython.collect());
+                    let pause = start.elapsed();
+                    assert_eq!(found, objects);
+                    total += pause;
+                    cpython_pauses.push(pause);
+                }
+                total
+            });
+        });
+
+        let mut rust_pauses = Vec::new();
+        group.bench_function(BenchmarkId::new("python_gc", &workload), |b| {
+            b.iter_custom(|iters| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    let mut collector = build_rings(objects, cycles);
+                    let start = Instant::now();
+                    let found = black_box(collector.collect_generation(2).unwrap());
+                    let pause = start.elapsed();
+                    assert_eq!(found, objects);
+                    total += pause;
+                    rust_pauses.push(pause);
+                }
+                total
+            });
+        });
+
+        report_pauses(&format!("cpython_gc/{workload}"), &mut cpython_pauses);
+        report_pauses(&format!("python_gc/{workload}"), &mut rust_pauses);
+        if !cpython_pauses.is_empty() && !rust_pauses.is_empty() {
+            let ratio =
+                median(&mut rust_pauses).as_secs_f64() / median(&mut cpython_pauses).as_secs_f64();
+            println!("{workload}: python_gc median pause is {ratio:.2}x CPython's");
+        }
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_against_cpython);
+
+criterion_main!(benches);
@@ -0,0 +1,148 @@
+//! Collection benchmarks on object graphs with cycles. The cycle density is
+//! the chance that a node is joined into a two-node cycle with a random peer.
+//! Each collection mode also prints its pause-time percentiles, where an
+//! incremental collection's pause is its longest phase.
+
+use criterion::{BatchSize, BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use python_gc::PyObject;
+use python_gc::collector::Collector;
+use python_gc::object::ObjectData;
+use python_gc::testing::TestRng;
+use std::time::{Duration, Instant};
+
+const NODES: usize = 10_000;
+
+/// Lists with about one backward edge each, `cycle_density` of them joined
+/// into two-node cycles, and `rooted` of them held from outside the heap.
+/// Refcounts match the references.
+fn build_graph(cycle_density: f64, rooted: f64, rng: &mut TestRng) -> Vec<PyObject> {
+    let objects: Vec<PyObject> = (0..NODES)
+        .map(|index| {
+            let obj = PyObject::new(format!("node{index}"), ObjectData::List(Vec::new()));
+            obj.set_refcount(usize::from(rng.chance(rooted)));
+            obj
+        })
+        .collect();
+    let link = |from: usize, to: usize| {
+        objects[to].inc_ref();
+        if let ObjectData::List(items) = &mut *objects[from].data_mut() {
+            items.push(objects[to].clone());
+        }
+    };
+    for from in 1..NODES {
+        link(from, rng.below(from));
+        if rng.chance(cycle_density) {
+            let peer = rng.below(NODES);
+            link(from, peer);
+            link(peer, from);
+        }
+    }
+    objects
+}
+
+fn tracked(objects: Vec<PyObject>) -> Collector {
+    let mut collector = Collector::new();
+    collector.track_objects_bulk(objects).unwrap();
+    collector
+}
+
+/// A heap whose survivors have moved to the oldest generation, with a
+/// fresh graph of garbage in generation 0.
+fn aged_heap(rng: &mut TestRng) -> Collector {
+    let mut collector = tracked(build_graph(0.25, 1.0, rng));
+    collector.collect_generation(2).unwrap();
+    collector
+        .track_objects_bulk(build_graph(0.25, 0.0, rng))
+        .unwrap();
+    collector
+}
+
+fn report_pauses(name: &str, pauses: &mut [Duration]) {
+    if pauses.is_empty() {
+        return;
+    }
+    pauses.sort_unstable();
+    let at = |quantile: f64| pauses[((pauses.len() - 1) as f64 * quantile).round() as usize];
+    println!(
+        "{name}: pause p50 {:?}, p90 {:?}, p99 {:?}, max {:?} over {} collections",
+        at(0.5),
+        at(0.9),
+        at(0.99),
+        pauses[pauses.len() - 1],
+        pauses.len()
+    );
+}
+
+fn benchmark_cycle_density(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Cycle Density");
+    let mut rng = TestRng::new(1);
+
+    for density in [0.0, 0.1, 0.25, 0.5] {
+        group.bench_with_input(
+            BenchmarkId::new("full_collect_10000", density),
+            &density,
+            |b, &density| {
+                b.iter_batched(
+                    || tracked(build_graph(density, 0.1, &mut rng)),
+                    |mut collector| black_box(collector.collect_generation(2).unwrap()),
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn benchmark_collection_modes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Collection Mode");
+    let mut rng = TestRng::new(2);
+
+    for (name, generation) in [("full", 2), ("generational", 0)] {
+        let mut pauses = Vec::new();
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                let mut total = Duration::ZERO;
+                for _ in 0..iters {
+                    let mut collector = aged_heap(&mut rng);
+                    let start = Instant::now();
+                    black_box(collector.collect_generation(generation).unwrap());
+                    let pause = start.elapsed();
+                    total += pause;
+                    pauses.push(pause);
+                }
+                total
+            });
+        });
+        report_pauses(name, &mut pauses);
+    }
+
+    let mut pauses = Vec::new();
+    group.bench_function("incremental", |b| {
+        b.iter_custom(|iters| {
+            let mut total = Duration::ZERO;
+            for _ in 0..iters {
+                let mut collector = aged_heap(&mut rng);
+                let mut step = collector.begin_collection(2).unwrap();
+                let mut longest = Duration::ZERO;
+                while !step.is_finished() {
+                    let start = Instant::now();
+                    collector.step_collection(&mut step).unwrap();
+                    let phase = start.elapsed();
+                    longest = longest.max(phase);
+                    total += phase;
+                }
+                black_box(step.collected.len());
+                pauses.push(longest);
+            }
+            total
+        });
+    });
+    report_pauses("incremental", &mut pauses);
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_cycle_density, benchmark_collection_modes);
+
+criterion_main!(benches);
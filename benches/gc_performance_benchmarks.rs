@@ -1,5 +1,8 @@
 use criterion::{Criterion, black_box, criterion_group, criterion_main};
-use python_gc::{GarbageCollector, PyObject, object::ObjectData};
+use python_gc::{
+    GarbageCollector, PyObject,
+    object::{ObjectData, PyDict},
+};
 
 fn create_test_objects(count: usize) -> Vec<PyObject> {
     static NAMES: [&str; 3] = ["list", "dict", "set"];
@@ -11,7 +14,7 @@ fn create_test_objects(count: usize) -> Vec<PyObject> {
 
             match name_idx {
                 0 => PyObject::new(name.to_string(), ObjectData::List(Vec::new())),
-                1 => PyObject::new(name.to_string(), ObjectData::Dict(Vec::new())),
+                1 => PyObject::new(name.to_string(), ObjectData::Dict(PyDict::new())),
                 _ => PyObject::new(name.to_string(), ObjectData::List(Vec::new())),
             }
         })
@@ -43,7 +46,7 @@ fn benchmark_object_tracking(c: &mut Criterion) {
 
     group.bench_function("track_1000_objects", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
             let objects = create_test_objects(1000);
 
             for obj in objects {
@@ -56,7 +59,7 @@ fn benchmark_object_tracking(c: &mut Criterion) {
 
     group.bench_function("track_10000_objects", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
             let objects = create_test_objects(10000);
 
             for obj in objects {
@@ -69,7 +72,7 @@ fn benchmark_object_tracking(c: &mut Criterion) {
 
     group.bench_function("track_10000_objects_bulk", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
             let objects = create_test_objects(10000);
 
             gc.track_bulk(objects).unwrap();
@@ -93,7 +96,7 @@ fn benchmark_garbage_collection(c: &mut Criterion) {
 
     group.bench_function("collect_with_1000_objects", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
             let objects = create_test_objects(1000);
 
             for obj in objects {
@@ -106,7 +109,7 @@ fn benchmark_garbage_collection(c: &mut Criterion) {
 
     group.bench_function("collect_with_10000_objects", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
             let objects = create_test_objects(10000);
 
             for obj in objects {
@@ -119,7 +122,7 @@ fn benchmark_garbage_collection(c: &mut Criterion) {
 
     group.bench_function("collect_with_10000_objects_fast", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
             let objects = create_test_objects(10000);
 
             gc.track_bulk(objects).unwrap();
@@ -136,7 +139,7 @@ fn benchmark_generation_management(c: &mut Criterion) {
 
     group.bench_function("promote_generations", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
 
             for i in 0..1000 {
                 let obj = PyObject::new("test".to_string(), ObjectData::Integer(i as i64));
@@ -159,7 +162,7 @@ fn benchmark_memory_usage(c: &mut Criterion) {
 
     group.bench_function("memory_tracking_10000", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
             let objects = create_test_objects(10000);
 
             let estimated_memory = objects.len() * std::mem::size_of::<PyObject>();
@@ -180,7 +183,7 @@ fn benchmark_python_object_tracking(c: &mut Criterion) {
 
     group.bench_function("track_10000_python_objects", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
 
             for i in 0..10000 {
                 let obj = PyObject::new_ffi(
@@ -197,7 +200,7 @@ fn benchmark_python_object_tracking(c: &mut Criterion) {
 
     group.bench_function("collect_10000_python_objects", |b| {
         b.iter(|| {
-            let mut gc = GarbageCollector::new();
+            let gc = GarbageCollector::new();
 
             for i in 0..10000 {
                 let obj = PyObject::new_ffi(
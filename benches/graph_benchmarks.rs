@@ -0,0 +1,119 @@
+//! Contention on a shared object graph: each thread adds and then removes
+//! edges among its own slice of the objects, while every thread also points
+//! at one shared hub. The sharded `SharedObjectGraph` is measured against an
+//! `ObjectGraph` behind a single mutex.
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use parking_lot::Mutex;
+use python_gc::PyObject;
+use python_gc::object::ObjectData;
+use python_gc::traversal::{ObjectGraph, ReferenceType, SharedObjectGraph};
+
+const OBJECTS: usize = 1_024;
+const EDGES_PER_THREAD: usize = 4_096;
+
+fn objects() -> Vec<PyObject> {
+    (0..OBJECTS)
+        .map(|index| PyObject::new(format!("node{index}"), ObjectData::List(Vec::new())))
+        .collect()
+}
+
+/// The edges thread `thread` of `threads` adds: within its own slice of the
+/// objects, with every eighth one pointing at the hub instead.
+fn edges(thread: usize, threads: usize) -> Vec<(usize, usize)> {
+    let slice = OBJECTS / threads;
+    let base = thread * slice;
+    (0..EDGES_PER_THREAD)
+        .map(|i| {
+            let from = base + i % slice;
+            let to = if i % 8 == 0 {
+                0
+            } else {
+                base + (i * 7 + 1) % slice
+            };
+            (from, to)
+        })
+        .collect()
+}
+
+fn run_shared(graph: &SharedObjectGraph, objects: &[PyObject], threads: usize) {
+    std::thread::scope(|scope| {
+        for thread in 0..threads {
+            scope.spawn(move || {
+                let edges = edges(thread, threads);
+                for &(from, to) in &edges {
+                    graph
+                        .add_reference(objects[from].id(), objects[to].id(), ReferenceType::Direct)
+                        .unwrap();
+                }
+                for &(from, to) in &edges {
+                    graph
+                        .remove_reference(objects[from].id(), objects[to].id())
+                        .unwrap();
+                }
+            });
+        }
+    });
+}
+
+fn run_locked(graph: &Mutex<ObjectGraph>, objects: &[PyObject], threads: usize) {
+    std::thread::scope(|scope| {
+        for thread in 0..threads {
+            scope.spawn(move || {
+                let edges = edges(thread, threads);
+                for &(from, to) in &edges {
+                    graph
+                        .lock()
+                        .add_reference(objects[from].id(), objects[to].id(), ReferenceType::Direct)
+                        .unwrap();
+                }
+                for &(from, to) in &edges {
+                    graph
+                        .lock()
+                        .remove_reference(objects[from].id(), objects[to].id())
+                        .unwrap();
+                }
+            });
+        }
+    });
+}
+
+fn benchmark_graph_contention(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Graph Contention");
+    let objects = objects();
+
+    for threads in [1, 2, 4, 8] {
+        group.throughput(Throughput::Elements(
+            (threads * EDGES_PER_THREAD * 2) as u64,
+        ));
+
+        let shared = SharedObjectGraph::new();
+        objects
+            .iter()
+            .for_each(|obj| shared.add_object(obj.clone()));
+        group.bench_with_input(
+            BenchmarkId::new("sharded", threads),
+            &threads,
+            |b, &threads| {
+                b.iter(|| run_shared(&shared, &objects, threads));
+            },
+        );
+
+        let mut graph = ObjectGraph::new();
+        objects.iter().for_each(|obj| graph.add_object(obj.clone()));
+        let locked = Mutex::new(graph);
+        group.bench_with_input(
+            BenchmarkId::new("mutex", threads),
+            &threads,
+            |b, &threads| {
+                b.iter(|| run_locked(&locked, &objects, threads));
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_graph_contention);
+
+criterion_main!(benches);
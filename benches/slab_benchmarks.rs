@@ -0,0 +1,87 @@
+//! Tracked object storage: `ObjectSlab` against the `HashMap<ObjectId,
+//! PyObject>` it replaced, for the operations a collection leans on. Run
+//! with `cargo bench --bench slab_benchmarks`.
+
+use criterion::{BatchSize, BenchmarkId, Criterion, black_box, criterion_group, criterion_main};
+use python_gc::PyObject;
+use python_gc::object::{ObjectData, ObjectId};
+use python_gc::slab::ObjectSlab;
+use std::collections::HashMap;
+
+const OBJECTS: usize = 10_000;
+
+fn objects() -> Vec<PyObject> {
+    (0..OBJECTS)
+        .map(|index| PyObject::new(format!("obj{index}"), ObjectData::Integer(index as i64)))
+        .collect()
+}
+
+fn slab(objects: &[PyObject]) -> ObjectSlab {
+    let mut slab = ObjectSlab::new();
+    for obj in objects {
+        let _ = slab.insert(obj.id(), obj.clone());
+    }
+    slab
+}
+
+fn map(objects: &[PyObject]) -> HashMap<ObjectId, PyObject> {
+    objects.iter().map(|obj| (obj.id(), obj.clone())).collect()
+}
+
+fn benchmark_tracked_storage(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Tracked Storage");
+    let objects = objects();
+    let ids: Vec<ObjectId> = objects.iter().map(PyObject::id).collect();
+
+    group.bench_function(BenchmarkId::new("insert", "slab"), |b| {
+        b.iter(|| black_box(slab(&objects)))
+    });
+    group.bench_function(BenchmarkId::new("insert", "map"), |b| {
+        b.iter(|| black_box(map(&objects)))
+    });
+
+    let (filled_slab, filled_map) = (slab(&objects), map(&objects));
+    group.bench_function(BenchmarkId::new("lookup", "slab"), |b| {
+        b.iter(|| ids.iter().filter(|id| filled_slab.contains_key(id)).count())
+    });
+    group.bench_function(BenchmarkId::new("lookup", "map"), |b| {
+        b.iter(|| ids.iter().filter(|id| filled_map.contains_key(id)).count())
+    });
+
+    group.bench_function(BenchmarkId::new("iterate", "slab"), |b| {
+        b.iter(|| {
+            filled_slab
+                .values()
+                .map(PyObject::get_refcount)
+                .sum::<usize>()
+        })
+    });
+    group.bench_function(BenchmarkId::new("iterate", "map"), |b| {
+        b.iter(|| {
+            filled_map
+                .values()
+                .map(PyObject::get_refcount)
+                .sum::<usize>()
+        })
+    });
+
+    group.bench_function(BenchmarkId::new("remove", "slab"), |b| {
+        b.iter_batched(
+            || slab(&objects),
+            |mut slab| ids.iter().for_each(|id| drop(slab.remove(id))),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function(BenchmarkId::new("remove", "map"), |b| {
+        b.iter_batched(
+            || map(&objects),
+            |mut map| ids.iter().for_each(|id| drop(map.remove(id))),
+            BatchSize::LargeInput,
+        )
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, benchmark_tracked_storage);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+//! Links libpython into the benchmarks when the `cpython-bench` feature is on,
+//! and into the tests when `cpython-tests` is on, using the flags
+//! `python3-config --ldflags --embed` reports. Set `PYTHON_CONFIG` to pick
+//! another interpreter's config script.
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=PYTHON_CONFIG");
+    let targets: Vec<&str> = [
+        ("CARGO_FEATURE_CPYTHON_BENCH", "benches"),
+        ("CARGO_FEATURE_CPYTHON_TESTS", "tests"),
+    ]
+    .into_iter()
+    .filter(|(feature, _)| env::var_os(feature).is_some())
+    .map(|(_, targets)| targets)
+    .collect();
+    if targets.is_empty() {
+        return;
+    }
+
+    let config = env::var("PYTHON_CONFIG").unwrap_or_else(|_| "python3-config".to_string());
+    let output = Command::new(&config)
+        .args(["--ldflags", "--embed"])
+        .output()
+        .unwrap_or_else(|err| panic!("linking libpython needs {config}: {err}"));
+    assert!(
+        output.status.success(),
+        "{config} --ldflags --embed failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for flag in String::from_utf8_lossy(&output.stdout).split_whitespace() {
+        for targets in &targets {
+            println!("cargo:rustc-link-arg-{targets}={flag}");
+        }
+    }
+}
@@ -0,0 +1,89 @@
+//! Hunting a leak with heap snapshots. Each simulated request registers a
+//! callback on a long-lived event bus and never unregisters it, so the
+//! request/callback cycle survives every collection. Two snapshots taken
+//! around a batch of requests show which types grow, the new cycles, and
+//! the object retaining them.
+//!
+//! Run with `cargo run --example leak_hunting`.
+
+use python_gc::object::ObjectData;
+use python_gc::snapshot::Analyzer;
+use python_gc::{GCResult, GarbageCollector, PyObject};
+
+fn push(list: &PyObject, item: &PyObject) {
+    if let ObjectData::List(items) = &mut *list.data_mut() {
+        items.push(item.clone());
+        item.inc_ref();
+    }
+}
+
+/// A request and its callback refer to each other. With `subscribe` the bus
+/// keeps the callback too, which is the leak.
+fn handle_request(gc: &GarbageCollector, bus: &PyObject, subscribe: bool) -> GCResult<()> {
+    let request = PyObject::new("Request".to_string(), ObjectData::List(Vec::new()));
+    let callback = PyObject::new("Callback".to_string(), ObjectData::List(Vec::new()));
+    push(&request, &callback);
+    push(&callback, &request);
+    if subscribe {
+        push(bus, &callback);
+    }
+    gc.track_bulk(vec![request.clone(), callback.clone()])?;
+
+    gc.decref(&request.id())?;
+    gc.decref(&callback.id())?;
+    Ok(())
+}
+
+/// Returns how many objects the leaky batch left behind.
+fn run() -> GCResult<isize> {
+    let gc = GarbageCollector::new();
+    let bus = PyObject::new("EventBus".to_string(), ObjectData::List(Vec::new()));
+    gc.track(bus.clone())?;
+
+    for request in 0..10 {
+        handle_request(&gc, &bus, request % 2 == 0)?;
+    }
+    gc.collect()?;
+    let before = gc.snapshot();
+
+    for _ in 0..50 {
+        handle_request(&gc, &bus, true)?;
+    }
+    gc.collect()?;
+    let after = gc.snapshot();
+
+    let analyzer = Analyzer::new(before, after);
+    let growth = analyzer.growth_by_type();
+    for entry in &growth {
+        println!(
+            "{}: {} -> {} ({:+})",
+            entry.type_name, entry.before, entry.after, entry.delta
+        );
+    }
+    println!("{} new cycles", analyzer.new_cycles().len());
+    let retainers = analyzer.biggest_retainers(1);
+    for retainer in &retainers {
+        println!(
+            "biggest retainer: {} #{} keeps {} objects reachable",
+            retainer.type_name, retainer.id, retainer.retained
+        );
+    }
+
+    assert_eq!(analyzer.new_cycles().len(), 50);
+    assert_eq!(retainers[0].type_name, "EventBus");
+    Ok(growth.iter().map(|entry| entry.delta).sum())
+}
+
+fn main() -> GCResult<()> {
+    let leaked = run()?;
+    println!("{leaked} objects leaked by 50 requests");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_snapshots_point_at_the_event_bus() {
+        assert_eq!(super::run().unwrap(), 100);
+    }
+}
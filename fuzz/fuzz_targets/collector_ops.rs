@@ -0,0 +1,44 @@
+#![no_main]
+
+//! Drive the collector with arbitrary track/untrack/add_reference/collect
+//! sequences and check its invariants after every operation.
+
+use libfuzzer_sys::fuzz_target;
+use python_gc::ObjectId;
+use python_gc::object::ObjectData;
+use python_gc::testing::TestHarness;
+
+fuzz_target!(|data: &[u8]| {
+    let mut harness = TestHarness::new(0);
+    let mut ids: Vec<ObjectId> = Vec::new();
+
+    for op in data.chunks_exact(3) {
+        let pick = |byte: u8| ids.get(byte as usize % ids.len().max(1)).copied();
+        match op[0] % 4 {
+            0 => {
+                let obj = harness.object("fuzz", ObjectData::None);
+                obj.set_finalizer(op[1] % 8 == 0);
+                ids.push(harness.track(obj).unwrap());
+            }
+            1 => {
+                if let Some(obj_id) = pick(op[1]) {
+                    let _ = harness.collector_mut().untrack_object(&obj_id);
+                }
+            }
+            2 => {
+                if let (Some(from), Some(to)) = (pick(op[1]), pick(op[2])) {
+                    let _ = harness.add_reference(from, to);
+                }
+            }
+            _ => {
+                harness
+                    .collector_mut()
+                    .collect_generation(op[1] as usize % 3)
+                    .unwrap();
+            }
+        }
+
+        let violations = harness.collector().verify_heap();
+        assert!(violations.is_empty(), "{violations:?}");
+    }
+});
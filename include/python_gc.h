@@ -5,6 +5,7 @@
 extern "C" {
 #endif
 
+#include <stddef.h>
 #include <stdint.h>
 
 // Return codes for GC operations
@@ -15,8 +16,60 @@ typedef enum {
     GC_ERROR_COLLECTION_IN_PROGRESS = -3,
     GC_ERROR_INVALID_GENERATION = -4,
     GC_ERROR_INTERNAL = -5,
+    GC_ERROR_BUFFER_TOO_SMALL = -6,
+    GC_ERROR_NOT_INITIALIZED = -7,
 } gc_return_code_t;
 
+// Log levels passed to the log callback
+typedef enum {
+    GC_LOG_DEBUG = 0,
+    GC_LOG_INFO = 1,
+    GC_LOG_WARNING = 2,
+    GC_LOG_ERROR = 3,
+} gc_log_level_t;
+
+// Debug flags (Python gc module compatibility)
+#define GC_DEBUG_STATS         (1 << 0)
+#define GC_DEBUG_COLLECTABLE   (1 << 1)
+#define GC_DEBUG_UNCOLLECTABLE (1 << 2)
+#define GC_DEBUG_SAVEALL       (1 << 5)
+#define GC_DEBUG_LEAK          (GC_DEBUG_COLLECTABLE | GC_DEBUG_UNCOLLECTABLE | GC_DEBUG_SAVEALL)
+// Audit refcounts against the object graph at the start of each
+// collection, logging each mismatch; walks the whole heap
+#define GC_DEBUG_AUDIT_REFS    (1 << 6)
+// After each minor collection, check the whole heap for references to the
+// objects it freed, logging each as a missed write barrier
+#define GC_DEBUG_VERIFY_BARRIERS (1 << 7)
+
+typedef void (*gc_log_callback_t)(int32_t level, const char* message);
+
+// Reference kinds for py_gc_add_reference_typed; weak edges are ignored by
+// cycle detection
+typedef enum {
+    GC_REF_DIRECT = 0,
+    GC_REF_WEAK = 1,
+    GC_REF_FINALIZER = 2
+} gc_reference_type_t;
+
+// Object visitor; return nonzero to stop the iteration early
+typedef int32_t (*gc_visit_callback_t)(void* obj_ptr, void* user_data);
+
+// What py_gc_watch_object watches for, and what its callback reports
+typedef enum {
+    GC_WATCH_REFCOUNT = 0,
+    GC_WATCH_REACHABILITY = 1,
+    GC_WATCH_COLLECTION = 2
+} gc_watch_kind_t;
+
+// Watch callback; refcount is the new refcount for GC_WATCH_REFCOUNT, 0 otherwise
+typedef void (*gc_watch_callback_t)(void* obj_ptr, int32_t event, size_t refcount, void* user_data);
+
+// Interpreter hooks; gil_ensure/gil_release map onto PyGILState_Ensure/Release
+typedef struct {
+    int32_t (*gil_ensure)(void);
+    void (*gil_release)(int32_t state);
+} gc_python_hooks_t;
+
 // GC statistics structure
 typedef struct {
     int32_t total_tracked;
@@ -24,10 +77,60 @@ typedef struct {
     int32_t uncollectable;
 } gc_stats_t;
 
+// Extended GC statistics; set size to sizeof(gc_stats_extended_t) before use
+typedef struct {
+    size_t size;
+    size_t total_tracked;
+    size_t generation_counts[3];
+    size_t uncollectable;
+    size_t collections[3];
+    size_t total_collected;
+    uint64_t last_pause_ns;
+    size_t thresholds[3];
+    size_t reported_allocations;
+    size_t reported_frees;
+    size_t long_lived_total;   // survivors of the last full collection
+    size_t long_lived_pending; // promoted into generation 2 since then
+    size_t collected[3];       // objects freed by collections of each generation
+    size_t large_object_count; // objects in the large object space
+    size_t large_object_bytes; // and their shallow size
+    uint64_t stats_epoch;      // collections that have finished and published stats
+    size_t collection_in_progress; // 1 if read while a collection was running
+    size_t permanent_count;    // objects in the permanent generation
+    size_t permanent_bytes;    // and their shallow size
+    size_t generation_bytes[3]; // shallow size of each generation's objects
+    size_t scanned[3];         // candidates examined by each generation's collections
+    size_t promoted[3];        // survivors they moved on to the next generation
+} gc_stats_extended_t;
+
+// Called before each collection with the generation about to be collected
+// and the current stats; return nonzero to veto the collection
+typedef int32_t (*gc_pre_collect_callback_t)(int32_t generation, const gc_stats_extended_t* stats, void* user_data);
+
+// One generation's statistics, from py_gc_get_generation_stats
+typedef struct {
+    size_t count;
+    size_t bytes;         // combined shallow size
+    size_t threshold;
+    size_t collections;
+    size_t collected;     // objects freed by its collections
+    size_t scanned;       // candidates its collections examined
+    size_t promoted;      // survivors moved on to the next generation
+    double survivor_rate; // share of scanned objects that survived; 0 before any collection
+} gc_generation_stats_t;
+
+// Objects sharing one tag, from py_gc_get_tag_stats
+typedef struct {
+    size_t objects;
+    size_t bytes;     // combined shallow size
+    size_t survivors; // objects that survived at least one collection
+} gc_tag_stats_t;
+
 // Core GC Management Functions
 
 /**
  * Initialize the global garbage collector
+ * Calling this again reinitializes the collector and discards all tracked state.
  * @return GC_SUCCESS on success, error code on failure
  */
 gc_return_code_t py_gc_init(void);
@@ -46,6 +149,7 @@ gc_return_code_t py_gc_enable(void);
 
 /**
  * Disable automatic garbage collection
+ * Objects are still tracked and py_gc_collect still collects
  * @return GC_SUCCESS on success, error code on failure
  */
 gc_return_code_t py_gc_disable(void);
@@ -62,6 +166,165 @@ int32_t py_gc_is_enabled(void);
  */
 int32_t py_gc_is_initialized(void);
 
+/**
+ * Restore the collector in the child process after fork()
+ * Releases locks held by other threads at fork time and clears this thread's
+ * error state; tracked objects are preserved. Register it as the child
+ * handler of pthread_atfork() or call it from PyOS_AfterFork_Child.
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_after_fork_child(void);
+
+// Error Reporting
+
+// String-returning functions write a NUL-terminated string, truncating it if
+// the buffer is too small and returning GC_ERROR_BUFFER_TOO_SMALL. Pass a NULL
+// buffer to query the size: py_gc_get_required_buffer_size() then reports the
+// number of bytes (including the terminator) the last such call needed.
+
+/**
+ * Get the buffer size required by the last string-returning call on this thread
+ * @return Required size in bytes, including the NUL terminator
+ */
+size_t py_gc_get_required_buffer_size(void);
+
+/**
+ * Get the message describing the last failed call on the calling thread
+ * Querying the size with a NULL buffer does not replace the last error.
+ * @param buffer Buffer to write the message to (empty string if no error), or NULL
+ * @param buffer_size Size of the buffer
+ * @return GC_SUCCESS on success, GC_ERROR_BUFFER_TOO_SMALL if truncated
+ */
+gc_return_code_t py_gc_get_last_error(char* buffer, size_t buffer_size);
+
+/**
+ * Get the return code of the last failed call on the calling thread
+ * @return Error code, or GC_SUCCESS if no error has been recorded
+ */
+int32_t py_gc_get_last_error_code(void);
+
+/**
+ * Clear the last error recorded on the calling thread
+ */
+void py_gc_clear_last_error(void);
+
+// Python C-API backend; each entry maps onto the matching CPython function
+// (PyList_New, PyList_SetItem, PyList_GetItem, PyList_Size, Py_IncRef, Py_DecRef,
+// _PyObject_GC_New, PyObject_GC_Del)
+typedef struct {
+    void* (*list_new)(intptr_t size);
+    int32_t (*list_set_item)(void* list, intptr_t index, void* item);
+    void* (*list_get_item)(void* list, intptr_t index);
+    intptr_t (*list_size)(void* list);
+    void (*incref)(void* obj);
+    void (*decref)(void* obj);
+    void* (*gc_new)(void* type); // optional; py_gc_object_new returns NULL without it
+    void (*gc_del)(void* obj);   // optional; py_gc_object_del does nothing without it
+} gc_python_api_t;
+
+/**
+ * Register the Python C-API backend used to build and inspect Python objects.
+ * Until a backend is registered, list-returning functions return NULL.
+ * @param api Pointer to a complete API table, or NULL to restore the stub backend
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_set_python_api(const gc_python_api_t* api);
+
+/**
+ * Count every object allocated through py_gc_object_new or freed through
+ * py_gc_object_del, and report it to automatic tracking. Only these explicit
+ * calls are counted: CPython's own allocator is not intercepted, so objects
+ * the interpreter allocates itself are not seen. Enabling twice is a no-op;
+ * reporting survives py_gc_set_python_api.
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_enable_allocation_reporting(void);
+
+/**
+ * Stop reporting allocations made through py_gc_object_new/py_gc_object_del
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_disable_allocation_reporting(void);
+
+/**
+ * Check if allocation reporting is enabled
+ * @return 1 if enabled, 0 otherwise
+ */
+int32_t py_gc_allocation_reporting_enabled(void);
+
+/**
+ * Allocate a GC-enabled object through the backend's gc_new
+ * @param type Python type object to instantiate
+ * @return New object, or NULL on failure or if the backend has no gc_new
+ */
+void* py_gc_object_new(void* type);
+
+/**
+ * Free a GC-enabled object through the backend's gc_del
+ * @param obj Object previously returned by py_gc_object_new
+ */
+void py_gc_object_del(void* obj);
+
+// Threading
+//
+// Functions that create or inspect Python objects (py_gc_get_objects,
+// py_gc_get_objects_by_type, py_gc_get_objects_by_tag, py_gc_get_referrers,
+// py_gc_get_referents,
+// py_gc_find_cycles, py_gc_get_garbage, py_gc_set_garbage,
+// py_gc_get_saveall_garbage, py_gc_set_refcount)
+// acquire the GIL through the registered hooks, so they may be called from
+// threads that do not hold it.
+// Without hooks the caller must hold the GIL.
+//
+// Collections run with the world stopped: they wait while another thread
+// holds py_gc_stop_the_world, and the thread hooks below let the embedder
+// pause its own threads around them (on free-threaded CPython, a
+// stop-the-world over every attached thread state). Builds with the `ft`
+// feature also share the object registry between threads, so an object
+// tracked on one thread can be released on another.
+
+typedef struct {
+    void (*stop_the_world)(void);
+    void (*start_the_world)(void);
+} gc_thread_hooks_t;
+
+/**
+ * Register callbacks run whenever the world is stopped and restarted
+ * @param hooks Pointer to the hooks, or NULL to remove them
+ * @return GC_SUCCESS
+ */
+gc_return_code_t py_gc_set_thread_hooks(const gc_thread_hooks_t* hooks);
+
+/**
+ * Stop the world: wait for any running collection, then hold off collections
+ * from other threads until py_gc_resume_the_world. Calls nest on the stopping
+ * thread, which can keep using the library.
+ * @return GC_SUCCESS
+ */
+gc_return_code_t py_gc_stop_the_world(void);
+
+/**
+ * Undo one py_gc_stop_the_world made on this thread
+ * @return GC_SUCCESS, or GC_ERROR_INTERNAL if this thread had not stopped the world
+ */
+gc_return_code_t py_gc_resume_the_world(void);
+
+/**
+ * Register the interpreter hooks used to acquire and release the GIL
+ * @param hooks Pointer to the hooks (both functions required), or NULL to clear
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_set_python_hooks(const gc_python_hooks_t* hooks);
+
+// Logging
+
+/**
+ * Route diagnostic output (debug state, GC_DEBUG_STATS) to a callback
+ * @param callback Function receiving the level and message, or NULL for stderr
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_set_log_callback(gc_log_callback_t callback);
+
 // Automatic Tracking Functions
 
 /**
@@ -103,7 +366,8 @@ gc_return_code_t py_gc_object_destroyed(void* obj_ptr);
  * @param obj_ptr Pointer to the Python object
  * @param old_count Previous reference count
  * @param new_count New reference count
- * @return GC_SUCCESS on success, error code on failure
+ * @return GC_SUCCESS on success, GC_ERROR_INTERNAL if the change would take a
+ *         tracked object's count below zero (the count is left at zero)
  */
 gc_return_code_t py_gc_refcount_changed(void* obj_ptr, int32_t old_count, int32_t new_count);
 
@@ -111,7 +375,9 @@ gc_return_code_t py_gc_refcount_changed(void* obj_ptr, int32_t old_count, int32_
 
 /**
  * Track an object for garbage collection
- * @param obj_ptr Pointer to the Python object
+ * The GC must be initialized; the object is registered with the collector.
+ * Its type name and finalizer slots are read from the object header.
+ * @param obj_ptr Pointer to a live Python object
  * @return GC_SUCCESS on success, error code on failure
  */
 gc_return_code_t py_gc_track(void* obj_ptr);
@@ -123,6 +389,26 @@ gc_return_code_t py_gc_track(void* obj_ptr);
  */
 gc_return_code_t py_gc_untrack(void* obj_ptr);
 
+/**
+ * Track an array of objects with a single collector update
+ * Objects that are already tracked are skipped.
+ * @param objects Array of object pointers
+ * @param count Number of entries in the array
+ * @param out_tracked Receives the number of newly tracked objects (may be NULL)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_track_batch(void* const* objects, size_t count, size_t* out_tracked);
+
+/**
+ * Untrack an array of objects with a single collector update
+ * Objects that are not tracked are skipped.
+ * @param objects Array of object pointers
+ * @param count Number of entries in the array
+ * @param out_untracked Receives the number of untracked objects (may be NULL)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_untrack_batch(void* const* objects, size_t count, size_t* out_untracked);
+
 /**
  * Check if an object is tracked by the garbage collector
  * @param obj_ptr Pointer to the Python object
@@ -153,6 +439,69 @@ gc_return_code_t py_gc_untrack_python(void* obj_ptr);
  */
 int32_t py_gc_is_tracked_python(void* obj_ptr);
 
+// Interpreter ABI
+//
+// Objects passed to the *_python functions are read through a layout table,
+// or through the functions registered with py_gc_set_abi3_api.
+// The default matches a release build of any CPython 3 version; select
+// another with py_gc_set_abi_version.
+
+// Py_TRACE_REFS build (extra pointers before ob_refcnt, before 3.13)
+#define GC_ABI_TRACE_REFS 0x1
+// Free-threaded build (Py_GIL_DISABLED, 3.13 and later)
+#define GC_ABI_FREE_THREADED 0x2
+
+typedef struct {
+    int has_gc;
+    int has_traverse;
+    int has_clear;
+    int has_finalize;
+} gc_type_info_t;
+
+/**
+ * Select the object and type layout for CPython major.minor
+ * @param major Python major version; only 3 is supported
+ * @param minor Python minor version
+ * @param abi_flags Bitwise OR of GC_ABI_* flags
+ * @return GC_SUCCESS on success, GC_ERROR_INTERNAL for unsupported versions or flags
+ */
+gc_return_code_t py_gc_set_abi_version(int major, int minor, unsigned int abi_flags);
+
+/**
+ * Read the GC-related slots of an object's type through the configured layout
+ * @param obj_ptr Pointer to the Python object
+ * @param info Receives whether the type has Py_TPFLAGS_HAVE_GC, tp_traverse,
+ *             tp_clear and tp_finalize
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_get_type_info(void* obj_ptr, gc_type_info_t* info);
+
+// abi3 mode: interpreter functions used instead of reading PyObject and
+// PyTypeObject fields; each entry maps onto the stable-ABI function named
+// in its comment
+typedef struct {
+    void* (*object_type)(void* obj);                                   // Py_TYPE (borrowed)
+    intptr_t (*type_name)(void* type, char* buffer, size_t buffer_size); // PyType_GetName; returns length or < 0
+    unsigned long (*type_flags)(void* type);                           // PyType_GetFlags
+    void* (*type_get_slot)(void* type, int slot);                      // PyType_GetSlot
+    int (*gc_is_tracked)(void* obj);                                   // PyObject_GC_IsTracked
+    intptr_t (*refcnt)(void* obj);                                     // Py_REFCNT
+} gc_abi3_api_t;
+
+/**
+ * Enter abi3 mode, where no interpreter struct is ever dereferenced and the
+ * layout from py_gc_set_abi_version is ignored. Call right after py_gc_init.
+ * @param api Pointer to a complete table, or NULL to leave abi3 mode
+ * @return GC_SUCCESS on success, GC_ERROR_INTERNAL if the table is incomplete
+ */
+gc_return_code_t py_gc_set_abi3_api(const gc_abi3_api_t* api);
+
+/**
+ * Check whether abi3 mode is active
+ * @return 1 if an abi3 table is installed, 0 otherwise
+ */
+int32_t py_gc_is_abi3_mode(void);
+
 // Reference Counting Functions
 
 /**
@@ -173,7 +522,7 @@ gc_return_code_t py_gc_set_refcount(void* obj_ptr, int32_t refcount);
 // Collection Functions
 
 /**
- * Perform garbage collection on a specific generation
+ * Perform garbage collection on a specific generation and all younger ones
  * @param generation Generation number (0, 1, or 2)
  * @return GC_SUCCESS on success, error code on failure
  */
@@ -181,18 +530,49 @@ gc_return_code_t py_gc_collect_generation(int32_t generation);
 
 /**
  * Perform a full garbage collection (all generations)
+ * Only objects whose reference count is accounted for by references from
+ * other tracked objects are reclaimed; anything held from outside survives,
+ * along with everything it reaches.
+ * Objects reclaimed by the collection are removed from the object registry.
  * @return GC_SUCCESS on success, error code on failure
  */
 gc_return_code_t py_gc_collect(void);
 
+/**
+ * Perform garbage collection on a specific generation and all younger ones,
+ * and report the result
+ * @param generation Generation number (0, 1, or 2)
+ * @param out_collected Receives the number of collected objects (may be NULL)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_collect_generation_ex(int32_t generation, size_t* out_collected);
+
+/**
+ * Perform a full garbage collection and report the result
+ * (Python gc.collect() return value compatibility)
+ * @param out_collected Receives the number of collected objects (may be NULL)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_collect_ex(size_t* out_collected);
+
 /**
  * Check if collection is needed
+ * Automatic collection is off while the generation 0 threshold is 0;
+ * tracking and explicit collections keep working.
  * @return 1 if collection is needed, 0 if not
  */
 int32_t py_gc_needs_collection(void);
 
 /**
- * Collect if thresholds are exceeded
+ * Get the generation py_gc_collect_if_needed would collect now: the oldest
+ * one over its threshold
+ * @return 0, 1 or 2, or -1 if no collection is needed
+ */
+int32_t py_gc_needs_collection_generation(void);
+
+/**
+ * Collect if thresholds are exceeded; does nothing while the generation 0
+ * threshold is 0
  * @return GC_SUCCESS on success, error code on failure
  */
 gc_return_code_t py_gc_collect_if_needed(void);
@@ -201,11 +581,33 @@ gc_return_code_t py_gc_collect_if_needed(void);
 
 /**
  * Get garbage collection statistics
+ * Never waits for a running collection; mid-collection the stats published
+ * at its last phase boundary are returned.
  * @param stats Pointer to GCStats structure to fill
  * @return GC_SUCCESS on success, error code on failure
  */
 gc_return_code_t py_gc_get_stats(gc_stats_t* stats);
 
+/**
+ * Get extended garbage collection statistics
+ * Only the first size bytes are written; stats->size receives the number of
+ * bytes filled in, so older callers with a shorter struct remain compatible.
+ * @param stats Pointer to extended statistics structure to fill
+ * @param size Size of the caller's structure, normally sizeof(gc_stats_extended_t)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_get_stats_extended(gc_stats_extended_t* stats, size_t size);
+
+/**
+ * Get the statistics of one generation, for tuning its threshold
+ * Never waits for a running collection, like py_gc_get_stats.
+ * @param generation Generation number (0, 1, or 2)
+ * @param stats Structure to fill
+ * @return GC_SUCCESS on success, GC_ERROR_INVALID_GENERATION for a bad
+ *         generation, other error code on failure
+ */
+gc_return_code_t py_gc_get_generation_stats(int32_t generation, gc_generation_stats_t* stats);
+
 /**
  * Get the number of tracked objects
  * @return Number of tracked objects
@@ -219,6 +621,32 @@ int32_t py_gc_get_count(void);
  */
 int32_t py_gc_get_generation_count(int32_t generation);
 
+/**
+ * Get the generation a tracked object is in
+ * @param obj_ptr Pointer to the object
+ * @return 0, 1 or 2, or -1 if the object is not tracked or is uncollectable
+ */
+int32_t py_gc_get_object_generation(void* obj_ptr);
+
+/**
+ * Move every tracked object into the permanent generation, which no
+ * collection looks at (Python gc.freeze() compatibility)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_freeze(void);
+
+/**
+ * Move the permanent generation back into the oldest generation
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_unfreeze(void);
+
+/**
+ * Get the number of objects in the permanent generation
+ * @return Number of frozen objects
+ */
+int32_t py_gc_get_freeze_count(void);
+
 /**
  * Get collection counts (Python gc.get_count() compatibility)
  * @return Pointer to array of 3 integers [gen0, gen1, gen2], or NULL on error
@@ -260,6 +688,37 @@ gc_return_code_t py_gc_set_threshold(int32_t generation, int32_t threshold);
  */
 int32_t py_gc_get_threshold(int32_t generation);
 
+/**
+ * Set the thresholds of all three generations at once, like Python's
+ * gc.set_threshold(threshold0[, threshold1[, threshold2]])
+ * @param threshold0 New threshold for generation 0; 0 disables automatic collection
+ * @param threshold1 New threshold for generation 1, or negative to keep it
+ * @param threshold2 New threshold for generation 2, or negative to keep it
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_set_thresholds(int32_t threshold0, int32_t threshold1, int32_t threshold2);
+
+/**
+ * Get the thresholds of all three generations
+ * @param out Receives the thresholds of generations 0, 1 and 2
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_get_thresholds(int32_t out[3]);
+
+/**
+ * Send objects of at least threshold bytes to the large object space,
+ * which only full collections visit. Already tracked objects stay put.
+ * @param threshold Size in bytes; 0 turns the large object space off
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_set_large_object_threshold(size_t threshold);
+
+/**
+ * Get the large object threshold
+ * @return Threshold in bytes, or 0 if the large object space is off
+ */
+size_t py_gc_get_large_object_threshold(void);
+
 // Debug and State Functions
 
 /**
@@ -284,27 +743,139 @@ int32_t py_gc_get_debug_flags(void);
 
 /**
  * Get a string representation of the GC state
- * @param buffer Buffer to write state string to
+ * @param buffer Buffer to write state string to, or NULL to query the size
  * @param buffer_size Size of the buffer
- * @return GC_SUCCESS on success, error code on failure
+ * @return GC_SUCCESS on success, GC_ERROR_BUFFER_TOO_SMALL if truncated,
+ *         other error code on failure
  */
 gc_return_code_t py_gc_get_state_string(char* buffer, size_t buffer_size);
 
 /**
  * Get information about a tracked object
  * @param obj_ptr Pointer to the tracked object
- * @param buffer Buffer to write object info to
+ * @param buffer Buffer to write object info to, or NULL to query the size
  * @param buffer_size Size of the buffer
- * @return GC_SUCCESS on success, error code on failure
+ * @return GC_SUCCESS on success, GC_ERROR_BUFFER_TOO_SMALL if truncated,
+ *         other error code on failure
  */
 gc_return_code_t py_gc_get_tracked_info(void* obj_ptr, char* buffer, size_t buffer_size);
 
+/**
+ * Explain why a tracked object is alive: generation, age in survived
+ * collections, refcount and the chain of references retaining it
+ * @param obj_ptr Pointer to the tracked object
+ * @param buffer Buffer to write the explanation to, or NULL to query the size
+ * @param buffer_size Size of the buffer
+ * @return GC_SUCCESS on success, GC_ERROR_BUFFER_TOO_SMALL if truncated,
+ *         GC_ERROR_NOT_TRACKED if the object is not tracked, other error code on failure
+ */
+gc_return_code_t py_gc_explain_object(void* obj_ptr, char* buffer, size_t buffer_size);
+
+/**
+ * Record the references added to and removed from each tracked object through
+ * py_gc_add_reference and py_gc_remove_reference. Drops any history recorded
+ * so far.
+ * @param per_object Changes kept per object
+ * @param max_objects Objects with a history; the oldest loses it first
+ * @return GC_SUCCESS on success, error code on failure. Zero for either
+ *         limit turns recording off.
+ */
+gc_return_code_t py_gc_set_reference_history(size_t per_object, size_t max_objects);
+
+/**
+ * Describe the last changes to the references into an object, oldest first,
+ * one per line: time on the collector's clock, "added" or "removed", and the
+ * referrer
+ * @param obj_ptr Pointer to the tracked object
+ * @param n Number of changes to describe
+ * @param buffer Buffer to write the history to, or NULL to query the size
+ * @param buffer_size Size of the buffer
+ * @return GC_SUCCESS on success, GC_ERROR_BUFFER_TOO_SMALL if truncated,
+ *         GC_ERROR_NOT_TRACKED if the object is not tracked, other error code on failure
+ */
+gc_return_code_t py_gc_get_reference_history(void* obj_ptr, size_t n, char* buffer, size_t buffer_size);
+
+/**
+ * Call a callback when an object's refcount crosses a threshold, a collection
+ * finds it unreachable, or it is freed. The callback runs with the collector
+ * unlocked, so it may call back into it. Watches end when the object is freed.
+ * @param obj_ptr Pointer to a tracked object
+ * @param kind One of gc_watch_kind_t
+ * @param threshold Refcount to watch for with GC_WATCH_REFCOUNT; ignored otherwise
+ * @param callback Called with the object, the kind that fired and user_data
+ * @param user_data Passed through to the callback
+ * @param out_watch_id Receives the ID to pass to py_gc_unwatch_object (may be NULL)
+ * @return GC_SUCCESS on success, GC_ERROR_NOT_TRACKED if the object is not
+ *         tracked, other error code on failure
+ */
+gc_return_code_t py_gc_watch_object(void* obj_ptr, int32_t kind, size_t threshold, gc_watch_callback_t callback, void* user_data, uint64_t* out_watch_id);
+
+/**
+ * Remove a watch set with py_gc_watch_object
+ * @param watch_id ID from py_gc_watch_object
+ * @return GC_SUCCESS on success, error code if no such watch is set
+ */
+gc_return_code_t py_gc_unwatch_object(uint64_t watch_id);
+
+/**
+ * Call a function before every collection, like a gc.callbacks "start"
+ * call, so caches can be dropped for the collection to find
+ * @param callback Called with the generation about to be collected and the
+ *        current stats; returning nonzero vetoes the collection
+ * @param user_data Passed to the callback; must stay valid while registered
+ * @param out_hook_id Receives the ID to pass to
+ *        py_gc_remove_pre_collect_callback (may be NULL)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_add_pre_collect_callback(gc_pre_collect_callback_t callback, void* user_data, uint64_t* out_hook_id);
+
+/**
+ * Remove a callback added with py_gc_add_pre_collect_callback
+ * @param hook_id ID from py_gc_add_pre_collect_callback
+ * @return GC_SUCCESS on success, error code if there is no such callback
+ */
+gc_return_code_t py_gc_remove_pre_collect_callback(uint64_t hook_id);
+
+/**
+ * Write a heap snapshot to a file for offline analysis
+ * @param path Path of the file to create or overwrite
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_dump_heap(const char* path);
+
+/**
+ * Write a heap snapshot holding only the objects with a given tag
+ * @param path Path of the file to create or overwrite
+ * @param tag Null-terminated tag
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_dump_heap_tagged(const char* path, const char* tag);
+
+/**
+ * Put an object in an operational group, e.g. "cache" or "plugin:X", so heap
+ * usage can be attributed by subsystem rather than by type
+ * @param obj_ptr Pointer to a tracked object
+ * @param tag Null-terminated tag, or NULL to clear the object's tag
+ * @return GC_SUCCESS on success, GC_ERROR_NOT_TRACKED if the object is not
+ *         tracked, other error code on failure
+ */
+gc_return_code_t py_gc_set_tag(void* obj_ptr, const char* tag);
+
+/**
+ * Get the count, shallow size and survivors of the objects with a tag
+ * @param tag Null-terminated tag
+ * @param stats Filled in; all zero if no object has the tag
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_get_tag_stats(const char* tag, gc_tag_stats_t* stats);
+
 /**
  * Get object type name
  * @param obj_ptr Pointer to the object
- * @param buffer Buffer to write type name to
+ * @param buffer Buffer to write type name to, or NULL to query the size
  * @param buffer_size Size of the buffer
- * @return GC_SUCCESS on success, error code on failure
+ * @return GC_SUCCESS on success, GC_ERROR_BUFFER_TOO_SMALL if truncated,
+ *         other error code on failure
  */
 gc_return_code_t py_gc_get_object_type_name(void* obj_ptr, char* buffer, size_t buffer_size);
 
@@ -340,6 +911,47 @@ gc_return_code_t py_gc_set_finalizer(void* obj_ptr, int32_t has_finalizer);
  */
 void* py_gc_get_objects(void);
 
+/**
+ * Call a visitor for every tracked object without building a Python list
+ * The objects are captured up front, so the visitor may track or untrack objects.
+ * @param callback Visitor; returning nonzero stops the iteration
+ * @param user_data Passed through to the visitor
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_iterate_objects(gc_visit_callback_t callback, void* user_data);
+
+/**
+ * Call a visitor for every object that refers to the given object
+ * @param obj_ptr Pointer to the object
+ * @param callback Visitor; returning nonzero stops the iteration
+ * @param user_data Passed through to the visitor
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_iterate_referrers(void* obj_ptr, gc_visit_callback_t callback, void* user_data);
+
+/**
+ * Call a visitor for every object the given object refers to
+ * @param obj_ptr Pointer to the object
+ * @param callback Visitor; returning nonzero stops the iteration
+ * @param user_data Passed through to the visitor
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_iterate_referents(void* obj_ptr, gc_visit_callback_t callback, void* user_data);
+
+/**
+ * Get tracked objects whose type name (tp_name) matches
+ * @param type_name Null-terminated type name, e.g. "Foo"
+ * @return Python list of matching objects, or NULL if none match
+ */
+void* py_gc_get_objects_by_type(const char* type_name);
+
+/**
+ * Get tracked objects with a given tag
+ * @param tag Null-terminated tag
+ * @return Python list of matching objects, or NULL if none match
+ */
+void* py_gc_get_objects_by_tag(const char* tag);
+
 /**
  * Get objects that refer to the given object (Python gc.get_referrers() compatibility)
  * @param obj_ptr Pointer to the object
@@ -354,6 +966,19 @@ void* py_gc_get_referrers(void* obj_ptr);
  */
 void* py_gc_get_referents(void* obj_ptr);
 
+/**
+ * Find reference cycles among the references registered with py_gc_add_reference
+ * Each cycle is a strongly connected component of the reference graph.
+ * @return List of lists, one list of object pointers per cycle, or NULL
+ */
+void* py_gc_find_cycles(void);
+
+/**
+ * Count reference cycles among the registered references
+ * @return Number of cycles
+ */
+int32_t py_gc_count_cycles(void);
+
 /**
  * Get garbage (uncollectable objects) (Python gc.get_garbage() compatibility)
  * @return Pointer to array of uncollectable objects, or NULL
@@ -367,6 +992,25 @@ void* py_gc_get_garbage(void);
  */
 gc_return_code_t py_gc_set_garbage(void* garbage_list);
 
+/**
+ * Get the objects collections kept instead of freeing while
+ * GC_DEBUG_SAVEALL was set, oldest first
+ * @return New reference to a Python list, or NULL if there are none
+ */
+void* py_gc_get_saveall_garbage(void);
+
+/**
+ * Get the number of objects kept under GC_DEBUG_SAVEALL
+ * @return Number of saved objects
+ */
+int32_t py_gc_get_saveall_garbage_count(void);
+
+/**
+ * Release the objects kept under GC_DEBUG_SAVEALL
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_clear_saveall_garbage(void);
+
 // Reference Management Functions
 
 /**
@@ -377,6 +1021,43 @@ gc_return_code_t py_gc_set_garbage(void* garbage_list);
  */
 gc_return_code_t py_gc_add_reference(void* from_obj, void* to_obj);
 
+/**
+ * Add a strong reference held in the field, slot or key `name`, so the
+ * retaining path from py_gc_explain_object says where the object is kept
+ * @param from_obj Pointer to the referring object
+ * @param to_obj Pointer to the referenced object
+ * @param name Field name such as "_cache" or "['key']" (NULL for none)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_add_reference_named(void* from_obj, void* to_obj, const char* name);
+
+/**
+ * Add a reference of the given kind from one object to another
+ * Re-adding an existing reference replaces its kind.
+ * @param from_obj Source object
+ * @param to_obj Target object
+ * @param ref_type One of gc_reference_type_t
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_add_reference_typed(void* from_obj, void* to_obj, int32_t ref_type);
+
+/**
+ * Get the kind of the reference between two objects
+ * @param from_obj Source object
+ * @param to_obj Target object
+ * @return One of gc_reference_type_t, or -1 if there is no such reference
+ */
+int32_t py_gc_get_reference_type(void* from_obj, void* to_obj);
+
+/**
+ * Add references from_objects[i] -> to_objects[i] for every index
+ * @param from_objects Array of referring objects
+ * @param to_objects Array of referenced objects
+ * @param count Number of references to add
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_add_references_batch(void* const* from_objects, void* const* to_objects, size_t count);
+
 /**
  * Remove a reference from one object to another
  * @param from_obj Pointer to the referring object
@@ -385,6 +1066,17 @@ gc_return_code_t py_gc_add_reference(void* from_obj, void* to_obj);
  */
 gc_return_code_t py_gc_remove_reference(void* from_obj, void* to_obj);
 
+/**
+ * Drop the references an object holds, as tp_clear would: those recorded
+ * with py_gc_add_reference and the counts they kept on tracked objects.
+ * Objects freed this way drop theirs in turn, so breaking one link frees a
+ * cycle nothing else holds. The embedder clears the Python object itself.
+ * @param obj_ptr Tracked object whose references to drop
+ * @param out_freed Receives the number of objects freed; may be NULL
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_break_cycle(void* obj_ptr, size_t* out_freed);
+
 /**
  * Mark an object as uncollectable
  * @param obj_ptr Pointer to the object
@@ -416,11 +1108,46 @@ int32_t py_gc_is_uncollectable(void* obj_ptr);
 gc_return_code_t py_gc_debug_untrack(void* obj_ptr);
 
 /**
- * Print debug state information
+ * Print debug state information through the log callback (stderr if unset)
  * @return GC_SUCCESS on success, error code on failure
  */
 gc_return_code_t py_gc_debug_state(void);
 
+/**
+ * Check the collector's internal invariants, logging each violation found
+ * through the log callback (stderr if unset)
+ * @param out_violations Receives the number of violations (may be NULL)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_verify(size_t* out_violations);
+
+/**
+ * Compare each object's refcount with its referrers plus its declared
+ * external references, logging each mismatch found through the log callback
+ * (stderr if unset). A refcount above that usually means a missing
+ * py_gc_add_reference call.
+ * @param out_discrepancies Receives the number of mismatches (may be NULL)
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_audit_refcounts(size_t* out_discrepancies);
+
+/**
+ * Declare a reference to an object held from outside the object graph, such
+ * as by a C global, so the refcount audit accounts for it
+ * @param obj_ptr Pointer to a tracked object
+ * @return GC_SUCCESS on success, GC_ERROR_NOT_TRACKED if the object is not
+ *         tracked, other error code on failure
+ */
+gc_return_code_t py_gc_add_external_ref(void* obj_ptr);
+
+/**
+ * Drop a reference declared with py_gc_add_external_ref
+ * @param obj_ptr Pointer to a tracked object
+ * @return GC_SUCCESS on success, GC_ERROR_NOT_TRACKED if the object is not
+ *         tracked, other error code if it has no declared references
+ */
+gc_return_code_t py_gc_release_external_ref(void* obj_ptr);
+
 /**
  * Clear uncollectable objects
  * @return GC_SUCCESS on success, error code on failure
@@ -433,6 +1160,132 @@ gc_return_code_t py_gc_clear_uncollectable(void);
  */
 gc_return_code_t py_gc_clear_registry(void);
 
+// GC Instances
+//
+// Each instance has its own collector and object registry, for embedders
+// running subinterpreters or several isolated runtimes. The functions above
+// act on the default instance (handle 0); each has an _h variant taking the
+// instance first. An unknown handle fails with GC_ERROR_NOT_INITIALIZED, or
+// returns 0 or NULL.
+
+typedef uint64_t gc_instance_t;
+
+#define GC_DEFAULT_INSTANCE ((gc_instance_t)0)
+
+/**
+ * Create an initialized GC instance
+ * @return Handle for the _h functions
+ */
+gc_instance_t py_gc_create_instance(void);
+
+/**
+ * Destroy an instance from py_gc_create_instance, dropping its tracked objects
+ * @param handle Instance to destroy; the default instance cannot be destroyed
+ * @return GC_SUCCESS on success, error code on failure
+ */
+gc_return_code_t py_gc_destroy_instance(gc_instance_t handle);
+
+gc_return_code_t py_gc_init_h(gc_instance_t handle);
+gc_return_code_t py_gc_cleanup_h(gc_instance_t handle);
+gc_return_code_t py_gc_enable_h(gc_instance_t handle);
+gc_return_code_t py_gc_disable_h(gc_instance_t handle);
+int32_t py_gc_is_enabled_h(gc_instance_t handle);
+int32_t py_gc_is_initialized_h(gc_instance_t handle);
+gc_return_code_t py_gc_get_state_string_h(gc_instance_t handle, char* buffer, size_t buffer_size);
+gc_return_code_t py_gc_track_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_track_batch_h(gc_instance_t handle, void* const* objects, size_t count, size_t* out_tracked);
+gc_return_code_t py_gc_untrack_batch_h(gc_instance_t handle, void* const* objects, size_t count, size_t* out_untracked);
+gc_return_code_t py_gc_untrack_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_collect_generation_h(gc_instance_t handle, int32_t generation);
+gc_return_code_t py_gc_collect_h(gc_instance_t handle);
+gc_return_code_t py_gc_collect_generation_ex_h(gc_instance_t handle, int32_t generation, size_t* out_collected);
+gc_return_code_t py_gc_collect_ex_h(gc_instance_t handle, size_t* out_collected);
+int32_t py_gc_needs_collection_h(gc_instance_t handle);
+int32_t py_gc_needs_collection_generation_h(gc_instance_t handle);
+gc_return_code_t py_gc_collect_if_needed_h(gc_instance_t handle);
+int32_t py_gc_get_count_h(gc_instance_t handle);
+int32_t py_gc_get_generation_count_h(gc_instance_t handle, int32_t generation);
+int32_t py_gc_get_object_generation_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_freeze_h(gc_instance_t handle);
+gc_return_code_t py_gc_unfreeze_h(gc_instance_t handle);
+int32_t py_gc_get_freeze_count_h(gc_instance_t handle);
+gc_return_code_t py_gc_set_threshold_h(gc_instance_t handle, int32_t generation, int32_t threshold);
+int32_t py_gc_get_threshold_h(gc_instance_t handle, int32_t generation);
+gc_return_code_t py_gc_set_thresholds_h(gc_instance_t handle, int32_t threshold0, int32_t threshold1, int32_t threshold2);
+gc_return_code_t py_gc_get_thresholds_h(gc_instance_t handle, int32_t out[3]);
+gc_return_code_t py_gc_set_large_object_threshold_h(gc_instance_t handle, size_t threshold);
+size_t py_gc_get_large_object_threshold_h(gc_instance_t handle);
+gc_return_code_t py_gc_set_debug_h(gc_instance_t handle, int32_t flags);
+gc_return_code_t py_gc_get_stats_h(gc_instance_t handle, gc_stats_t* stats);
+gc_return_code_t py_gc_get_stats_extended_h(gc_instance_t handle, gc_stats_extended_t* stats, size_t size);
+gc_return_code_t py_gc_get_generation_stats_h(gc_instance_t handle, int32_t generation, gc_generation_stats_t* stats);
+gc_return_code_t py_gc_add_pre_collect_callback_h(gc_instance_t handle, gc_pre_collect_callback_t callback, void* user_data, uint64_t* out_hook_id);
+gc_return_code_t py_gc_remove_pre_collect_callback_h(gc_instance_t handle, uint64_t hook_id);
+int32_t py_gc_is_tracked_h(gc_instance_t handle, void* obj_ptr);
+int32_t py_gc_get_uncollectable_count_h(gc_instance_t handle);
+int32_t py_gc_get_registry_count_h(gc_instance_t handle);
+gc_return_code_t py_gc_clear_uncollectable_h(gc_instance_t handle);
+gc_return_code_t py_gc_clear_registry_h(gc_instance_t handle);
+gc_return_code_t py_gc_add_reference_h(gc_instance_t handle, void* from_obj, void* to_obj);
+gc_return_code_t py_gc_add_reference_named_h(gc_instance_t handle, void* from_obj, void* to_obj, const char* name);
+gc_return_code_t py_gc_add_reference_typed_h(gc_instance_t handle, void* from_obj, void* to_obj, int32_t ref_type);
+int32_t py_gc_get_reference_type_h(gc_instance_t handle, void* from_obj, void* to_obj);
+gc_return_code_t py_gc_add_references_batch_h(gc_instance_t handle, void* const* from_objects, void* const* to_objects, size_t count);
+gc_return_code_t py_gc_remove_reference_h(gc_instance_t handle, void* from_obj, void* to_obj);
+gc_return_code_t py_gc_break_cycle_h(gc_instance_t handle, void* obj_ptr, size_t* out_freed);
+gc_return_code_t py_gc_mark_uncollectable_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_unmark_uncollectable_h(gc_instance_t handle, void* obj_ptr);
+int32_t py_gc_is_uncollectable_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_get_tracked_info_h(gc_instance_t handle, void* obj_ptr, char* buffer, size_t buffer_size);
+gc_return_code_t py_gc_explain_object_h(gc_instance_t handle, void* obj_ptr, char* buffer, size_t buffer_size);
+gc_return_code_t py_gc_set_reference_history_h(gc_instance_t handle, size_t per_object, size_t max_objects);
+gc_return_code_t py_gc_get_reference_history_h(gc_instance_t handle, void* obj_ptr, size_t n, char* buffer, size_t buffer_size);
+gc_return_code_t py_gc_watch_object_h(gc_instance_t handle, void* obj_ptr, int32_t kind, size_t threshold, gc_watch_callback_t callback, void* user_data, uint64_t* out_watch_id);
+gc_return_code_t py_gc_unwatch_object_h(gc_instance_t handle, uint64_t watch_id);
+gc_return_code_t py_gc_dump_heap_h(gc_instance_t handle, const char* path);
+gc_return_code_t py_gc_dump_heap_tagged_h(gc_instance_t handle, const char* path, const char* tag);
+gc_return_code_t py_gc_set_tag_h(gc_instance_t handle, void* obj_ptr, const char* tag);
+gc_return_code_t py_gc_get_tag_stats_h(gc_instance_t handle, const char* tag, gc_tag_stats_t* stats);
+gc_return_code_t py_gc_debug_untrack_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_debug_state_h(gc_instance_t handle);
+gc_return_code_t py_gc_verify_h(gc_instance_t handle, size_t* out_violations);
+gc_return_code_t py_gc_audit_refcounts_h(gc_instance_t handle, size_t* out_discrepancies);
+gc_return_code_t py_gc_add_external_ref_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_release_external_ref_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_enable_automatic_tracking_h(gc_instance_t handle);
+gc_return_code_t py_gc_disable_automatic_tracking_h(gc_instance_t handle);
+int32_t py_gc_is_automatic_tracking_enabled_h(gc_instance_t handle);
+gc_return_code_t py_gc_object_created_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_object_destroyed_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_refcount_changed_h(gc_instance_t handle, void* obj_ptr, int32_t old_count, int32_t new_count);
+int32_t py_gc_get_refcount_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_set_refcount_h(gc_instance_t handle, void* obj_ptr, int32_t refcount);
+void* py_gc_get_objects_h(gc_instance_t handle);
+void* py_gc_get_referrers_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_iterate_objects_h(gc_instance_t handle, gc_visit_callback_t callback, void* user_data);
+gc_return_code_t py_gc_iterate_referrers_h(gc_instance_t handle, void* obj_ptr, gc_visit_callback_t callback, void* user_data);
+gc_return_code_t py_gc_iterate_referents_h(gc_instance_t handle, void* obj_ptr, gc_visit_callback_t callback, void* user_data);
+void* py_gc_get_objects_by_type_h(gc_instance_t handle, const char* type_name);
+void* py_gc_get_objects_by_tag_h(gc_instance_t handle, const char* tag);
+void* py_gc_get_referents_h(gc_instance_t handle, void* obj_ptr);
+void* py_gc_find_cycles_h(gc_instance_t handle);
+int32_t py_gc_count_cycles_h(gc_instance_t handle);
+int32_t py_gc_is_tracked_python_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_track_python_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_untrack_python_h(gc_instance_t handle, void* obj_ptr);
+int32_t* py_gc_get_collection_counts_h(gc_instance_t handle);
+void* py_gc_get_garbage_h(gc_instance_t handle);
+gc_return_code_t py_gc_set_garbage_h(gc_instance_t handle, void* garbage_list);
+void* py_gc_get_saveall_garbage_h(gc_instance_t handle);
+int32_t py_gc_get_saveall_garbage_count_h(gc_instance_t handle);
+gc_return_code_t py_gc_clear_saveall_garbage_h(gc_instance_t handle);
+gc_return_code_t py_gc_set_debug_flags_h(gc_instance_t handle, int32_t flags);
+int32_t py_gc_get_debug_flags_h(gc_instance_t handle);
+int32_t py_gc_has_finalizer_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_set_finalizer_h(gc_instance_t handle, void* obj_ptr, int32_t has_finalizer);
+int32_t py_gc_get_object_size_h(gc_instance_t handle, void* obj_ptr);
+gc_return_code_t py_gc_get_object_type_name_h(gc_instance_t handle, void* obj_ptr, char* buffer, size_t buffer_size);
+
 #ifdef __cplusplus
 }
 #endif
@@ -0,0 +1,275 @@
+"""Stand-in for the stdlib ``gc`` module backed by libpython_gc.
+
+    import rust_gc as gc
+
+The functions below keep the stdlib signatures so scripts can swap modules
+to benchmark this collector. Build the library and copy it next to this file
+with ``make python-shim``, or point ``PYTHON_GC_LIBRARY`` at it.
+
+Objects are only known to the collector once passed to ``track``; the shim
+keeps them alive until the collector reclaims them or ``untrack`` is called.
+References between tracked objects are discovered with the stdlib
+``gc.get_referents`` when an object is tracked. The native registry is
+per-thread, so use the shim from a single thread.
+"""
+
+import ctypes
+import gc as _stdlib_gc
+import os
+import sys
+
+__all__ = [
+    "enable", "disable", "isenabled", "collect", "get_count",
+    "get_threshold", "set_threshold", "get_debug", "set_debug",
+    "get_objects", "get_referrers", "get_referents", "get_stats",
+    "is_tracked", "freeze", "unfreeze", "get_freeze_count",
+    "track", "untrack", "garbage",
+    "DEBUG_STATS", "DEBUG_COLLECTABLE", "DEBUG_UNCOLLECTABLE",
+    "DEBUG_SAVEALL", "DEBUG_LEAK",
+]
+
+DEBUG_STATS = 1
+DEBUG_COLLECTABLE = 2
+DEBUG_UNCOLLECTABLE = 4
+DEBUG_SAVEALL = 32
+DEBUG_LEAK = DEBUG_COLLECTABLE | DEBUG_UNCOLLECTABLE | DEBUG_SAVEALL
+
+GC_SUCCESS = 0
+
+# Objects the collector could not free; kept for parity with gc.garbage.
+garbage = []
+
+_LIBRARY_NAMES = ("libpython_gc.so", "libpython_gc.dylib", "python_gc.dll")
+
+
+class _StatsExtended(ctypes.Structure):
+    _fields_ = [
+        ("size", ctypes.c_size_t),
+        ("total_tracked", ctypes.c_size_t),
+        ("generation_counts", ctypes.c_size_t * 3),
+        ("uncollectable", ctypes.c_size_t),
+        ("collections", ctypes.c_size_t * 3),
+        ("total_collected", ctypes.c_size_t),
+        ("last_pause_ns", ctypes.c_uint64),
+        ("thresholds", ctypes.c_size_t * 3),
+        ("reported_allocations", ctypes.c_size_t),
+        ("reported_frees", ctypes.c_size_t),
+        ("long_lived_total", ctypes.c_size_t),
+        ("long_lived_pending", ctypes.c_size_t),
+        ("collected", ctypes.c_size_t * 3),
+        ("large_object_count", ctypes.c_size_t),
+        ("large_object_bytes", ctypes.c_size_t),
+        ("stats_epoch", ctypes.c_uint64),
+        ("collection_in_progress", ctypes.c_size_t),
+        ("permanent_count", ctypes.c_size_t),
+        ("permanent_bytes", ctypes.c_size_t),
+        ("generation_bytes", ctypes.c_size_t * 3),
+        ("scanned", ctypes.c_size_t * 3),
+        ("promoted", ctypes.c_size_t * 3),
+    ]
+
+
+_VISIT = ctypes.CFUNCTYPE(ctypes.c_int32, ctypes.c_void_p, ctypes.c_void_p)
+
+
+def _load_library():
+    explicit = os.environ.get("PYTHON_GC_LIBRARY")
+    if explicit:
+        return ctypes.CDLL(explicit)
+
+    here = os.path.dirname(os.path.abspath(__file__))
+    search = (here, os.path.join(here, "..", "target", "release"))
+    for directory in search:
+        for name in _LIBRARY_NAMES:
+            path = os.path.join(directory, name)
+            if os.path.exists(path):
+                return ctypes.CDLL(path)
+    raise ImportError(
+        "libpython_gc not found; run `make python-shim` or set PYTHON_GC_LIBRARY"
+    )
+
+
+def _declare(lib):
+    void_p, i32, code = ctypes.c_void_p, ctypes.c_int32, ctypes.c_int32
+    signatures = {
+        "py_gc_init": ([], code),
+        "py_gc_enable": ([], code),
+        "py_gc_disable": ([], code),
+        "py_gc_is_enabled": ([], i32),
+        "py_gc_collect_generation_ex": ([i32, ctypes.POINTER(ctypes.c_size_t)], code),
+        "py_gc_get_generation_count": ([i32], i32),
+        "py_gc_set_thresholds": ([i32, i32, i32], code),
+        "py_gc_get_thresholds": ([ctypes.POINTER(i32)], code),
+        "py_gc_set_debug": ([i32], code),
+        "py_gc_get_debug_flags": ([], i32),
+        "py_gc_get_stats_extended": ([ctypes.POINTER(_StatsExtended), ctypes.c_size_t], code),
+        "py_gc_track_python": ([void_p], code),
+        "py_gc_untrack_python": ([void_p], code),
+        "py_gc_is_tracked": ([void_p], i32),
+        "py_gc_get_object_generation": ([void_p], i32),
+        "py_gc_freeze": ([], code),
+        "py_gc_unfreeze": ([], code),
+        "py_gc_get_freeze_count": ([], i32),
+        "py_gc_set_refcount": ([void_p, i32], code),
+        "py_gc_add_reference": ([void_p, void_p], code),
+        "py_gc_iterate_objects": ([_VISIT, void_p], code),
+        "py_gc_iterate_referrers": ([void_p, _VISIT, void_p], code),
+        "py_gc_iterate_referents": ([void_p, _VISIT, void_p], code),
+        "py_gc_get_last_error": ([ctypes.c_char_p, ctypes.c_size_t], code),
+    }
+    for name, (argtypes, restype) in signatures.items():
+        function = getattr(lib, name)
+        function.argtypes = argtypes
+        function.restype = restype
+
+
+_lib = _load_library()
+_declare(_lib)
+_lib.py_gc_init()
+
+# id -> object for everything tracked, so tracked pointers stay valid.
+_tracked = {}
+
+
+def _check(result):
+    if result != GC_SUCCESS:
+        buffer = ctypes.create_string_buffer(256)
+        _lib.py_gc_get_last_error(buffer, len(buffer))
+        raise RuntimeError(buffer.value.decode(errors="replace") or f"error {result}")
+
+
+def _collect_ids(iterate, *args):
+    ids = []
+
+    def visit(obj_ptr, _user_data):
+        ids.append(obj_ptr)
+        return 0
+
+    _check(iterate(*args, _VISIT(visit), None))
+    return [_tracked[obj_id] for obj_id in ids if obj_id in _tracked]
+
+
+def enable():
+    _check(_lib.py_gc_enable())
+
+
+def disable():
+    _check(_lib.py_gc_disable())
+
+
+def isenabled():
+    return bool(_lib.py_gc_is_enabled())
+
+
+def collect(generation=2):
+    if generation not in (0, 1, 2):
+        raise ValueError("invalid generation")
+
+    # The native collector sees no Python references, so mirror each
+    # object's refcount minus the references held by the shim itself.
+    # Held here by _tracked, obj and getrefcount's own argument.
+    for obj_id in list(_tracked):
+        obj = _tracked[obj_id]
+        _check(_lib.py_gc_set_refcount(obj_id, max(sys.getrefcount(obj) - 3, 0)))
+    obj = None
+
+    collected = ctypes.c_size_t(0)
+    _check(_lib.py_gc_collect_generation_ex(generation, ctypes.byref(collected)))
+    for obj_id in [obj_id for obj_id in _tracked if not _lib.py_gc_is_tracked(obj_id)]:
+        del _tracked[obj_id]
+    return collected.value
+
+
+def get_count():
+    return tuple(_lib.py_gc_get_generation_count(generation) for generation in range(3))
+
+
+def get_threshold():
+    thresholds = (ctypes.c_int32 * 3)()
+    _check(_lib.py_gc_get_thresholds(thresholds))
+    return tuple(thresholds)
+
+
+def set_threshold(threshold0, threshold1=None, threshold2=None):
+    optional = (lambda value: -1 if value is None else value)
+    _check(_lib.py_gc_set_thresholds(threshold0, optional(threshold1), optional(threshold2)))
+
+
+def get_debug():
+    return _lib.py_gc_get_debug_flags()
+
+
+def set_debug(flags):
+    _check(_lib.py_gc_set_debug(flags))
+
+
+def get_objects(generation=None):
+    if generation is not None and generation not in (0, 1, 2):
+        raise ValueError("generation parameter must be 0, 1 or 2")
+    objects = _collect_ids(_lib.py_gc_iterate_objects)
+    if generation is None:
+        return objects
+    return [obj for obj in objects if _lib.py_gc_get_object_generation(id(obj)) == generation]
+
+
+def get_referrers(*objs):
+    referrers = []
+    for obj in objs:
+        referrers.extend(_collect_ids(_lib.py_gc_iterate_referrers, id(obj)))
+    return referrers
+
+
+def get_referents(*objs):
+    referents = []
+    for obj in objs:
+        referents.extend(_collect_ids(_lib.py_gc_iterate_referents, id(obj)))
+    return referents
+
+
+def get_stats():
+    stats = _StatsExtended()
+    _check(_lib.py_gc_get_stats_extended(ctypes.byref(stats), ctypes.sizeof(stats)))
+    # Objects with finalizers are set aside when tracked rather than found
+    # by a collection, so no generation reports any as uncollectable.
+    return [
+        {
+            "collections": stats.collections[generation],
+            "collected": stats.collected[generation],
+            "uncollectable": 0,
+        }
+        for generation in range(3)
+    ]
+
+
+def is_tracked(obj):
+    return bool(_lib.py_gc_is_tracked(id(obj)))
+
+
+def freeze():
+    _check(_lib.py_gc_freeze())
+
+
+def unfreeze():
+    _check(_lib.py_gc_unfreeze())
+
+
+def get_freeze_count():
+    return _lib.py_gc_get_freeze_count()
+
+
+def track(obj):
+    """Hand ``obj`` to the collector. Not part of the stdlib module."""
+    _check(_lib.py_gc_track_python(id(obj)))
+    _tracked[id(obj)] = obj
+    for referent in _stdlib_gc.get_referents(obj):
+        if id(referent) in _tracked:
+            _check(_lib.py_gc_add_reference(id(obj), id(referent)))
+    for other_id, other in _tracked.items():
+        if other_id != id(obj) and any(r is obj for r in _stdlib_gc.get_referents(other)):
+            _check(_lib.py_gc_add_reference(other_id, id(obj)))
+
+
+def untrack(obj):
+    """Take ``obj`` away from the collector. Not part of the stdlib module."""
+    _check(_lib.py_gc_untrack_python(id(obj)))
+    _tracked.pop(id(obj), None)
@@ -0,0 +1,624 @@
+//! Pooled storage for object payloads. Payload bytes live in cells of
+//! fixed-size arenas, one cell size per arena, and objects reach them through
+//! a [`GcHandle`] instead of an address. Compaction can then move cells out of
+//! sparse arenas into dense ones and free the arenas it empties.
+
+use crate::GCResult;
+use crate::error::GCError;
+use parking_lot::{Mutex, MutexGuard};
+use std::cmp::Reverse;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+
+/// Size of an arena of pooled cells.
+pub const ARENA_BYTES: usize = 64 * 1024;
+
+/// Smallest and largest pooled cell. Cell sizes are the powers of two in
+/// between; a larger payload gets an arena of its own.
+pub const MIN_CELL: usize = 16;
+pub const MAX_CELL: usize = 4096;
+
+const CLASSES: usize = (MAX_CELL.trailing_zeros() - MIN_CELL.trailing_zeros() + 1) as usize;
+
+/// Owner of a cell that holds nothing.
+const VACANT: u32 = u32::MAX;
+
+/// Indirect reference to a pooled payload. Compaction moves the payload and
+/// updates the table the handle indexes, so the handle stays valid. Freeing
+/// the payload moves its table entry to the next generation, and the handle
+/// then resolves to nothing even once the entry is reused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GcHandle {
+    index: u32,
+    generation: u32,
+}
+
+impl GcHandle {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// How much of the arenas' memory holds live payloads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FragmentationStats {
+    pub arenas: usize,
+    /// Bytes the arenas hold, in use or not.
+    pub committed_bytes: usize,
+    /// Bytes of the cells in use.
+    pub live_bytes: usize,
+    pub free_bytes: usize,
+    /// Arenas a compaction would empty and free, and their size.
+    pub reclaimable_arenas: usize,
+    pub reclaimable_bytes: usize,
+}
+
+impl FragmentationStats {
+    /// Share of the committed bytes that sit in free cells; 0 without arenas.
+    pub fn fragmentation(&self) -> f64 {
+        if self.committed_bytes == 0 {
+            return 0.0;
+        }
+        self.free_bytes as f64 / self.committed_bytes as f64
+    }
+}
+
+/// What compaction did, for one run or summed over all of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CompactionStats {
+    pub compactions: usize,
+    pub cells_moved: usize,
+    pub bytes_moved: usize,
+    pub arenas_released: usize,
+    pub bytes_released: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Place {
+    arena: usize,
+    cell: u32,
+    len: u32,
+}
+
+#[derive(Debug)]
+struct HandleEntry {
+    generation: u32,
+    place: Option<Place>,
+}
+
+struct Arena {
+    /// Size class, or `None` for an arena holding one large payload.
+    class: Option<usize>,
+    cell_size: usize,
+    bytes: Box<[u8]>,
+    /// Handle table index of each cell's payload, `VACANT` for free cells.
+    owners: Vec<u32>,
+    free: Vec<u32>,
+}
+
+impl Arena {
+    fn new(class: Option<usize>, cell_size: usize, cells: usize) -> Self {
+        Self {
+            class,
+            cell_size,
+            bytes: vec![0; cell_size * cells].into_boxed_slice(),
+            owners: vec![VACANT; cells],
+            free: (0..cells as u32).rev().collect(),
+        }
+    }
+
+    fn live(&self) -> usize {
+        self.owners.len() - self.free.len()
+    }
+
+    fn cell(&self, cell: u32, len: usize) -> &[u8] {
+        let start = cell as usize * self.cell_size;
+        &self.bytes[start..start + len]
+    }
+
+    fn cell_mut(&mut self, cell: u32, len: usize) -> &mut [u8] {
+        let start = cell as usize * self.cell_size;
+        &mut self.bytes[start..start + len]
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Arena")
+            .field("class", &self.class)
+            .field("cell_size", &self.cell_size)
+            .field("cells", &self.owners.len())
+            .field("live", &self.live())
+            .finish()
+    }
+}
+
+fn class_of(len: usize) -> Option<usize> {
+    (len <= MAX_CELL).then(|| {
+        (len.max(MIN_CELL).next_power_of_two().trailing_zeros() - MIN_CELL.trailing_zeros())
+            as usize
+    })
+}
+
+fn class_size(class: usize) -> usize {
+    MIN_CELL << class
+}
+
+/// Arenas and the handle table that indexes their cells. Arenas emptied by
+/// frees are kept for reuse until a compaction frees them.
+#[derive(Debug, Default)]
+pub struct ArenaHeap {
+    arenas: Vec<Option<Arena>>,
+    /// Positions in `arenas` whose arena was freed, for reuse.
+    spare: Vec<usize>,
+    /// Arenas of each size class with a free cell, fullest first, so an
+    /// allocation finds its arena without scanning the others.
+    roomy: [BTreeSet<(Reverse<usize>, usize)>; CLASSES],
+    table: Vec<HandleEntry>,
+    free_handles: Vec<u32>,
+    stats: CompactionStats,
+}
+
+impl ArenaHeap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate a zeroed payload of `len` bytes. Small payloads go to the
+    /// fullest arena of their size class with room, so sparse arenas drain.
+    pub fn alloc(&mut self, len: usize) -> GCResult<GcHandle> {
+        let len = u32::try_from(len).map_err(|_| {
+            GCError::AllocationFailed(format!("pooled payload of {len} bytes is too large"))
+        })?;
+        // Reserve the handle first: past this point nothing fails, so no
+        // cell or arena is taken for an allocation that doesn't happen.
+        let index = match self.free_handles.pop() {
+            Some(index) => index,
+            None => {
+                let index = u32::try_from(self.table.len())
+                    .ok()
+                    .filter(|&index| index != VACANT)
+                    .ok_or_else(|| GCError::AllocationFailed("handle table is full".into()))?;
+                self.table.push(HandleEntry {
+                    generation: 0,
+                    place: None,
+                });
+                index
+            }
+        };
+
+        let (arena, cell) = match class_of(len as usize) {
+            Some(class) => {
+                let arena = match self.roomy[class].first() {
+                    Some(&(_, arena)) => arena,
+                    None => {
+                        let size = class_size(class);
+                        self.add_arena(Arena::new(Some(class), size, ARENA_BYTES / size))
+                    }
+                };
+                self.unlist(arena);
+                let cell = self.arenas[arena]
+                    .as_mut()
+                    .and_then(|arena| arena.free.pop())
+                    .expect("chosen arena has a free cell");
+                self.list(arena);
+                (arena, cell)
+            }
+            None => (self.add_arena(Arena::new(None, len as usize, 1)), 0),
+        };
+
+        let entry = &mut self.table[index as usize];
+        entry.place = Some(Place { arena, cell, len });
+        let handle = GcHandle {
+            index,
+            generation: entry.generation,
+        };
+
+        let arena = self.arenas[arena].as_mut().expect("chosen arena is live");
+        arena.owners[cell as usize] = index;
+        arena.cell_mut(cell, len as usize).fill(0);
+        Ok(handle)
+    }
+
+    /// Free the payload behind `handle`. A large payload's arena is freed with
+    /// it. Returns false for a stale handle.
+    pub fn free(&mut self, handle: GcHandle) -> bool {
+        let Some(place) = self.take_place(handle) else {
+            return false;
+        };
+        self.unlist(place.arena);
+        let arena = self.arenas[place.arena]
+            .as_mut()
+            .expect("a live handle points into a live arena");
+        arena.owners[place.cell as usize] = VACANT;
+        arena.free.push(place.cell);
+        if arena.class.is_none() {
+            self.remove_arena(place.arena);
+        } else {
+            self.list(place.arena);
+        }
+        true
+    }
+
+    /// Key of the arena at `slot` in its class's `roomy` set.
+    fn roomy_key(&self, slot: usize) -> Option<(usize, (Reverse<usize>, usize))> {
+        let arena = self.arenas[slot].as_ref()?;
+        Some((arena.class?, (Reverse(arena.live()), slot)))
+    }
+
+    /// Take the arena at `slot` off its class's `roomy` set, before its live
+    /// count changes.
+    fn unlist(&mut self, slot: usize) {
+        if let Some((class, key)) = self.roomy_key(slot) {
+            self.roomy[class].remove(&key);
+        }
+    }
+
+    /// Put the arena at `slot` back in its class's `roomy` set if it has a
+    /// free cell.
+    fn list(&mut self, slot: usize) {
+        if let Some((class, key)) = self.roomy_key(slot)
+            && self.arenas[slot]
+                .as_ref()
+                .is_some_and(|a| !a.free.is_empty())
+        {
+            self.roomy[class].insert(key);
+        }
+    }
+
+    fn take_place(&mut self, handle: GcHandle) -> Option<Place> {
+        let entry = self.table.get_mut(handle.index as usize)?;
+        if entry.generation != handle.generation {
+            return None;
+        }
+        let place = entry.place.take()?;
+        // An entry whose generation can't advance is retired, so no later
+        // payload is reached through an old handle.
+        if let Some(next) = entry.generation.checked_add(1) {
+            entry.generation = next;
+            self.free_handles.push(handle.index);
+        }
+        Some(place)
+    }
+
+    fn place(&self, handle: GcHandle) -> Option<Place> {
+        let entry = self.table.get(handle.index as usize)?;
+        (entry.generation == handle.generation)
+            .then_some(entry.place)
+            .flatten()
+    }
+
+    pub fn contains(&self, handle: GcHandle) -> bool {
+        self.place(handle).is_some()
+    }
+
+    pub fn get(&self, handle: GcHandle) -> Option<&[u8]> {
+        let place = self.place(handle)?;
+        let arena = self.arenas[place.arena].as_ref()?;
+        Some(arena.cell(place.cell, place.len as usize))
+    }
+
+    pub fn get_mut(&mut self, handle: GcHandle) -> Option<&mut [u8]> {
+        let place = self.place(handle)?;
+        let arena = self.arenas[place.arena].as_mut()?;
+        Some(arena.cell_mut(place.cell, place.len as usize))
+    }
+
+    fn add_arena(&mut self, arena: Arena) -> usize {
+        match self.spare.pop() {
+            Some(slot) => {
+                self.arenas[slot] = Some(arena);
+                slot
+            }
+            None => {
+                self.arenas.push(Some(arena));
+                self.arenas.len() - 1
+            }
+        }
+    }
+
+    fn remove_arena(&mut self, slot: usize) -> Option<Arena> {
+        let arena = self.arenas[slot].take()?;
+        self.spare.push(slot);
+        Some(arena)
+    }
+
+    /// Live cells and arenas of each size class.
+    fn class_usage(&self) -> [(usize, usize); CLASSES] {
+        let mut usage = [(0, 0); CLASSES];
+        for arena in self.arenas.iter().flatten() {
+            if let Some(class) = arena.class {
+                usage[class].0 += arena.live();
+                usage[class].1 += 1;
+            }
+        }
+        usage
+    }
+
+    pub fn fragmentation(&self) -> FragmentationStats {
+        let mut stats = FragmentationStats::default();
+        for arena in self.arenas.iter().flatten() {
+            stats.arenas += 1;
+            stats.committed_bytes += arena.bytes.len();
+            stats.live_bytes += arena.live() * arena.cell_size;
+        }
+        stats.free_bytes = stats.committed_bytes - stats.live_bytes;
+        for (class, (live, arenas)) in self.class_usage().into_iter().enumerate() {
+            let needed = live.div_ceil(ARENA_BYTES / class_size(class));
+            stats.reclaimable_arenas += arenas - needed;
+            stats.reclaimable_bytes += (arenas - needed) * ARENA_BYTES;
+        }
+        stats
+    }
+
+    /// Move the payloads of each size class into as few arenas as hold them,
+    /// keeping the fullest, and free the arenas left empty. Handles follow
+    /// their payloads.
+    pub fn compact(&mut self) -> CompactionStats {
+        let mut run = CompactionStats {
+            compactions: 1,
+            ..CompactionStats::default()
+        };
+        for (class, (live, _)) in self.class_usage().into_iter().enumerate() {
+            let mut members: Vec<usize> = self
+                .arenas
+                .iter()
+                .enumerate()
+                .filter(|(_, arena)| arena.as_ref().is_some_and(|a| a.class == Some(class)))
+                .map(|(i, _)| i)
+                .collect();
+            members.sort_by_key(|&i| (Reverse(self.arenas[i].as_ref().map_or(0, Arena::live)), i));
+            let keep = live.div_ceil(ARENA_BYTES / class_size(class));
+            let (kept, drained) = members.split_at(keep);
+            self.roomy[class].clear();
+
+            for &slot in drained {
+                let source = self.remove_arena(slot).expect("class member is live");
+                for (cell, &owner) in source.owners.iter().enumerate() {
+                    if owner == VACANT {
+                        continue;
+                    }
+                    let (target, to) = kept
+                        .iter()
+                        .find_map(|&i| {
+                            let arena = self.arenas[i].as_mut()?;
+                            let to = arena.free.pop()?;
+                            Some((i, to))
+                        })
+                        .expect("kept arenas have room for every live cell");
+                    let place = self.table[owner as usize]
+                        .place
+                        .as_mut()
+                        .expect("an owned cell has a live handle");
+                    let len = place.len as usize;
+                    let arena = self.arenas[target].as_mut().expect("kept arena is live");
+                    arena
+                        .cell_mut(to, len)
+                        .copy_from_slice(source.cell(cell as u32, len));
+                    arena.owners[to as usize] = owner;
+                    place.arena = target;
+                    place.cell = to;
+                    run.cells_moved += 1;
+                    run.bytes_moved += len;
+                }
+                run.arenas_released += 1;
+                run.bytes_released += source.bytes.len();
+            }
+            for &slot in kept {
+                self.list(slot);
+            }
+        }
+        while self.arenas.last().is_some_and(Option::is_none) {
+            self.arenas.pop();
+        }
+        let len = self.arenas.len();
+        self.spare.retain(|&slot| slot < len);
+
+        self.stats.compactions += run.compactions;
+        self.stats.cells_moved += run.cells_moved;
+        self.stats.bytes_moved += run.bytes_moved;
+        self.stats.arenas_released += run.arenas_released;
+        self.stats.bytes_released += run.bytes_released;
+        run
+    }
+
+    /// Compaction work summed over every run.
+    pub fn stats(&self) -> CompactionStats {
+        self.stats
+    }
+}
+
+/// An [`ArenaHeap`] shared by a collector and the objects whose payloads it
+/// holds. A dropped object that finds the heap locked leaves its handle for
+/// the next holder of the lock to free, so dropping one while working on a
+/// payload doesn't deadlock.
+#[derive(Debug, Default)]
+pub struct ArenaPool {
+    heap: Mutex<ArenaHeap>,
+    orphaned: Mutex<Vec<GcHandle>>,
+}
+
+impl ArenaPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, ArenaHeap> {
+        let mut heap = self.heap.lock();
+        for handle in self.orphaned.lock().drain(..) {
+            heap.free(handle);
+        }
+        heap
+    }
+
+    fn release(&self, handle: GcHandle) {
+        match self.heap.try_lock() {
+            Some(mut heap) => {
+                heap.free(handle);
+            }
+            None => self.orphaned.lock().push(handle),
+        }
+    }
+}
+
+/// An object's pooled payload, freed when the object is.
+#[derive(Debug)]
+pub(crate) struct PooledCell {
+    pool: Arc<ArenaPool>,
+    handle: GcHandle,
+    len: usize,
+}
+
+impl PooledCell {
+    pub(crate) fn new(pool: Arc<ArenaPool>, handle: GcHandle, len: usize) -> Self {
+        Self { pool, handle, len }
+    }
+
+    pub(crate) fn handle(&self) -> GcHandle {
+        self.handle
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Run `f` on a copy of the payload and store the copy back. The pool is
+    /// unlocked while `f` runs, so `f` may allocate, free or compact.
+    pub(crate) fn with<R>(&self, f: impl FnOnce(&mut [u8]) -> R) -> Option<R> {
+        let mut bytes = self.pool.lock().get(self.handle)?.to_vec();
+        let result = f(&mut bytes);
+        if let Some(cell) = self.pool.lock().get_mut(self.handle) {
+            cell.copy_from_slice(&bytes);
+        }
+        Some(result)
+    }
+}
+
+impl Drop for PooledCell {
+    fn drop(&mut self) {
+        self.pool.release(self.handle);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_size_classes() {
+        assert_eq!(class_of(0), Some(0));
+        assert_eq!(class_of(16), Some(0));
+        assert_eq!(class_of(17), Some(1));
+        assert_eq!(class_of(MAX_CELL), Some(CLASSES - 1));
+        assert_eq!(class_of(MAX_CELL + 1), None);
+    }
+
+    #[test]
+    fn test_stale_handle_resolves_to_nothing() {
+        let mut heap = ArenaHeap::new();
+        let handle = heap.alloc(8).unwrap();
+        heap.get_mut(handle).unwrap().copy_from_slice(b"payload!");
+        assert!(heap.free(handle));
+        assert!(!heap.free(handle));
+
+        let reused = heap.alloc(8).unwrap();
+        assert_eq!(reused.index(), handle.index());
+        assert_ne!(reused, handle);
+        assert_eq!(heap.get(handle), None);
+        assert_eq!(heap.get(reused), Some(&[0; 8][..]));
+    }
+
+    #[test]
+    fn test_compaction_packs_cells_and_frees_arenas() {
+        let mut heap = ArenaHeap::new();
+        let per_arena = ARENA_BYTES / 64;
+        let handles: Vec<_> = (0..per_arena * 4)
+            .map(|i| {
+                let handle = heap.alloc(64).unwrap();
+                heap.get_mut(handle).unwrap().fill(i as u8);
+                (i, handle)
+            })
+            .collect();
+        // Keep every fourth payload, a quarter of each arena.
+        let (kept, dropped): (Vec<_>, Vec<_>) = handles.into_iter().partition(|(i, _)| i % 4 == 0);
+        for (_, handle) in dropped {
+            heap.free(handle);
+        }
+
+        let before = heap.fragmentation();
+        assert_eq!(before.arenas, 4);
+        assert_eq!(before.live_bytes, per_arena * 64);
+        assert_eq!(before.reclaimable_arenas, 3);
+        assert!((before.fragmentation() - 0.75).abs() < 1e-9);
+
+        let run = heap.compact();
+        assert_eq!(run.arenas_released, 3);
+        assert_eq!(run.bytes_released, 3 * ARENA_BYTES);
+        assert_eq!(run.cells_moved, per_arena * 3 / 4);
+
+        let after = heap.fragmentation();
+        assert_eq!(after.arenas, 1);
+        assert_eq!(after.free_bytes, 0);
+        assert_eq!(after.reclaimable_arenas, 0);
+        for (i, handle) in kept {
+            assert!(heap.get(handle).unwrap().iter().all(|&b| b == i as u8));
+        }
+        assert_eq!(heap.stats().compactions, 1);
+    }
+
+    #[test]
+    fn test_allocation_fills_the_fullest_arena_first() {
+        let mut heap = ArenaHeap::new();
+        let per_arena = ARENA_BYTES / 64;
+        let handles: Vec<_> = (0..per_arena * 3)
+            .map(|_| heap.alloc(64).unwrap())
+            .collect();
+        // Arena 0 keeps all but one cell, arena 1 half, arena 2 none.
+        heap.free(handles[0]);
+        for &handle in &handles[per_arena..per_arena * 5 / 2] {
+            heap.free(handle);
+        }
+        for &handle in &handles[per_arena * 2..] {
+            heap.free(handle);
+        }
+        let first = heap.alloc(64).unwrap();
+        assert_eq!(heap.place(first).unwrap().arena, 0);
+        let second = heap.alloc(64).unwrap();
+        assert_eq!(heap.place(second).unwrap().arena, 1);
+
+        heap.compact();
+        assert_eq!(heap.fragmentation().arenas, 2);
+        let handle = heap.alloc(64).unwrap();
+        assert_eq!(heap.fragmentation().arenas, 2);
+        assert!(heap.contains(handle));
+    }
+
+    #[test]
+    fn test_large_payload_gets_its_own_arena() {
+        let mut heap = ArenaHeap::new();
+        let handle = heap.alloc(MAX_CELL * 3).unwrap();
+        assert_eq!(heap.get(handle).unwrap().len(), MAX_CELL * 3);
+        assert_eq!(heap.fragmentation().arenas, 1);
+        heap.free(handle);
+        assert_eq!(heap.fragmentation().arenas, 0);
+    }
+
+    #[test]
+    fn test_drop_under_lock_is_deferred() {
+        let pool = Arc::new(ArenaPool::new());
+        let handle = pool.lock().alloc(32).unwrap();
+        let cell = PooledCell::new(pool.clone(), handle, 32);
+        let heap = pool.lock();
+        drop(cell);
+        assert!(heap.contains(handle));
+        drop(heap);
+        assert!(!pool.lock().contains(handle));
+    }
+}
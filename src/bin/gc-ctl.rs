@@ -0,0 +1,56 @@
+//! Client for the collector control socket.
+//!
+//! `gc-ctl <socket> <command> [args...]` sends one command to a process that
+//! called `GarbageCollector::serve_control_socket` and prints the response.
+//! `gc-ctl <socket> snapshot <file>` saves a heap snapshot that the snapshot
+//! `Analyzer` can read.
+
+use python_gc::control;
+use std::process::ExitCode;
+
+const USAGE: &str = "\
+Usage: gc-ctl <socket> <command> [args...]
+
+Commands:
+  collect [generation]     Run a collection (full by default)
+  stats                    Print collector statistics
+  thresholds [t0 t1 t2]    Print, or set then print, generation thresholds
+  summary                  Print tracked object counts by type
+  snapshot <file>          Save a heap snapshot to <file>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [socket, command, rest @ ..] = args.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+
+    let (request, output) = match (command.as_str(), rest) {
+        ("snapshot", [file]) => ("snapshot".to_string(), Some(file)),
+        ("snapshot", _) => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+        _ => (args[1..].join(" "), None),
+    };
+
+    let body = match control::request(socket, &request) {
+        Ok(body) => body,
+        Err(err) => {
+            eprintln!("gc-ctl: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match output {
+        Some(file) => {
+            if let Err(err) = std::fs::write(file, &body) {
+                eprintln!("gc-ctl: cannot write {file}: {err}");
+                return ExitCode::FAILURE;
+            }
+            println!("wrote {} bytes to {file}", body.len());
+        }
+        None => println!("{}", String::from_utf8_lossy(&body)),
+    }
+    ExitCode::SUCCESS
+}
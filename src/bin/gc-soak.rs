@@ -0,0 +1,225 @@
+//! Long-running soak test for the collector.
+//!
+//! Worker threads allocate, mutate and drop objects against one shared
+//! `GarbageCollector` while the main thread periodically prints stats and
+//! verifies heap integrity. Exits non-zero on the first integrity violation.
+
+use python_gc::GarbageCollector;
+use python_gc::object::{ObjectData, ObjectId, PyObject};
+use python_gc::testing::TestRng;
+use std::collections::VecDeque;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+Usage: gc-soak [OPTIONS]
+
+Options:
+  --objects <N>          Live objects kept per thread [default: 10000]
+  --mutation-rate <P>    Chance per step of dropping a random live object [default: 0.1]
+  --cycle-ratio <P>      Chance per step of allocating a two-object cycle [default: 0.2]
+  --threads <N>          Worker threads [default: 4]
+  --duration <SECS>      How long to run, 0 for forever [default: 60]
+  --report-interval <SECS>
+                         Seconds between stats reports [default: 10]
+  --seed <N>             Base RNG seed [default: 0]
+  -h, --help             Print this help";
+
+#[derive(Debug, Clone)]
+struct Config {
+    objects: usize,
+    mutation_rate: f64,
+    cycle_ratio: f64,
+    threads: usize,
+    duration: Option<Duration>,
+    report_interval: Duration,
+    seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            objects: 10_000,
+            mutation_rate: 0.1,
+            cycle_ratio: 0.2,
+            threads: 4,
+            duration: Some(Duration::from_secs(60)),
+            report_interval: Duration::from_secs(10),
+            seed: 0,
+        }
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Config>, String> {
+    fn value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+        let value = value.ok_or_else(|| format!("{flag} needs a value"))?;
+        value
+            .parse()
+            .map_err(|_| format!("invalid value for {flag}: {value}"))
+    }
+
+    let mut config = Config::default();
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--objects" => config.objects = value(&flag, args.next())?,
+            "--mutation-rate" => config.mutation_rate = value(&flag, args.next())?,
+            "--cycle-ratio" => config.cycle_ratio = value(&flag, args.next())?,
+            "--threads" => config.threads = value(&flag, args.next())?,
+            "--duration" => {
+                let secs: u64 = value(&flag, args.next())?;
+                config.duration = (secs > 0).then(|| Duration::from_secs(secs));
+            }
+            "--report-interval" => {
+                config.report_interval = Duration::from_secs(value(&flag, args.next())?);
+            }
+            "--seed" => config.seed = value(&flag, args.next())?,
+            "-h" | "--help" => return Ok(None),
+            _ => return Err(format!("unknown option: {flag}")),
+        }
+    }
+
+    if config.threads == 0 || config.objects == 0 {
+        return Err("--threads and --objects must be at least 1".to_string());
+    }
+    if config.report_interval.is_zero() {
+        return Err("--report-interval must be at least 1".to_string());
+    }
+    Ok(Some(config))
+}
+
+fn allocate(gc: &GarbageCollector, rng: &mut TestRng, config: &Config) -> Vec<ObjectId> {
+    if rng.chance(config.cycle_ratio) {
+        // Each half is held by the other and by this worker's live list.
+        let a = PyObject::new("cycle".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("cycle".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        a.set_refcount(2);
+        b.set_refcount(2);
+        let ids = vec![a.id(), b.id()];
+        gc.track(a).ok();
+        gc.track(b).ok();
+        ids
+    } else {
+        let obj = PyObject::new(
+            "leaf".to_string(),
+            ObjectData::Integer(rng.next_u64() as i64),
+        );
+        let obj_id = obj.id();
+        gc.track(obj).ok();
+        vec![obj_id]
+    }
+}
+
+fn worker(
+    gc: Arc<GarbageCollector>,
+    config: Config,
+    seed: u64,
+    stop: Arc<AtomicBool>,
+    steps: Arc<AtomicUsize>,
+) {
+    let mut rng = TestRng::new(seed);
+    let mut live: VecDeque<ObjectId> = VecDeque::with_capacity(config.objects);
+
+    while !stop.load(Ordering::Relaxed) {
+        live.extend(allocate(&gc, &mut rng, &config));
+
+        if !live.is_empty() && rng.chance(config.mutation_rate) {
+            let index = rng.below(live.len());
+            if let Some(obj_id) = live.swap_remove_back(index) {
+                gc.decref(&obj_id).ok();
+            }
+        }
+
+        while live.len() > config.objects {
+            if let Some(obj_id) = live.pop_front() {
+                gc.decref(&obj_id).ok();
+            }
+        }
+
+        gc.collect_if_needed().ok();
+        steps.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+fn report(gc: &GarbageCollector, started: Instant, steps: usize) -> bool {
+    let stats = gc.get_stats();
+    println!(
+        "[{:>8.1}s] steps={} tracked={} gens={:?} collections={} collected={} uncollectable={} last_pause={:?}",
+        started.elapsed().as_secs_f64(),
+        steps,
+        stats.total_tracked,
+        stats.generation_counts,
+        stats.collections,
+        stats.collected,
+        stats.uncollectable,
+        stats.last_pause,
+    );
+
+    let violations = gc.verify_heap();
+    for violation in &violations {
+        eprintln!("integrity violation: {violation}");
+    }
+    violations.is_empty()
+}
+
+fn main() -> ExitCode {
+    let config = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(config)) => config,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("error: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    println!("gc-soak: {config:?}");
+
+    let gc = Arc::new(GarbageCollector::new());
+    let stop = Arc::new(AtomicBool::new(false));
+    let steps = Arc::new(AtomicUsize::new(0));
+    let workers: Vec<_> = (0..config.threads as u64)
+        .map(|index| {
+            let seed = config.seed.wrapping_add(index);
+            let (gc, config, stop, steps) =
+                (gc.clone(), config.clone(), stop.clone(), steps.clone());
+            thread::spawn(move || worker(gc, config, seed, stop, steps))
+        })
+        .collect();
+
+    let started = Instant::now();
+    let mut healthy = true;
+    while healthy {
+        let remaining = config
+            .duration
+            .map(|duration| duration.saturating_sub(started.elapsed()));
+        if remaining.is_some_and(|remaining| remaining.is_zero()) {
+            break;
+        }
+
+        let pause = remaining.map_or(config.report_interval, |remaining| {
+            remaining.min(config.report_interval)
+        });
+        thread::sleep(pause);
+        healthy = report(&gc, started, steps.load(Ordering::Relaxed));
+    }
+
+    stop.store(true, Ordering::Relaxed);
+    for handle in workers {
+        if handle.join().is_err() {
+            healthy = false;
+        }
+    }
+
+    if healthy {
+        println!("gc-soak: finished without integrity violations");
+        ExitCode::SUCCESS
+    } else {
+        eprintln!("gc-soak: heap integrity check failed");
+        ExitCode::FAILURE
+    }
+}
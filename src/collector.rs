@@ -1,8 +1,21 @@
 use crate::GCResult;
+use crate::clock::{Clock, SystemClock};
 use crate::error::GCError;
-use crate::generation::GenerationManager;
-use crate::object::{ObjectId, PyObject};
-use std::collections::{HashMap, HashSet};
+use crate::gc::{DEBUG_AUDIT_REFS, DEBUG_SAVEALL, DEBUG_VERIFY_BARRIERS};
+use crate::generation::{CollectionGuard, GenerationManager};
+use crate::object::{ObjectData, ObjectId, PyDict, PyObject};
+use crate::slab::ObjectSlab;
+use crate::traversal::{
+    ObjectGraph, ReferenceType, Trace, edge_label, strongly_connected_components, visit_children,
+};
+use parking_lot::RwLock;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use thiserror::Error;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum GCState {
@@ -11,18 +24,459 @@ pub enum GCState {
     HasFinalizer,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollectionPhase {
+    /// Flag every tracked object as a collection candidate and find the
+    /// ones nothing outside the candidates keeps alive.
+    Mark,
+    /// Untrack the candidates that turned out to be garbage.
+    Sweep,
+    /// Move the survivors into the next generation.
+    Promote,
+    /// Clear collection flags and record stats.
+    Finish,
+}
+
+/// An in-progress collection, advanced one phase at a time by
+/// [`Collector::step_collection`].
+#[derive(Debug)]
+pub struct CollectionStep {
+    pub generation: usize,
+    pub next_phase: Option<CollectionPhase>,
+    pub marked: Vec<ObjectId>,
+    pub unreachable: Vec<ObjectId>,
+    pub collected: Vec<ObjectId>,
+    pub promoted: Vec<ObjectId>,
+    /// Collected objects with finalizers, and the garbage they reach, kept
+    /// whole until the caller has run the finalizers.
+    pub finalizable: Vec<PyObject>,
+    started: Duration,
+    pause: Duration,
+    uncollectable: usize,
+    /// Held until Finish, or until the step is dropped part way.
+    guard: Option<CollectionGuard>,
+}
+
+impl CollectionStep {
+    pub fn is_finished(&self) -> bool {
+        self.next_phase.is_none()
+    }
+
+    /// What the collection did so far; complete once it has finished.
+    /// Finalizers are run by the caller, so none are counted here.
+    pub fn report(&self) -> CollectionReport {
+        CollectionReport {
+            generation: self.generation,
+            scanned: self.marked.len(),
+            unreachable_found: self.unreachable.len(),
+            collected: self.collected.len(),
+            uncollectable: self.uncollectable,
+            finalizers_run: 0,
+            duration: self.pause,
+            promoted: self.promoted.len(),
+            pre_collect_time: Duration::ZERO,
+            vetoed: false,
+        }
+    }
+}
+
+/// What one collection did, for observability and test assertions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CollectionReport {
+    pub generation: usize,
+    /// Candidates the mark examined.
+    pub scanned: usize,
+    pub unreachable_found: usize,
+    pub collected: usize,
+    /// Objects with finalizers held back from collection when it finished.
+    pub uncollectable: usize,
+    /// Finalizers run on collected objects.
+    pub finalizers_run: usize,
+    pub duration: Duration,
+    /// Survivors moved to the next generation.
+    pub promoted: usize,
+    /// Time the pre-collect hooks took before the collection started.
+    pub pre_collect_time: Duration,
+    /// A pre-collect hook vetoed the collection, so nothing else happened.
+    pub vetoed: bool,
+}
+
+/// Stats as a collector last published them, for readers that must not
+/// wait on the collector lock.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PublishedStats {
+    pub stats: crate::GCStats,
+    /// How many collections have finished and published their stats.
+    pub epoch: u64,
+    /// A collection was running when the stats were read, so they may be
+    /// from before it.
+    pub collection_in_progress: bool,
+}
+
+/// The stats published by the last finished collection. Its lock is only
+/// held to swap or clone an `Arc`, never across a collection phase.
+#[derive(Debug, Default)]
+pub struct StatsBoard {
+    current: RwLock<Arc<PublishedStats>>,
+    /// The generation manager's collecting flag.
+    collecting: Arc<AtomicBool>,
+}
+
+impl StatsBoard {
+    fn new(collecting: Arc<AtomicBool>) -> Self {
+        Self {
+            current: RwLock::default(),
+            collecting,
+        }
+    }
+
+    pub fn publish(&self, stats: crate::GCStats) {
+        let mut current = self.current.write();
+        let epoch = current.epoch + 1;
+        *current = Arc::new(PublishedStats {
+            stats,
+            epoch,
+            collection_in_progress: false,
+        });
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.current.read().epoch
+    }
+
+    pub fn is_collecting(&self) -> bool {
+        self.collecting.load(Ordering::Acquire)
+    }
+
+    pub fn read(&self) -> PublishedStats {
+        PublishedStats {
+            collection_in_progress: self.is_collecting(),
+            ..(**self.current.read()).clone()
+        }
+    }
+}
+
+/// What [`Collector::begin_concurrent_mark`] captured: the candidates and
+/// their reference counts. The mark traverses the live objects without the
+/// collector, so references moved meanwhile must go through the write
+/// barrier for [`Collector::remark`] to see them.
+#[derive(Debug, Clone, Default)]
+pub struct MarkSnapshot {
+    pub candidates: Vec<PyObject>,
+    pub refcounts: HashMap<ObjectId, usize>,
+    pub tracer: Option<Arc<dyn Trace>>,
+}
+
+impl MarkSnapshot {
+    /// Candidates that look like garbage, going by the snapshot's counts.
+    pub fn find_unreachable(&self) -> Vec<ObjectId> {
+        let objects: HashMap<ObjectId, &PyObject> =
+            self.candidates.iter().map(|obj| (obj.id(), obj)).collect();
+        let candidates: Vec<ObjectId> = self.candidates.iter().map(PyObject::id).collect();
+        let tracer = self.tracer.as_deref();
+        unreachable_among(&candidates, self.refcounts.clone(), &mut |obj_id, visit| {
+            if let Some(obj) = objects.get(obj_id) {
+                visit_children(obj, tracer, visit);
+            }
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LeakReport {
+    pub object_id: ObjectId,
+    pub type_name: String,
+    pub collections_survived: usize,
+    pub refcount: usize,
+    pub internal_refs: usize,
+    /// Retainers from the root down to the object. For a cycle the root is the
+    /// object itself; otherwise it is a tracked object nothing else refers to.
+    pub retaining_path: Vec<ObjectId>,
+    /// Where each step of `retaining_path` holds the next: entry `i` names
+    /// the field of `retaining_path[i]` referring to `retaining_path[i + 1]`.
+    pub retaining_labels: Vec<Option<String>>,
+}
+
+/// Tracked objects of one type and their combined shallow size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TypeStats {
+    pub count: usize,
+    pub bytes: usize,
+}
+
+/// Tracked objects with one tag, whatever their type.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TagStats {
+    pub objects: usize,
+    pub bytes: usize,
+    /// Objects that survived at least one collection.
+    pub survivors: usize,
+}
+
+/// Objects a quota applies to: those with a tag, or those of a type.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum QuotaKey {
+    Tag(String),
+    Type(String),
+}
+
+/// Upper bounds on the tracked objects under one [`QuotaKey`]. A bound left
+/// at `None` isn't checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quota {
+    pub max_objects: Option<usize>,
+    pub max_bytes: Option<usize>,
+}
+
+/// Why an object is (still) alive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ObjectExplanation {
+    pub object_id: ObjectId,
+    pub type_name: String,
+    /// Generation holding the object, or `None` for uncollectable objects.
+    pub generation: Option<usize>,
+    pub collections_survived: usize,
+    pub refcount: usize,
+    pub internal_refs: usize,
+    pub has_finalizer: bool,
+    /// Retainers from the root down to the object, as for [`LeakReport`].
+    pub retaining_path: Vec<ObjectId>,
+    pub retaining_labels: Vec<Option<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum IntegrityViolation {
+    #[error("object {0:?} is tracked more than once")]
+    DuplicateObject(ObjectId),
+
+    #[error("object {object:?} is stored under key {key:?}")]
+    KeyMismatch { key: ObjectId, object: ObjectId },
+
+    #[error("generation counts sum to {generation_total} but {tracked} objects are tracked")]
+    GenerationCountMismatch {
+        generation_total: usize,
+        tracked: usize,
+    },
+
+    #[error("object {from:?} refers to {to:?}, which claims to be tracked but is not")]
+    DanglingReference { from: ObjectId, to: ObjectId },
+
+    #[error("object {object:?} has refcount {refcount} but {internal_refs} tracked referrers")]
+    RefcountBelowInternalRefs {
+        object: ObjectId,
+        refcount: usize,
+        internal_refs: usize,
+    },
+
+    #[error("object {0:?} is tracked but not flagged as tracked")]
+    TrackedFlagCleared(ObjectId),
+
+    #[error("object {object:?} has gc_refs {gc_refs} above its refcount {refcount}")]
+    GcRefsAboveRefcount {
+        object: ObjectId,
+        gc_refs: isize,
+        refcount: usize,
+    },
+
+    #[error("object {0:?} is flagged as collecting outside of a collection")]
+    StaleCollectingFlag(ObjectId),
+
+    #[error("object {0:?} is flagged as unreachable outside of a collection")]
+    StaleUnreachableFlag(ObjectId),
+
+    #[error(
+        "object {from:?} refers to {to:?} {forward} times but is recorded as its referrer {reverse} times"
+    )]
+    ReferrerMismatch {
+        from: ObjectId,
+        to: ObjectId,
+        forward: usize,
+        reverse: usize,
+    },
+}
+
+/// An object whose refcount isn't the number of references the collector
+/// can account for: its referrers in the graph plus the external references
+/// declared for it. A refcount above that usually means an edge the
+/// embedder never reported; one below, a reference dropped twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RefcountDiscrepancy {
+    pub object: ObjectId,
+    pub type_name: String,
+    pub refcount: usize,
+    pub internal_refs: usize,
+    pub external_refs: usize,
+    /// Tracked objects holding a reference to this one.
+    pub referrers: Vec<ObjectId>,
+}
+
+impl fmt::Display for RefcountDiscrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} object {:?} has refcount {} but {} referrers and {} external references (referrers: {:?})",
+            self.type_name,
+            self.object,
+            self.refcount,
+            self.internal_refs,
+            self.external_refs,
+            self.referrers
+        )
+    }
+}
+
+/// A reference a minor collection missed: `from`, which it didn't scan (a
+/// large, permanent or uncollectable object), still refers to `to`, which
+/// it freed. Refcounts stand in for a remembered set, so `to`'s refcount
+/// never counted the reference; typically it was stored without the write
+/// barrier's incref.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct BarrierViolation {
+    pub from: ObjectId,
+    pub from_type: String,
+    /// `None` for uncollectable and permanent objects.
+    pub from_generation: Option<usize>,
+    pub to: ObjectId,
+    /// The youngest generation collected, whose collection freed `to`.
+    pub generation: usize,
+}
+
+impl fmt::Display for BarrierViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} object {:?}", self.from_type, self.from)?;
+        if let Some(generation) = self.from_generation {
+            write!(f, " in generation {generation}")?;
+        }
+        write!(
+            f,
+            " still refers to {:?}, freed by a generation {} collection",
+            self.to, self.generation
+        )
+    }
+}
+
+/// What happens to one unreachable group of objects with finalizers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UncollectableAction {
+    /// Leave them in the uncollectable list.
+    Keep,
+    /// Run their finalizers, then free them.
+    Break,
+    /// Stop tracking them without freeing them.
+    Leak,
+}
+
+/// Picks the action for each unreachable group of uncollectable objects.
+/// It runs with the collector locked, so it must not call back into it.
+pub type UncollectableHandler = Arc<dyn Fn(&[PyObject]) -> UncollectableAction + Send + Sync>;
+
+/// How full collections treat objects with finalizers that nothing outside
+/// them keeps alive. They are found one strongly connected group at a time.
+#[derive(Clone, Default)]
+pub enum UncollectablePolicy {
+    /// Keep them in the uncollectable list, where they stay until cleared.
+    #[default]
+    ReportOnly,
+    BreakCyclesAnyway,
+    LeakSilently,
+    Callback(UncollectableHandler),
+}
+
+impl fmt::Debug for UncollectablePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ReportOnly => f.write_str("ReportOnly"),
+            Self::BreakCyclesAnyway => f.write_str("BreakCyclesAnyway"),
+            Self::LeakSilently => f.write_str("LeakSilently"),
+            Self::Callback(_) => f.write_str("Callback"),
+        }
+    }
+}
+
+/// When survivors of a young collection move on to the next generation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PromotionPolicy {
+    /// Promote every survivor, as CPython does.
+    #[default]
+    EverySurvival,
+    /// Keep a survivor in its generation until it has survived `survivals`
+    /// collections there. Objects of at least `early_bytes` shallow size go
+    /// at once, since copying them around young generations is the costly
+    /// part.
+    Aged {
+        survivals: usize,
+        early_bytes: Option<usize>,
+    },
+}
+
+/// What the promotion policy has done since it was last set, to measure its
+/// effect on the growth of the oldest generation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PromotionStats {
+    pub promoted: usize,
+    /// Of `promoted`, those that reached the oldest generation.
+    pub into_oldest: usize,
+    /// Survivors kept back because they were too young.
+    pub held_back: usize,
+    /// Survivors promoted while too young because of their size.
+    pub promoted_early: usize,
+}
+
 #[derive(Debug)]
 pub struct Collector {
     pub generation_manager: GenerationManager,
-    pub tracked_objects: HashMap<ObjectId, PyObject>,
+    pub tracked_objects: ObjectSlab,
     pub collecting_objects: HashSet<ObjectId>,
     pub uncollectable: Vec<PyObject>,
+    /// Garbage kept instead of freed while `DEBUG_SAVEALL` is set.
+    pub saved_garbage: Vec<PyObject>,
     pub debug_flags: u32,
+    pub total_collected: usize,
+    pub last_pause: Duration,
+    pub survival_counts: HashMap<ObjectId, usize>,
+    /// Generation of each tracked object past generation 0.
+    pub object_generations: HashMap<ObjectId, usize>,
+    /// Tracked objects in the permanent generation, which no collection
+    /// looks at. They stay in `tracked_objects` but in no generation count.
+    pub permanent: HashSet<ObjectId>,
+    pub clock: Arc<dyn Clock>,
+    pub tracer: Option<Arc<dyn Trace>>,
+    /// Shallow size of every tracked object, kept up to date as objects come
+    /// and go and recomputed after each collection.
+    pub tracked_bytes: usize,
+    /// Lists and dicts holding only atomic values, untracked after surviving
+    /// a collection as CPython does. They come back once written to in a way
+    /// that gives them references worth tracking.
+    pub elided: HashMap<ObjectId, PyObject>,
+    pub uncollectable_policy: UncollectablePolicy,
+    /// Published at every collection phase boundary.
+    pub stats_board: Arc<StatsBoard>,
+    /// Found by the audit at the start of the last collection run with
+    /// `DEBUG_AUDIT_REFS`.
+    pub refcount_discrepancies: Vec<RefcountDiscrepancy>,
+    /// Found by the check after the last minor collection run with
+    /// `DEBUG_VERIFY_BARRIERS`.
+    pub barrier_violations: Vec<BarrierViolation>,
+    /// Values held by ephemerons, by key. Each pair holds one reference on
+    /// its value, which marking treats as an edge from the key: the value
+    /// lives only as long as the key is reachable on its own.
+    pub ephemerons: HashMap<ObjectId, Vec<ObjectId>>,
+    pub promotion_policy: PromotionPolicy,
+    pub promotion_stats: PromotionStats,
+    /// Collections survived in its current generation by each object the
+    /// promotion policy held back.
+    pub promotion_ages: HashMap<ObjectId, usize>,
 }
 
-unsafe impl Send for Collector {}
-unsafe impl Sync for Collector {}
-
 impl Default for Collector {
     fn default() -> Self {
         Self::new()
@@ -31,145 +485,1662 @@ impl Default for Collector {
 
 impl Collector {
     pub fn new() -> Self {
+        let generation_manager = GenerationManager::new();
         Self {
-            generation_manager: GenerationManager::new(),
-            tracked_objects: HashMap::new(),
+            stats_board: Arc::new(StatsBoard::new(generation_manager.collecting_flag())),
+            generation_manager,
+            tracked_objects: ObjectSlab::new(),
             collecting_objects: HashSet::new(),
             uncollectable: Vec::new(),
+            saved_garbage: Vec::new(),
             debug_flags: 0,
+            total_collected: 0,
+            last_pause: Duration::ZERO,
+            survival_counts: HashMap::new(),
+            object_generations: HashMap::new(),
+            permanent: HashSet::new(),
+            clock: Arc::new(SystemClock::new()),
+            tracer: None,
+            tracked_bytes: 0,
+            elided: HashMap::new(),
+            uncollectable_policy: UncollectablePolicy::default(),
+            refcount_discrepancies: Vec::new(),
+            barrier_violations: Vec::new(),
+            ephemerons: HashMap::new(),
+            promotion_policy: PromotionPolicy::default(),
+            promotion_stats: PromotionStats::default(),
+            promotion_ages: HashMap::new(),
         }
     }
 
-    pub fn track_object(&mut self, mut obj: PyObject) -> GCResult<()> {
-        if obj.gc_tracked {
-            return Err(GCError::AlreadyTracked);
+    pub fn track_object(&mut self, obj: PyObject) -> GCResult<()> {
+        if obj.is_gc_tracked() {
+            return Err(GCError::AlreadyTracked { id: obj.id() });
         }
+        self.elided.remove(&obj.id());
 
-        obj.gc_head.set_refs(obj.get_refcount() as isize);
-        obj.gc_tracked = true;
-        let obj_id = obj.id;
+        obj.gc_head().set_refs(obj.get_refcount() as isize);
+        let obj_id = obj.id();
 
-        if obj.has_finalizer {
+        if obj.has_finalizer() {
+            self.admit(&obj);
             self.uncollectable.push(obj);
         } else {
-            self.tracked_objects.insert(obj_id, obj);
-            self.generation_manager.add_to_generation0_fast(obj_id)?;
+            self.tracked_objects.insert(obj_id, obj.clone())?;
+            self.admit(&obj);
+            self.generation_manager
+                .add_object(obj_id, obj.counted_size())?;
         }
 
         Ok(())
     }
 
-    pub fn track_object_fast(&mut self, mut obj: PyObject) -> GCResult<()> {
-        if obj.gc_tracked {
-            return Err(GCError::AlreadyTracked);
+    pub fn track_object_fast(&mut self, obj: PyObject) -> GCResult<()> {
+        if obj.is_gc_tracked() {
+            return Err(GCError::AlreadyTracked { id: obj.id() });
         }
+        self.elided.remove(&obj.id());
 
-        obj.gc_tracked = true;
-        let obj_id = obj.id;
+        let obj_id = obj.id();
 
-        if obj.has_finalizer {
+        if obj.has_finalizer() {
+            self.admit(&obj);
             self.uncollectable.push(obj);
         } else {
-            self.tracked_objects.insert(obj_id, obj);
-            self.generation_manager.add_to_generation0_fast(obj_id)?;
+            self.tracked_objects.insert(obj_id, obj.clone())?;
+            self.admit(&obj);
+            self.generation_manager
+                .add_object(obj_id, obj.counted_size())?;
         }
 
         Ok(())
     }
 
     pub fn track_objects_bulk(&mut self, objects: Vec<PyObject>) -> GCResult<()> {
+        for obj in objects {
+            if !obj.is_gc_tracked() {
+                self.elided.remove(&obj.id());
+                self.tracked_objects.insert(obj.id(), obj.clone())?;
+                self.admit(&obj);
+                self.generation_manager
+                    .add_object(obj.id(), obj.counted_size())?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn untrack_objects_bulk(&mut self, obj_ids: &[ObjectId]) -> GCResult<usize> {
         let mut count = 0;
-        for mut obj in objects {
-            if !obj.gc_tracked {
-                obj.gc_tracked = true;
-                self.tracked_objects.insert(obj.id, obj);
+        for obj_id in obj_ids {
+            if let Some(obj) = self.tracked_objects.remove(obj_id) {
+                self.release(&obj);
+                self.uncount(&obj);
                 count += 1;
             }
         }
 
-        self.generation_manager.generations[0].count += count;
-
-        Ok(())
+        Ok(count)
     }
 
     pub fn untrack_object(&mut self, obj_id: &ObjectId) -> GCResult<()> {
         if !self.tracked_objects.contains_key(obj_id) {
-            return Err(GCError::NotTracked);
+            return Err(GCError::NotTracked { id: *obj_id });
         }
 
-        self.tracked_objects.remove(obj_id);
-        self.generation_manager
-            .get_generation_mut(0)
-            .ok_or(GCError::Internal("Generation 0 not found".to_string()))?
-            .remove_object(obj_id)?;
+        if let Some(obj) = self.tracked_objects.remove(obj_id) {
+            self.release(&obj);
+            self.uncount(&obj);
+        }
 
         Ok(())
     }
 
     pub fn untrack_object_fast(&mut self, obj_id: &ObjectId) -> GCResult<()> {
         if !self.tracked_objects.contains_key(obj_id) {
-            return Err(GCError::NotTracked);
+            return Err(GCError::NotTracked { id: *obj_id });
         }
 
-        self.tracked_objects.remove(obj_id);
+        if let Some(obj) = self.tracked_objects.remove(obj_id) {
+            self.release(&obj);
+            self.uncount(&obj);
+        }
         Ok(())
     }
 
+    /// Drop one reference to `obj_id`. Objects whose count reaches zero are
+    /// removed, and the references they held are dropped in turn, using a
+    /// worklist so long chains cannot overflow the stack. Returns the
+    /// reclaimed objects in the order they were freed.
+    pub fn decref(&mut self, obj_id: &ObjectId) -> GCResult<Vec<PyObject>> {
+        if !self.is_tracked(obj_id) && !self.elided.contains_key(obj_id) {
+            return Err(GCError::NotTracked { id: *obj_id });
+        }
+
+        let mut reclaimed = Vec::new();
+        let mut worklist = vec![*obj_id];
+        while let Some(obj_id) = worklist.pop() {
+            let Some(obj) = self.find_object_mut(&obj_id) else {
+                continue;
+            };
+            if !obj.try_dec_ref()? {
+                continue;
+            }
+
+            let obj = self.remove_object(&obj_id)?;
+            visit_children(&obj, self.tracer.as_deref(), &mut |child_id| {
+                worklist.push(child_id)
+            });
+            worklist.extend(self.ephemerons.remove(&obj_id).unwrap_or_default());
+            reclaimed.push(obj);
+        }
+
+        Ok(reclaimed)
+    }
+
+    /// Clear the references `obj_id` holds, as `tp_clear` does, and drop the
+    /// counts they held on tracked objects. Breaking one link of a cycle
+    /// frees the rest once nothing else holds it. Returns what was freed.
+    pub fn break_cycle(&mut self, obj_id: &ObjectId) -> GCResult<Vec<PyObject>> {
+        if self.is_collecting() {
+            return Err(GCError::CollectionInProgress);
+        }
+        let obj = self
+            .find_object_mut(obj_id)
+            .ok_or(GCError::NotTracked { id: *obj_id })?
+            .clone();
+
+        let mut children = Vec::new();
+        visit_children(&obj, self.tracer.as_deref(), &mut |child_id| {
+            children.push(child_id)
+        });
+        let cleared = {
+            let mut data = obj.data_mut();
+            match &*data {
+                ObjectData::List(_) => std::mem::replace(&mut *data, ObjectData::List(Vec::new())),
+                ObjectData::Dict(_) => {
+                    std::mem::replace(&mut *data, ObjectData::Dict(PyDict::new()))
+                }
+                ObjectData::Custom(_) => {
+                    return Err(GCError::internal(
+                        "break_cycle",
+                        "custom objects hold references the collector cannot clear",
+                    ));
+                }
+                _ => return Ok(Vec::new()),
+            }
+        };
+
+        let mut reclaimed = Vec::new();
+        for child_id in children {
+            if self.find_object_mut(&child_id).is_some() {
+                reclaimed.extend(self.decref(&child_id)?);
+            }
+        }
+        drop(cleared);
+        Ok(reclaimed)
+    }
+
+    /// Look up a tracked object, including uncollectable and elided ones.
+    pub fn find_object(&self, obj_id: &ObjectId) -> Option<&PyObject> {
+        self.tracked_objects
+            .get(obj_id)
+            .or_else(|| self.elided.get(obj_id))
+            .or_else(|| self.uncollectable.iter().find(|obj| obj.id() == *obj_id))
+    }
+
+    /// As [`Collector::find_object`], mutably.
+    pub fn find_object_mut(&mut self, obj_id: &ObjectId) -> Option<&mut PyObject> {
+        if let Some(obj) = self.tracked_objects.get_mut(obj_id) {
+            return Some(obj);
+        }
+        if let Some(obj) = self.elided.get_mut(obj_id) {
+            return Some(obj);
+        }
+        self.uncollectable
+            .iter_mut()
+            .find(|obj| obj.id() == *obj_id)
+    }
+
+    fn remove_object(&mut self, obj_id: &ObjectId) -> GCResult<PyObject> {
+        self.survival_counts.remove(obj_id);
+        self.promotion_ages.remove(obj_id);
+        if let Some(obj) = self.elided.remove(obj_id) {
+            return Ok(obj);
+        }
+        let obj = match self.tracked_objects.remove(obj_id) {
+            Some(obj) => {
+                self.uncount(&obj);
+                obj
+            }
+            None => {
+                let index = self
+                    .uncollectable
+                    .iter()
+                    .position(|obj| obj.id() == *obj_id)
+                    .ok_or(GCError::NotTracked { id: *obj_id })?;
+                self.uncollectable.remove(index)
+            }
+        };
+        self.release(&obj);
+        Ok(obj)
+    }
+
+    /// Ids of `obj_id` and, with `closure`, every object held here that it
+    /// reaches: the objects [`take_objects`](Self::take_objects) removes.
+    pub fn objects_to_take(&self, obj_id: &ObjectId, closure: bool) -> GCResult<Vec<ObjectId>> {
+        if self.is_collecting() {
+            return Err(GCError::CollectionInProgress);
+        }
+        if !self.is_tracked(obj_id) {
+            return Err(GCError::NotTracked { id: *obj_id });
+        }
+
+        let mut seen = HashSet::new();
+        let mut taken = Vec::new();
+        let mut worklist = vec![*obj_id];
+        while let Some(obj_id) = worklist.pop() {
+            let Some(obj) = self.find_object(&obj_id) else {
+                continue;
+            };
+            if !seen.insert(obj_id) {
+                continue;
+            }
+            if closure {
+                visit_children(obj, self.tracer.as_deref(), &mut |child_id| {
+                    worklist.push(child_id)
+                });
+            }
+            taken.push(obj_id);
+        }
+
+        Ok(taken)
+    }
+
+    /// Remove `obj_ids`, as listed by [`objects_to_take`](Self::objects_to_take),
+    /// so the objects can be tracked by another heap. Survival counts stay
+    /// behind; the receiving heap sees them as new objects.
+    pub fn take_objects(&mut self, obj_ids: &[ObjectId]) -> Vec<PyObject> {
+        obj_ids
+            .iter()
+            .filter_map(|obj_id| self.remove_object(obj_id).ok())
+            .collect()
+    }
+
+    /// Fail the way [`track_object_fast`](Self::track_object_fast) would
+    /// for an object with id `obj_id`, without tracking anything.
+    pub fn check_trackable(&self, obj_id: &ObjectId) -> GCResult<()> {
+        if self.is_collecting() {
+            return Err(GCError::CollectionInProgress);
+        }
+        if self.is_tracked(obj_id) || self.elided.contains_key(obj_id) {
+            return Err(GCError::AlreadyTracked { id: *obj_id });
+        }
+        self.tracked_objects.check_vacant(obj_id)
+    }
+
+    /// References from objects in this heap to objects tracked by `other`,
+    /// as `(referrer, referent)` pairs. `other` never subtracts these when
+    /// it collects, so each referent is kept alive as an external root.
+    pub fn cross_heap_edges(&self, other: &Collector) -> Vec<(ObjectId, ObjectId)> {
+        let mut edges = Vec::new();
+        for obj in self
+            .tracked_objects
+            .values()
+            .chain(self.uncollectable.iter())
+        {
+            visit_children(obj, self.tracer.as_deref(), &mut |child_id| {
+                if other.is_tracked(&child_id) && !self.is_tracked(&child_id) {
+                    edges.push((obj.id(), child_id));
+                }
+            });
+        }
+        edges.sort_by_key(|(from, to)| (from.as_u64(), to.as_u64()));
+        edges
+    }
+
+    fn admit(&mut self, obj: &PyObject) {
+        obj.set_gc_tracked(true);
+        self.tracked_bytes += obj.count_size();
+    }
+
+    /// Take an untracked object off the count of the generation it was in.
+    fn uncount(&mut self, obj: &PyObject) {
+        let obj_id = obj.id();
+        if self.permanent.remove(&obj_id) {
+            return;
+        }
+        let generation = self.object_generations.remove(&obj_id).unwrap_or(0);
+        self.generation_manager
+            .remove_object(&obj_id, generation, obj.counted_size());
+    }
+
+    fn release(&mut self, obj: &PyObject) {
+        obj.set_gc_tracked(false);
+        self.tracked_bytes = self.tracked_bytes.saturating_sub(obj.counted_size());
+    }
+
+    pub fn clear_uncollectable(&mut self) {
+        for obj in std::mem::take(&mut self.uncollectable) {
+            self.release(&obj);
+        }
+    }
+
+    pub fn set_uncollectable_policy(&mut self, policy: UncollectablePolicy) {
+        self.uncollectable_policy = policy;
+    }
+
+    pub fn clear_saved_garbage(&mut self) {
+        self.saved_garbage.clear();
+    }
+
+    /// Whether a collection has begun and not finished, or was dropped part
+    /// way with objects still flagged as collecting.
+    pub fn is_collecting(&self) -> bool {
+        self.generation_manager.is_collecting() || !self.collecting_objects.is_empty()
+    }
+
+    /// Full collection, the same as `collect_generation(2)`.
     pub fn collect(&mut self) -> GCResult<usize> {
-        self.collect_generation(0)
+        self.collect_generation(2)
     }
 
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Set the tracer used to find the objects held by `ObjectData::Custom`
+    /// payloads. Without one, custom data keeps nothing alive.
+    pub fn set_tracer(&mut self, tracer: Option<Arc<dyn Trace>>) {
+        self.tracer = tracer;
+    }
+
+    /// Collect only the youngest generation.
     pub fn collect_fast(&mut self) -> GCResult<usize> {
-        if self.tracked_objects.len() < 100 {
-            let mut collected = 0;
-            let objects_to_collect: Vec<ObjectId> = self.tracked_objects.keys().cloned().collect();
+        self.collect_generation(0)
+    }
+
+    /// Collect `generation` together with every younger one, which are
+    /// merged into it first. Older generations are left alone.
+    pub fn collect_generation(&mut self, generation: usize) -> GCResult<usize> {
+        Ok(self.run_collection(generation)?.collected.len())
+    }
+
+    /// Run a whole collection and return the finished step, for callers
+    /// that want its report or its objects awaiting finalization.
+    pub fn run_collection(&mut self, generation: usize) -> GCResult<CollectionStep> {
+        let mut step = self.begin_collection(generation)?;
+        while !step.is_finished() {
+            self.step_collection(&mut step)?;
+        }
+
+        Ok(step)
+    }
+
+    pub fn begin_collection(&mut self, generation: usize) -> GCResult<CollectionStep> {
+        if generation >= self.generation_manager.generations.len() {
+            return Err(GCError::InvalidGeneration(generation));
+        }
+        // A step dropped part way leaves its candidates flagged, so refuse
+        // to start over them as well.
+        if !self.collecting_objects.is_empty() {
+            return Err(GCError::CollectionInProgress);
+        }
+        let guard = self.generation_manager.start_collection()?;
+        if self.debug_flags & DEBUG_AUDIT_REFS != 0 {
+            self.refcount_discrepancies = self.audit_refcounts();
+        }
+
+        Ok(CollectionStep {
+            generation,
+            next_phase: Some(CollectionPhase::Mark),
+            marked: Vec::new(),
+            unreachable: Vec::new(),
+            collected: Vec::new(),
+            promoted: Vec::new(),
+            finalizable: Vec::new(),
+            started: self.clock.now(),
+            pause: Duration::ZERO,
+            uncollectable: 0,
+            guard: Some(guard),
+        })
+    }
+
+    /// Start a collection whose mark runs away from the collector, on
+    /// another thread if need be. The candidates are flagged as collecting
+    /// and the returned step resumes at Sweep once [`Collector::remark`]
+    /// has settled which of them are garbage.
+    pub fn begin_concurrent_mark(
+        &mut self,
+        generation: usize,
+    ) -> GCResult<(CollectionStep, MarkSnapshot)> {
+        let mut step = self.begin_collection(generation)?;
+        self.mark_candidates(&mut step)?;
+
+        let candidates: Vec<PyObject> = step
+            .marked
+            .iter()
+            .map(|obj_id| self.tracked_objects[obj_id].clone())
+            .collect();
+        let snapshot = MarkSnapshot {
+            refcounts: candidates
+                .iter()
+                .map(|obj| (obj.id(), obj.get_refcount()))
+                .collect(),
+            candidates,
+            tracer: self.tracer.clone(),
+        };
+        step.next_phase = Some(CollectionPhase::Sweep);
+
+        Ok((step, snapshot))
+    }
+
+    /// Settle a concurrent mark. `unreachable` is what the mark found in
+    /// `snapshot`; a candidate stays alive if the write barrier `logged`
+    /// it, if it gained references since the snapshot, or if it can be
+    /// reached from such an object. Must run while mutators are stopped.
+    pub fn remark(
+        &mut self,
+        step: &mut CollectionStep,
+        snapshot: &MarkSnapshot,
+        unreachable: &[ObjectId],
+        logged: &[ObjectId],
+    ) {
+        let mut garbage: HashSet<ObjectId> = unreachable
+            .iter()
+            .copied()
+            .filter(|obj_id| self.tracked_objects.contains_key(obj_id))
+            .collect();
+        let mut worklist = logged.to_vec();
+        worklist.extend(garbage.iter().copied().filter(|obj_id| {
+            self.tracked_objects[obj_id].get_refcount()
+                > snapshot.refcounts.get(obj_id).copied().unwrap_or(0)
+        }));
+
+        let tracer = self.tracer.as_deref();
+        while let Some(obj_id) = worklist.pop() {
+            garbage.remove(&obj_id);
+            if let Some(obj) = self.tracked_objects.get(&obj_id) {
+                visit_children(obj, tracer, &mut |child_id| {
+                    if garbage.contains(&child_id) {
+                        worklist.push(child_id);
+                    }
+                });
+            }
+        }
 
-            for obj_id in objects_to_collect {
-                if self.untrack_object_fast(&obj_id).is_ok() {
-                    collected += 1;
+        step.unreachable = step
+            .marked
+            .iter()
+            .copied()
+            .filter(|obj_id| garbage.contains(obj_id))
+            .collect();
+        for obj_id in &step.unreachable {
+            if let Some(obj) = self.tracked_objects.get_mut(obj_id) {
+                obj.gc_head().set_unreachable();
+            }
+        }
+    }
+
+    /// Merge the younger generations into `step`'s and flag the objects in
+    /// it as candidates. Older generations, the permanent one and, short of
+    /// a full collection, large objects are left alone; their references
+    /// into the candidates count as coming from outside.
+    fn mark_candidates(&mut self, step: &mut CollectionStep) -> GCResult<()> {
+        self.retrack_mutated()?;
+        self.retrack_dead()?;
+        self.generation_manager.merge_younger(step.generation)?;
+        step.marked = self
+            .tracked_objects
+            .keys()
+            .copied()
+            .filter(|obj_id| {
+                self.generation_of(obj_id)
+                    .is_some_and(|generation| generation <= step.generation)
+            })
+            .collect();
+        step.marked.sort_by_key(|obj_id| obj_id.as_u64());
+        if step.generation > 0 {
+            for obj_id in &step.marked {
+                self.object_generations.insert(*obj_id, step.generation);
+            }
+        }
+        for obj_id in &step.marked {
+            if let Some(obj) = self.tracked_objects.get_mut(obj_id) {
+                obj.gc_head().set_collecting();
+            }
+        }
+        self.collecting_objects.extend(step.marked.iter().copied());
+        Ok(())
+    }
+
+    /// Whether `obj` is a list or dict whose children can't take part in a
+    /// cycle: each is untracked and either atomic or itself elided.
+    fn can_elide(&self, obj: &PyObject) -> bool {
+        if obj.has_finalizer() || !matches!(&*obj.data(), ObjectData::List(_) | ObjectData::Dict(_))
+        {
+            return false;
+        }
+        obj.children().iter().all(|child| {
+            !child.is_gc_tracked()
+                && (!child.data().should_track() || self.elided.contains_key(&child.id()))
+        })
+    }
+
+    /// Untrack the survivors in `candidates` that `can_elide` allows.
+    fn elide_atomic_containers(&mut self, candidates: &[ObjectId]) {
+        let elided: Vec<ObjectId> = candidates
+            .iter()
+            .copied()
+            .filter(|obj_id| self.can_elide(&self.tracked_objects[obj_id]))
+            .collect();
+        for obj_id in &elided {
+            if let Some(obj) = self.tracked_objects.remove(obj_id) {
+                self.release(&obj);
+                self.uncount(&obj);
+                obj.take_mutated();
+                self.survival_counts.remove(obj_id);
+                self.promotion_ages.remove(obj_id);
+                self.elided.insert(*obj_id, obj);
+            }
+        }
+    }
+
+    /// Track again every elided container that no longer qualifies. Only
+    /// needed once one of them was written to, but then all are checked,
+    /// since a container holding one that came back must come back too.
+    fn retrack_mutated(&mut self) -> GCResult<()> {
+        let mutated = self
+            .elided
+            .values()
+            .filter(|obj| obj.take_mutated())
+            .count();
+        if mutated == 0 {
+            return Ok(());
+        }
+
+        loop {
+            let stale: Vec<ObjectId> = self
+                .elided
+                .values()
+                .filter(|obj| !self.can_elide(obj))
+                .map(PyObject::id)
+                .collect();
+            if stale.is_empty() {
+                return Ok(());
+            }
+            for obj_id in stale {
+                if let Some(obj) = self.elided.remove(&obj_id) {
+                    self.track_object_fast(obj)?;
                 }
             }
+        }
+    }
 
-            Ok(collected)
+    /// Track again the elided containers nothing counts a reference to, so
+    /// this collection can free them. They are in generation 0 and so
+    /// candidates of every collection; one still held by a live container
+    /// survives and is elided again.
+    fn retrack_dead(&mut self) -> GCResult<()> {
+        let dead: Vec<ObjectId> = self
+            .elided
+            .values()
+            .filter(|obj| obj.get_refcount() == 0)
+            .map(PyObject::id)
+            .collect();
+        for obj_id in dead {
+            if let Some(obj) = self.elided.remove(&obj_id) {
+                self.track_object_fast(obj)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the next phase of `step` and return which phase ran, or `None`
+    /// if the collection had already finished.
+    pub fn step_collection(
+        &mut self,
+        step: &mut CollectionStep,
+    ) -> GCResult<Option<CollectionPhase>> {
+        let Some(phase) = step.next_phase else {
+            return Ok(None);
+        };
+
+        match phase {
+            CollectionPhase::Mark => {
+                self.mark_candidates(step)?;
+                step.unreachable = self.find_unreachable(&step.marked);
+                for obj_id in &step.unreachable {
+                    if let Some(obj) = self.tracked_objects.get_mut(obj_id) {
+                        obj.gc_head().set_unreachable();
+                    }
+                }
+                step.next_phase = Some(CollectionPhase::Sweep);
+            }
+            CollectionPhase::Sweep => {
+                // Clear garbage before dropping it, as tp_clear does, so
+                // cycles between handles are broken and long chains are
+                // freed one object at a time rather than recursively. Saved
+                // garbage is kept whole, cycles and all, and so is garbage
+                // with a finalizer, and whatever it reaches, until the
+                // finalizer has run and maybe resurrected it.
+                let save_all = self.debug_flags & DEBUG_SAVEALL != 0;
+                let kept = if save_all {
+                    HashSet::new()
+                } else {
+                    self.reached_by_finalizers(&step.unreachable)
+                };
+                let cleared: Vec<ObjectData> = if save_all {
+                    Vec::new()
+                } else {
+                    step.unreachable
+                        .iter()
+                        .filter(|obj_id| !kept.contains(obj_id))
+                        .filter_map(|obj_id| self.tracked_objects.get(obj_id))
+                        .map(PyObject::take_data)
+                        .collect()
+                };
+                for obj_id in &step.unreachable {
+                    if let Some(obj) = self.tracked_objects.remove(obj_id) {
+                        self.release(&obj);
+                        self.uncount(&obj);
+                        self.promotion_ages.remove(obj_id);
+                        self.collecting_objects.remove(obj_id);
+                        step.collected.push(*obj_id);
+                        if save_all {
+                            obj.gc_head().clear_unreachable();
+                            self.saved_garbage.push(obj);
+                        } else if kept.contains(obj_id) {
+                            step.finalizable.push(obj);
+                        }
+                    }
+                }
+                drop(cleared);
+                let freed = step.unreachable.clone();
+                self.release_ephemerons(&freed);
+                step.next_phase = Some(CollectionPhase::Promote);
+            }
+            CollectionPhase::Promote => {
+                let large = &self.generation_manager.large_objects.objects;
+                step.promoted = step
+                    .marked
+                    .iter()
+                    .copied()
+                    .filter(|obj_id| {
+                        self.tracked_objects.contains_key(obj_id) && !large.contains(obj_id)
+                    })
+                    .collect();
+                self.elide_atomic_containers(&step.promoted);
+                step.promoted
+                    .retain(|obj_id| !self.elided.contains_key(obj_id));
+                let next_generation =
+                    (step.generation + 1).min(self.generation_manager.generations.len() - 1);
+                if step.generation != next_generation {
+                    self.hold_back_young(step);
+                }
+                self.promote_survivors(&step.promoted, next_generation);
+
+                // Objects tracked since Mark are in generation 0 and stay
+                // there; only the survivors move up.
+                let survivors = step.promoted.len();
+                if step.generation != next_generation {
+                    let bytes = step
+                        .promoted
+                        .iter()
+                        .filter_map(|obj_id| self.tracked_objects.get(obj_id))
+                        .map(PyObject::counted_size)
+                        .sum();
+                    self.generation_manager.move_objects(
+                        step.generation,
+                        next_generation,
+                        survivors,
+                        bytes,
+                    );
+                }
+                let manager = &mut self.generation_manager;
+                if step.generation != next_generation {
+                    manager.generations[step.generation].promoted += survivors;
+                    self.promotion_stats.promoted += survivors;
+                    if next_generation == manager.generations.len() - 1 {
+                        self.promotion_stats.into_oldest += survivors;
+                    }
+                }
+                if step.generation == next_generation {
+                    manager.long_lived_total = survivors;
+                    manager.long_lived_pending = 0;
+                } else if next_generation == manager.generations.len() - 1 {
+                    manager.long_lived_pending += survivors;
+                }
+                step.next_phase = Some(CollectionPhase::Finish);
+            }
+            CollectionPhase::Finish => {
+                for obj_id in self.collecting_objects.drain() {
+                    if let Some(obj) = self.tracked_objects.get_mut(&obj_id) {
+                        obj.gc_head().clear_collecting();
+                        obj.gc_head().clear_unreachable();
+                    }
+                }
+                if step.generation == self.generation_manager.generations.len() - 1 {
+                    self.handle_uncollectable(step);
+                } else if self.debug_flags & DEBUG_VERIFY_BARRIERS != 0 {
+                    self.barrier_violations = self.verify_barriers(step);
+                }
+                step.pause = self.clock.now().saturating_sub(step.started);
+                step.uncollectable = self.uncollectable.len();
+                self.record_collection(step);
+                if let Some(guard) = step.guard.take() {
+                    self.generation_manager.end_collection(guard);
+                }
+                step.next_phase = None;
+                self.publish_stats();
+            }
+        }
+
+        Ok(Some(phase))
+    }
+
+    /// The garbage among `garbage` that has a finalizer, and all garbage it
+    /// reaches: what a finalizer could resurrect.
+    fn reached_by_finalizers(&self, garbage: &[ObjectId]) -> HashSet<ObjectId> {
+        let tracer = self.tracer.as_deref();
+        let garbage_set: HashSet<ObjectId> = garbage.iter().copied().collect();
+        let mut pending: Vec<ObjectId> = garbage
+            .iter()
+            .copied()
+            .filter(|obj_id| {
+                self.tracked_objects
+                    .get(obj_id)
+                    .is_some_and(PyObject::has_finalizer)
+            })
+            .collect();
+        let mut kept = HashSet::new();
+        while let Some(obj_id) = pending.pop() {
+            if !kept.insert(obj_id) {
+                continue;
+            }
+            if let Some(obj) = self.tracked_objects.get(&obj_id) {
+                visit_children(obj, tracer, &mut |child_id| {
+                    if garbage_set.contains(&child_id) && !kept.contains(&child_id) {
+                        pending.push(child_id);
+                    }
+                });
+            }
+        }
+        kept
+    }
+
+    /// Track `revived` again after their finalizers resurrected them, along
+    /// with the collected garbage among `kept` that they reach. Like CPython,
+    /// everything goes to the oldest generation with its finalized flag left
+    /// set, so a finalizer never runs twice and an object is revived at most
+    /// once. Ephemerons keyed by a revived object were dropped when it was
+    /// collected and don't come back. Returns how many objects were tracked
+    /// again.
+    pub fn resurrect(&mut self, revived: &[PyObject], kept: &[PyObject]) -> usize {
+        let tracer = self.tracer.clone();
+        let kept: HashMap<ObjectId, &PyObject> = kept.iter().map(|obj| (obj.id(), obj)).collect();
+        let mut pending: Vec<PyObject> = revived.to_vec();
+        let mut count = 0;
+        while let Some(obj) = pending.pop() {
+            let obj_id = obj.id();
+            if self.tracked_objects.contains_key(&obj_id)
+                || self.tracked_objects.insert(obj_id, obj.clone()).is_err()
+            {
+                continue;
+            }
+            visit_children(&obj, tracer.as_deref(), &mut |child_id| {
+                if let Some(child) = kept.get(&child_id) {
+                    pending.push((*child).clone());
+                }
+            });
+            {
+                let mut head = obj.gc_head();
+                head.clear_unreachable();
+                head.clear_collecting();
+            }
+            self.admit(&obj);
+            self.place_in_oldest(obj_id, obj.counted_size());
+            count += 1;
+        }
+        count
+    }
+
+    /// Count `obj_id` into the oldest generation, or the large object space
+    /// if it is large.
+    fn place_in_oldest(&mut self, obj_id: ObjectId, size: usize) {
+        let oldest = self.generation_manager.generations.len() - 1;
+        if self.generation_manager.large_objects.is_large(size) {
+            self.generation_manager.large_objects.objects.insert(obj_id);
+            self.generation_manager.large_objects.bytes += size;
         } else {
-            self.collect()
+            self.generation_manager.generations[oldest].count += 1;
+            self.generation_manager.generations[oldest].bytes += size;
+            if oldest > 0 {
+                self.object_generations.insert(obj_id, oldest);
+            }
         }
     }
 
-    pub fn collect_generation(&mut self, generation: usize) -> GCResult<usize> {
-        if generation >= 3 {
-            return Ok(0);
+    /// Apply the uncollectable policy to the uncollectable objects that are
+    /// garbage among themselves. Broken ones count as collected and wait in
+    /// `step.finalizable` like any other collected object with a finalizer.
+    fn handle_uncollectable(&mut self, step: &mut CollectionStep) {
+        if matches!(self.uncollectable_policy, UncollectablePolicy::ReportOnly) {
+            return;
+        }
+
+        let tracer = self.tracer.as_deref();
+        let objects: HashMap<ObjectId, &PyObject> = self
+            .uncollectable
+            .iter()
+            .map(|obj| (obj.id(), obj))
+            .collect();
+        let candidates: Vec<ObjectId> = self.uncollectable.iter().map(PyObject::id).collect();
+        let gc_refs = objects
+            .values()
+            .map(|obj| (obj.id(), obj.get_refcount()))
+            .collect();
+        let unreachable: HashSet<ObjectId> =
+            unreachable_among(&candidates, gc_refs, &mut |obj_id, visit| {
+                if let Some(obj) = objects.get(obj_id) {
+                    visit_children(obj, tracer, visit);
+                }
+            })
+            .into_iter()
+            .collect();
+        if unreachable.is_empty() {
+            return;
+        }
+
+        let nodes: Vec<ObjectId> = candidates
+            .into_iter()
+            .filter(|obj_id| unreachable.contains(obj_id))
+            .collect();
+        let groups = strongly_connected_components(&nodes, |obj_id| {
+            let mut children = Vec::new();
+            if let Some(obj) = objects.get(obj_id) {
+                visit_children(obj, tracer, &mut |child_id| {
+                    if unreachable.contains(&child_id) {
+                        children.push(child_id);
+                    }
+                });
+            }
+            children
+        });
+        let decide = |members: &[PyObject]| match &self.uncollectable_policy {
+            UncollectablePolicy::ReportOnly => UncollectableAction::Keep,
+            UncollectablePolicy::BreakCyclesAnyway => UncollectableAction::Break,
+            UncollectablePolicy::LeakSilently => UncollectableAction::Leak,
+            UncollectablePolicy::Callback(handler) => handler(members),
+        };
+        let mut decided = HashMap::new();
+        for group in groups {
+            let members: Vec<PyObject> =
+                group.iter().map(|obj_id| objects[obj_id].clone()).collect();
+            let action = decide(&members);
+            decided.extend(group.into_iter().map(|obj_id| (obj_id, action)));
+        }
+
+        let save_all = self.debug_flags & DEBUG_SAVEALL != 0;
+        let broken: Vec<ObjectId> = decided
+            .iter()
+            .filter(|(_, action)| matches!(action, UncollectableAction::Break))
+            .map(|(obj_id, _)| *obj_id)
+            .collect();
+        self.release_ephemerons(&broken);
+        for obj in std::mem::take(&mut self.uncollectable) {
+            match decided.get(&obj.id()) {
+                None | Some(UncollectableAction::Keep) => self.uncollectable.push(obj),
+                Some(UncollectableAction::Break) => {
+                    self.release(&obj);
+                    step.collected.push(obj.id());
+                    if save_all {
+                        self.saved_garbage.push(obj);
+                    } else {
+                        step.finalizable.push(obj);
+                    }
+                }
+                Some(UncollectableAction::Leak) => self.release(&obj),
+            }
+        }
+    }
+
+    /// Candidates that nothing outside the candidate set keeps alive. A
+    /// candidate whose refcount exceeds the references other candidates hold
+    /// on it is a root, and everything a root reaches survives.
+    fn find_unreachable(&self, candidates: &[ObjectId]) -> Vec<ObjectId> {
+        let tracer = self.tracer.as_deref();
+        let gc_refs = candidates
+            .iter()
+            .filter_map(|obj_id| self.tracked_objects.get(obj_id))
+            .map(|obj| (obj.id(), obj.get_refcount()))
+            .collect();
+        unreachable_among(candidates, gc_refs, &mut |obj_id, visit| {
+            if let Some(obj) = self.tracked_objects.get(obj_id) {
+                visit_children(obj, tracer, visit);
+            }
+            self.visit_ephemerons(obj_id, visit);
+        })
+    }
+
+    /// Call `visit` with every edge marking follows out of `obj_id`: its own
+    /// references, through the tracer for custom data, then the values of
+    /// the ephemerons it keys.
+    pub fn visit_children(
+        &self,
+        obj_id: &ObjectId,
+        visit: &mut dyn FnMut(ObjectId),
+    ) -> GCResult<()> {
+        let obj = self
+            .find_object(obj_id)
+            .ok_or(GCError::NotTracked { id: *obj_id })?;
+        visit_children(obj, self.tracer.as_deref(), visit);
+        self.visit_ephemerons(obj_id, visit);
+        Ok(())
+    }
+
+    /// Visit the values of the ephemerons keyed by `obj_id`, the edges
+    /// marking follows besides an object's own references.
+    fn visit_ephemerons(&self, obj_id: &ObjectId, visit: &mut dyn FnMut(ObjectId)) {
+        for value in self.ephemerons.get(obj_id).into_iter().flatten() {
+            visit(*value);
+        }
+    }
+
+    /// Pair `key` with `value`, taking a reference on `value` that keeps it
+    /// alive only while `key` is reachable without it.
+    pub fn add_ephemeron(&mut self, key: &ObjectId, value: &ObjectId) -> GCResult<()> {
+        if self.is_collecting() {
+            return Err(GCError::CollectionInProgress);
+        }
+        if !self.is_tracked(key) {
+            return Err(GCError::NotTracked { id: *key });
+        }
+        let value_obj = self
+            .find_object(value)
+            .ok_or(GCError::NotTracked { id: *value })?;
+        value_obj.inc_ref();
+        self.ephemerons.entry(*key).or_default().push(*value);
+        Ok(())
+    }
+
+    /// Remove one pairing of `key` with `value`, returning whether there was
+    /// one. The reference it held on `value` is the caller's to drop.
+    pub fn remove_ephemeron(&mut self, key: &ObjectId, value: &ObjectId) -> bool {
+        let Some(values) = self.ephemerons.get_mut(key) else {
+            return false;
+        };
+        let Some(index) = values.iter().position(|v| v == value) else {
+            return false;
+        };
+        values.swap_remove(index);
+        if values.is_empty() {
+            self.ephemerons.remove(key);
+        }
+        true
+    }
+
+    /// Drop the ephemerons of the keys in `freed`. Values freed with them
+    /// need nothing more; the others lose the reference the ephemeron held,
+    /// and any that drop to zero are left for their generation's collection.
+    fn release_ephemerons(&mut self, freed: &[ObjectId]) {
+        if self.ephemerons.is_empty() {
+            return;
+        }
+        let freed_set: HashSet<&ObjectId> = freed.iter().collect();
+        for key in freed {
+            for value in self.ephemerons.remove(key).unwrap_or_default() {
+                if freed_set.contains(&value) {
+                    continue;
+                }
+                if let Some(obj) = self.find_object(&value) {
+                    obj.dec_ref();
+                }
+            }
+        }
+    }
+
+    /// Take the current size of every object as the one counted. Objects
+    /// resized since they were tracked make the byte totals drift; a
+    /// collection has visited everything anyway.
+    fn recount_bytes(&mut self) {
+        let oldest = self.generation_manager.generations.len() - 1;
+        let mut bytes = vec![0; oldest + 1];
+        let (mut large, mut total) = (0, 0);
+        for (obj_id, obj) in self.tracked_objects.iter() {
+            let size = obj.count_size();
+            total += size;
+            if self.permanent.contains(obj_id) {
+                continue;
+            }
+            if self
+                .generation_manager
+                .large_objects
+                .objects
+                .contains(obj_id)
+            {
+                large += size;
+            } else {
+                let generation = self.object_generations.get(obj_id).copied().unwrap_or(0);
+                bytes[generation.min(oldest)] += size;
+            }
+        }
+        total += self
+            .uncollectable
+            .iter()
+            .map(PyObject::count_size)
+            .sum::<usize>();
+
+        self.tracked_bytes = total;
+        self.generation_manager.large_objects.bytes = large;
+        for (generation, bytes) in self.generation_manager.generations.iter_mut().zip(bytes) {
+            generation.bytes = bytes;
+        }
+    }
+
+    fn record_collection(&mut self, step: &CollectionStep) {
+        let collected = step.collected.len();
+        let generation = &mut self.generation_manager.generations[step.generation];
+        generation.collections += 1;
+        generation.collected += collected;
+        generation.scanned += step.marked.len();
+        self.total_collected += collected;
+        self.last_pause = step.pause;
+        self.recount_bytes();
+
+        let survivors = self
+            .tracked_objects
+            .keys()
+            .copied()
+            .chain(self.uncollectable.iter().map(PyObject::id));
+        self.survival_counts = survivors
+            .map(|obj_id| {
+                (
+                    obj_id,
+                    self.survival_counts.get(&obj_id).copied().unwrap_or(0) + 1,
+                )
+            })
+            .collect();
+    }
+
+    /// Objects that survived at least `min_generations_survived` collections
+    /// while every reference to them comes from other tracked objects.
+    pub fn detect_probable_leaks(&self, min_generations_survived: usize) -> Vec<LeakReport> {
+        let objects = self.all_objects();
+        let referrers = referrers_of(&objects, &self.ephemerons);
+
+        let mut reports: Vec<LeakReport> = objects
+            .values()
+            .filter_map(|obj| {
+                let collections_survived = self.survival_counts.get(&obj.id()).copied()?;
+                let internal_refs = referrers.get(&obj.id()).map_or(0, Vec::len);
+                if collections_survived < min_generations_survived
+                    || internal_refs == 0
+                    || obj.get_refcount() > internal_refs
+                {
+                    return None;
+                }
+
+                let path = retaining_path(obj.id(), &referrers);
+                Some(LeakReport {
+                    object_id: obj.id(),
+                    type_name: obj.name().to_string(),
+                    collections_survived,
+                    refcount: obj.get_refcount(),
+                    internal_refs,
+                    retaining_labels: self.path_labels(&objects, &path),
+                    retaining_path: path,
+                })
+            })
+            .collect();
+        reports.sort_by_key(|report| objects[&report.object_id].allocation());
+        reports
+    }
+
+    /// Check the collector's bookkeeping invariants, returning every
+    /// violation found. An empty result means the heap is consistent.
+    pub fn verify_heap(&self) -> Vec<IntegrityViolation> {
+        let mut violations = Vec::new();
+
+        let mut keys: Vec<&ObjectId> = self.tracked_objects.keys().collect();
+        keys.sort_by_key(|obj_id| obj_id.as_u64());
+        let mut objects: Vec<&PyObject> = Vec::new();
+        for key in keys {
+            let obj = &self.tracked_objects[key];
+            if obj.id() != *key {
+                violations.push(IntegrityViolation::KeyMismatch {
+                    key: *key,
+                    object: obj.id(),
+                });
+            }
+            objects.push(obj);
+        }
+        objects.extend(self.uncollectable.iter());
+
+        let mut seen = HashSet::new();
+        for obj in &objects {
+            if !seen.insert(obj.id()) {
+                violations.push(IntegrityViolation::DuplicateObject(obj.id()));
+            }
+        }
+
+        let generation_total = self.generation_manager.get_total_count()
+            + self.generation_manager.large_objects.objects.len();
+        if generation_total != self.tracked_objects.len() {
+            violations.push(IntegrityViolation::GenerationCountMismatch {
+                generation_total,
+                tracked: self.tracked_objects.len(),
+            });
+        }
+
+        let mut edges = Vec::new();
+        for obj in &objects {
+            for child in obj.children() {
+                if seen.contains(&child.id()) {
+                    edges.push((obj.id(), child.id(), ReferenceType::Direct));
+                } else if child.is_gc_tracked() {
+                    violations.push(IntegrityViolation::DanglingReference {
+                        from: obj.id(),
+                        to: child.id(),
+                    });
+                }
+            }
+        }
+        // Every edge joins two of `objects`, so building the graph can't fail.
+        let graph = ObjectGraph::from_edges(objects.iter().map(|&obj| obj.clone()), edges)
+            .unwrap_or_default();
+        violations.extend(graph.verify_consistency());
+
+        for obj in &objects {
+            let refcount = obj.get_refcount();
+            let referrers = graph.referrer_count(&obj.id());
+            if refcount < referrers {
+                violations.push(IntegrityViolation::RefcountBelowInternalRefs {
+                    object: obj.id(),
+                    refcount,
+                    internal_refs: referrers,
+                });
+            }
+
+            if !obj.is_gc_tracked() {
+                violations.push(IntegrityViolation::TrackedFlagCleared(obj.id()));
+            }
+
+            let gc_refs = obj.gc_head().get_refs();
+            if gc_refs > refcount as isize {
+                violations.push(IntegrityViolation::GcRefsAboveRefcount {
+                    object: obj.id(),
+                    gc_refs,
+                    refcount,
+                });
+            }
+
+            if obj.gc_head().is_collecting() && !self.collecting_objects.contains(&obj.id()) {
+                violations.push(IntegrityViolation::StaleCollectingFlag(obj.id()));
+            }
+
+            if obj.gc_head().is_unreachable() && self.collecting_objects.is_empty() {
+                violations.push(IntegrityViolation::StaleUnreachableFlag(obj.id()));
+            }
+        }
+
+        violations
+    }
+
+    /// Scan the whole heap for references to what the minor collection
+    /// `step` freed, each one an old-to-young edge it missed.
+    pub fn verify_barriers(&self, step: &CollectionStep) -> Vec<BarrierViolation> {
+        let freed: HashSet<ObjectId> = step.collected.iter().copied().collect();
+        let tracer = self.tracer.as_deref();
+        let mut violations = Vec::new();
+        for obj in self.all_objects().into_values() {
+            let mut missed = Vec::new();
+            visit_children(obj, tracer, &mut |child_id| {
+                if freed.contains(&child_id) {
+                    missed.push(child_id);
+                }
+            });
+            self.visit_ephemerons(&obj.id(), &mut |value| {
+                if freed.contains(&value) {
+                    missed.push(value);
+                }
+            });
+            violations.extend(missed.into_iter().map(|to| BarrierViolation {
+                from: obj.id(),
+                from_type: obj.name().to_string(),
+                from_generation: self.generation_of(&obj.id()),
+                to,
+                generation: step.generation,
+            }));
         }
+        violations.sort_by_key(|v| (v.from.as_u64(), v.to.as_u64()));
+        violations
+    }
 
-        let mut collected = 0;
-        let objects_to_collect: Vec<ObjectId> = self.tracked_objects.keys().cloned().collect();
+    /// Compare every tracked object's refcount with its referrers plus its
+    /// declared external references. Immortal objects are skipped. Costs a
+    /// walk of the whole graph.
+    pub fn audit_refcounts(&self) -> Vec<RefcountDiscrepancy> {
+        let objects = self.all_objects();
+        let referrers = referrers_of(&objects, &self.ephemerons);
+        let mut discrepancies: Vec<RefcountDiscrepancy> = objects
+            .values()
+            .filter(|obj| !obj.is_immortal())
+            .filter_map(|obj| {
+                let mut owners = referrers.get(&obj.id()).cloned().unwrap_or_default();
+                let refcount = obj.get_refcount();
+                let external_refs = obj.external_refs();
+                if refcount == owners.len() + external_refs {
+                    return None;
+                }
+                owners.sort_by_key(ObjectId::as_u64);
+                Some(RefcountDiscrepancy {
+                    object: obj.id(),
+                    type_name: obj.name().to_string(),
+                    refcount,
+                    internal_refs: owners.len(),
+                    external_refs,
+                    referrers: owners,
+                })
+            })
+            .collect();
+        discrepancies.sort_by_key(|d| d.object.as_u64());
+        discrepancies
+    }
+
+    pub fn explain_object(&self, obj_id: &ObjectId) -> Option<ObjectExplanation> {
+        let objects = self.all_objects();
+        let obj = objects.get(obj_id)?;
+        let referrers = referrers_of(&objects, &self.ephemerons);
+        let path = retaining_path(*obj_id, &referrers);
+
+        Some(ObjectExplanation {
+            object_id: obj.id(),
+            type_name: obj.name().to_string(),
+            generation: self.generation_of(obj_id),
+            collections_survived: self.survival_counts.get(obj_id).copied().unwrap_or(0),
+            refcount: obj.get_refcount(),
+            internal_refs: referrers.get(obj_id).map_or(0, Vec::len),
+            has_finalizer: obj.has_finalizer(),
+            retaining_labels: self.path_labels(&objects, &path),
+            retaining_path: path,
+        })
+    }
+
+    /// Name the reference behind each step of a retaining path, calling an
+    /// ephemeron's hold on its value `ephemeron`.
+    fn path_labels(
+        &self,
+        objects: &HashMap<ObjectId, &PyObject>,
+        path: &[ObjectId],
+    ) -> Vec<Option<String>> {
+        path.windows(2)
+            .map(|step| {
+                let (from, to) = (&step[0], &step[1]);
+                objects
+                    .get(from)
+                    .and_then(|obj| edge_label(obj, self.tracer.as_deref(), to))
+                    .or_else(|| {
+                        self.ephemerons
+                            .get(from)
+                            .is_some_and(|values| values.contains(to))
+                            .then(|| "ephemeron".to_string())
+                    })
+            })
+            .collect()
+    }
+
+    /// The generation `obj_id` is in, or `None` if it isn't tracked, is
+    /// uncollectable or is in the permanent generation. Large objects only
+    /// go with full collections, so they report the oldest generation.
+    pub fn generation_of(&self, obj_id: &ObjectId) -> Option<usize> {
+        if !self.tracked_objects.contains_key(obj_id) || self.permanent.contains(obj_id) {
+            return None;
+        }
+        if self
+            .generation_manager
+            .large_objects
+            .objects
+            .contains(obj_id)
+        {
+            return Some(self.generation_manager.generations.len() - 1);
+        }
+        Some(self.object_generations.get(obj_id).copied().unwrap_or(0))
+    }
+
+    /// Take the survivors the promotion policy keeps in their generation out
+    /// of `step.promoted`, leaving them in the collected generation with
+    /// their age counted.
+    fn hold_back_young(&mut self, step: &mut CollectionStep) {
+        let PromotionPolicy::Aged {
+            survivals,
+            early_bytes,
+        } = self.promotion_policy
+        else {
+            return;
+        };
+        let mut held = 0;
+        let mut promoted = Vec::with_capacity(step.promoted.len());
+        for obj_id in std::mem::take(&mut step.promoted) {
+            let age = self.promotion_ages.get(&obj_id).copied().unwrap_or(0) + 1;
+            if age >= survivals {
+                self.promotion_ages.remove(&obj_id);
+                promoted.push(obj_id);
+                continue;
+            }
+            let size = self.tracked_objects[&obj_id].shallow_size();
+            if early_bytes.is_some_and(|early| size >= early) {
+                self.promotion_ages.remove(&obj_id);
+                self.promotion_stats.promoted_early += 1;
+                promoted.push(obj_id);
+                continue;
+            }
+            self.promotion_ages.insert(obj_id, age);
+            held += 1;
+        }
+        self.promotion_stats.held_back += held;
+        step.promoted = promoted;
+    }
+
+    /// Change the promotion policy, starting its statistics afresh.
+    pub fn set_promotion_policy(&mut self, policy: PromotionPolicy) {
+        self.promotion_policy = policy;
+        self.promotion_stats = PromotionStats::default();
+        self.promotion_ages.clear();
+    }
 
-        for obj_id in objects_to_collect {
-            if self.untrack_object_fast(&obj_id).is_ok() {
-                collected += 1;
+    /// Record the survivors of a collection as being in generation `next`.
+    fn promote_survivors(&mut self, survivors: &[ObjectId], next: usize) {
+        if next > 0 {
+            for obj_id in survivors {
+                self.object_generations.insert(*obj_id, next);
             }
         }
+    }
+
+    /// Move `obj_id` into the next older generation, carrying its count
+    /// along. Returns the generation it ends up in.
+    pub fn promote(&mut self, obj_id: &ObjectId) -> GCResult<usize> {
+        let oldest = self.generation_manager.generations.len() - 1;
+        self.move_generation(obj_id, |generation| (generation + 1).min(oldest))
+    }
+
+    /// Move `obj_id` into the next younger generation, so the next young
+    /// collection counts it again. Returns the generation it ends up in.
+    pub fn demote(&mut self, obj_id: &ObjectId) -> GCResult<usize> {
+        self.move_generation(obj_id, |generation| generation.saturating_sub(1))
+    }
+
+    /// Move `obj_id` into the permanent generation, where collections leave
+    /// it alone until `unfreeze`.
+    pub fn move_to_permanent(&mut self, obj_id: &ObjectId) -> GCResult<()> {
+        if self.is_collecting() {
+            return Err(GCError::CollectionInProgress);
+        }
+        if !self.tracked_objects.contains_key(obj_id) {
+            return Err(GCError::NotTracked { id: *obj_id });
+        }
+        if !self.permanent.contains(obj_id) {
+            let obj = self.tracked_objects[obj_id].clone();
+            self.uncount(&obj);
+            self.permanent.insert(*obj_id);
+        }
+        Ok(())
+    }
 
-        self.generation_manager.generations[generation].count = 0;
+    /// Move every tracked object into the permanent generation, as
+    /// `gc.freeze()` does after a fork server has warmed up. Returns how
+    /// many objects were moved.
+    pub fn freeze(&mut self) -> GCResult<usize> {
+        let thawed: Vec<ObjectId> = self
+            .tracked_objects
+            .keys()
+            .copied()
+            .filter(|obj_id| !self.permanent.contains(obj_id))
+            .collect();
+        for obj_id in &thawed {
+            self.move_to_permanent(obj_id)?;
+        }
+        Ok(thawed.len())
+    }
+
+    /// Move the permanent generation back into the oldest generation, or
+    /// the large object space for large objects. Returns how many objects
+    /// were moved.
+    pub fn unfreeze(&mut self) -> GCResult<usize> {
+        if self.is_collecting() {
+            return Err(GCError::CollectionInProgress);
+        }
+        let frozen = std::mem::take(&mut self.permanent);
+        for obj_id in &frozen {
+            let size = self.tracked_objects[obj_id].counted_size();
+            self.place_in_oldest(*obj_id, size);
+        }
+        Ok(frozen.len())
+    }
+
+    pub fn freeze_count(&self) -> usize {
+        self.permanent.len()
+    }
+
+    fn move_generation(
+        &mut self,
+        obj_id: &ObjectId,
+        to: impl FnOnce(usize) -> usize,
+    ) -> GCResult<usize> {
+        if self.is_collecting() {
+            return Err(GCError::CollectionInProgress);
+        }
+        if self.permanent.contains(obj_id) {
+            return Err(GCError::internal(
+                "move_generation",
+                "object is in the permanent generation",
+            ));
+        }
+        let from = self
+            .generation_of(obj_id)
+            .ok_or(GCError::NotTracked { id: *obj_id })?;
+        if self
+            .generation_manager
+            .large_objects
+            .objects
+            .contains(obj_id)
+        {
+            return Ok(from);
+        }
+        let to = to(from);
+        if to != from {
+            let size = self
+                .tracked_objects
+                .get(obj_id)
+                .map_or(0, PyObject::counted_size);
+            self.generation_manager.move_objects(from, to, 1, size);
+            if to == 0 {
+                self.object_generations.remove(obj_id);
+            } else {
+                self.object_generations.insert(*obj_id, to);
+            }
+        }
+        Ok(to)
+    }
 
-        Ok(collected)
+    fn all_objects(&self) -> HashMap<ObjectId, &PyObject> {
+        self.tracked_objects
+            .values()
+            .chain(self.uncollectable.iter())
+            .map(|obj| (obj.id(), obj))
+            .collect()
+    }
+
+    /// Tag `obj_id`, or clear its tag with `None`. The tag goes with the
+    /// object, so it survives elision and moves between heaps.
+    pub fn set_tag(&mut self, obj_id: &ObjectId, tag: Option<&str>) -> GCResult<()> {
+        let obj = self
+            .find_object_mut(obj_id)
+            .ok_or(GCError::NotTracked { id: *obj_id })?;
+        obj.set_tag(tag);
+        Ok(())
+    }
+
+    /// Count, shallow size and survivors of tracked objects, by tag.
+    /// Untagged objects are left out.
+    pub fn tag_stats(&self) -> BTreeMap<String, TagStats> {
+        let mut stats: BTreeMap<String, TagStats> = BTreeMap::new();
+        for obj in self
+            .tracked_objects
+            .values()
+            .chain(self.uncollectable.iter())
+        {
+            let Some(tag) = obj.tag() else {
+                continue;
+            };
+            let entry = stats.entry(tag.to_string()).or_default();
+            entry.objects += 1;
+            entry.bytes += obj.shallow_size();
+            if self.survival_counts.get(&obj.id()).is_some_and(|&n| n > 0) {
+                entry.survivors += 1;
+            }
+        }
+        stats
+    }
+
+    /// Tracked objects under `key` to evict, oldest first, for them to fit
+    /// `quota` again; empty while they already fit. Age is the number of
+    /// collections survived, with ties broken by id.
+    pub fn quota_candidates(&self, key: &QuotaKey, quota: &Quota) -> Vec<ObjectId> {
+        let mut objects: Vec<(usize, ObjectId, usize)> = self
+            .tracked_objects
+            .values()
+            .filter(|obj| match key {
+                QuotaKey::Tag(tag) => obj.tag().is_some_and(|t| *t == **tag),
+                QuotaKey::Type(name) => obj.name() == name,
+            })
+            .map(|obj| {
+                let survived = self.survival_counts.get(&obj.id()).copied().unwrap_or(0);
+                (survived, obj.id(), obj.shallow_size())
+            })
+            .collect();
+        let bytes: usize = objects.iter().map(|&(_, _, size)| size).sum();
+        let mut excess_objects = quota
+            .max_objects
+            .map_or(0, |max| objects.len().saturating_sub(max));
+        let mut excess_bytes = quota.max_bytes.map_or(0, |max| bytes.saturating_sub(max));
+        if excess_objects == 0 && excess_bytes == 0 {
+            return Vec::new();
+        }
+
+        objects.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.as_u64().cmp(&b.1.as_u64())));
+        let mut candidates = Vec::new();
+        for (_, id, size) in objects {
+            if excess_objects == 0 && excess_bytes == 0 {
+                break;
+            }
+            candidates.push(id);
+            excess_objects = excess_objects.saturating_sub(1);
+            excess_bytes = excess_bytes.saturating_sub(size);
+        }
+        candidates
+    }
+
+    /// Count and shallow size of tracked objects, by type name.
+    pub fn type_histogram(&self) -> BTreeMap<String, TypeStats> {
+        let mut histogram: BTreeMap<String, TypeStats> = BTreeMap::new();
+        for obj in self
+            .tracked_objects
+            .values()
+            .chain(self.uncollectable.iter())
+        {
+            let stats = histogram.entry(obj.name().to_string()).or_default();
+            stats.count += 1;
+            stats.bytes += obj.shallow_size();
+        }
+        histogram
+    }
+
+    /// Deep size of every tracked object, counting objects reachable from
+    /// several roots once.
+    pub fn total_heap_bytes(&self) -> usize {
+        let mut visited = HashSet::new();
+        self.tracked_objects
+            .values()
+            .chain(self.uncollectable.iter())
+            .map(|obj| obj.deep_size_excluding(&mut visited))
+            .sum()
     }
 
     pub fn get_count(&self) -> usize {
         self.tracked_objects.len()
     }
 
+    pub fn is_tracked(&self, obj_id: &ObjectId) -> bool {
+        self.tracked_objects.contains_key(obj_id)
+            || self.uncollectable.iter().any(|obj| obj.id() == *obj_id)
+    }
+
+    pub fn publish_stats(&self) {
+        self.stats_board.publish(self.get_stats());
+    }
+
     pub fn get_stats(&self) -> crate::GCStats {
+        let generations = &self.generation_manager.generations;
         crate::GCStats {
-            collections: 0,
-            collected: 0,
+            collections: generations
+                .iter()
+                .map(|generation| generation.collections)
+                .sum(),
+            collected: self.total_collected,
             uncollectable: self.uncollectable.len(),
             total_tracked: self.tracked_objects.len(),
             generation_counts: [
-                self.generation_manager.generations[0].count,
-                self.generation_manager.generations[1].count,
-                self.generation_manager.generations[2].count,
+                generations[0].count,
+                generations[1].count,
+                generations[2].count,
+            ],
+            generation_collections: [
+                generations[0].collections,
+                generations[1].collections,
+                generations[2].collections,
+            ],
+            generation_collected: [
+                generations[0].collected,
+                generations[1].collected,
+                generations[2].collected,
+            ],
+            thresholds: [
+                generations[0].threshold,
+                generations[1].threshold,
+                generations[2].threshold,
+            ],
+            generation_bytes: [
+                generations[0].bytes,
+                generations[1].bytes,
+                generations[2].bytes,
+            ],
+            generation_scanned: [
+                generations[0].scanned,
+                generations[1].scanned,
+                generations[2].scanned,
             ],
+            generation_promoted: [
+                generations[0].promoted,
+                generations[1].promoted,
+                generations[2].promoted,
+            ],
+            last_pause: self.last_pause,
+            long_lived_total: self.generation_manager.long_lived_total,
+            long_lived_pending: self.generation_manager.long_lived_pending,
+            elided: self.elided.len(),
+            large_object_count: self.generation_manager.large_objects.objects.len(),
+            large_object_bytes: self.generation_manager.large_objects.bytes,
+            permanent_count: self.permanent.len(),
+            permanent_bytes: self
+                .permanent
+                .iter()
+                .filter_map(|obj_id| self.tracked_objects.get(obj_id))
+                .map(PyObject::shallow_size)
+                .sum(),
+            rss_bytes: 0,
+            rss_growth_per_sec: 0,
         }
     }
 
@@ -181,3 +2152,96 @@ impl Collector {
         self.debug_flags
     }
 }
+
+/// Calls its second argument with each object the first one refers to.
+type VisitChildren<'a> = dyn FnMut(&ObjectId, &mut dyn FnMut(ObjectId)) + 'a;
+
+/// Trial deletion over `candidates`: subtract the references candidates
+/// hold on each other from `gc_refs`, then keep whatever is reachable from
+/// a candidate with references left over. Returns the rest, in order.
+fn unreachable_among(
+    candidates: &[ObjectId],
+    mut gc_refs: HashMap<ObjectId, usize>,
+    children: &mut VisitChildren<'_>,
+) -> Vec<ObjectId> {
+    for obj_id in candidates {
+        children(obj_id, &mut |child_id| {
+            if let Some(refs) = gc_refs.get_mut(&child_id) {
+                *refs = refs.saturating_sub(1);
+            }
+        });
+    }
+
+    let mut reachable = HashSet::new();
+    let mut worklist: Vec<ObjectId> = gc_refs
+        .iter()
+        .filter(|&(_, &refs)| refs > 0)
+        .map(|(obj_id, _)| *obj_id)
+        .collect();
+    while let Some(obj_id) = worklist.pop() {
+        if !reachable.insert(obj_id) {
+            continue;
+        }
+        children(&obj_id, &mut |child_id| {
+            if gc_refs.contains_key(&child_id) && !reachable.contains(&child_id) {
+                worklist.push(child_id);
+            }
+        });
+    }
+
+    candidates
+        .iter()
+        .copied()
+        .filter(|obj_id| gc_refs.contains_key(obj_id) && !reachable.contains(obj_id))
+        .collect()
+}
+
+/// Who refers to each of `objects`, counting an ephemeron's key as a
+/// referrer of its value.
+fn referrers_of(
+    objects: &HashMap<ObjectId, &PyObject>,
+    ephemerons: &HashMap<ObjectId, Vec<ObjectId>>,
+) -> HashMap<ObjectId, Vec<ObjectId>> {
+    let mut referrers: HashMap<ObjectId, Vec<ObjectId>> = HashMap::new();
+    for obj in objects.values() {
+        let values = ephemerons.get(&obj.id()).into_iter().flatten().copied();
+        for child in obj.children().iter().map(PyObject::id).chain(values) {
+            if objects.contains_key(&child) {
+                referrers.entry(child).or_default().push(obj.id());
+            }
+        }
+    }
+    referrers
+}
+
+/// Shortest chain of referrers from a root down to `obj_id`. A root is either
+/// `obj_id` itself (the object sits on a cycle) or a node nothing refers to.
+pub(crate) fn retaining_path<N: Copy + Eq + Hash>(
+    obj_id: N,
+    referrers: &HashMap<N, Vec<N>>,
+) -> Vec<N> {
+    let mut retained_by: HashMap<N, N> = HashMap::new();
+    let mut queue = VecDeque::from([obj_id]);
+
+    while let Some(current) = queue.pop_front() {
+        for referrer in referrers.get(&current).into_iter().flatten() {
+            let is_root = *referrer == obj_id || !referrers.contains_key(referrer);
+            if is_root {
+                let mut path = vec![*referrer, current];
+                let mut node = current;
+                while let Some(next) = retained_by.get(&node) {
+                    path.push(*next);
+                    node = *next;
+                }
+                return path;
+            }
+
+            if *referrer != obj_id && !retained_by.contains_key(referrer) {
+                retained_by.insert(*referrer, current);
+                queue.push_back(*referrer);
+            }
+        }
+    }
+
+    vec![obj_id]
+}
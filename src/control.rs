@@ -0,0 +1,406 @@
+//! Control socket for inspecting and driving a running collector.
+//!
+//! Every message in either direction is a frame: a little-endian `u32`
+//! payload length followed by the payload. Requests are UTF-8 command lines;
+//! responses start with a status byte ([`STATUS_OK`] or [`STATUS_ERROR`])
+//! followed by the body.
+//!
+//! | Command                   | Response body                               |
+//! |---------------------------|---------------------------------------------|
+//! | `collect [generation]`    | `collected <n>` (full collection by default)|
+//! | `stats`                   | `key=value` lines                           |
+//! | `thresholds [t0 t1 t2]`   | current thresholds, after setting if given  |
+//! | `summary`                 | tracked totals and per-type object counts   |
+//! | `snapshot`                | heap snapshot in the `HeapSnapshot::save` format |
+
+use crate::GCResult;
+use crate::collector::Collector;
+use crate::error::GCError;
+use crate::snapshot::HeapSnapshot;
+use parking_lot::RwLock;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+pub const STATUS_OK: u8 = 0;
+pub const STATUS_ERROR: u8 = 1;
+
+/// Largest request the server will read; snapshots only flow server to client.
+const MAX_REQUEST_LEN: u32 = 64 * 1024;
+
+/// How long a connection waits for data before checking the stop flag. A
+/// client that stalls this long in the middle of a frame is dropped.
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub fn write_frame(mut writer: impl Write, payload: &[u8]) -> io::Result<()> {
+    let len = u32::try_from(payload.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too large"))?;
+    writer.write_all(&len.to_le_bytes())?;
+    writer.write_all(payload)?;
+    writer.flush()
+}
+
+/// Read one frame, or `None` on a clean end of stream.
+pub fn read_frame(mut reader: impl Read, max_len: u32) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+
+    let len = u32::from_le_bytes(len);
+    if len > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("frame of {len} bytes exceeds limit of {max_len}"),
+        ));
+    }
+
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    Ok(Some(payload))
+}
+
+/// Send `command` to the server at `path` and return the response body.
+pub fn request(path: impl AsRef<Path>, command: &str) -> GCResult<Vec<u8>> {
+    let mut stream = UnixStream::connect(path)?;
+    write_frame(&mut stream, command.as_bytes())?;
+    let response = read_frame(&mut stream, u32::MAX)?
+        .ok_or_else(|| GCError::Control("Connection closed before a response".to_string()))?;
+
+    match response.split_first() {
+        Some((&STATUS_OK, body)) => Ok(body.to_vec()),
+        Some((&STATUS_ERROR, body)) => {
+            Err(GCError::Control(String::from_utf8_lossy(body).into_owned()))
+        }
+        _ => Err(GCError::Control("Malformed response".to_string())),
+    }
+}
+
+/// A running control server. Dropping it stops the server and removes the
+/// socket file.
+#[derive(Debug)]
+pub struct ControlServer {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ControlServer {
+    pub(crate) fn bind(
+        collector: Arc<RwLock<Collector>>,
+        path: impl AsRef<Path>,
+    ) -> GCResult<Self> {
+        let path = path.as_ref().to_path_buf();
+        if path.exists() {
+            if UnixStream::connect(&path).is_ok() {
+                return Err(GCError::Control(format!(
+                    "{} is already being served",
+                    path.display()
+                )));
+            }
+            std::fs::remove_file(&path)?;
+        }
+
+        let listener = UnixListener::bind(&path)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            std::thread::Builder::new()
+                .name("gc-control".to_string())
+                .spawn(move || serve(listener, collector, stop))?
+        };
+
+        Ok(Self {
+            path,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn shutdown(mut self) {
+        self.stop_thread();
+    }
+
+    fn stop_thread(&mut self) {
+        let Some(thread) = self.thread.take() else {
+            return;
+        };
+
+        self.stop.store(true, Ordering::Release);
+        // Wake the blocking accept so the thread sees the stop flag.
+        let _ = UnixStream::connect(&self.path);
+        let _ = thread.join();
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        self.stop_thread();
+    }
+}
+
+/// Accept connections until stopped, serving each on its own thread so an
+/// idle client cannot hold up the others.
+fn serve(listener: UnixListener, collector: Arc<RwLock<Collector>>, stop: Arc<AtomicBool>) {
+    let mut connections: Vec<JoinHandle<()>> = Vec::new();
+    for stream in listener.incoming() {
+        if stop.load(Ordering::Acquire) {
+            break;
+        }
+        connections.retain(|connection| !connection.is_finished());
+        if let Ok(stream) = stream {
+            let collector = collector.clone();
+            let stop = stop.clone();
+            let connection = std::thread::Builder::new()
+                .name("gc-control-conn".to_string())
+                .spawn(move || {
+                    let _ = handle_connection(stream, &collector, &stop);
+                });
+            if let Ok(connection) = connection {
+                connections.push(connection);
+            }
+        }
+    }
+    for connection in connections {
+        let _ = connection.join();
+    }
+}
+
+fn handle_connection(
+    mut stream: UnixStream,
+    collector: &RwLock<Collector>,
+    stop: &AtomicBool,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    loop {
+        if stop.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        // Only the wait for a frame's first byte is retried, so a timeout
+        // never splits one.
+        let mut first = [0u8; 1];
+        match stream.read(&mut first) {
+            Ok(0) => return Ok(()),
+            Ok(_) => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue;
+            }
+            Err(err) => return Err(err),
+        }
+        let Some(request) = read_frame(first.as_slice().chain(&mut stream), MAX_REQUEST_LEN)?
+        else {
+            return Ok(());
+        };
+        let response = match std::str::from_utf8(&request) {
+            Ok(command) => execute(collector, command),
+            Err(_) => Err(GCError::Control("Command is not UTF-8".to_string())),
+        };
+
+        let mut payload = Vec::new();
+        match response {
+            Ok(body) => {
+                payload.push(STATUS_OK);
+                payload.extend(body);
+            }
+            Err(err) => {
+                payload.push(STATUS_ERROR);
+                payload.extend(err.to_string().into_bytes());
+            }
+        }
+        write_frame(&mut stream, &payload)?;
+    }
+}
+
+fn parse_number(word: &str) -> GCResult<usize> {
+    word.parse()
+        .map_err(|_| GCError::Control(format!("Expected a number, got {word:?}")))
+}
+
+fn execute(collector: &RwLock<Collector>, command: &str) -> GCResult<Vec<u8>> {
+    let mut words = command.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let args: Vec<&str> = words.collect();
+
+    let text = match (name, args.as_slice()) {
+        ("collect", []) => format!("collected {}", collector.write().collect_generation(2)?),
+        ("collect", [generation]) => {
+            let generation = parse_number(generation)?;
+            let collected = collector.write().collect_generation(generation)?;
+            format!("collected {collected}")
+        }
+        ("stats", []) => {
+            let collector = collector.read();
+            let stats = collector.get_stats();
+            format!(
+                "collections={}\ncollected={}\nuncollectable={}\ntotal_tracked={}\n\
+                 generation_counts={:?}\ngeneration_collections={:?}\nthresholds={:?}\n\
+                 last_pause_ns={}\nheap_bytes={}",
+                stats.collections,
+                stats.collected,
+                stats.uncollectable,
+                stats.total_tracked,
+                stats.generation_counts,
+                stats.generation_collections,
+                stats.thresholds,
+                stats.last_pause.as_nanos(),
+                collector.total_heap_bytes(),
+            )
+        }
+        ("thresholds", []) => format_thresholds(&collector.read()),
+        ("thresholds", [t0, t1, t2]) => {
+            let thresholds = [parse_number(t0)?, parse_number(t1)?, parse_number(t2)?];
+            let mut collector = collector.write();
+            for (generation, threshold) in thresholds.into_iter().enumerate() {
+                collector.generation_manager.generations[generation].threshold = threshold;
+            }
+            format_thresholds(&collector)
+        }
+        ("summary", []) => summary(&collector.read()),
+        ("snapshot", []) => {
+            let snapshot = HeapSnapshot::capture(&collector.read());
+            let mut bytes = Vec::new();
+            snapshot.write_to(&mut bytes)?;
+            return Ok(bytes);
+        }
+        _ => return Err(GCError::Control(format!("Unknown command: {command:?}"))),
+    };
+
+    Ok(text.into_bytes())
+}
+
+fn format_thresholds(collector: &Collector) -> String {
+    let thresholds: Vec<String> = collector
+        .generation_manager
+        .generations
+        .iter()
+        .map(|generation| generation.threshold.to_string())
+        .collect();
+    thresholds.join(" ")
+}
+
+fn summary(collector: &Collector) -> String {
+    let mut by_type: BTreeMap<&str, usize> = BTreeMap::new();
+    for obj in collector
+        .tracked_objects
+        .values()
+        .chain(collector.uncollectable.iter())
+    {
+        *by_type.entry(obj.name()).or_default() += 1;
+    }
+    let mut by_type: Vec<(&str, usize)> = by_type.into_iter().collect();
+    by_type.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    let mut text = format!(
+        "tracked {}\nuncollectable {}",
+        collector.tracked_objects.len(),
+        collector.uncollectable.len()
+    );
+    for (type_name, count) in by_type {
+        let _ = write!(text, "\n{type_name} {count}");
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GarbageCollector;
+    use crate::object::{ObjectData, PyObject};
+
+    fn socket_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("python_gc_{}_{name}.sock", std::process::id()))
+    }
+
+    #[test]
+    fn test_control_socket_commands() {
+        let gc = GarbageCollector::new();
+        let widget = PyObject::new("widget".to_string(), ObjectData::None);
+        widget.set_refcount(0);
+        gc.track(widget).unwrap();
+        gc.track(PyObject::new_with_finalizer(
+            "handle".to_string(),
+            ObjectData::None,
+        ))
+        .unwrap();
+
+        let path = socket_path("commands");
+        let server = gc.serve_control_socket(&path).unwrap();
+
+        let summary = request(&path, "summary").unwrap();
+        assert_eq!(
+            String::from_utf8(summary).unwrap(),
+            "tracked 1\nuncollectable 1\nhandle 1\nwidget 1"
+        );
+
+        let snapshot = request(&path, "snapshot").unwrap();
+        let snapshot = HeapSnapshot::read_from(snapshot.as_slice()).unwrap();
+        assert_eq!(snapshot.objects.len(), 2);
+
+        assert_eq!(request(&path, "collect").unwrap(), b"collected 1");
+        assert_eq!(gc.get_count(), 0);
+
+        assert_eq!(request(&path, "thresholds 100 5 5").unwrap(), b"100 5 5");
+        assert_eq!(gc.get_threshold(0), Some(100));
+
+        let stats = String::from_utf8(request(&path, "stats").unwrap()).unwrap();
+        assert!(stats.contains("collected=1"));
+        assert!(stats.contains("heap_bytes="));
+
+        assert!(matches!(
+            request(&path, "collect 7"),
+            Err(GCError::Control(message)) if message == "Invalid generation: 7"
+        ));
+        assert!(matches!(request(&path, "bogus"), Err(GCError::Control(_))));
+
+        server.shutdown();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_idle_client_does_not_block_others() {
+        let gc = GarbageCollector::new();
+        let path = socket_path("idle");
+        let server = gc.serve_control_socket(&path).unwrap();
+
+        // Connected but silent, and another one stalled mid-frame.
+        let _idle = UnixStream::connect(&path).unwrap();
+        let mut stalled = UnixStream::connect(&path).unwrap();
+        stalled.write_all(&16u32.to_le_bytes()).unwrap();
+
+        assert_eq!(request(&path, "thresholds").unwrap(), b"700 10 10");
+
+        // Shutting down does not wait on either of them.
+        server.shutdown();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_frames_reject_oversized_requests() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"hello").unwrap();
+        assert_eq!(
+            read_frame(buffer.as_slice(), 16).unwrap(),
+            Some(b"hello".to_vec())
+        );
+        assert!(read_frame(buffer.as_slice(), 4).is_err());
+        assert_eq!(read_frame(&[][..], 16).unwrap(), None);
+    }
+}
@@ -1,12 +1,15 @@
+use crate::object::ObjectId;
+use std::error::Error;
+use std::sync::Arc;
 use thiserror::Error;
 
 #[derive(Error, Debug, Clone)]
 pub enum GCError {
-    #[error("Object is already tracked")]
-    AlreadyTracked,
+    #[error("Object {id} is already tracked")]
+    AlreadyTracked { id: ObjectId },
 
-    #[error("Object is not tracked")]
-    NotTracked,
+    #[error("Object {id} is not tracked")]
+    NotTracked { id: ObjectId },
 
     #[error("Garbage collection already in progress")]
     CollectionInProgress,
@@ -14,22 +17,85 @@ pub enum GCError {
     #[error("Invalid generation: {0}")]
     InvalidGeneration(usize),
 
-    #[error("Object has finalizer and cannot be collected")]
-    HasFinalizer,
+    #[error("GC not initialized")]
+    NotInitialized,
 
-    #[error("Internal error: {0}")]
-    Internal(String),
+    #[error("Object {id} has finalizer and cannot be collected")]
+    HasFinalizer { id: ObjectId },
+
+    #[error("Internal error in {operation}: {message}")]
+    Internal {
+        operation: &'static str,
+        message: String,
+        #[source]
+        source: Option<Arc<dyn Error + Send + Sync>>,
+    },
 
     #[error("Memory allocation failed: {0}")]
     AllocationFailed(String),
 
-    #[error("Reference count error: {0}")]
-    ReferenceCountError(String),
+    #[error("Reference count error on object {id}: {message}")]
+    ReferenceCountError { id: ObjectId, message: String },
+
+    #[error("Snapshot error: {0}")]
+    Snapshot(String),
+
+    #[error("Control socket error: {0}")]
+    Control(String),
+}
+
+impl GCError {
+    pub fn internal(operation: &'static str, message: impl Into<String>) -> Self {
+        GCError::Internal {
+            operation,
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    /// Like [`internal`](Self::internal), keeping `source` reachable through
+    /// [`Error::source`].
+    pub fn internal_with_source(
+        operation: &'static str,
+        message: impl Into<String>,
+        source: impl Error + Send + Sync + 'static,
+    ) -> Self {
+        GCError::Internal {
+            operation,
+            message: message.into(),
+            source: Some(Arc::new(source)),
+        }
+    }
+
+    /// Stable numeric code, matching the FFI layer's `gc_return_code_t`.
+    pub fn code(&self) -> i32 {
+        match self {
+            GCError::AlreadyTracked { .. } => -1,
+            GCError::NotTracked { .. } => -2,
+            GCError::CollectionInProgress => -3,
+            GCError::InvalidGeneration(_) => -4,
+            GCError::NotInitialized => -7,
+            _ => -5,
+        }
+    }
+}
+
+/// Serialized as its stable `code` and display message.
+#[cfg(feature = "serde")]
+impl serde::Serialize for GCError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("GCError", 2)?;
+        state.serialize_field("code", &self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
 }
 
 impl From<std::io::Error> for GCError {
     fn from(err: std::io::Error) -> Self {
-        GCError::Internal(format!("IO error: {err}"))
+        GCError::internal_with_source("io", format!("IO error: {err}"), err)
     }
 }
 
@@ -1,33 +1,380 @@
-use crate::object::{ObjectData, PyObject};
+use crate::collector::retaining_path;
+use crate::error::GCError;
+use crate::gc::{
+    DEBUG_AUDIT_REFS, DEBUG_SAVEALL, DEBUG_STATS, DEBUG_VERIFY_BARRIERS, EdgeChange, PreCollect,
+    PreCollectHookId, ReferenceHistoryLimits, WatchEvent, WatchId, WatchKind,
+    reset_lock_after_fork,
+};
+use crate::object::{ObjectData, ObjectId, PyObject};
+use crate::python_api::{self, GCPythonApi, PythonApi};
+use crate::traversal::{ReferenceType, cyclic_components};
 use crate::{GCResult, GarbageCollector};
-use std::cell::RefCell;
-use std::collections::HashMap;
+use parking_lot::{Mutex, RwLock};
+use std::borrow::Borrow;
+use std::cell::{Cell, RefCell};
 use std::collections::HashSet;
-use std::ffi::{c_char, c_int, c_uint, c_void};
-use std::sync::atomic::{AtomicBool, Ordering};
-
-unsafe extern "C" {
-    fn PyList_New(size: isize) -> *mut c_void;
-    fn PyList_SetItem(list: *mut c_void, index: isize, item: *mut c_void) -> c_int;
-    fn PyList_GetItem(list: *mut c_void, index: isize) -> *mut c_void;
-    fn PyList_Size(list: *mut c_void) -> isize;
-    fn Py_IncRef(obj: *mut c_void);
-    fn Py_DecRef(obj: *mut c_void);
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::{CString, c_char, c_int, c_uint, c_ulong, c_void};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+macro_rules! instance_registry {
+    ($name:ident: $ty:ty) => {
+        #[cfg(not(feature = "ft"))]
+        static $name: Registry<$ty> = {
+            thread_local! {
+                static CELLS: InstanceCells<$ty> = RefCell::new(HashMap::new());
+            }
+            Registry { cells: &CELLS }
+        };
+        #[cfg(feature = "ft")]
+        static $name: Registry<$ty> = Registry {
+            cells: std::sync::OnceLock::new(),
+        };
+    };
 }
 
-static mut GC: Option<GarbageCollector> = None;
-static AUTOMATIC_TRACKING: AtomicBool = AtomicBool::new(false);
+static DEFAULT_INSTANCE: Instance = Instance::new();
+static INSTANCES: RwLock<BTreeMap<GCInstanceHandle, Arc<Instance>>> = RwLock::new(BTreeMap::new());
+static NEXT_INSTANCE: AtomicU64 = AtomicU64::new(1);
+static UNWRAPPED_PYTHON_API: RwLock<Option<Arc<dyn PythonApi>>> = RwLock::new(None);
+static REPORTED_ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static REPORTED_FREES: AtomicUsize = AtomicUsize::new(0);
+static LOG_CALLBACK: RwLock<Option<LogCallback>> = RwLock::new(None);
+static PYTHON_HOOKS: RwLock<Option<GCPythonHooks>> = RwLock::new(None);
+static TYPE_LAYOUT: RwLock<TypeLayout> = RwLock::new(TypeLayout::BUILTIN);
+static ABI3_API: RwLock<Option<GCAbi3Api>> = RwLock::new(None);
+
+static THREAD_HOOKS: RwLock<Option<GCThreadHooks>> = RwLock::new(None);
+static WORLD: Mutex<()> = Mutex::new(());
+
+instance_registry!(OBJECT_REGISTRY: HashMap<ObjectPtr, PyObject>);
+instance_registry!(OBJECT_POINTERS: HashMap<ObjectId, ObjectPtr>);
+instance_registry!(REFCOUNT_CALLBACKS: HashMap<ObjectPtr, RefCountCallback>);
+instance_registry!(REFERENCE_TRACKING: HashMap<ObjectPtr, HashMap<ObjectPtr, ReferenceType>>);
+instance_registry!(REFERENCE_LABELS: HashMap<(ObjectPtr, ObjectPtr), String>);
+instance_registry!(UNCOLLECTABLE_OBJECTS: Vec<ObjectPtr>);
+instance_registry!(SAVED_GARBAGE: Vec<ObjectPtr>);
+
+/// An object pointer as the registries hold it. They only hash, compare
+/// and hand it back; it is dereferenced, like any pointer a caller passes
+/// in, under that call's safety contract.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct ObjectPtr(*mut c_void);
+
+// SAFETY: an address has no thread affinity, and the registry never
+// dereferences it. Free-threaded interpreters share objects between
+// threads, so a pointer tracked on one may be released on another.
+unsafe impl Send for ObjectPtr {}
+
+impl Borrow<*mut c_void> for ObjectPtr {
+    fn borrow(&self) -> &*mut c_void {
+        &self.0
+    }
+}
 
 thread_local! {
-    static OBJECT_REGISTRY: RefCell<HashMap<*mut c_void, PyObject>> = RefCell::new(HashMap::new());
-    static REFCOUNT_CALLBACKS: RefCell<HashMap<*mut c_void, RefCountCallback>> = RefCell::new(HashMap::new());
-    static REFERENCE_TRACKING: RefCell<HashMap<*mut c_void, HashSet<*mut c_void>>> = RefCell::new(HashMap::new());
-    static UNCOLLECTABLE_OBJECTS: RefCell<Vec<*mut c_void>> = const { RefCell::new(Vec::new()) };
+    static LAST_ERROR: RefCell<Option<(GCReturnCode, String)>> = const { RefCell::new(None) };
+    static REQUIRED_BUFFER_SIZE: Cell<usize> = const { Cell::new(0) };
+    static WORLD_DEPTH: Cell<usize> = const { Cell::new(0) };
+    static CURRENT_INSTANCE: Cell<GCInstanceHandle> = const { Cell::new(DEFAULT_INSTANCE_HANDLE) };
+}
+
+/// Handle naming a GC instance. The global functions act on the default
+/// instance; the `_h` variants act on the one named.
+pub type GCInstanceHandle = u64;
+
+/// Handle of the instance the global functions use.
+pub const DEFAULT_INSTANCE_HANDLE: GCInstanceHandle = 0;
+
+/// One collector and the FFI state that goes with it; the object registries
+/// live in `instance_registry!` statics keyed by the instance's handle.
+struct Instance {
+    gc: RwLock<Option<GarbageCollector>>,
+    automatic_tracking: AtomicBool,
+}
+
+impl Instance {
+    const fn new() -> Self {
+        Self {
+            gc: RwLock::new(None),
+            automatic_tracking: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Run `f` against the calling thread's current instance, or return `None`
+/// if that instance has been destroyed.
+fn with_instance<R>(f: impl FnOnce(&Instance) -> R) -> Option<R> {
+    match CURRENT_INSTANCE.get() {
+        DEFAULT_INSTANCE_HANDLE => Some(f(&DEFAULT_INSTANCE)),
+        handle => {
+            let instance = INSTANCES.read().get(&handle).cloned();
+            instance.map(|instance| f(&instance))
+        }
+    }
+}
+
+/// Run `f` with `handle` as this thread's current instance.
+fn in_instance<R: InstanceResult>(handle: GCInstanceHandle, f: impl FnOnce() -> R) -> R {
+    if handle != DEFAULT_INSTANCE_HANDLE && !INSTANCES.read().contains_key(&handle) {
+        return R::unknown_instance(handle);
+    }
+    let previous = CURRENT_INSTANCE.replace(handle);
+    let result = f();
+    CURRENT_INSTANCE.set(previous);
+    result
+}
+
+/// What a `_h` variant returns when its handle names no instance.
+trait InstanceResult {
+    fn unknown_instance(handle: GCInstanceHandle) -> Self;
+}
+
+impl InstanceResult for GCReturnCode {
+    fn unknown_instance(handle: GCInstanceHandle) -> Self {
+        fail(
+            GCReturnCode::ErrorNotInitialized,
+            format!("Unknown GC instance {handle}"),
+        )
+    }
+}
+
+impl InstanceResult for c_int {
+    fn unknown_instance(handle: GCInstanceHandle) -> Self {
+        GCReturnCode::unknown_instance(handle);
+        0
+    }
+}
+
+impl InstanceResult for usize {
+    fn unknown_instance(handle: GCInstanceHandle) -> Self {
+        GCReturnCode::unknown_instance(handle);
+        0
+    }
+}
+
+impl<T> InstanceResult for *mut T {
+    fn unknown_instance(handle: GCInstanceHandle) -> Self {
+        GCReturnCode::unknown_instance(handle);
+        std::ptr::null_mut()
+    }
+}
+
+impl InstanceResult for () {
+    fn unknown_instance(handle: GCInstanceHandle) {
+        GCReturnCode::unknown_instance(handle);
+    }
+}
+
+type InstanceCells<T> = RefCell<HashMap<GCInstanceHandle, Rc<RefCell<T>>>>;
+
+/// Per-instance FFI state. `with` keeps the shape of `LocalKey::with`,
+/// handing out the current instance's cell. The cells are thread-local,
+/// except with the `ft` feature: free-threaded interpreters may release an
+/// object on a different thread than tracked it, so the cells are shared
+/// and every access runs under `REGISTRY_LOCK`.
+struct Registry<T: 'static> {
+    #[cfg(not(feature = "ft"))]
+    cells: &'static std::thread::LocalKey<InstanceCells<T>>,
+    #[cfg(feature = "ft")]
+    cells: std::sync::OnceLock<InstanceCells<T>>,
+}
+
+// SAFETY: the cells, and the `Rc`s in them, are never touched without
+// `REGISTRY_LOCK` held: `with_cells` is the only way to reach them and
+// takes the lock's guard, and `with` drops its clone of an `Rc` before it
+// lets go of the guard. So no two threads touch a `RefCell` or an `Rc`
+// count at once. `T: Send` because the state is handed between the threads
+// that take turns holding the lock.
+#[cfg(feature = "ft")]
+unsafe impl<T: Send> Sync for Registry<T> {}
+
+#[cfg(feature = "ft")]
+static REGISTRY_LOCK: parking_lot::ReentrantMutex<()> = parking_lot::ReentrantMutex::new(());
+
+/// Proof that the registries may be touched: the `REGISTRY_LOCK` guard
+/// with `ft`, nothing without it, as the cells are then per-thread.
+#[cfg(feature = "ft")]
+type RegistryGuard = parking_lot::ReentrantMutexGuard<'static, ()>;
+#[cfg(not(feature = "ft"))]
+struct RegistryGuard;
+
+fn lock_registries() -> RegistryGuard {
+    #[cfg(feature = "ft")]
+    return REGISTRY_LOCK.lock();
+    #[cfg(not(feature = "ft"))]
+    RegistryGuard
+}
+
+impl<T: Default> Registry<T> {
+    fn with<R>(&'static self, f: impl FnOnce(&RefCell<T>) -> R) -> R {
+        let locked = lock_registries();
+        let instance = CURRENT_INSTANCE.get();
+        let cell = self.with_cells(&locked, |cells| {
+            cells
+                .borrow_mut()
+                .entry(instance)
+                .or_insert_with(Rc::default)
+                .clone()
+        });
+        // `cell` is dropped before `locked`, so the Rc count changes under
+        // the lock.
+        f(&cell)
+    }
+
+    /// Drop `instance`'s state; without `ft`, only this thread's.
+    fn remove(&'static self, instance: GCInstanceHandle) {
+        let locked = lock_registries();
+        self.with_cells(&locked, |cells| cells.borrow_mut().remove(&instance));
+    }
+
+    #[cfg(not(feature = "ft"))]
+    fn with_cells<R>(
+        &'static self,
+        _locked: &RegistryGuard,
+        f: impl FnOnce(&InstanceCells<T>) -> R,
+    ) -> R {
+        self.cells.with(f)
+    }
+
+    #[cfg(feature = "ft")]
+    fn with_cells<R>(
+        &'static self,
+        _locked: &RegistryGuard,
+        f: impl FnOnce(&InstanceCells<T>) -> R,
+    ) -> R {
+        f(self.cells.get_or_init(Default::default))
+    }
 }
 
 type RefCountCallback = Box<dyn Fn(*mut c_void, i32) + Send + Sync>;
 
-const PY_TPFLAGS_HAVE_GC: u64 = 0x00000020;
+pub type LogCallback = extern "C" fn(level: c_int, message: *const c_char);
+
+pub type ObjectVisitor = extern "C" fn(obj_ptr: *mut c_void, user_data: *mut c_void) -> c_int;
+
+/// Called with the watched object, the `GC_WATCH_*` kind that fired, its
+/// new refcount for `GC_WATCH_REFCOUNT` (0 otherwise) and the user data.
+pub type ObjectWatcher =
+    extern "C" fn(obj_ptr: *mut c_void, event: c_int, refcount: usize, user_data: *mut c_void);
+
+/// Called before each collection with the generation about to be collected
+/// and the current stats. Returning nonzero vetoes the collection.
+pub type PreCollectCallback = extern "C" fn(
+    generation: c_int,
+    stats: *const GCStatsExtended,
+    user_data: *mut c_void,
+) -> c_int;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GCPythonHooks {
+    pub gil_ensure: Option<extern "C" fn() -> c_int>,
+    pub gil_release: Option<extern "C" fn(c_int)>,
+}
+
+struct GilGuard {
+    release: Option<(extern "C" fn(c_int), c_int)>,
+}
+
+impl GilGuard {
+    fn acquire() -> Self {
+        let hooks = *PYTHON_HOOKS.read();
+        let release = match hooks {
+            Some(GCPythonHooks {
+                gil_ensure: Some(ensure),
+                gil_release: Some(release),
+            }) => Some((release, ensure())),
+            _ => None,
+        };
+
+        Self { release }
+    }
+}
+
+impl Drop for GilGuard {
+    fn drop(&mut self) {
+        if let Some((release, state)) = self.release.take() {
+            release(state);
+        }
+    }
+}
+
+/// Embedder callbacks run when the world is stopped and restarted, for
+/// pausing interpreter threads the library can't see (on free-threaded
+/// CPython, a stop-the-world over every attached thread state).
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GCThreadHooks {
+    pub stop_the_world: Option<extern "C" fn()>,
+    pub start_the_world: Option<extern "C" fn()>,
+}
+
+/// Holds the world stopped: collections wait for it, and the thread holding
+/// it may stop it again without blocking.
+struct WorldGuard;
+
+impl WorldGuard {
+    fn acquire() -> Self {
+        stop_world();
+        Self
+    }
+}
+
+impl Drop for WorldGuard {
+    fn drop(&mut self) {
+        resume_world();
+    }
+}
+
+fn stop_world() {
+    let depth = WORLD_DEPTH.get();
+    if depth == 0 {
+        std::mem::forget(WORLD.lock());
+        if let Some(GCThreadHooks {
+            stop_the_world: Some(stop),
+            ..
+        }) = *THREAD_HOOKS.read()
+        {
+            stop();
+        }
+    }
+    WORLD_DEPTH.set(depth + 1);
+}
+
+/// Returns false if this thread had not stopped the world.
+fn resume_world() -> bool {
+    let depth = WORLD_DEPTH.get();
+    if depth == 0 {
+        return false;
+    }
+    WORLD_DEPTH.set(depth - 1);
+    if depth == 1 {
+        if let Some(GCThreadHooks {
+            start_the_world: Some(start),
+            ..
+        }) = *THREAD_HOOKS.read()
+        {
+            start();
+        }
+        // Locked by this thread in `stop_world`, its guard forgotten.
+        unsafe { WORLD.force_unlock() };
+    }
+    true
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GCLogLevel {
+    Debug = 0,
+    Info = 1,
+    Warning = 2,
+    Error = 3,
+}
+
+const PY_TPFLAGS_HAVE_GC: c_ulong = 1 << 14;
 
 #[repr(C)]
 struct PyObject_HEAD {
@@ -58,7 +405,7 @@ struct PyTypeObject {
     tp_getattro: Option<unsafe extern "C" fn(*mut c_void, *mut c_void) -> *mut c_void>,
     tp_setattro: Option<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> c_int>,
     tp_as_buffer: *mut c_void,
-    tp_flags: u64,
+    tp_flags: c_ulong,
     tp_doc: *const c_char,
     tp_traverse: Option<unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> c_int>,
     tp_clear: Option<unsafe extern "C" fn(*mut c_void) -> c_int>,
@@ -91,10 +438,266 @@ struct PyTypeObject {
     tp_finalize: Option<unsafe extern "C" fn(*mut c_void)>,
 }
 
+type TraverseProc = unsafe extern "C" fn(*mut c_void, *mut c_void, *mut c_void) -> c_int;
+type InquiryProc = unsafe extern "C" fn(*mut c_void) -> c_int;
+type DestructorProc = unsafe extern "C" fn(*mut c_void);
+
+/// `Py_TRACE_REFS` build: two extra pointers precede `ob_refcnt` in every
+/// object before 3.13.
+pub const GC_ABI_TRACE_REFS: c_uint = 0x1;
+
+/// Free-threaded (`Py_GIL_DISABLED`) build, 3.13 and later: the object
+/// header carries an owning thread id and a split local/shared refcount.
+pub const GC_ABI_FREE_THREADED: c_uint = 0x2;
+
+/// `_Py_REF_SHARED_SHIFT`: low bits of `ob_ref_shared` hold flags.
+const PY_REF_SHARED_SHIFT: u32 = 2;
+
+/// Byte offsets of the object and type fields this layer reads, for one
+/// interpreter build. All field reads go through the accessors below so the
+/// layout can follow `py_gc_set_abi_version`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TypeLayout {
+    /// `ob_refcnt`, or the 32-bit `ob_ref_local` in free-threaded builds.
+    ob_refcnt: usize,
+    ob_ref_shared: Option<usize>,
+    ob_type: usize,
+    tp_name: usize,
+    tp_flags: usize,
+    tp_traverse: usize,
+    tp_clear: usize,
+    tp_del: usize,
+    tp_finalize: Option<usize>,
+}
+
+impl TypeLayout {
+    /// The `PyObject_HEAD` and `PyTypeObject` structs declared above.
+    const BUILTIN: TypeLayout = TypeLayout {
+        ob_refcnt: std::mem::offset_of!(PyObject_HEAD, ob_refcnt),
+        ob_ref_shared: None,
+        ob_type: std::mem::offset_of!(PyObject_HEAD, ob_type),
+        tp_name: std::mem::offset_of!(PyTypeObject, tp_name),
+        tp_flags: std::mem::offset_of!(PyTypeObject, tp_flags),
+        tp_traverse: std::mem::offset_of!(PyTypeObject, tp_traverse),
+        tp_clear: std::mem::offset_of!(PyTypeObject, tp_clear),
+        tp_del: std::mem::offset_of!(PyTypeObject, tp_del),
+        tp_finalize: Some(std::mem::offset_of!(PyTypeObject, tp_finalize)),
+    };
+
+    /// Layout of CPython 3.`minor`. Every slot up to `tp_finalize` is one
+    /// pointer wide (`tp_flags` is padded to one on LP64 and LLP64 alike),
+    /// so the offsets are slot indices scaled by the pointer size.
+    fn cpython(minor: c_int, abi_flags: c_uint) -> TypeLayout {
+        const WORD: usize = size_of::<*const c_void>();
+        // ob_tid, then ob_flags, ob_mutex and ob_gc_bits in four bytes.
+        let free_threaded = abi_flags & GC_ABI_FREE_THREADED != 0;
+        let head = if free_threaded {
+            WORD + 4
+        } else if abi_flags & GC_ABI_TRACE_REFS != 0 && minor < 13 {
+            2 * WORD
+        } else {
+            0
+        };
+        // The free-threaded head puts ob_ref_local in the last four bytes
+        // before ob_ref_shared, which takes the refcount's own slot.
+        let head_end = if free_threaded { head + 4 } else { head };
+        let slot = |index: usize| head_end + index * WORD;
+
+        TypeLayout {
+            ob_refcnt: if free_threaded { head } else { slot(0) },
+            ob_ref_shared: free_threaded.then(|| slot(0)),
+            ob_type: slot(1),
+            tp_name: slot(3),
+            tp_flags: slot(21),
+            tp_traverse: slot(23),
+            tp_clear: slot(24),
+            tp_del: slot(47),
+            // tp_finalize was added in 3.4 (PEP 442).
+            tp_finalize: (minor >= 4).then(|| slot(49)),
+        }
+    }
+}
+
+/// Interpreter functions used instead of reading object and type structs
+/// when the library runs in abi3 mode; each entry maps onto the stable-ABI
+/// function in its comment.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GCAbi3Api {
+    /// `Py_TYPE`: the object's type as a borrowed reference.
+    pub object_type: Option<extern "C" fn(*mut c_void) -> *mut c_void>,
+    /// `PyType_GetName`: write the UTF-8 name of a type into the buffer and
+    /// return its length without the terminator, or a negative value.
+    pub type_name: Option<extern "C" fn(*mut c_void, *mut c_char, usize) -> isize>,
+    /// `PyType_GetFlags`.
+    pub type_flags: Option<extern "C" fn(*mut c_void) -> c_ulong>,
+    /// `PyType_GetSlot`.
+    pub type_get_slot: Option<extern "C" fn(*mut c_void, c_int) -> *mut c_void>,
+    /// `PyObject_GC_IsTracked`.
+    pub gc_is_tracked: Option<extern "C" fn(*mut c_void) -> c_int>,
+    /// `Py_REFCNT`.
+    pub refcnt: Option<extern "C" fn(*mut c_void) -> isize>,
+}
+
+impl GCAbi3Api {
+    pub fn is_complete(&self) -> bool {
+        self.object_type.is_some()
+            && self.type_name.is_some()
+            && self.type_flags.is_some()
+            && self.type_get_slot.is_some()
+            && self.gc_is_tracked.is_some()
+            && self.refcnt.is_some()
+    }
+}
+
+// Slot ids from CPython's typeslots.h, for PyType_GetSlot.
+const PY_TP_CLEAR: c_int = 51;
+const PY_TP_DEL: c_int = 53;
+const PY_TP_TRAVERSE: c_int = 71;
+const PY_TP_FINALIZE: c_int = 80;
+
+fn abi3_api() -> Option<GCAbi3Api> {
+    *ABI3_API.read()
+}
+
+unsafe fn read_field<T: Copy>(base: *const c_void, offset: usize) -> T {
+    unsafe { base.cast::<u8>().add(offset).cast::<T>().read_unaligned() }
+}
+
+unsafe fn object_refcnt(obj_ptr: *const c_void) -> usize {
+    if let Some(api) = abi3_api()
+        && let Some(refcnt) = api.refcnt
+    {
+        return refcnt(obj_ptr.cast_mut()).max(0) as usize;
+    }
+    let layout = *TYPE_LAYOUT.read();
+    match layout.ob_ref_shared {
+        None => unsafe { read_field(obj_ptr, layout.ob_refcnt) },
+        Some(shared) => unsafe {
+            let local: u32 = read_field(obj_ptr, layout.ob_refcnt);
+            let shared: isize = read_field(obj_ptr, shared);
+            (local as usize).saturating_add((shared.max(0) >> PY_REF_SHARED_SHIFT) as usize)
+        },
+    }
+}
+
+/// Stores the count directly; in abi3 mode and for split refcounts the
+/// increfs and decrefs that preceded the call are left to have done it.
+unsafe fn set_object_refcnt(obj_ptr: *mut c_void, refcount: usize) {
+    let layout = *TYPE_LAYOUT.read();
+    if abi3_api().is_some() || layout.ob_ref_shared.is_some() {
+        return;
+    }
+    let offset = layout.ob_refcnt;
+    unsafe {
+        obj_ptr
+            .cast::<u8>()
+            .add(offset)
+            .cast::<usize>()
+            .write_unaligned(refcount)
+    }
+}
+
+unsafe fn object_type(obj_ptr: *const c_void) -> *const c_void {
+    if let Some(api) = abi3_api()
+        && let Some(object_type) = api.object_type
+    {
+        return object_type(obj_ptr.cast_mut());
+    }
+    unsafe { read_field(obj_ptr, TYPE_LAYOUT.read().ob_type) }
+}
+
+unsafe fn type_name(type_ptr: *const c_void) -> Option<String> {
+    if let Some(api) = abi3_api()
+        && let Some(type_name) = api.type_name
+    {
+        let mut buffer = [0 as c_char; 256];
+        let len = type_name(type_ptr.cast_mut(), buffer.as_mut_ptr(), buffer.len());
+        let len = usize::try_from(len).ok()?.min(buffer.len() - 1);
+        let bytes = unsafe { std::slice::from_raw_parts(buffer.as_ptr().cast::<u8>(), len) };
+        return Some(String::from_utf8_lossy(bytes).into_owned());
+    }
+
+    let name_ptr: *const c_char = unsafe { read_field(type_ptr, TYPE_LAYOUT.read().tp_name) };
+    if name_ptr.is_null() {
+        return None;
+    }
+    Some(
+        unsafe { std::ffi::CStr::from_ptr(name_ptr) }
+            .to_string_lossy()
+            .into_owned(),
+    )
+}
+
+unsafe fn type_flags(type_ptr: *const c_void) -> c_ulong {
+    if let Some(api) = abi3_api()
+        && let Some(type_flags) = api.type_flags
+    {
+        return type_flags(type_ptr.cast_mut());
+    }
+    unsafe { read_field(type_ptr, TYPE_LAYOUT.read().tp_flags) }
+}
+
+/// `PyType_GetSlot` lookup in abi3 mode; `None` outside it.
+fn abi3_slot(type_ptr: *const c_void, slot: c_int) -> Option<Option<*mut c_void>> {
+    let get_slot = abi3_api()?.type_get_slot?;
+    let function = get_slot(type_ptr.cast_mut(), slot);
+    Some((!function.is_null()).then_some(function))
+}
+
+unsafe fn type_traverse(type_ptr: *const c_void) -> Option<TraverseProc> {
+    if let Some(function) = abi3_slot(type_ptr, PY_TP_TRAVERSE) {
+        return function.map(|f| unsafe { std::mem::transmute::<*mut c_void, TraverseProc>(f) });
+    }
+    unsafe { read_field(type_ptr, TYPE_LAYOUT.read().tp_traverse) }
+}
+
+unsafe fn type_clear(type_ptr: *const c_void) -> Option<InquiryProc> {
+    if let Some(function) = abi3_slot(type_ptr, PY_TP_CLEAR) {
+        return function.map(|f| unsafe { std::mem::transmute::<*mut c_void, InquiryProc>(f) });
+    }
+    unsafe { read_field(type_ptr, TYPE_LAYOUT.read().tp_clear) }
+}
+
+unsafe fn type_finalize(type_ptr: *const c_void) -> Option<DestructorProc> {
+    if let Some(function) = abi3_slot(type_ptr, PY_TP_FINALIZE) {
+        return function.map(|f| unsafe { std::mem::transmute::<*mut c_void, DestructorProc>(f) });
+    }
+    let offset = TYPE_LAYOUT.read().tp_finalize?;
+    unsafe { read_field(type_ptr, offset) }
+}
+
+unsafe fn type_del(type_ptr: *const c_void) -> Option<DestructorProc> {
+    if let Some(function) = abi3_slot(type_ptr, PY_TP_DEL) {
+        return function.map(|f| unsafe { std::mem::transmute::<*mut c_void, DestructorProc>(f) });
+    }
+    unsafe { read_field(type_ptr, TYPE_LAYOUT.read().tp_del) }
+}
+
+#[inline(always)]
+fn with_gc<F, R>(f: F) -> Option<R>
+where
+    F: FnOnce(&GarbageCollector) -> R,
+{
+    with_instance(|instance| instance.gc.read().as_ref().map(f)).flatten()
+}
+
+fn automatic_tracking() -> bool {
+    with_instance(|instance| instance.automatic_tracking.load(Ordering::Relaxed)).unwrap_or(false)
+}
+
+fn set_automatic_tracking(enabled: bool) {
+    with_instance(|instance| {
+        instance
+            .automatic_tracking
+            .store(enabled, Ordering::Relaxed)
+    });
+}
+
 #[inline(always)]
 fn with_object_registry<F, R>(f: F) -> R
 where
-    F: FnOnce(&mut HashMap<*mut c_void, PyObject>) -> R,
+    F: FnOnce(&mut HashMap<ObjectPtr, PyObject>) -> R,
 {
     OBJECT_REGISTRY.with(|registry| {
         let mut registry = registry.borrow_mut();
@@ -111,21 +714,103 @@ fn is_object_tracked(obj_ptr: *mut c_void) -> bool {
 }
 
 #[inline(always)]
-fn track_object_fast(obj_ptr: *mut c_void, obj: PyObject) {
+fn track_object_fast(obj_ptr: *mut c_void, obj: PyObject) -> GCResult<()> {
+    with_gc(|gc| gc.track(obj.clone())).unwrap_or(Err(GCError::NotInitialized))?;
+
+    OBJECT_POINTERS.with(|pointers| {
+        pointers.borrow_mut().insert(obj.id(), ObjectPtr(obj_ptr));
+    });
     OBJECT_REGISTRY.with(|registry| {
-        registry.borrow_mut().insert(obj_ptr, obj);
+        registry.borrow_mut().insert(ObjectPtr(obj_ptr), obj);
     });
+    Ok(())
 }
 
 #[inline(always)]
 fn untrack_object_fast(obj_ptr: *mut c_void) -> bool {
-    OBJECT_REGISTRY.with(|registry| registry.borrow_mut().remove(&obj_ptr).is_some())
+    let removed = OBJECT_REGISTRY.with(|registry| registry.borrow_mut().remove(&obj_ptr));
+
+    match removed {
+        Some(obj) => {
+            OBJECT_POINTERS.with(|pointers| pointers.borrow_mut().remove(&obj.id()));
+            with_gc(|gc| gc.untrack(&obj.id()).ok());
+            true
+        }
+        None => false,
+    }
+}
+
+/// Run a collection with the world stopped, then drop what it reclaimed.
+/// The world is stopped before the collector lock is taken, so a thread
+/// holding it can still initialize or clean up.
+fn collect_stopped<T>(collect: impl FnOnce(&GarbageCollector) -> T) -> Option<T> {
+    let _world = WorldGuard::acquire();
+    with_gc(|gc| {
+        let result = collect(gc);
+        reclaim_collected_objects(gc);
+        result
+    })
+}
+
+fn reclaim_collected_objects(gc: &GarbageCollector) {
+    let saved: HashSet<ObjectId> = if gc.get_debug() & DEBUG_SAVEALL != 0 {
+        gc.saved_garbage()
+            .iter()
+            .map(|garbage| garbage.object.id())
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    let reclaimed: Vec<ObjectPtr> = with_object_registry(|reg| {
+        let reclaimed = reg
+            .iter()
+            .filter(|(_, obj)| !gc.is_tracked(&obj.id()))
+            .map(|(obj_ptr, _)| *obj_ptr)
+            .collect::<Vec<_>>();
+
+        for obj_ptr in &reclaimed {
+            if let Some(obj) = reg.remove(obj_ptr) {
+                OBJECT_POINTERS.with(|pointers| pointers.borrow_mut().remove(&obj.id()));
+                if saved.contains(&obj.id()) {
+                    SAVED_GARBAGE.with(|garbage| garbage.borrow_mut().push(*obj_ptr));
+                }
+            }
+        }
+
+        reclaimed
+    });
+
+    if reclaimed.is_empty() {
+        return;
+    }
+
+    REFCOUNT_CALLBACKS.with(|callbacks| {
+        let mut callbacks = callbacks.borrow_mut();
+        for obj_ptr in &reclaimed {
+            callbacks.remove(obj_ptr);
+        }
+    });
+    REFERENCE_TRACKING.with(|refs| {
+        let mut refs = refs.borrow_mut();
+        for obj_ptr in &reclaimed {
+            refs.remove(obj_ptr);
+        }
+        for references in refs.values_mut() {
+            references.retain(|to_obj, _| !reclaimed.contains(to_obj));
+        }
+        refs.retain(|_, references| !references.is_empty());
+    });
+    REFERENCE_LABELS.with(|labels| {
+        labels.borrow_mut().retain(|(from_obj, to_obj), _| {
+            !reclaimed.contains(from_obj) && !reclaimed.contains(to_obj)
+        });
+    });
 }
 
 #[inline(always)]
 fn register_refcount_callback(obj_ptr: *mut c_void, callback: RefCountCallback) {
     REFCOUNT_CALLBACKS.with(|callbacks| {
-        callbacks.borrow_mut().insert(obj_ptr, callback);
+        callbacks.borrow_mut().insert(ObjectPtr(obj_ptr), callback);
     });
 }
 
@@ -146,24 +831,61 @@ fn notify_refcount_change(obj_ptr: *mut c_void, delta: i32) {
 }
 
 #[inline(always)]
-fn add_reference(from_obj: *mut c_void, to_obj: *mut c_void) {
-    REFERENCE_TRACKING.with(|refs| {
+fn add_reference(from_obj: *mut c_void, to_obj: *mut c_void, ref_type: ReferenceType) {
+    let added = REFERENCE_TRACKING.with(|refs| {
         let mut refs = refs.borrow_mut();
-        refs.entry(from_obj).or_default().insert(to_obj);
+        refs.entry(ObjectPtr(from_obj))
+            .or_default()
+            .insert(ObjectPtr(to_obj), ref_type)
+            .is_none()
+    });
+    if added {
+        record_reference_changes(&[(from_obj, to_obj)], EdgeChange::Added);
+    }
+}
+
+/// Feed reference history, when it is on, with edges between tracked
+/// objects.
+fn record_reference_changes(edges: &[(*mut c_void, *mut c_void)], change: EdgeChange) {
+    with_gc(|gc| {
+        if !gc.is_recording_references() {
+            return;
+        }
+        let ids: Vec<(ObjectId, ObjectId)> = with_object_registry(|reg| {
+            edges
+                .iter()
+                .filter_map(|(from_obj, to_obj)| {
+                    Some((reg.get(from_obj)?.id(), reg.get(to_obj)?.id()))
+                })
+                .collect()
+        });
+        for (from, to) in ids {
+            gc.record_reference_change(&from, &to, change);
+        }
     });
 }
 
 #[inline(always)]
 fn remove_reference(from_obj: *mut c_void, to_obj: *mut c_void) {
-    REFERENCE_TRACKING.with(|refs| {
+    let removed = REFERENCE_TRACKING.with(|refs| {
         let mut refs = refs.borrow_mut();
-        if let Some(references) = refs.get_mut(&from_obj) {
-            references.remove(&to_obj);
-            if references.is_empty() {
-                refs.remove(&from_obj);
-            }
+        let Some(references) = refs.get_mut(&from_obj) else {
+            return false;
+        };
+        let removed = references.remove(&to_obj).is_some();
+        if references.is_empty() {
+            refs.remove(&from_obj);
         }
+        removed
+    });
+    REFERENCE_LABELS.with(|labels| {
+        labels
+            .borrow_mut()
+            .remove(&(ObjectPtr(from_obj), ObjectPtr(to_obj)))
     });
+    if removed {
+        record_reference_changes(&[(from_obj, to_obj)], EdgeChange::Removed);
+    }
 }
 
 #[inline(always)]
@@ -171,7 +893,7 @@ fn get_references(from_obj: *mut c_void) -> Vec<*mut c_void> {
     REFERENCE_TRACKING.with(|refs| {
         refs.borrow()
             .get(&from_obj)
-            .map(|references| references.iter().copied().collect())
+            .map(|references| references.keys().map(|to_obj| to_obj.0).collect())
             .unwrap_or_default()
     })
 }
@@ -181,7 +903,9 @@ fn get_referrers(to_obj: *mut c_void) -> Vec<*mut c_void> {
     REFERENCE_TRACKING.with(|refs| {
         refs.borrow()
             .iter()
-            .filter_map(|(from_obj, references)| references.contains(&to_obj).then_some(*from_obj))
+            .filter_map(|(from_obj, references)| {
+                references.contains_key(&to_obj).then_some(from_obj.0)
+            })
             .collect()
     })
 }
@@ -192,30 +916,77 @@ unsafe fn create_python_list_from_objects(objects: Vec<*mut c_void>) -> *mut c_v
         return std::ptr::null_mut();
     }
 
+    let _gil = GilGuard::acquire();
+    let api = python_api::python_api();
     let list_size = objects.len() as isize;
-    let py_list = unsafe { PyList_New(list_size) };
+    let py_list = api.list_new(list_size);
     if py_list.is_null() {
         return std::ptr::null_mut();
     }
 
     for (index, obj_ptr) in objects.into_iter().enumerate() {
         if !obj_ptr.is_null() {
-            unsafe {
-                Py_IncRef(obj_ptr);
-                if PyList_SetItem(py_list, index as isize, obj_ptr) != 0 {
-                    Py_DecRef(obj_ptr);
-                }
+            api.incref(obj_ptr);
+            if api.list_set_item(py_list, index as isize, obj_ptr) != 0 {
+                api.decref(obj_ptr);
             }
         }
     }
     py_list
 }
 
+unsafe fn create_python_list_of_lists(groups: Vec<Vec<*mut c_void>>) -> *mut c_void {
+    let _gil = GilGuard::acquire();
+    let api = python_api::python_api();
+    let py_list = api.list_new(groups.len() as isize);
+    if py_list.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    for (index, group) in groups.into_iter().enumerate() {
+        let inner = unsafe { create_python_list_from_objects(group) };
+        if !inner.is_null() && api.list_set_item(py_list, index as isize, inner) != 0 {
+            api.decref(inner);
+        }
+    }
+    py_list
+}
+
+fn find_reference_cycles() -> Vec<Vec<*mut c_void>> {
+    REFERENCE_TRACKING.with(|refs| {
+        let refs = refs.borrow();
+        let mut nodes: Vec<*mut c_void> = refs
+            .iter()
+            .flat_map(|(from_obj, references)| {
+                std::iter::once(from_obj.0).chain(references.keys().map(|to_obj| to_obj.0))
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        nodes.sort();
+
+        cyclic_components(&nodes, |obj_ptr| {
+            let mut references: Vec<*mut c_void> = refs
+                .get(obj_ptr)
+                .map(|references| {
+                    references
+                        .iter()
+                        .filter(|(_, ref_type)| **ref_type != ReferenceType::Weak)
+                        .map(|(to_obj, _)| to_obj.0)
+                        .collect()
+                })
+                .unwrap_or_default();
+            references.sort();
+            references
+        })
+    })
+}
+
 #[inline(always)]
 fn add_uncollectable(obj_ptr: *mut c_void) {
     UNCOLLECTABLE_OBJECTS.with(|uncollectable| {
-        if !uncollectable.borrow().contains(&obj_ptr) {
-            uncollectable.borrow_mut().push(obj_ptr);
+        if !uncollectable.borrow().contains(&ObjectPtr(obj_ptr)) {
+            uncollectable.borrow_mut().push(ObjectPtr(obj_ptr));
         }
     });
 }
@@ -223,13 +994,16 @@ fn add_uncollectable(obj_ptr: *mut c_void) {
 #[inline(always)]
 fn remove_uncollectable(obj_ptr: *mut c_void) {
     UNCOLLECTABLE_OBJECTS.with(|uncollectable| {
-        uncollectable.borrow_mut().retain(|&ptr| ptr != obj_ptr);
+        uncollectable
+            .borrow_mut()
+            .retain(|&ptr| ptr != ObjectPtr(obj_ptr));
     });
 }
 
 #[inline(always)]
 fn get_uncollectable_objects() -> Vec<*mut c_void> {
-    UNCOLLECTABLE_OBJECTS.with(|uncollectable| uncollectable.borrow().clone())
+    UNCOLLECTABLE_OBJECTS
+        .with(|uncollectable| uncollectable.borrow().iter().map(|ptr| ptr.0).collect())
 }
 
 #[inline(always)]
@@ -237,15 +1011,18 @@ fn clear_uncollectable_objects() {
     UNCOLLECTABLE_OBJECTS.with(|uncollectable| uncollectable.borrow_mut().clear());
 }
 
-const COMMON_NAMES: [&str; 4] = ["tracked_ptr", "list", "dict", "tuple"];
-
-#[inline(always)]
-fn get_fast_object_name(ptr_addr: usize) -> &'static str {
-    let index = ptr_addr & (COMMON_NAMES.len() - 1);
-    COMMON_NAMES[index]
+fn read_type_name(obj_ptr: *mut c_void) -> String {
+    unsafe {
+        let py_type = object_type(obj_ptr);
+        if py_type.is_null() {
+            return "unknown".to_string();
+        }
+        type_name(py_type).unwrap_or_else(|| "unknown".to_string())
+    }
 }
 
 #[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GCReturnCode {
     Success = 0,
     ErrorAlreadyTracked = -1,
@@ -253,1146 +1030,4799 @@ pub enum GCReturnCode {
     ErrorCollectionInProgress = -3,
     ErrorInvalidGeneration = -4,
     ErrorInternal = -5,
+    ErrorBufferTooSmall = -6,
+    ErrorNotInitialized = -7,
+}
+
+impl GCReturnCode {
+    const ALL: [GCReturnCode; 8] = [
+        GCReturnCode::Success,
+        GCReturnCode::ErrorAlreadyTracked,
+        GCReturnCode::ErrorNotTracked,
+        GCReturnCode::ErrorCollectionInProgress,
+        GCReturnCode::ErrorInvalidGeneration,
+        GCReturnCode::ErrorInternal,
+        GCReturnCode::ErrorBufferTooSmall,
+        GCReturnCode::ErrorNotInitialized,
+    ];
+
+    /// The return code for a [`GCError::code`], or `ErrorInternal` for codes
+    /// this layer doesn't know.
+    fn from_code(code: i32) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|&known| known as i32 == code)
+            .unwrap_or(GCReturnCode::ErrorInternal)
+    }
 }
 
-impl From<GCResult<()>> for GCReturnCode {
-    fn from(result: GCResult<()>) -> Self {
+impl<T> From<GCResult<T>> for GCReturnCode {
+    fn from(result: GCResult<T>) -> Self {
         match result {
             Ok(_) => GCReturnCode::Success,
-            Err(e) => match e {
-                crate::error::GCError::AlreadyTracked => GCReturnCode::ErrorAlreadyTracked,
-                crate::error::GCError::NotTracked => GCReturnCode::ErrorNotTracked,
-                crate::error::GCError::CollectionInProgress => {
-                    GCReturnCode::ErrorCollectionInProgress
-                }
-                crate::error::GCError::InvalidGeneration(_) => GCReturnCode::ErrorInvalidGeneration,
-                _ => GCReturnCode::ErrorInternal,
-            },
+            Err(e) => fail(GCReturnCode::from_code(e.code()), e.to_string()),
         }
     }
 }
 
-impl From<GCResult<usize>> for GCReturnCode {
-    fn from(result: GCResult<usize>) -> Self {
-        match result {
-            Ok(_) => GCReturnCode::Success,
-            Err(e) => match e {
-                crate::error::GCError::AlreadyTracked => GCReturnCode::ErrorAlreadyTracked,
-                crate::error::GCError::NotTracked => GCReturnCode::ErrorNotTracked,
-                crate::error::GCError::CollectionInProgress => {
-                    GCReturnCode::ErrorCollectionInProgress
-                }
-                crate::error::GCError::InvalidGeneration(_) => GCReturnCode::ErrorInvalidGeneration,
-                _ => GCReturnCode::ErrorInternal,
-            },
+fn fail(code: GCReturnCode, message: impl Into<String>) -> GCReturnCode {
+    LAST_ERROR.with(|last_error| {
+        *last_error.borrow_mut() = Some((code, message.into()));
+    });
+    code
+}
+
+/// Copy `text` into `buffer` as a NUL-terminated string, truncating if it does
+/// not fit, and return the buffer size needed to hold all of it.
+unsafe fn write_c_string(text: &str, buffer: *mut c_char, buffer_size: usize) -> usize {
+    let required = text.len() + 1;
+    if !buffer.is_null() && buffer_size > 0 {
+        unsafe {
+            let bytes_to_copy = std::cmp::min(text.len(), buffer_size - 1);
+            std::ptr::copy_nonoverlapping(text.as_ptr(), buffer as *mut u8, bytes_to_copy);
+            *buffer.add(bytes_to_copy) = 0;
         }
     }
+    REQUIRED_BUFFER_SIZE.with(|size| size.set(required));
+    required
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_init() -> GCReturnCode {
-    unsafe {
-        GC = Some(GarbageCollector::new());
-        AUTOMATIC_TRACKING.store(false, Ordering::Relaxed);
+unsafe fn fill_buffer(text: &str, buffer: *mut c_char, buffer_size: usize) -> GCReturnCode {
+    let required = unsafe { write_c_string(text, buffer, buffer_size) };
+    if buffer.is_null() || buffer_size < required {
+        return fail(
+            GCReturnCode::ErrorBufferTooSmall,
+            format!("Buffer of {buffer_size} bytes is too small, {required} required"),
+        );
     }
     GCReturnCode::Success
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_cleanup() -> GCReturnCode {
-    unsafe {
-        with_object_registry(|reg| reg.clear());
-        REFCOUNT_CALLBACKS.with(|callbacks| callbacks.borrow_mut().clear());
-        REFERENCE_TRACKING.with(|refs| refs.borrow_mut().clear());
-        clear_uncollectable_objects();
+pub extern "C" fn py_gc_get_required_buffer_size() -> usize {
+    REQUIRED_BUFFER_SIZE.with(|size| size.get())
+}
 
-        GC = None;
-        AUTOMATIC_TRACKING.store(false, Ordering::Relaxed);
-    }
-    GCReturnCode::Success
+fn not_initialized() -> GCReturnCode {
+    GCResult::<()>::Err(GCError::NotInitialized).into()
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_enable() -> GCReturnCode {
-    unsafe {
-        if let Some(ref mut gc) = GC {
-            gc.enable();
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorInternal
-        }
+/// Copy the message of the last failed FFI call on this thread into `buffer`
+///
+/// Pass a null `buffer` to query the required size through
+/// `py_gc_get_required_buffer_size`. The last error itself is left untouched.
+///
+/// # Safety
+///
+/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes, or null
+/// - The buffer must be writable and not overlap with any other memory being accessed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_last_error(
+    buffer: *mut c_char,
+    buffer_size: usize,
+) -> GCReturnCode {
+    let message = LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map(|(_, message)| message.clone())
+            .unwrap_or_default()
+    });
+
+    let required = unsafe { write_c_string(&message, buffer, buffer_size) };
+    if buffer.is_null() || buffer_size < required {
+        return GCReturnCode::ErrorBufferTooSmall;
     }
+
+    GCReturnCode::Success
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_disable() -> GCReturnCode {
-    unsafe {
-        if let Some(ref mut gc) = GC {
-            gc.disable();
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorInternal
-        }
-    }
+pub extern "C" fn py_gc_get_last_error_code() -> c_int {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map(|(code, _)| *code as c_int)
+            .unwrap_or(GCReturnCode::Success as c_int)
+    })
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_is_enabled() -> c_int {
-    unsafe {
-        if let Some(ref gc) = GC {
-            if gc.is_enabled() { 1 } else { 0 }
-        } else {
-            0
+pub extern "C" fn py_gc_clear_last_error() {
+    LAST_ERROR.with(|last_error| last_error.borrow_mut().take());
+}
+
+fn log_message(level: GCLogLevel, message: &str) {
+    let callback = *LOG_CALLBACK.read();
+    match callback {
+        Some(callback) => {
+            let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+            callback(level as c_int, message.as_ptr());
         }
+        None => eprintln!("{message}"),
     }
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_is_initialized() -> c_int {
-    unsafe {
-        match GC {
-            Some(_) => 1,
-            None => 0,
-        }
+fn log_collection_stats(gc: &GarbageCollector, generation: usize, result: &GCResult<usize>) {
+    if gc.get_debug() & DEBUG_STATS == 0 {
+        return;
+    }
+
+    let stats = gc.get_stats();
+    log_message(
+        GCLogLevel::Debug,
+        &format!("gc: collecting generation {generation}..."),
+    );
+    log_message(
+        GCLogLevel::Debug,
+        &format!(
+            "gc: objects in each generation: {} {} {}",
+            stats.generation_counts[0], stats.generation_counts[1], stats.generation_counts[2]
+        ),
+    );
+
+    match result {
+        Ok(collected) => log_message(
+            GCLogLevel::Debug,
+            &format!("gc: done, {collected} unreachable objects collected"),
+        ),
+        Err(e) => log_message(GCLogLevel::Error, &format!("gc: collection failed: {e}")),
     }
 }
 
-/// Get GC state information as a string
+fn log_refcount_audit(gc: &GarbageCollector) {
+    if gc.get_debug() & DEBUG_AUDIT_REFS == 0 {
+        return;
+    }
+    for discrepancy in gc.last_refcount_audit() {
+        log_message(
+            GCLogLevel::Warning,
+            &format!("Refcount audit: {discrepancy}"),
+        );
+    }
+}
+
+fn log_barrier_violations(gc: &GarbageCollector, generation: usize) {
+    if gc.get_debug() & DEBUG_VERIFY_BARRIERS == 0 || generation == 2 {
+        return;
+    }
+    for violation in gc.last_barrier_violations() {
+        log_message(
+            GCLogLevel::Warning,
+            &format!("Missed write barrier: {violation}"),
+        );
+    }
+}
+
+/// Register the interpreter hooks used to acquire the GIL
 ///
 /// # Safety
 ///
-/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes
-/// - `buffer_size` must be greater than 0
-/// - The buffer must be writable and not overlap with any other memory being accessed
+/// - `hooks` must be a valid pointer to a `GCPythonHooks` struct or null
+/// - The hook functions must remain callable until they are replaced or cleared
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_get_state_string(
-    buffer: *mut c_char,
-    buffer_size: usize,
-) -> GCReturnCode {
-    if buffer.is_null() || buffer_size == 0 {
-        return GCReturnCode::ErrorInternal;
+pub unsafe extern "C" fn py_gc_set_python_hooks(hooks: *const GCPythonHooks) -> GCReturnCode {
+    if hooks.is_null() {
+        *PYTHON_HOOKS.write() = None;
+        return GCReturnCode::Success;
     }
 
-    unsafe {
-        if let Some(ref gc) = GC {
-            let state_info = format!(
-                "GC State: enabled={}, tracked={}, gen0={}, gen1={}, gen2={}, uncollectable={}",
-                gc.is_enabled(),
-                gc.get_count(),
-                gc.get_generation_count(0).unwrap_or(0),
-                gc.get_generation_count(1).unwrap_or(0),
-                gc.get_generation_count(2).unwrap_or(0),
-                gc.get_uncollectable().len()
-            );
+    let hooks = unsafe { *hooks };
+    if hooks.gil_ensure.is_some() != hooks.gil_release.is_some() {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            "gil_ensure and gil_release must be provided together",
+        );
+    }
 
-            let bytes_to_copy = std::cmp::min(state_info.len(), buffer_size - 1);
-            std::ptr::copy_nonoverlapping(state_info.as_ptr(), buffer as *mut u8, bytes_to_copy);
-            *buffer.add(bytes_to_copy) = 0;
+    *PYTHON_HOOKS.write() = Some(hooks);
+    GCReturnCode::Success
+}
 
-            GCReturnCode::Success
-        } else {
-            let error_msg = "GC not initialized";
-            let bytes_to_copy = std::cmp::min(error_msg.len(), buffer_size - 1);
-            std::ptr::copy_nonoverlapping(error_msg.as_ptr(), buffer as *mut u8, bytes_to_copy);
-            *buffer.add(bytes_to_copy) = 0;
+/// Register callbacks run whenever the world is stopped and restarted
+///
+/// # Safety
+///
+/// - `hooks` must be a valid pointer to a `GCThreadHooks` struct or null
+/// - The hook functions must remain callable until they are replaced or cleared
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_set_thread_hooks(hooks: *const GCThreadHooks) -> GCReturnCode {
+    *THREAD_HOOKS.write() = if hooks.is_null() {
+        None
+    } else {
+        Some(unsafe { *hooks })
+    };
+    GCReturnCode::Success
+}
 
-            GCReturnCode::ErrorInternal
-        }
-    }
+/// Stop the world: wait for any running collection, then hold off further
+/// collections from other threads until `py_gc_resume_the_world`. Calls
+/// nest on the stopping thread, which can keep using the library.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_stop_the_world() -> GCReturnCode {
+    stop_world();
+    GCReturnCode::Success
 }
 
+/// Undo one `py_gc_stop_the_world` made on this thread.
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_track(obj_ptr: *mut c_void) -> GCReturnCode {
-    if obj_ptr.is_null() {
-        return GCReturnCode::ErrorInternal;
+pub extern "C" fn py_gc_resume_the_world() -> GCReturnCode {
+    if resume_world() {
+        GCReturnCode::Success
+    } else {
+        fail(
+            GCReturnCode::ErrorInternal,
+            "World was not stopped by this thread",
+        )
     }
+}
 
-    if is_object_tracked(obj_ptr) {
-        return GCReturnCode::ErrorAlreadyTracked;
+/// Register the Python C-API backend used to build and inspect Python objects
+///
+/// # Safety
+///
+/// - `api` must be a valid pointer to a `GCPythonApi` struct or null
+/// - The API functions must remain callable until they are replaced or cleared
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_set_python_api(api: *const GCPythonApi) -> GCReturnCode {
+    if api.is_null() {
+        register_python_api(Arc::new(python_api::StubPythonApi));
+        return GCReturnCode::Success;
     }
 
-    let ptr_addr = obj_ptr as usize;
-    let _obj_name = get_fast_object_name(ptr_addr);
-
-    let obj = unsafe {
-        let original_obj = &*(obj_ptr as *mut PyObject);
-        original_obj.clone()
-    };
+    let api = unsafe { *api };
+    if !api.is_complete() {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            "Python API vtable is missing required functions",
+        );
+    }
 
-    track_object_fast(obj_ptr, obj);
+    register_python_api(Arc::new(api));
     GCReturnCode::Success
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_untrack(obj_ptr: *mut c_void) -> GCReturnCode {
-    unsafe {
-        if let Some(ref mut _gc) = GC {
-            if obj_ptr.is_null() {
-                return GCReturnCode::ErrorInternal;
-            }
+/// Wraps the backend so allocations and frees made through
+/// `py_gc_object_new`/`py_gc_object_del` are counted and reported to
+/// automatic tracking. CPython's own allocator is not intercepted.
+struct ReportingApi {
+    inner: Arc<dyn PythonApi>,
+}
 
-            if !untrack_object_fast(obj_ptr) {
-                return GCReturnCode::ErrorNotTracked;
-            }
+impl PythonApi for ReportingApi {
+    fn list_new(&self, size: isize) -> *mut c_void {
+        self.inner.list_new(size)
+    }
 
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorInternal
-        }
+    fn list_set_item(&self, list: *mut c_void, index: isize, item: *mut c_void) -> c_int {
+        self.inner.list_set_item(list, index, item)
     }
-}
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_collect_generation(generation: c_int) -> GCReturnCode {
-    unsafe {
-        if let Some(ref gc) = GC {
-            if !(0..=2).contains(&generation) {
-                return GCReturnCode::ErrorInvalidGeneration;
-            }
+    fn list_get_item(&self, list: *mut c_void, index: isize) -> *mut c_void {
+        self.inner.list_get_item(list, index)
+    }
 
-            gc.collect_generation(generation as usize).into()
-        } else {
-            GCReturnCode::ErrorInternal
-        }
+    fn list_size(&self, list: *mut c_void) -> isize {
+        self.inner.list_size(list)
     }
-}
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_collect() -> GCReturnCode {
-    unsafe {
-        if let Some(ref gc) = GC {
-            gc.collect().into()
-        } else {
-            GCReturnCode::ErrorInternal
-        }
+    fn incref(&self, obj: *mut c_void) {
+        self.inner.incref(obj)
     }
-}
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_needs_collection() -> c_int {
-    unsafe {
-        if let Some(ref gc) = GC {
-            if gc.needs_collection() { 1 } else { 0 }
-        } else {
-            0
-        }
+    fn decref(&self, obj: *mut c_void) {
+        self.inner.decref(obj)
     }
-}
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_collect_if_needed() -> GCReturnCode {
-    unsafe {
-        if let Some(ref gc) = GC {
-            gc.collect_if_needed().into()
-        } else {
-            GCReturnCode::ErrorInternal
+    fn gc_new(&self, type_obj: *mut c_void) -> *mut c_void {
+        let obj_ptr = self.inner.gc_new(type_obj);
+        if !obj_ptr.is_null() {
+            REPORTED_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+            py_gc_object_created(obj_ptr);
         }
+        obj_ptr
     }
-}
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_count() -> c_int {
-    unsafe {
-        if let Some(ref gc) = GC {
-            gc.get_count() as c_int
-        } else {
-            0
+    fn gc_del(&self, obj: *mut c_void) {
+        if obj.is_null() {
+            return;
+        }
+
+        REPORTED_FREES.fetch_add(1, Ordering::Relaxed);
+        if is_object_tracked(obj) {
+            py_gc_object_destroyed(obj);
         }
+        self.inner.gc_del(obj)
     }
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_generation_count(generation: c_int) -> c_int {
-    unsafe {
-        if let Some(ref gc) = GC {
-            if !(0..=2).contains(&generation) {
-                return -1;
-            }
-
-            gc.get_generation_count(generation as usize).unwrap_or(0) as c_int
-        } else {
-            0
-        }
+fn register_python_api(api: Arc<dyn PythonApi>) {
+    let mut unwrapped = UNWRAPPED_PYTHON_API.write();
+    if unwrapped.is_some() {
+        *unwrapped = Some(api.clone());
+        python_api::set_python_api(Arc::new(ReportingApi { inner: api }));
+    } else {
+        python_api::set_python_api(api);
     }
 }
 
+/// Count the objects allocated and freed through `py_gc_object_new` and
+/// `py_gc_object_del`, and report them to automatic tracking. Only those two
+/// calls are seen: objects CPython allocates itself are not.
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_set_threshold(generation: c_int, threshold: c_int) -> GCReturnCode {
-    unsafe {
-        if let Some(ref mut gc) = GC {
-            if !(0..=2).contains(&generation) || threshold < 0 {
-                return GCReturnCode::ErrorInvalidGeneration;
-            }
-
-            gc.set_threshold(generation as usize, threshold as usize)
-                .into()
-        } else {
-            GCReturnCode::ErrorInternal
-        }
+pub extern "C" fn py_gc_enable_allocation_reporting() -> GCReturnCode {
+    let mut unwrapped = UNWRAPPED_PYTHON_API.write();
+    if unwrapped.is_none() {
+        let inner = python_api::python_api();
+        *unwrapped = Some(inner.clone());
+        python_api::set_python_api(Arc::new(ReportingApi { inner }));
     }
+    GCReturnCode::Success
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_threshold(generation: c_int) -> c_int {
-    unsafe {
-        if let Some(ref gc) = GC {
-            if !(0..=2).contains(&generation) {
-                return -1;
-            }
-
-            gc.get_threshold(generation as usize).unwrap_or(0) as c_int
-        } else {
-            0
-        }
+pub extern "C" fn py_gc_disable_allocation_reporting() -> GCReturnCode {
+    if let Some(inner) = UNWRAPPED_PYTHON_API.write().take() {
+        python_api::set_python_api(inner);
     }
+    GCReturnCode::Success
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_set_debug(flags: c_int) -> GCReturnCode {
-    unsafe {
-        if let Some(ref mut gc) = GC {
-            if flags < 0 {
-                return GCReturnCode::ErrorInternal;
-            }
-
-            gc.set_debug(flags as u32);
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorInternal
-        }
-    }
+pub extern "C" fn py_gc_allocation_reporting_enabled() -> c_int {
+    UNWRAPPED_PYTHON_API.read().is_some() as c_int
 }
 
-#[repr(C)]
-pub struct GCStats {
-    pub total_tracked: c_int,
-    pub generation_counts: [c_int; 3],
-    pub uncollectable: c_int,
+/// Allocate a GC-enabled object through the registered Python API backend
+///
+/// # Safety
+///
+/// - `type_obj` must be a valid pointer to a Python type object
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_object_new(type_obj: *mut c_void) -> *mut c_void {
+    let _gil = GilGuard::acquire();
+    python_api::python_api().gc_new(type_obj)
 }
 
-/// Retrieves garbage collection statistics.
+/// Free a GC-enabled object through the registered Python API backend
 ///
 /// # Safety
 ///
-/// The caller must ensure that `stats` is a valid pointer to a `GCStats` struct.
-/// The function will write to the memory pointed to by `stats`.
+/// - `obj_ptr` must have been allocated by `py_gc_object_new` or be null
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_get_stats(stats: *mut GCStats) -> GCReturnCode {
-    unsafe {
-        if let Some(ref gc) = GC {
-            if stats.is_null() {
-                return GCReturnCode::ErrorInternal;
-            }
+pub unsafe extern "C" fn py_gc_object_del(obj_ptr: *mut c_void) {
+    let _gil = GilGuard::acquire();
+    python_api::python_api().gc_del(obj_ptr)
+}
 
-            let rust_stats = gc.get_stats();
-            *stats = GCStats {
-                total_tracked: rust_stats.total_tracked as c_int,
-                generation_counts: [
-                    rust_stats.generation_counts[0] as c_int,
-                    rust_stats.generation_counts[1] as c_int,
-                    rust_stats.generation_counts[2] as c_int,
-                ],
-                uncollectable: rust_stats.uncollectable as c_int,
-            };
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_set_log_callback(callback: Option<LogCallback>) -> GCReturnCode {
+    *LOG_CALLBACK.write() = callback;
+    GCReturnCode::Success
+}
 
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorInternal
-        }
-    }
+fn clear_ffi_state() {
+    with_object_registry(|reg| reg.clear());
+    OBJECT_POINTERS.with(|pointers| pointers.borrow_mut().clear());
+    REFCOUNT_CALLBACKS.with(|callbacks| callbacks.borrow_mut().clear());
+    REFERENCE_TRACKING.with(|refs| refs.borrow_mut().clear());
+    REFERENCE_LABELS.with(|labels| labels.borrow_mut().clear());
+    clear_uncollectable_objects();
+    SAVED_GARBAGE.with(|garbage| garbage.borrow_mut().clear());
+    set_automatic_tracking(false);
+    REPORTED_ALLOCATIONS.store(0, Ordering::Relaxed);
+    REPORTED_FREES.store(0, Ordering::Relaxed);
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_is_tracked(obj_ptr: *mut c_void) -> c_int {
-    if obj_ptr.is_null() {
-        return 0;
-    }
-
-    is_object_tracked(obj_ptr) as c_int
+pub extern "C" fn py_gc_init() -> GCReturnCode {
+    with_instance(|instance| {
+        let mut gc = instance.gc.write();
+        clear_ffi_state();
+        *gc = Some(GarbageCollector::new());
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
 }
 
+/// Restore the collector in the child process after `fork()`
+///
+/// Locks that another thread held at fork time are released, this thread's
+/// error state is cleared and any collection that was interrupted is
+/// abandoned. Tracked objects are preserved. Mirrors CPython's
+/// `PyOS_AfterFork_Child`.
+///
+/// # Safety
+///
+/// - Must only be called in the child right after `fork()`, before any other thread is started
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_uncollectable_count() -> c_int {
+pub unsafe extern "C" fn py_gc_after_fork_child() -> GCReturnCode {
     unsafe {
-        if let Some(ref gc) = GC {
-            gc.get_uncollectable().len() as c_int
-        } else {
-            0
+        reset_lock_after_fork(&DEFAULT_INSTANCE.gc);
+        reset_lock_after_fork(&INSTANCES);
+        for instance in INSTANCES.read().values() {
+            reset_lock_after_fork(&instance.gc);
         }
+        reset_lock_after_fork(&LOG_CALLBACK);
+        reset_lock_after_fork(&PYTHON_HOOKS);
+        reset_lock_after_fork(&UNWRAPPED_PYTHON_API);
+        reset_lock_after_fork(&TYPE_LAYOUT);
+        reset_lock_after_fork(&ABI3_API);
+        reset_lock_after_fork(&THREAD_HOOKS);
+        python_api::after_fork();
+        crate::object::after_fork();
     }
+
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = None);
+    REQUIRED_BUFFER_SIZE.with(|size| size.set(0));
+
+    with_gc(|gc| unsafe { gc.after_fork() });
+    GCReturnCode::Success
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_registry_count() -> c_int {
-    with_object_registry(|reg| reg.len() as c_int)
+pub extern "C" fn py_gc_cleanup() -> GCReturnCode {
+    with_instance(|instance| {
+        let mut gc = instance.gc.write();
+        clear_ffi_state();
+        *gc = None;
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_clear_uncollectable() -> GCReturnCode {
-    unsafe {
-        if let Some(ref gc) = GC {
-            gc.clear_uncollectable();
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorInternal
-        }
-    }
+pub extern "C" fn py_gc_enable() -> GCReturnCode {
+    with_gc(|gc| {
+        gc.enable();
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_clear_registry() -> GCReturnCode {
-    with_object_registry(|reg| {
-        reg.clear();
+pub extern "C" fn py_gc_disable() -> GCReturnCode {
+    with_gc(|gc| {
+        gc.disable();
         GCReturnCode::Success
-    });
-    GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_add_reference(from_obj: *mut c_void, to_obj: *mut c_void) -> GCReturnCode {
-    if from_obj.is_null() || to_obj.is_null() {
-        return GCReturnCode::ErrorInternal;
-    }
-
-    add_reference(from_obj, to_obj);
-    GCReturnCode::Success
+pub extern "C" fn py_gc_is_enabled() -> c_int {
+    with_gc(|gc| gc.is_enabled() as c_int).unwrap_or(0)
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_remove_reference(
-    from_obj: *mut c_void,
-    to_obj: *mut c_void,
-) -> GCReturnCode {
-    if from_obj.is_null() || to_obj.is_null() {
-        return GCReturnCode::ErrorInternal;
-    }
-
-    remove_reference(from_obj, to_obj);
-    GCReturnCode::Success
+pub extern "C" fn py_gc_is_initialized() -> c_int {
+    with_instance(|instance| instance.gc.read().is_some()).unwrap_or(false) as c_int
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_mark_uncollectable(obj_ptr: *mut c_void) -> GCReturnCode {
-    if obj_ptr.is_null() {
-        return GCReturnCode::ErrorInternal;
-    }
+/// Get GC state information as a string
+///
+/// Pass a null `buffer` to query the required size through
+/// `py_gc_get_required_buffer_size`.
+///
+/// # Safety
+///
+/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes, or null
+/// - The buffer must be writable and not overlap with any other memory being accessed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_state_string(
+    buffer: *mut c_char,
+    buffer_size: usize,
+) -> GCReturnCode {
+    let state_info = with_gc(|gc| {
+        format!(
+            "GC State: enabled={}, tracked={}, gen0={}, gen1={}, gen2={}, uncollectable={}",
+            gc.is_enabled(),
+            gc.get_count(),
+            gc.get_generation_count(0).unwrap_or(0),
+            gc.get_generation_count(1).unwrap_or(0),
+            gc.get_generation_count(2).unwrap_or(0),
+            gc.iter_uncollectable().iter().count()
+        )
+    });
 
-    add_uncollectable(obj_ptr);
-    GCReturnCode::Success
+    match state_info {
+        Some(state_info) => unsafe { fill_buffer(&state_info, buffer, buffer_size) },
+        None => {
+            unsafe { write_c_string("GC not initialized", buffer, buffer_size) };
+            not_initialized()
+        }
+    }
 }
 
+/// Track an object, naming it after its type
+///
+/// # Safety
+///
+/// - `obj_ptr` must be null or point to a live Python object, whose type
+///   object the collector reads `tp_name`, `tp_finalize` and `tp_del` from
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_unmark_uncollectable(obj_ptr: *mut c_void) -> GCReturnCode {
+pub unsafe extern "C" fn py_gc_track(obj_ptr: *mut c_void) -> GCReturnCode {
     if obj_ptr.is_null() {
-        return GCReturnCode::ErrorInternal;
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
     }
 
-    remove_uncollectable(obj_ptr);
-    GCReturnCode::Success
+    if is_object_tracked(obj_ptr) {
+        return fail(
+            GCReturnCode::ErrorAlreadyTracked,
+            "Object is already tracked",
+        );
+    }
+
+    let obj = object_from_ptr(obj_ptr);
+
+    track_object_fast(obj_ptr, obj).into()
 }
 
+/// Track every object in an array of pointers with a single collector update
+///
+/// # Safety
+///
+/// - `objects` must be a valid pointer to an array of at least `count` object pointers
+/// - Every pointer in the array must satisfy the requirements of `py_gc_track`
+/// - `out_tracked` must be a valid pointer to a writable `usize` or null
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_is_uncollectable(obj_ptr: *mut c_void) -> c_int {
-    if obj_ptr.is_null() {
-        return 0;
+pub unsafe extern "C" fn py_gc_track_batch(
+    objects: *const *mut c_void,
+    count: usize,
+    out_tracked: *mut usize,
+) -> GCReturnCode {
+    if objects.is_null() && count > 0 {
+        return fail(GCReturnCode::ErrorInternal, "NULL object array");
     }
 
-    UNCOLLECTABLE_OBJECTS.with(|uncollectable| {
-        if uncollectable.borrow().contains(&obj_ptr) {
-            1
-        } else {
-            0
+    let pointers = unsafe { batch_slice(objects, count) };
+    if pointers.iter().any(|obj_ptr| obj_ptr.is_null()) {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer in batch");
+    }
+
+    let mut seen = HashSet::new();
+    let entries: Vec<(*mut c_void, PyObject)> = pointers
+        .iter()
+        .filter(|obj_ptr| !is_object_tracked(**obj_ptr) && seen.insert(**obj_ptr))
+        .map(|obj_ptr| (*obj_ptr, object_from_ptr(*obj_ptr)))
+        .collect();
+
+    let tracked = entries.len();
+    let result = with_gc(|gc| gc.track_bulk(entries.iter().map(|(_, obj)| obj.clone()).collect()))
+        .unwrap_or(Err(GCError::NotInitialized));
+    if result.is_err() {
+        return result.into();
+    }
+
+    OBJECT_POINTERS.with(|object_pointers| {
+        let mut object_pointers = object_pointers.borrow_mut();
+        for (obj_ptr, obj) in &entries {
+            object_pointers.insert(obj.id(), ObjectPtr(*obj_ptr));
         }
-    })
+    });
+    with_object_registry(|reg| {
+        reg.reserve(entries.len());
+        for (obj_ptr, obj) in entries {
+            reg.insert(ObjectPtr(obj_ptr), obj);
+        }
+    });
+
+    if !out_tracked.is_null() {
+        unsafe {
+            *out_tracked = tracked;
+        }
+    }
+
+    GCReturnCode::Success
 }
 
-/// Get information about a tracked object
+/// Untrack every object in an array of pointers with a single collector update
 ///
 /// # Safety
 ///
-/// - `obj_ptr` must be a valid pointer to a tracked object or null
-/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes
-/// - `buffer_size` must be greater than 0
-/// - The buffer must be writable and not overlap with any other memory being accessed
+/// - `objects` must be a valid pointer to an array of at least `count` object pointers
+/// - `out_untracked` must be a valid pointer to a writable `usize` or null
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_get_tracked_info(
-    obj_ptr: *mut c_void,
-    buffer: *mut c_char,
-    buffer_size: usize,
+pub unsafe extern "C" fn py_gc_untrack_batch(
+    objects: *const *mut c_void,
+    count: usize,
+    out_untracked: *mut usize,
 ) -> GCReturnCode {
-    if buffer.is_null() || buffer_size == 0 {
-        return GCReturnCode::ErrorInternal;
+    if py_gc_is_initialized() == 0 {
+        return not_initialized();
     }
 
-    unsafe {
-        if let Some(ref _gc) = GC {
-            if obj_ptr.is_null() {
-                let error_msg = "NULL pointer";
-                let bytes_to_copy = std::cmp::min(error_msg.len(), buffer_size - 1);
-                std::ptr::copy_nonoverlapping(error_msg.as_ptr(), buffer as *mut u8, bytes_to_copy);
-                *buffer.add(bytes_to_copy) = 0;
-                return GCReturnCode::ErrorInternal;
-            }
-
-            if !is_object_tracked(obj_ptr) {
-                let error_msg = "Pointer not tracked";
-                let bytes_to_copy = std::cmp::min(error_msg.len(), buffer_size - 1);
-                std::ptr::copy_nonoverlapping(error_msg.as_ptr(), buffer as *mut u8, bytes_to_copy);
-                *buffer.add(bytes_to_copy) = 0;
-                return GCReturnCode::ErrorNotTracked;
-            }
+    if objects.is_null() && count > 0 {
+        return fail(GCReturnCode::ErrorInternal, "NULL object array");
+    }
 
-            let obj_info = with_object_registry(|reg| {
-                if let Some(obj) = reg.get(&obj_ptr) {
-                    format!(
-                        "Object: {} (ID: {}, Refs: {}, Ptr: {:p})",
-                        obj.name,
-                        obj.id.as_usize(),
-                        obj.get_refcount(),
-                        obj_ptr
-                    )
-                } else {
-                    "Object not found".to_string()
-                }
-            });
+    let pointers = unsafe { batch_slice(objects, count) };
+    let removed: Vec<PyObject> = with_object_registry(|reg| {
+        pointers
+            .iter()
+            .filter_map(|obj_ptr| reg.remove(obj_ptr))
+            .collect()
+    });
 
-            let bytes_to_copy = std::cmp::min(obj_info.len(), buffer_size - 1);
-            std::ptr::copy_nonoverlapping(obj_info.as_ptr(), buffer as *mut u8, bytes_to_copy);
-            *buffer.add(bytes_to_copy) = 0;
+    let obj_ids: Vec<ObjectId> = removed.iter().map(|obj| obj.id()).collect();
+    OBJECT_POINTERS.with(|object_pointers| {
+        let mut object_pointers = object_pointers.borrow_mut();
+        for obj_id in &obj_ids {
+            object_pointers.remove(obj_id);
+        }
+    });
+    with_gc(|gc| gc.untrack_bulk(&obj_ids).ok());
 
-            GCReturnCode::Success
-        } else {
-            let error_msg = "GC not initialized";
-            let bytes_to_copy = std::cmp::min(error_msg.len(), buffer_size - 1);
-            std::ptr::copy_nonoverlapping(error_msg.as_ptr(), buffer as *mut u8, bytes_to_copy);
-            *buffer.add(bytes_to_copy) = 0;
-            GCReturnCode::ErrorInternal
+    if !out_untracked.is_null() {
+        unsafe {
+            *out_untracked = removed.len();
         }
     }
+
+    GCReturnCode::Success
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_debug_untrack(obj_ptr: *mut c_void) -> GCReturnCode {
-    unsafe {
-        if let Some(ref mut _gc) = GC {
-            if obj_ptr.is_null() {
-                return GCReturnCode::ErrorInternal;
-            }
+unsafe fn batch_slice<'a>(objects: *const *mut c_void, count: usize) -> &'a [*mut c_void] {
+    if count == 0 {
+        &[]
+    } else {
+        unsafe { std::slice::from_raw_parts(objects, count) }
+    }
+}
 
-            if !untrack_object_fast(obj_ptr) {
-                return GCReturnCode::ErrorNotTracked;
-            }
+fn object_from_ptr(obj_ptr: *mut c_void) -> PyObject {
+    let obj = PyObject::new_ffi(&read_type_name(obj_ptr), ObjectData::None, obj_ptr);
+    obj.set_finalizer(has_finalizer_slot(obj_ptr));
+    obj
+}
 
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorInternal
-        }
+/// Whether the object's type defines `tp_finalize` or the legacy `tp_del`.
+fn has_finalizer_slot(obj_ptr: *mut c_void) -> bool {
+    unsafe {
+        let py_type = object_type(obj_ptr);
+        !py_type.is_null() && (type_finalize(py_type).is_some() || type_del(py_type).is_some())
     }
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_debug_state() -> GCReturnCode {
-    unsafe {
-        if let Some(ref gc) = GC {
-            let stats = gc.get_stats();
-            println!("GC Debug State:");
-            println!("  Total tracked: {}", stats.total_tracked);
-            println!("  Generation 0: {}", stats.generation_counts[0]);
-            println!("  Generation 1: {}", stats.generation_counts[1]);
-            println!("  Generation 2: {}", stats.generation_counts[2]);
-            println!("  Uncollectable: {}", stats.uncollectable);
-
-            let registry_count = with_object_registry(|reg| reg.len());
-            println!("  Registry count: {registry_count}");
+pub extern "C" fn py_gc_untrack(obj_ptr: *mut c_void) -> GCReturnCode {
+    if py_gc_is_initialized() == 0 {
+        return not_initialized();
+    }
 
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorInternal
-        }
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
     }
+
+    if !untrack_object_fast(obj_ptr) {
+        return fail(GCReturnCode::ErrorNotTracked, "Object is not tracked");
+    }
+
+    GCReturnCode::Success
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_enable_automatic_tracking() -> GCReturnCode {
-    AUTOMATIC_TRACKING.store(true, Ordering::Relaxed);
-    GCReturnCode::Success
+pub extern "C" fn py_gc_collect_generation(generation: c_int) -> GCReturnCode {
+    unsafe { py_gc_collect_generation_ex(generation, std::ptr::null_mut()) }
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_disable_automatic_tracking() -> GCReturnCode {
-    AUTOMATIC_TRACKING.store(false, Ordering::Relaxed);
-    GCReturnCode::Success
+pub extern "C" fn py_gc_collect() -> GCReturnCode {
+    unsafe { py_gc_collect_ex(std::ptr::null_mut()) }
 }
 
+/// Collect a generation and report how many objects were collected
+///
+/// # Safety
+///
+/// - `out_collected` must be a valid pointer to a writable `usize` or null
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_is_automatic_tracking_enabled() -> c_int {
-    if AUTOMATIC_TRACKING.load(Ordering::Relaxed) {
-        1
-    } else {
-        0
+pub unsafe extern "C" fn py_gc_collect_generation_ex(
+    generation: c_int,
+    out_collected: *mut usize,
+) -> GCReturnCode {
+    if !(0..=2).contains(&generation) {
+        if py_gc_is_initialized() == 0 {
+            return not_initialized();
+        }
+        return fail(
+            GCReturnCode::ErrorInvalidGeneration,
+            format!("Invalid generation: {generation}"),
+        );
     }
+
+    collect_stopped(|gc| {
+        let result = gc.collect_generation(generation as usize);
+        log_collection_stats(gc, generation as usize, &result);
+        log_refcount_audit(gc);
+        log_barrier_violations(gc, generation as usize);
+        unsafe { write_collected(result, out_collected) }
+    })
+    .unwrap_or_else(not_initialized)
 }
 
+/// Perform a full collection and report how many objects were collected
+///
+/// # Safety
+///
+/// - `out_collected` must be a valid pointer to a writable `usize` or null
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_object_created(obj_ptr: *mut c_void) -> GCReturnCode {
-    if !AUTOMATIC_TRACKING.load(Ordering::Relaxed) {
-        return GCReturnCode::Success;
-    }
+pub unsafe extern "C" fn py_gc_collect_ex(out_collected: *mut usize) -> GCReturnCode {
+    collect_stopped(|gc| {
+        let result = gc.collect();
+        log_collection_stats(gc, 2, &result);
+        log_refcount_audit(gc);
+        unsafe { write_collected(result, out_collected) }
+    })
+    .unwrap_or_else(not_initialized)
+}
 
-    unsafe {
-        if obj_ptr.is_null() {
-            return GCReturnCode::ErrorInternal;
+unsafe fn write_collected(result: GCResult<usize>, out_collected: *mut usize) -> GCReturnCode {
+    if let Ok(collected) = result
+        && !out_collected.is_null()
+    {
+        unsafe {
+            *out_collected = collected;
         }
+    }
 
-        if is_object_tracked(obj_ptr) {
-            return GCReturnCode::ErrorAlreadyTracked;
-        }
+    result.into()
+}
 
-        let py_obj = obj_ptr as *mut PyObject_HEAD;
-        let py_type = (*py_obj).ob_type;
-        let type_name = if !py_type.is_null() {
-            let type_name_ptr = (*py_type).tp_name;
-            if !type_name_ptr.is_null() {
-                std::ffi::CStr::from_ptr(type_name_ptr)
-                    .to_string_lossy()
-                    .to_string()
-            } else {
-                "unknown".to_string()
-            }
-        } else {
-            "unknown".to_string()
-        };
+/// Returns 1 if `py_gc_collect_if_needed` would collect now. Always 0 while
+/// the generation 0 threshold is 0.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_needs_collection() -> c_int {
+    with_gc(|gc| gc.needs_collection().is_some() as c_int).unwrap_or(0)
+}
+
+/// Returns the oldest generation over its threshold, the one
+/// `py_gc_collect_if_needed` would collect now, or -1 if none is due.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_needs_collection_generation() -> c_int {
+    with_gc(|gc| {
+        gc.needs_collection()
+            .map_or(-1, |generation| generation as c_int)
+    })
+    .unwrap_or(-1)
+}
 
-        let obj = PyObject::new_ffi(&type_name, ObjectData::None, obj_ptr);
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_collect_if_needed() -> GCReturnCode {
+    collect_stopped(|gc| gc.collect_if_needed().into()).unwrap_or_else(not_initialized)
+}
 
-        track_object_fast(obj_ptr, obj);
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_count() -> c_int {
+    with_gc(|gc| gc.get_count() as c_int).unwrap_or(0)
+}
 
-        register_refcount_callback(
-            obj_ptr,
-            Box::new(|obj_ptr, delta| {
-                if delta < 0 && py_gc_get_refcount(obj_ptr) == 0 {
-                    if let Some(ref gc) = GC {
-                        gc.collect_if_needed().ok();
-                    }
-                }
-            }),
-        );
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_generation_count(generation: c_int) -> c_int {
+    with_gc(|gc| {
+        if !(0..=2).contains(&generation) {
+            return -1;
+        }
 
-        GCReturnCode::Success
-    }
+        gc.get_generation_count(generation as usize).unwrap_or(0) as c_int
+    })
+    .unwrap_or(0)
 }
 
+/// Returns the generation the object at `obj_ptr` is in, or -1 if it isn't
+/// tracked or is uncollectable.
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_object_destroyed(obj_ptr: *mut c_void) -> GCReturnCode {
+pub extern "C" fn py_gc_get_object_generation(obj_ptr: *mut c_void) -> c_int {
     if obj_ptr.is_null() {
-        return GCReturnCode::ErrorInternal;
+        return -1;
     }
+    let Some(obj_id) = with_object_registry(|reg| reg.get(&obj_ptr).map(|obj| obj.id())) else {
+        return -1;
+    };
+    with_gc(|gc| {
+        gc.generation_of(&obj_id)
+            .map_or(-1, |generation| generation as c_int)
+    })
+    .unwrap_or(-1)
+}
 
-    unregister_refcount_callback(obj_ptr);
+/// Moves every tracked object into the permanent generation, which no
+/// collection looks at, as Python's `gc.freeze()` does.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_freeze() -> GCReturnCode {
+    with_gc(|gc| gc.freeze().into()).unwrap_or_else(not_initialized)
+}
 
-    if untrack_object_fast(obj_ptr) {
-        GCReturnCode::Success
-    } else {
-        GCReturnCode::ErrorNotTracked
-    }
+/// Moves the permanent generation back into the oldest generation.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_unfreeze() -> GCReturnCode {
+    with_gc(|gc| gc.unfreeze().into()).unwrap_or_else(not_initialized)
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_refcount_changed(
-    obj_ptr: *mut c_void,
-    old_count: c_int,
-    new_count: c_int,
-) -> GCReturnCode {
-    if !AUTOMATIC_TRACKING.load(Ordering::Relaxed) {
-        return GCReturnCode::Success;
-    }
+pub extern "C" fn py_gc_get_freeze_count() -> c_int {
+    with_gc(|gc| gc.get_freeze_count() as c_int).unwrap_or(0)
+}
 
-    unsafe {
-        if obj_ptr.is_null() {
-            return GCReturnCode::ErrorInternal;
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_set_threshold(generation: c_int, threshold: c_int) -> GCReturnCode {
+    with_gc(|gc| {
+        if !(0..=2).contains(&generation) || threshold < 0 {
+            return fail(
+                GCReturnCode::ErrorInvalidGeneration,
+                format!("Invalid threshold {threshold} for generation {generation}"),
+            );
         }
 
-        let delta = new_count - old_count;
-        notify_refcount_change(obj_ptr, delta);
+        gc.set_threshold(generation as usize, threshold as usize)
+            .into()
+    })
+    .unwrap_or_else(not_initialized)
+}
 
-        if new_count == 0 {
-            if let Some(ref gc) = GC {
-                gc.collect_if_needed().ok();
-            }
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_threshold(generation: c_int) -> c_int {
+    with_gc(|gc| {
+        if !(0..=2).contains(&generation) {
+            return -1;
         }
 
-        GCReturnCode::Success
-    }
+        gc.get_threshold(generation as usize).unwrap_or(0) as c_int
+    })
+    .unwrap_or(0)
 }
 
+/// Sets all three thresholds in one call, mirroring Python's
+/// `gc.set_threshold(threshold0[, threshold1[, threshold2]])`. A negative
+/// `threshold1` or `threshold2` leaves that generation's threshold as it is,
+/// and a `threshold0` of 0 turns automatic collection off.
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_refcount(obj_ptr: *mut c_void) -> c_int {
-    if obj_ptr.is_null() {
-        return 0;
-    }
-
-    with_object_registry(|reg| {
-        if let Some(obj) = reg.get(&obj_ptr) {
-            obj.get_refcount() as c_int
-        } else {
-            unsafe {
-                let py_obj = obj_ptr as *mut PyObject_HEAD;
-                (*py_obj).ob_refcnt as c_int
-            }
+pub extern "C" fn py_gc_set_thresholds(
+    threshold0: c_int,
+    threshold1: c_int,
+    threshold2: c_int,
+) -> GCReturnCode {
+    with_gc(|gc| {
+        if threshold0 < 0 {
+            return fail(
+                GCReturnCode::ErrorInvalidGeneration,
+                format!("Invalid threshold {threshold0} for generation 0"),
+            );
         }
+
+        let optional = |threshold: c_int| usize::try_from(threshold).ok();
+        gc.set_thresholds(
+            threshold0 as usize,
+            optional(threshold1),
+            optional(threshold2),
+        );
+        GCReturnCode::Success
     })
+    .unwrap_or_else(not_initialized)
 }
 
-/// Set the reference count of an object
+/// Writes the thresholds of generations 0, 1 and 2 to `out`.
 ///
 /// # Safety
 ///
-/// - `obj_ptr` must be a valid pointer to a Python object or null
-/// - The object must not be in an inconsistent state
-/// - `refcount` must be non-negative
+/// The caller must ensure that `out` points to at least three writable
+/// `c_int`s.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_set_refcount(obj_ptr: *mut c_void, refcount: c_int) -> GCReturnCode {
-    if obj_ptr.is_null() || refcount < 0 {
-        return GCReturnCode::ErrorInternal;
-    }
-
-    let mut success = false;
-    with_object_registry(|reg| {
-        if let Some(obj) = reg.get_mut(&obj_ptr) {
-            let current_refcount = obj.get_refcount();
-            let target_refcount = refcount as usize;
-
-            if target_refcount > current_refcount {
-                for _ in 0..(target_refcount - current_refcount) {
-                    obj.inc_ref();
-                }
-            } else if target_refcount < current_refcount {
-                for _ in 0..(current_refcount - target_refcount) {
-                    obj.dec_ref();
-                }
-            }
-
-            success = true;
-        } else {
-            unsafe {
-                let py_obj = obj_ptr as *mut PyObject_HEAD;
-                let current_refcount = (*py_obj).ob_refcnt;
-                let target_refcount = refcount as usize;
-
-                if target_refcount > current_refcount {
-                    for _ in 0..(target_refcount - current_refcount) {
-                        Py_IncRef(obj_ptr);
-                    }
-                } else if target_refcount < current_refcount {
-                    for _ in 0..(current_refcount - target_refcount) {
-                        Py_DecRef(obj_ptr);
-                    }
-                }
-
-                (*py_obj).ob_refcnt = target_refcount;
-            }
-
-            let ptr_addr = obj_ptr as usize;
-            let type_name = get_fast_object_name(ptr_addr);
-            let obj = PyObject::new_ffi(type_name, ObjectData::None, obj_ptr);
-            reg.insert(obj_ptr, obj);
-            success = true;
+pub unsafe extern "C" fn py_gc_get_thresholds(out: *mut c_int) -> GCReturnCode {
+    with_gc(|gc| {
+        if out.is_null() {
+            return fail(GCReturnCode::ErrorInternal, "NULL thresholds pointer");
         }
-    });
 
-    if success {
+        let thresholds = gc.get_thresholds().map(|threshold| threshold as c_int);
+        unsafe { std::ptr::copy_nonoverlapping(thresholds.as_ptr(), out, thresholds.len()) };
         GCReturnCode::Success
-    } else {
-        GCReturnCode::ErrorInternal
-    }
+    })
+    .unwrap_or_else(not_initialized)
 }
 
-/// Get all tracked objects as a Python list
-///
-/// # Safety
-///
-/// - The returned pointer must be properly managed by the caller
-/// - The caller is responsible for decrementing the reference count when done
+/// Objects at least `threshold` bytes go to the large object space, which
+/// only full collections visit. 0 turns the space off.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_get_objects() -> *mut c_void {
-    with_object_registry(|reg| {
-        let objects: Vec<*mut c_void> = reg.keys().copied().collect();
-        unsafe { create_python_list_from_objects(objects) }
+pub extern "C" fn py_gc_set_large_object_threshold(threshold: usize) -> GCReturnCode {
+    with_gc(|gc| {
+        gc.set_large_object_threshold(threshold);
+        GCReturnCode::Success
     })
+    .unwrap_or_else(not_initialized)
 }
 
-/// Get objects that refer to the given object
-///
-/// # Safety
-///
-/// - `obj_ptr` must be a valid pointer to a tracked object or null
-/// - The returned pointer must be properly managed by the caller
-/// - The caller is responsible for decrementing the reference count when done
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_get_referrers(obj_ptr: *mut c_void) -> *mut c_void {
-    if obj_ptr.is_null() {
-        return std::ptr::null_mut();
-    }
-
-    let referrers = get_referrers(obj_ptr);
-    unsafe { create_python_list_from_objects(referrers) }
+pub extern "C" fn py_gc_get_large_object_threshold() -> usize {
+    with_gc(|gc| gc.get_large_object_threshold()).unwrap_or(0)
 }
 
-/// Get objects that the given object refers to
-///
-/// # Safety
-///
-/// - `obj_ptr` must be a valid pointer to a tracked object or null
-/// - The returned pointer must be properly managed by the caller
-/// - The caller is responsible for decrementing the reference count when done
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_get_referents(obj_ptr: *mut c_void) -> *mut c_void {
-    if obj_ptr.is_null() {
-        return std::ptr::null_mut();
-    }
+pub extern "C" fn py_gc_set_debug(flags: c_int) -> GCReturnCode {
+    with_gc(|gc| {
+        if flags < 0 {
+            return fail(
+                GCReturnCode::ErrorInternal,
+                "Debug flags must be non-negative",
+            );
+        }
 
-    let references = get_references(obj_ptr);
-    unsafe { create_python_list_from_objects(references) }
+        gc.set_debug(flags as u32);
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_is_tracked_python(obj_ptr: *mut c_void) -> c_int {
-    if obj_ptr.is_null() {
-        return 0;
-    }
-
-    unsafe {
-        let py_obj = obj_ptr as *mut PyObject_HEAD;
-        let py_type = (*py_obj).ob_type;
-        if !py_type.is_null() {
-            let flags = (*py_type).tp_flags;
-            if (flags & PY_TPFLAGS_HAVE_GC) != 0 && is_object_tracked(obj_ptr) {
-                1
-            } else {
-                0
-            }
-        } else {
-            0
-        }
-    }
+#[repr(C)]
+pub struct GCStats {
+    pub total_tracked: c_int,
+    pub generation_counts: [c_int; 3],
+    pub uncollectable: c_int,
 }
 
+/// Retrieves garbage collection statistics.
+///
+/// Never waits for a running collection: while one holds the collector, the
+/// stats it published at its last phase boundary are reported.
+///
+/// # Safety
+///
+/// The caller must ensure that `stats` is a valid pointer to a `GCStats` struct.
+/// The function will write to the memory pointed to by `stats`.
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_track_python(obj_ptr: *mut c_void) -> GCReturnCode {
-    if obj_ptr.is_null() {
-        return GCReturnCode::ErrorInternal;
-    }
-
-    if is_object_tracked(obj_ptr) {
-        return GCReturnCode::ErrorAlreadyTracked;
-    }
-
-    let type_name = unsafe {
-        let py_obj = obj_ptr as *mut PyObject_HEAD;
-        let py_type = (*py_obj).ob_type;
-        if !py_type.is_null() {
-            let type_name_ptr = (*py_type).tp_name;
-            if !type_name_ptr.is_null() {
-                std::ffi::CStr::from_ptr(type_name_ptr)
-                    .to_string_lossy()
-                    .to_string()
-            } else {
-                "unknown".to_string()
-            }
-        } else {
-            "unknown".to_string()
+pub unsafe extern "C" fn py_gc_get_stats(stats: *mut GCStats) -> GCReturnCode {
+    with_gc(|gc| {
+        if stats.is_null() {
+            return fail(GCReturnCode::ErrorInternal, "NULL stats pointer");
         }
-    };
-
-    let obj = PyObject::new_ffi(&type_name, ObjectData::None, obj_ptr);
 
-    track_object_fast(obj_ptr, obj);
-
-    GCReturnCode::Success
-}
-
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_untrack_python(obj_ptr: *mut c_void) -> GCReturnCode {
-    if obj_ptr.is_null() {
-        return GCReturnCode::ErrorInternal;
-    }
+        let rust_stats = gc.published_stats().stats;
+        unsafe {
+            *stats = GCStats {
+                total_tracked: rust_stats.total_tracked as c_int,
+                generation_counts: [
+                    rust_stats.generation_counts[0] as c_int,
+                    rust_stats.generation_counts[1] as c_int,
+                    rust_stats.generation_counts[2] as c_int,
+                ],
+                uncollectable: rust_stats.uncollectable as c_int,
+            };
+        }
 
-    if untrack_object_fast(obj_ptr) {
         GCReturnCode::Success
-    } else {
-        GCReturnCode::ErrorNotTracked
-    }
+    })
+    .unwrap_or_else(not_initialized)
 }
 
-#[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_collection_counts() -> *mut c_int {
-    unsafe {
-        if let Some(ref gc) = GC {
-            let counts = Box::new([
-                gc.get_generation_count(0).unwrap_or(0) as c_int,
-                gc.get_generation_count(1).unwrap_or(0) as c_int,
-                gc.get_generation_count(2).unwrap_or(0) as c_int,
-            ]);
-            Box::into_raw(counts) as *mut c_int
-        } else {
-            std::ptr::null_mut()
-        }
-    }
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct GCStatsExtended {
+    pub size: usize,
+    pub total_tracked: usize,
+    pub generation_counts: [usize; 3],
+    pub uncollectable: usize,
+    pub collections: [usize; 3],
+    pub total_collected: usize,
+    pub last_pause_ns: u64,
+    pub thresholds: [usize; 3],
+    pub reported_allocations: usize,
+    pub reported_frees: usize,
+    pub long_lived_total: usize,
+    pub long_lived_pending: usize,
+    pub collected: [usize; 3],
+    pub large_object_count: usize,
+    pub large_object_bytes: usize,
+    pub stats_epoch: u64,
+    pub collection_in_progress: usize,
+    pub permanent_count: usize,
+    pub permanent_bytes: usize,
+    pub generation_bytes: [usize; 3],
+    pub scanned: [usize; 3],
+    pub promoted: [usize; 3],
 }
 
-/// Free memory allocated for collection counts
+/// Retrieves extended garbage collection statistics.
+///
+/// Only the first `size` bytes of the struct are written, so callers built
+/// against an older, shorter layout keep working. The `size` field of the
+/// result holds the number of bytes actually filled in. Like
+/// `py_gc_get_stats`, this never waits for a running collection; it reports
+/// the stats published at the collection's last phase boundary instead.
 ///
 /// # Safety
 ///
-/// - `counts` must be a valid pointer previously returned by a GC function
-/// - The pointer must not be used after this call
+/// The caller must ensure that `stats` is a valid pointer to at least `size`
+/// writable bytes.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_free_collection_counts(counts: *mut c_int) {
-    if !counts.is_null() {
+pub unsafe extern "C" fn py_gc_get_stats_extended(
+    stats: *mut GCStatsExtended,
+    size: usize,
+) -> GCReturnCode {
+    with_gc(|gc| {
+        if stats.is_null() {
+            return fail(GCReturnCode::ErrorInternal, "NULL stats pointer");
+        }
+        if size < std::mem::size_of::<usize>() {
+            return fail(
+                GCReturnCode::ErrorInternal,
+                format!("Stats size {size} is too small"),
+            );
+        }
+
+        let published = gc.published_stats();
+        let written = size.min(std::mem::size_of::<GCStatsExtended>());
+        let extended = GCStatsExtended {
+            size: written,
+            ..extended_stats(
+                &published.stats,
+                published.epoch,
+                published.collection_in_progress,
+            )
+        };
         unsafe {
-            let _ = Box::from_raw(counts);
+            std::ptr::copy_nonoverlapping(
+                &extended as *const GCStatsExtended as *const u8,
+                stats as *mut u8,
+                written,
+            );
         }
-    }
+
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
 }
 
-/// Get uncollectable objects as a Python list
-///
-/// # Safety
-///
-/// - The returned pointer must be properly managed by the caller
-/// - The caller is responsible for decrementing the reference count when done
-#[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_get_garbage() -> *mut c_void {
-    let uncollectable = get_uncollectable_objects();
-    unsafe { create_python_list_from_objects(uncollectable) }
+fn extended_stats(
+    rust_stats: &crate::GCStats,
+    stats_epoch: u64,
+    collection_in_progress: bool,
+) -> GCStatsExtended {
+    GCStatsExtended {
+        size: std::mem::size_of::<GCStatsExtended>(),
+        total_tracked: rust_stats.total_tracked,
+        generation_counts: rust_stats.generation_counts,
+        uncollectable: rust_stats.uncollectable,
+        collections: rust_stats.generation_collections,
+        total_collected: rust_stats.collected,
+        last_pause_ns: rust_stats.last_pause.as_nanos() as u64,
+        thresholds: rust_stats.thresholds,
+        reported_allocations: REPORTED_ALLOCATIONS.load(Ordering::Relaxed),
+        reported_frees: REPORTED_FREES.load(Ordering::Relaxed),
+        long_lived_total: rust_stats.long_lived_total,
+        long_lived_pending: rust_stats.long_lived_pending,
+        collected: rust_stats.generation_collected,
+        large_object_count: rust_stats.large_object_count,
+        large_object_bytes: rust_stats.large_object_bytes,
+        stats_epoch,
+        collection_in_progress: collection_in_progress as usize,
+        permanent_count: rust_stats.permanent_count,
+        permanent_bytes: rust_stats.permanent_bytes,
+        generation_bytes: rust_stats.generation_bytes,
+        scanned: rust_stats.generation_scanned,
+        promoted: rust_stats.generation_promoted,
+    }
 }
 
-/// Set the garbage list for uncollectable objects
+/// Call `callback` before every collection, with the generation about to
+/// be collected and the current stats, so the embedder can drop caches for
+/// the collection to find; like a `gc.callbacks` "start" call. Returning
+/// nonzero vetoes the collection.
 ///
 /// # Safety
 ///
-/// - `garbage_list` must be a valid pointer to a Python list or null
-/// - The list must contain valid object pointers
+/// - `out_hook_id` must be a valid pointer to a writable `u64` or null
+/// - `user_data` must stay valid for as long as the callback is registered
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_set_garbage(garbage_list: *mut c_void) -> GCReturnCode {
-    if garbage_list.is_null() {
-        clear_uncollectable_objects();
-        return GCReturnCode::Success;
-    }
-
-    clear_uncollectable_objects();
-
-    unsafe {
-        let list_size = PyList_Size(garbage_list);
-        if list_size < 0 {
-            return GCReturnCode::ErrorInternal;
+pub unsafe extern "C" fn py_gc_add_pre_collect_callback(
+    callback: Option<PreCollectCallback>,
+    user_data: *mut c_void,
+    out_hook_id: *mut u64,
+) -> GCReturnCode {
+    let Some(callback) = callback else {
+        return fail(GCReturnCode::ErrorInternal, "NULL pre-collect callback");
+    };
+    // Raw pointers aren't Send; the embedder vouches for them.
+    let user_addr = user_data as usize;
+    let hook = Arc::new(move |generation: usize, stats: &crate::GCStats| {
+        let stats = extended_stats(stats, 0, false);
+        if callback(generation as c_int, &stats, user_addr as *mut c_void) != 0 {
+            PreCollect::Veto
+        } else {
+            PreCollect::Proceed
         }
+    });
 
-        for i in 0..list_size {
-            let item = PyList_GetItem(garbage_list, i);
-            if !item.is_null() {
-                Py_IncRef(item);
-                add_uncollectable(item);
+    with_gc(|gc| {
+        let hook_id = gc.add_pre_collect_hook(hook);
+        if !out_hook_id.is_null() {
+            unsafe {
+                *out_hook_id = hook_id.as_u64();
             }
         }
-    }
-
-    GCReturnCode::Success
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_set_debug_flags(flags: c_int) -> GCReturnCode {
-    unsafe {
-        if let Some(ref mut gc) = GC {
-            if flags < 0 {
-                return GCReturnCode::ErrorInternal;
-            }
-            gc.set_debug(flags as u32);
+pub extern "C" fn py_gc_remove_pre_collect_callback(hook_id: u64) -> GCReturnCode {
+    with_gc(|gc| {
+        if gc.remove_pre_collect_hook(PreCollectHookId::from_u64(hook_id)) {
             GCReturnCode::Success
         } else {
-            GCReturnCode::ErrorInternal
+            fail(
+                GCReturnCode::ErrorInternal,
+                format!("No pre-collect callback with ID {hook_id}"),
+            )
         }
-    }
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GCGenerationStats {
+    pub count: usize,
+    pub bytes: usize,
+    pub threshold: usize,
+    pub collections: usize,
+    pub collected: usize,
+    pub scanned: usize,
+    pub promoted: usize,
+    pub survivor_rate: f64,
 }
 
+/// Retrieves the statistics of one generation, for tuning its threshold.
+/// Like `py_gc_get_stats`, this never waits for a running collection.
+///
+/// # Safety
+///
+/// `stats` must be a valid pointer to a `GCGenerationStats` struct or null.
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_debug_flags() -> c_int {
-    unsafe {
-        if let Some(ref gc) = GC {
-            gc.get_debug() as c_int
-        } else {
-            0
+pub unsafe extern "C" fn py_gc_get_generation_stats(
+    generation: c_int,
+    stats: *mut GCGenerationStats,
+) -> GCReturnCode {
+    with_gc(|gc| {
+        if stats.is_null() {
+            return fail(GCReturnCode::ErrorInternal, "NULL stats pointer");
         }
-    }
+        if !(0..=2).contains(&generation) {
+            return fail(
+                GCReturnCode::ErrorInvalidGeneration,
+                format!("Invalid generation: {generation}"),
+            );
+        }
+
+        let rust_stats = gc.published_stats().stats;
+        let generation = generation as usize;
+        unsafe {
+            *stats = GCGenerationStats {
+                count: rust_stats.generation_counts[generation],
+                bytes: rust_stats.generation_bytes[generation],
+                threshold: rust_stats.thresholds[generation],
+                collections: rust_stats.generation_collections[generation],
+                collected: rust_stats.generation_collected[generation],
+                scanned: rust_stats.generation_scanned[generation],
+                promoted: rust_stats.generation_promoted[generation],
+                survivor_rate: rust_stats.survivor_rate(generation),
+            };
+        }
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_has_finalizer(obj_ptr: *mut c_void) -> c_int {
+pub extern "C" fn py_gc_is_tracked(obj_ptr: *mut c_void) -> c_int {
     if obj_ptr.is_null() {
         return 0;
     }
 
-    with_object_registry(|reg| {
-        if let Some(obj) = reg.get(&obj_ptr) {
-            if obj.has_finalizer { 1 } else { 0 }
-        } else {
-            0 // Object not tracked, so no finalizer
-        }
+    is_object_tracked(obj_ptr) as c_int
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_uncollectable_count() -> c_int {
+    with_gc(|gc| gc.iter_uncollectable().iter().count() as c_int).unwrap_or(0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_registry_count() -> c_int {
+    with_object_registry(|reg| reg.len() as c_int)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_clear_uncollectable() -> GCReturnCode {
+    with_gc(|gc| {
+        gc.clear_uncollectable();
+        GCReturnCode::Success
     })
+    .unwrap_or_else(not_initialized)
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_set_finalizer(obj_ptr: *mut c_void, has_finalizer: c_int) -> GCReturnCode {
-    if obj_ptr.is_null() {
-        return GCReturnCode::ErrorInternal;
-    }
+pub extern "C" fn py_gc_clear_registry() -> GCReturnCode {
+    let objects: Vec<PyObject> =
+        with_object_registry(|reg| reg.drain().map(|(_, obj)| obj).collect());
+    OBJECT_POINTERS.with(|pointers| pointers.borrow_mut().clear());
 
-    with_object_registry(|reg| {
-        if let Some(obj) = reg.get_mut(&obj_ptr) {
-            obj.set_finalizer(has_finalizer != 0);
-            GCReturnCode::Success
-        } else {
-            GCReturnCode::ErrorNotTracked
+    with_gc(|gc| {
+        for obj in &objects {
+            gc.untrack(&obj.id()).ok();
         }
-    })
+    });
+    GCReturnCode::Success
 }
 
 #[unsafe(no_mangle)]
-pub extern "C" fn py_gc_get_object_size(obj_ptr: *mut c_void) -> c_int {
-    if obj_ptr.is_null() {
-        return 0;
+pub extern "C" fn py_gc_add_reference(from_obj: *mut c_void, to_obj: *mut c_void) -> GCReturnCode {
+    if from_obj.is_null() || to_obj.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL reference endpoint");
     }
 
-    with_object_registry(|reg| {
-        if let Some(obj) = reg.get(&obj_ptr) {
-            match &obj.data {
-                ObjectData::Integer(_) => 8,
-                ObjectData::Float(_) => 8,
-                ObjectData::String(s) => s.len() as c_int,
-                ObjectData::List(l) => (l.len() * std::mem::size_of::<PyObject>()) as c_int,
-                ObjectData::Dict(d) => {
-                    (d.len() * std::mem::size_of::<(PyObject, PyObject)>()) as c_int
-                }
-                ObjectData::Custom(_) => std::mem::size_of::<*mut c_void>() as c_int,
-                ObjectData::None => 0,
-            }
-        } else {
-            0
-        }
-    })
+    add_reference(from_obj, to_obj, ReferenceType::Direct);
+    GCReturnCode::Success
 }
 
-/// Get the type name of an object
+/// Add a strong reference held in the field, slot or key `name`, so "why
+/// alive" reports say where the object is kept. A null `name` adds the
+/// reference unnamed, dropping any name it had.
 ///
 /// # Safety
 ///
-/// - `obj_ptr` must be a valid pointer to a tracked object or null
-/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes
-/// - `buffer_size` must be greater than 0
-/// - The buffer must be writable and not overlap with any other memory being accessed
+/// - `name` must be a valid NUL-terminated string or null
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn py_gc_get_object_type_name(
-    obj_ptr: *mut c_void,
-    buffer: *mut c_char,
-    buffer_size: usize,
+pub unsafe extern "C" fn py_gc_add_reference_named(
+    from_obj: *mut c_void,
+    to_obj: *mut c_void,
+    name: *const c_char,
 ) -> GCReturnCode {
-    if buffer.is_null() || buffer_size == 0 {
-        return GCReturnCode::ErrorInternal;
-    }
-
-    if obj_ptr.is_null() {
-        let error_msg = "NULL pointer";
-        unsafe {
-            let bytes_to_copy = std::cmp::min(error_msg.len(), buffer_size - 1);
-            std::ptr::copy_nonoverlapping(error_msg.as_ptr(), buffer as *mut u8, bytes_to_copy);
-            *buffer.add(bytes_to_copy) = 0;
-        }
-        return GCReturnCode::ErrorInternal;
+    if from_obj.is_null() || to_obj.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL reference endpoint");
     }
 
-    let type_name = with_object_registry(|reg| {
-        if let Some(obj) = reg.get(&obj_ptr) {
-            obj.name.clone()
+    add_reference(from_obj, to_obj, ReferenceType::Direct);
+    REFERENCE_LABELS.with(|labels| {
+        let mut labels = labels.borrow_mut();
+        let edge = (ObjectPtr(from_obj), ObjectPtr(to_obj));
+        if name.is_null() {
+            labels.remove(&edge);
         } else {
-            "unknown".to_string()
+            let name = unsafe { std::ffi::CStr::from_ptr(name) }.to_string_lossy();
+            labels.insert(edge, name.into_owned());
         }
     });
-
-    unsafe {
-        let bytes_to_copy = std::cmp::min(type_name.len(), buffer_size - 1);
-        std::ptr::copy_nonoverlapping(type_name.as_ptr(), buffer as *mut u8, bytes_to_copy);
-        *buffer.add(bytes_to_copy) = 0;
-    }
-
     GCReturnCode::Success
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_gc_init_cleanup() {
-        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
-        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+fn reference_type_from_c(ref_type: c_int) -> Option<ReferenceType> {
+    match ref_type {
+        0 => Some(ReferenceType::Direct),
+        1 => Some(ReferenceType::Weak),
+        2 => Some(ReferenceType::Finalizer),
+        _ => None,
     }
+}
 
-    #[test]
-    fn test_gc_enable_disable() {
-        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+fn reference_type_to_c(ref_type: ReferenceType) -> c_int {
+    match ref_type {
+        ReferenceType::Direct => 0,
+        ReferenceType::Weak => 1,
+        ReferenceType::Finalizer => 2,
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_add_reference_typed(
+    from_obj: *mut c_void,
+    to_obj: *mut c_void,
+    ref_type: c_int,
+) -> GCReturnCode {
+    if from_obj.is_null() || to_obj.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL reference endpoint");
+    }
+
+    let Some(ref_type) = reference_type_from_c(ref_type) else {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            format!("Invalid reference type: {ref_type}"),
+        );
+    };
+
+    add_reference(from_obj, to_obj, ref_type);
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_reference_type(from_obj: *mut c_void, to_obj: *mut c_void) -> c_int {
+    REFERENCE_TRACKING.with(|refs| {
+        refs.borrow()
+            .get(&from_obj)
+            .and_then(|references| references.get(&to_obj))
+            .map(|ref_type| reference_type_to_c(*ref_type))
+            .unwrap_or(-1)
+    })
+}
+
+/// Add references `from_objects[i] -> to_objects[i]` for every index in one pass
+///
+/// # Safety
+///
+/// - `from_objects` and `to_objects` must be valid pointers to arrays of at least
+///   `count` object pointers each
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_add_references_batch(
+    from_objects: *const *mut c_void,
+    to_objects: *const *mut c_void,
+    count: usize,
+) -> GCReturnCode {
+    if (from_objects.is_null() || to_objects.is_null()) && count > 0 {
+        return fail(GCReturnCode::ErrorInternal, "NULL reference array");
+    }
+
+    let from_pointers = unsafe { batch_slice(from_objects, count) };
+    let to_pointers = unsafe { batch_slice(to_objects, count) };
+    if from_pointers
+        .iter()
+        .chain(to_pointers)
+        .any(|obj_ptr| obj_ptr.is_null())
+    {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            "NULL reference endpoint in batch",
+        );
+    }
+
+    let recording = with_gc(GarbageCollector::is_recording_references).unwrap_or(false);
+    let mut added = Vec::new();
+    REFERENCE_TRACKING.with(|refs| {
+        let mut refs = refs.borrow_mut();
+        for (from_obj, to_obj) in from_pointers.iter().zip(to_pointers) {
+            let new = refs
+                .entry(ObjectPtr(*from_obj))
+                .or_default()
+                .insert(ObjectPtr(*to_obj), ReferenceType::Direct)
+                .is_none();
+            if new && recording {
+                added.push((*from_obj, *to_obj));
+            }
+        }
+    });
+    if !added.is_empty() {
+        record_reference_changes(&added, EdgeChange::Added);
+    }
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_remove_reference(
+    from_obj: *mut c_void,
+    to_obj: *mut c_void,
+) -> GCReturnCode {
+    if from_obj.is_null() || to_obj.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL reference endpoint");
+    }
+
+    remove_reference(from_obj, to_obj);
+    GCReturnCode::Success
+}
+
+/// Drop the references an object holds, as tp_clear would: those recorded
+/// with `py_gc_add_reference`, and the counts they kept on tracked objects.
+/// Objects freed this way drop theirs in turn, so breaking one link frees a
+/// cycle nothing else holds. The embedder clears the Python object itself.
+///
+/// # Safety
+///
+/// - `out_freed` must be a valid pointer to a writable `usize` or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_break_cycle(
+    obj_ptr: *mut c_void,
+    out_freed: *mut usize,
+) -> GCReturnCode {
+    if py_gc_is_initialized() == 0 {
+        return not_initialized();
+    }
+
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    let Some(obj_id) = with_object_registry(|reg| reg.get(&obj_ptr).map(PyObject::id)) else {
+        return fail(GCReturnCode::ErrorNotTracked, "Object is not tracked");
+    };
+
+    collect_stopped(|gc| {
+        let result = gc.break_cycle(&obj_id).and_then(|mut freed| {
+            let mut worklist = vec![obj_ptr];
+            while let Some(cleared) = worklist.pop() {
+                let references = REFERENCE_TRACKING
+                    .with(|refs| refs.borrow_mut().remove(&cleared))
+                    .unwrap_or_default();
+                REFERENCE_LABELS.with(|labels| {
+                    labels
+                        .borrow_mut()
+                        .retain(|(from_obj, _), _| from_obj.0 != cleared)
+                });
+                for (referent, ref_type) in references {
+                    let Some(referent_id) =
+                        with_object_registry(|reg| reg.get(&referent).map(PyObject::id))
+                    else {
+                        continue;
+                    };
+                    if ref_type == ReferenceType::Weak || !gc.is_tracked(&referent_id) {
+                        continue;
+                    }
+                    freed += gc.decref(&referent_id)?;
+                    if !gc.is_tracked(&referent_id) {
+                        worklist.push(referent.0);
+                    }
+                }
+            }
+            Ok(freed)
+        });
+        unsafe { write_collected(result, out_freed) }
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_mark_uncollectable(obj_ptr: *mut c_void) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    add_uncollectable(obj_ptr);
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_unmark_uncollectable(obj_ptr: *mut c_void) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    remove_uncollectable(obj_ptr);
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_is_uncollectable(obj_ptr: *mut c_void) -> c_int {
+    if obj_ptr.is_null() {
+        return 0;
+    }
+
+    UNCOLLECTABLE_OBJECTS.with(|uncollectable| {
+        if uncollectable.borrow().contains(&ObjectPtr(obj_ptr)) {
+            1
+        } else {
+            0
+        }
+    })
+}
+
+/// Get information about a tracked object
+///
+/// Pass a null `buffer` to query the required size through
+/// `py_gc_get_required_buffer_size`.
+///
+/// # Safety
+///
+/// - `obj_ptr` must be a valid pointer to a tracked object or null
+/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes, or null
+/// - The buffer must be writable and not overlap with any other memory being accessed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_tracked_info(
+    obj_ptr: *mut c_void,
+    buffer: *mut c_char,
+    buffer_size: usize,
+) -> GCReturnCode {
+    if py_gc_is_initialized() == 0 {
+        unsafe { write_c_string("GC not initialized", buffer, buffer_size) };
+        return not_initialized();
+    }
+
+    if obj_ptr.is_null() {
+        unsafe { write_c_string("NULL pointer", buffer, buffer_size) };
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    let obj_info = with_object_registry(|reg| {
+        reg.get(&obj_ptr).map(|obj| {
+            format!(
+                "Object: {} (ID: {}, Refs: {}, Ptr: {:p})",
+                obj.name(),
+                obj.id().as_u64(),
+                obj.get_refcount(),
+                obj_ptr
+            )
+        })
+    });
+
+    match obj_info {
+        Some(obj_info) => unsafe { fill_buffer(&obj_info, buffer, buffer_size) },
+        None => {
+            unsafe { write_c_string("Pointer not tracked", buffer, buffer_size) };
+            fail(GCReturnCode::ErrorNotTracked, "Object is not tracked")
+        }
+    }
+}
+
+/// Human-readable account of why the object at `obj_ptr` is alive, using the
+/// registered strong references for the retaining path.
+fn explain_object(gc: &GarbageCollector, obj_ptr: *mut c_void) -> Option<String> {
+    let obj_id = with_object_registry(|reg| reg.get(&obj_ptr).map(|obj| obj.id()))?;
+    let explanation = gc.explain_object(&obj_id)?;
+
+    let referrers: HashMap<*mut c_void, Vec<*mut c_void>> = REFERENCE_TRACKING.with(|refs| {
+        let mut referrers: HashMap<*mut c_void, Vec<*mut c_void>> = HashMap::new();
+        for (from_obj, references) in refs.borrow().iter() {
+            for (to_obj, ref_type) in references {
+                if *ref_type != ReferenceType::Weak {
+                    referrers.entry(to_obj.0).or_default().push(from_obj.0);
+                }
+            }
+        }
+        for sources in referrers.values_mut() {
+            sources.sort();
+        }
+        referrers
+    });
+
+    let generation = match explanation.generation {
+        Some(generation) => format!("generation {generation}"),
+        None => "uncollectable".to_string(),
+    };
+    let path = if referrers.contains_key(&obj_ptr) {
+        let path = retaining_path(obj_ptr, &referrers);
+        REFERENCE_LABELS.with(|labels| {
+            let labels = labels.borrow();
+            let mut text = format!("{:p}", path[0]);
+            for step in path.windows(2) {
+                match labels.get(&(ObjectPtr(step[0]), ObjectPtr(step[1]))) {
+                    Some(label) => text.push_str(&format!(" -[{label}]-> {:p}", step[1])),
+                    None => text.push_str(&format!(" -> {:p}", step[1])),
+                }
+            }
+            text
+        })
+    } else {
+        "none (no registered referrers)".to_string()
+    };
+
+    Some(format!(
+        "Object {obj_ptr:p} ({}, ID: {})\n  Status: {generation}\n  Age: survived {} collections\n  \
+         Refcount: {} ({} from referrers)\n  Finalizer: {}\n  Retaining path: {path}",
+        explanation.type_name,
+        explanation.object_id.as_u64(),
+        explanation.collections_survived,
+        py_gc_get_refcount(obj_ptr),
+        referrers.get(&obj_ptr).map_or(0, Vec::len),
+        if explanation.has_finalizer {
+            "yes"
+        } else {
+            "no"
+        },
+    ))
+}
+
+/// Explain why a tracked object is alive: generation, age, refcount and the
+/// chain of references retaining it
+///
+/// Pass a null `buffer` to query the required size through
+/// `py_gc_get_required_buffer_size`.
+///
+/// # Safety
+///
+/// - `obj_ptr` must be a valid pointer to a tracked object or null
+/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes, or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_explain_object(
+    obj_ptr: *mut c_void,
+    buffer: *mut c_char,
+    buffer_size: usize,
+) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        unsafe { write_c_string("NULL pointer", buffer, buffer_size) };
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    match with_gc(|gc| explain_object(gc, obj_ptr)) {
+        None => {
+            unsafe { write_c_string("GC not initialized", buffer, buffer_size) };
+            not_initialized()
+        }
+        Some(None) => {
+            unsafe { write_c_string("Pointer not tracked", buffer, buffer_size) };
+            fail(GCReturnCode::ErrorNotTracked, "Object is not tracked")
+        }
+        Some(Some(explanation)) => unsafe { fill_buffer(&explanation, buffer, buffer_size) },
+    }
+}
+
+/// Record the references added to and removed from each tracked object
+/// through `py_gc_add_reference` and `py_gc_remove_reference`, keeping the
+/// last `per_object` changes for at most `max_objects` objects. Zero for
+/// either turns recording off. Drops any history recorded so far.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_set_reference_history(
+    per_object: usize,
+    max_objects: usize,
+) -> GCReturnCode {
+    let limits = (per_object > 0 && max_objects > 0).then_some(ReferenceHistoryLimits {
+        per_object,
+        max_objects,
+    });
+    with_gc(|gc| {
+        gc.set_reference_history(limits);
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+/// Describe the last `n` changes to the references into a tracked object,
+/// oldest first, one per line
+///
+/// Pass a null `buffer` to query the required size through
+/// `py_gc_get_required_buffer_size`.
+///
+/// # Safety
+///
+/// - `obj_ptr` must be a valid pointer to a tracked object or null
+/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes, or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_reference_history(
+    obj_ptr: *mut c_void,
+    n: usize,
+    buffer: *mut c_char,
+    buffer_size: usize,
+) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        unsafe { write_c_string("NULL pointer", buffer, buffer_size) };
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    match with_gc(|gc| reference_history(gc, obj_ptr, n)) {
+        None => {
+            unsafe { write_c_string("GC not initialized", buffer, buffer_size) };
+            not_initialized()
+        }
+        Some(None) => {
+            unsafe { write_c_string("Pointer not tracked", buffer, buffer_size) };
+            fail(GCReturnCode::ErrorNotTracked, "Object is not tracked")
+        }
+        Some(Some(history)) => unsafe { fill_buffer(&history, buffer, buffer_size) },
+    }
+}
+
+fn reference_history(gc: &GarbageCollector, obj_ptr: *mut c_void, n: usize) -> Option<String> {
+    let obj_id = with_object_registry(|reg| reg.get(&obj_ptr).map(|obj| obj.id()))?;
+    let history = gc.reference_history(&obj_id, n);
+
+    let lines: Vec<String> = OBJECT_POINTERS.with(|pointers| {
+        let pointers = pointers.borrow();
+        history
+            .iter()
+            .map(|event| {
+                let change = match event.change {
+                    EdgeChange::Added => "added",
+                    EdgeChange::Removed => "removed",
+                };
+                let from = match pointers.get(&event.from) {
+                    Some(ptr) => format!("{:p}", ptr.0),
+                    None => format!("ID {}", event.from.as_u64()),
+                };
+                format!("{:.6}s {change} from {from}", event.at.as_secs_f64())
+            })
+            .collect()
+    });
+    Some(lines.join("\n"))
+}
+
+fn watch_kind_from_c(kind: c_int, threshold: usize) -> Option<WatchKind> {
+    match kind {
+        0 => Some(WatchKind::Refcount { threshold }),
+        1 => Some(WatchKind::Reachability),
+        2 => Some(WatchKind::Collection),
+        _ => None,
+    }
+}
+
+/// Call `callback` when a tracked object's refcount crosses `threshold`
+/// (`GC_WATCH_REFCOUNT`), a collection finds it unreachable
+/// (`GC_WATCH_REACHABILITY`) or it is freed (`GC_WATCH_COLLECTION`)
+///
+/// # Safety
+///
+/// - `out_watch_id` must be a valid pointer to a writable `u64` or null
+/// - `user_data` must stay valid for as long as the watch is set
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_watch_object(
+    obj_ptr: *mut c_void,
+    kind: c_int,
+    threshold: usize,
+    callback: Option<ObjectWatcher>,
+    user_data: *mut c_void,
+    out_watch_id: *mut u64,
+) -> GCReturnCode {
+    let Some(callback) = callback else {
+        return fail(GCReturnCode::ErrorInternal, "NULL watch callback");
+    };
+    let Some(kind) = watch_kind_from_c(kind, threshold) else {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            format!("Invalid watch kind: {kind}"),
+        );
+    };
+    // Raw pointers aren't Send; the embedder vouches for them.
+    let (obj_addr, user_addr) = (obj_ptr as usize, user_data as usize);
+    let watcher = Arc::new(move |_: ObjectId, event: WatchEvent| {
+        let (event, refcount) = match event {
+            WatchEvent::Refcount { new, .. } => (0, new),
+            WatchEvent::Unreachable => (1, 0),
+            WatchEvent::Collected => (2, 0),
+        };
+        callback(
+            obj_addr as *mut c_void,
+            event,
+            refcount,
+            user_addr as *mut c_void,
+        );
+    });
+
+    with_tracked_id(obj_ptr, |gc, obj_id| {
+        gc.watch(&obj_id, kind, watcher)
+            .map(|watch_id| {
+                if !out_watch_id.is_null() {
+                    unsafe {
+                        *out_watch_id = watch_id.as_u64();
+                    }
+                }
+            })
+            .into()
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_unwatch_object(watch_id: u64) -> GCReturnCode {
+    with_gc(|gc| {
+        if gc.unwatch(WatchId::from_u64(watch_id)) {
+            GCReturnCode::Success
+        } else {
+            fail(
+                GCReturnCode::ErrorInternal,
+                format!("No watch with ID {watch_id}"),
+            )
+        }
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+/// Write a heap snapshot to the file at `path`
+///
+/// The file can be loaded with `HeapSnapshot::load` or compared offline with
+/// the snapshot `Analyzer`.
+///
+/// # Safety
+///
+/// - `path` must be a valid NUL-terminated string or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_dump_heap(path: *const c_char) -> GCReturnCode {
+    if path.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL dump path");
+    }
+    let path = unsafe { std::ffi::CStr::from_ptr(path) }.to_string_lossy();
+
+    #[cfg(feature = "snapshot")]
+    {
+        with_gc(|gc| gc.save_snapshot(path.as_ref()).into()).unwrap_or_else(not_initialized)
+    }
+    #[cfg(not(feature = "snapshot"))]
+    {
+        fail(
+            GCReturnCode::ErrorInternal,
+            format!("Cannot dump heap to {path}: built without the snapshot feature"),
+        )
+    }
+}
+
+/// Write a heap snapshot holding only the objects tagged `tag`
+///
+/// # Safety
+///
+/// - `path` and `tag` must be valid NUL-terminated strings or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_dump_heap_tagged(
+    path: *const c_char,
+    tag: *const c_char,
+) -> GCReturnCode {
+    if path.is_null() || tag.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL dump path or tag");
+    }
+    let path = unsafe { std::ffi::CStr::from_ptr(path) }.to_string_lossy();
+    let tag = unsafe { std::ffi::CStr::from_ptr(tag) }.to_string_lossy();
+
+    #[cfg(feature = "snapshot")]
+    {
+        with_gc(|gc| gc.snapshot().filter_tag(&tag).save(path.as_ref()).into())
+            .unwrap_or_else(not_initialized)
+    }
+    #[cfg(not(feature = "snapshot"))]
+    {
+        fail(
+            GCReturnCode::ErrorInternal,
+            format!("Cannot dump {tag} objects to {path}: built without the snapshot feature"),
+        )
+    }
+}
+
+/// Put the object at `obj_ptr` in the group `tag`, or clear its tag when
+/// `tag` is null
+///
+/// # Safety
+///
+/// - `tag` must be a valid NUL-terminated string or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_set_tag(obj_ptr: *mut c_void, tag: *const c_char) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+    let Some(obj_id) = with_object_registry(|reg| reg.get(&obj_ptr).map(|obj| obj.id())) else {
+        return fail(GCReturnCode::ErrorNotTracked, "Object is not tracked");
+    };
+    let tag = (!tag.is_null()).then(|| unsafe { std::ffi::CStr::from_ptr(tag) }.to_string_lossy());
+
+    with_gc(|gc| match &tag {
+        Some(tag) => gc.set_tag(&obj_id, tag).into(),
+        None => gc.clear_tag(&obj_id).into(),
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GCTagStats {
+    pub objects: usize,
+    pub bytes: usize,
+    pub survivors: usize,
+}
+
+/// Count, shallow size and survivors of the objects tagged `tag`; all zero
+/// for a tag no object has
+///
+/// # Safety
+///
+/// - `tag` must be a valid NUL-terminated string or null
+/// - `stats` must be a valid pointer to a `GCTagStats` struct or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_tag_stats(
+    tag: *const c_char,
+    stats: *mut GCTagStats,
+) -> GCReturnCode {
+    if tag.is_null() || stats.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL tag or stats pointer");
+    }
+    let tag = unsafe { std::ffi::CStr::from_ptr(tag) }.to_string_lossy();
+
+    with_gc(|gc| {
+        let tagged = gc.tag_stats().remove(tag.as_ref()).unwrap_or_default();
+        unsafe {
+            *stats = GCTagStats {
+                objects: tagged.objects,
+                bytes: tagged.bytes,
+                survivors: tagged.survivors,
+            };
+        }
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_debug_untrack(obj_ptr: *mut c_void) -> GCReturnCode {
+    if py_gc_is_initialized() == 0 {
+        return not_initialized();
+    }
+
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    if !untrack_object_fast(obj_ptr) {
+        return fail(GCReturnCode::ErrorNotTracked, "Object is not tracked");
+    }
+
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_debug_state() -> GCReturnCode {
+    with_gc(|gc| {
+        let stats = gc.get_stats();
+        let registry_count = with_object_registry(|reg| reg.len());
+
+        let lines = [
+            "GC Debug State:".to_string(),
+            format!("  Total tracked: {}", stats.total_tracked),
+            format!("  Generation 0: {}", stats.generation_counts[0]),
+            format!("  Generation 1: {}", stats.generation_counts[1]),
+            format!("  Generation 2: {}", stats.generation_counts[2]),
+            format!("  Uncollectable: {}", stats.uncollectable),
+            format!("  Registry count: {registry_count}"),
+        ];
+        for line in &lines {
+            log_message(GCLogLevel::Info, line);
+        }
+
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+/// Check the collector's internal invariants, logging each violation found
+///
+/// # Safety
+///
+/// - `out_violations` must be a valid pointer to a writable `usize` or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_verify(out_violations: *mut usize) -> GCReturnCode {
+    with_gc(|gc| {
+        let violations = gc.verify_heap();
+        for violation in &violations {
+            log_message(GCLogLevel::Warning, &format!("Heap integrity: {violation}"));
+        }
+
+        if !out_violations.is_null() {
+            unsafe {
+                *out_violations = violations.len();
+            }
+        }
+
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+/// Compare each object's refcount with its referrers plus its declared
+/// external references, logging each mismatch found
+///
+/// # Safety
+///
+/// - `out_discrepancies` must be a valid pointer to a writable `usize` or null
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_audit_refcounts(out_discrepancies: *mut usize) -> GCReturnCode {
+    with_gc(|gc| {
+        let discrepancies = gc.audit_refcounts();
+        for discrepancy in &discrepancies {
+            log_message(
+                GCLogLevel::Warning,
+                &format!("Refcount audit: {discrepancy}"),
+            );
+        }
+
+        if !out_discrepancies.is_null() {
+            unsafe {
+                *out_discrepancies = discrepancies.len();
+            }
+        }
+
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+fn with_tracked_id(
+    obj_ptr: *mut c_void,
+    f: impl FnOnce(&GarbageCollector, ObjectId) -> GCReturnCode,
+) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+    let Some(obj_id) = with_object_registry(|reg| reg.get(&obj_ptr).map(|obj| obj.id())) else {
+        return fail(GCReturnCode::ErrorNotTracked, "Object is not tracked");
+    };
+    with_gc(|gc| f(gc, obj_id)).unwrap_or_else(not_initialized)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_add_external_ref(obj_ptr: *mut c_void) -> GCReturnCode {
+    with_tracked_id(obj_ptr, |gc, obj_id| gc.add_external_ref(&obj_id).into())
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_release_external_ref(obj_ptr: *mut c_void) -> GCReturnCode {
+    with_tracked_id(obj_ptr, |gc, obj_id| {
+        gc.release_external_ref(&obj_id).into()
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_enable_automatic_tracking() -> GCReturnCode {
+    set_automatic_tracking(true);
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_disable_automatic_tracking() -> GCReturnCode {
+    set_automatic_tracking(false);
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_is_automatic_tracking_enabled() -> c_int {
+    automatic_tracking() as c_int
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_object_created(obj_ptr: *mut c_void) -> GCReturnCode {
+    if !automatic_tracking() {
+        return GCReturnCode::Success;
+    }
+
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    if is_object_tracked(obj_ptr) {
+        return fail(
+            GCReturnCode::ErrorAlreadyTracked,
+            "Object is already tracked",
+        );
+    }
+
+    let obj = object_from_ptr(obj_ptr);
+
+    let result = track_object_fast(obj_ptr, obj);
+    if result.is_err() {
+        return result.into();
+    }
+
+    register_refcount_callback(
+        obj_ptr,
+        Box::new(|obj_ptr, delta| {
+            if delta < 0 && py_gc_get_refcount(obj_ptr) == 0 {
+                collect_stopped(|gc| gc.collect_if_needed().ok());
+            }
+        }),
+    );
+
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_object_destroyed(obj_ptr: *mut c_void) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    unregister_refcount_callback(obj_ptr);
+
+    if untrack_object_fast(obj_ptr) {
+        GCReturnCode::Success
+    } else {
+        fail(GCReturnCode::ErrorNotTracked, "Object is not tracked")
+    }
+}
+
+/// Apply an embedder-reported refcount change through the checked path, so an
+/// underflow surfaces as an error instead of corrupting the count. Returns
+/// the count before and after.
+fn apply_refcount_delta(obj: &PyObject, delta: c_int) -> GCResult<(usize, usize)> {
+    let delta = delta as isize;
+    let old = obj.try_adjust_ref(delta)?;
+    Ok((old, old.wrapping_add_signed(delta)))
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_refcount_changed(
+    obj_ptr: *mut c_void,
+    old_count: c_int,
+    new_count: c_int,
+) -> GCReturnCode {
+    if !automatic_tracking() {
+        return GCReturnCode::Success;
+    }
+
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    let delta = new_count - old_count;
+    notify_refcount_change(obj_ptr, delta);
+
+    let result = with_object_registry(|reg| match reg.get_mut(&obj_ptr) {
+        Some(obj) => apply_refcount_delta(obj, delta).map(|(old, new)| Some((obj.id(), old, new))),
+        None => Ok(None),
+    });
+    if result.is_err() {
+        return result.into();
+    }
+    if let Ok(Some((obj_id, old, new))) = result {
+        with_gc(|gc| gc.refcount_changed(&obj_id, old, new));
+    }
+
+    if new_count == 0 {
+        collect_stopped(|gc| gc.collect_if_needed().ok());
+    }
+
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_refcount(obj_ptr: *mut c_void) -> c_int {
+    if obj_ptr.is_null() {
+        return 0;
+    }
+
+    with_object_registry(|reg| {
+        if let Some(obj) = reg.get(&obj_ptr) {
+            obj.get_refcount() as c_int
+        } else {
+            unsafe { object_refcnt(obj_ptr.cast_const()) as c_int }
+        }
+    })
+}
+
+/// Set the reference count of an object
+///
+/// # Safety
+///
+/// - `obj_ptr` must be a valid pointer to a Python object or null
+/// - The object must not be in an inconsistent state
+/// - `refcount` must be non-negative
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_set_refcount(obj_ptr: *mut c_void, refcount: c_int) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    if refcount < 0 {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            format!("Invalid reference count: {refcount}"),
+        );
+    }
+
+    let mut untracked = None;
+    let mut changed = None;
+    with_object_registry(|reg| {
+        if let Some(obj) = reg.get_mut(&obj_ptr) {
+            changed = Some((obj.id(), obj.get_refcount()));
+            obj.set_refcount(refcount as usize);
+        } else {
+            let _gil = GilGuard::acquire();
+            let api = python_api::python_api();
+            unsafe {
+                let current_refcount = object_refcnt(obj_ptr);
+                let target_refcount = refcount as usize;
+
+                if target_refcount > current_refcount {
+                    for _ in 0..(target_refcount - current_refcount) {
+                        api.incref(obj_ptr);
+                    }
+                } else if target_refcount < current_refcount {
+                    for _ in 0..(current_refcount - target_refcount) {
+                        api.decref(obj_ptr);
+                    }
+                }
+
+                set_object_refcnt(obj_ptr, target_refcount);
+            }
+
+            untracked = Some(object_from_ptr(obj_ptr));
+        }
+    });
+
+    if let Some((obj_id, old)) = changed {
+        with_gc(|gc| gc.refcount_changed(&obj_id, old, refcount as usize));
+    }
+    match untracked {
+        Some(obj) => track_object_fast(obj_ptr, obj).into(),
+        None => GCReturnCode::Success,
+    }
+}
+
+/// Get all tracked objects as a Python list
+///
+/// # Safety
+///
+/// - The returned pointer must be properly managed by the caller
+/// - The caller is responsible for decrementing the reference count when done
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_objects() -> *mut c_void {
+    with_object_registry(|reg| {
+        let objects: Vec<*mut c_void> = reg.keys().map(|obj_ptr| obj_ptr.0).collect();
+        unsafe { create_python_list_from_objects(objects) }
+    })
+}
+
+/// Get objects that refer to the given object
+///
+/// # Safety
+///
+/// - `obj_ptr` must be a valid pointer to a tracked object or null
+/// - The returned pointer must be properly managed by the caller
+/// - The caller is responsible for decrementing the reference count when done
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_referrers(obj_ptr: *mut c_void) -> *mut c_void {
+    if obj_ptr.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    let referrers = get_referrers(obj_ptr);
+    unsafe { create_python_list_from_objects(referrers) }
+}
+
+fn visit_objects(
+    objects: Vec<*mut c_void>,
+    callback: Option<ObjectVisitor>,
+    user_data: *mut c_void,
+) -> GCReturnCode {
+    let Some(callback) = callback else {
+        return fail(GCReturnCode::ErrorInternal, "NULL visitor callback");
+    };
+
+    for obj_ptr in objects {
+        if callback(obj_ptr, user_data) != 0 {
+            break;
+        }
+    }
+    GCReturnCode::Success
+}
+
+/// Call `callback` for every tracked object, stopping early when it returns nonzero
+///
+/// The set of objects is captured before the first call, so the callback may
+/// track or untrack objects.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_iterate_objects(
+    callback: Option<ObjectVisitor>,
+    user_data: *mut c_void,
+) -> GCReturnCode {
+    let objects = with_object_registry(|reg| reg.keys().map(|obj_ptr| obj_ptr.0).collect());
+    visit_objects(objects, callback, user_data)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_iterate_referrers(
+    obj_ptr: *mut c_void,
+    callback: Option<ObjectVisitor>,
+    user_data: *mut c_void,
+) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+    visit_objects(get_referrers(obj_ptr), callback, user_data)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_iterate_referents(
+    obj_ptr: *mut c_void,
+    callback: Option<ObjectVisitor>,
+    user_data: *mut c_void,
+) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+    visit_objects(get_references(obj_ptr), callback, user_data)
+}
+
+/// Get all tracked objects with the given type name as a Python list
+///
+/// # Safety
+///
+/// - `type_name` must be a valid null-terminated string or null
+/// - The returned pointer must be properly managed by the caller
+/// - The caller is responsible for decrementing the reference count when done
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_objects_by_type(type_name: *const c_char) -> *mut c_void {
+    if type_name.is_null() {
+        fail(GCReturnCode::ErrorInternal, "NULL type name");
+        return std::ptr::null_mut();
+    }
+
+    let type_name = unsafe { std::ffi::CStr::from_ptr(type_name) }.to_string_lossy();
+    let objects: Vec<*mut c_void> = with_object_registry(|reg| {
+        reg.iter()
+            .filter(|(_, obj)| obj.name() == type_name)
+            .map(|(obj_ptr, _)| obj_ptr.0)
+            .collect()
+    });
+    unsafe { create_python_list_from_objects(objects) }
+}
+
+/// Get all tracked objects with the given tag as a Python list
+///
+/// # Safety
+///
+/// - `tag` must be a valid null-terminated string or null
+/// - The returned pointer must be properly managed by the caller
+/// - The caller is responsible for decrementing the reference count when done
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_objects_by_tag(tag: *const c_char) -> *mut c_void {
+    if tag.is_null() {
+        fail(GCReturnCode::ErrorInternal, "NULL tag");
+        return std::ptr::null_mut();
+    }
+
+    let tag = unsafe { std::ffi::CStr::from_ptr(tag) }.to_string_lossy();
+    let objects: Vec<*mut c_void> = with_object_registry(|reg| {
+        reg.iter()
+            .filter(|(_, obj)| obj.tag().as_deref() == Some(tag.as_ref()))
+            .map(|(obj_ptr, _)| obj_ptr.0)
+            .collect()
+    });
+    unsafe { create_python_list_from_objects(objects) }
+}
+
+/// Get objects that the given object refers to
+///
+/// # Safety
+///
+/// - `obj_ptr` must be a valid pointer to a tracked object or null
+/// - The returned pointer must be properly managed by the caller
+/// - The caller is responsible for decrementing the reference count when done
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_referents(obj_ptr: *mut c_void) -> *mut c_void {
+    if obj_ptr.is_null() {
+        return std::ptr::null_mut();
+    }
+
+    let references = get_references(obj_ptr);
+    unsafe { create_python_list_from_objects(references) }
+}
+
+/// Find reference cycles among the registered references
+///
+/// Returns a Python list with one inner list of object pointers per cycle.
+///
+/// # Safety
+///
+/// - The returned pointer must be properly managed by the caller
+/// - The caller is responsible for decrementing the reference count when done
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_find_cycles() -> *mut c_void {
+    unsafe { create_python_list_of_lists(find_reference_cycles()) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_count_cycles() -> c_int {
+    find_reference_cycles().len() as c_int
+}
+
+/// Interpreter type information read through the configured ABI layout.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct GCTypeInfo {
+    pub has_gc: c_int,
+    pub has_traverse: c_int,
+    pub has_clear: c_int,
+    pub has_finalize: c_int,
+}
+
+/// Select the `PyObject`/`PyTypeObject` layout used to read interpreter
+/// objects, for CPython `major`.`minor` built with `abi_flags`
+/// (`GC_ABI_TRACE_REFS`, `GC_ABI_FREE_THREADED`). Until this is called the built-in layout is used,
+/// which matches a release build of any CPython 3 version.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_set_abi_version(
+    major: c_int,
+    minor: c_int,
+    abi_flags: c_uint,
+) -> GCReturnCode {
+    if major != 3 || minor < 0 {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            format!("Unsupported Python ABI version: {major}.{minor}"),
+        );
+    }
+    if abi_flags & !(GC_ABI_TRACE_REFS | GC_ABI_FREE_THREADED) != 0 {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            format!("Unknown ABI flags: {abi_flags:#x}"),
+        );
+    }
+    if abi_flags & GC_ABI_FREE_THREADED != 0 && minor < 13 {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            format!("Free-threaded builds start at 3.13, not 3.{minor}"),
+        );
+    }
+
+    *TYPE_LAYOUT.write() = TypeLayout::cpython(minor, abi_flags);
+    GCReturnCode::Success
+}
+
+/// Switch to abi3 mode: object and type fields are read only through the
+/// functions in `api`, never by dereferencing interpreter structs, which
+/// makes the layout set by `py_gc_set_abi_version` irrelevant. Pass null to
+/// go back to reading the structs. Call it right after `py_gc_init`.
+///
+/// # Safety
+///
+/// - `api` must be a valid pointer to a `GCAbi3Api` struct or null
+/// - The functions must remain callable until they are replaced or cleared
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_set_abi3_api(api: *const GCAbi3Api) -> GCReturnCode {
+    if api.is_null() {
+        *ABI3_API.write() = None;
+        return GCReturnCode::Success;
+    }
+
+    let api = unsafe { *api };
+    if !api.is_complete() {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            "abi3 API table is missing required functions",
+        );
+    }
+
+    *ABI3_API.write() = Some(api);
+    GCReturnCode::Success
+}
+
+/// Whether `py_gc_set_abi3_api` has installed an abi3 table.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_is_abi3_mode() -> c_int {
+    abi3_api().is_some() as c_int
+}
+
+/// Read the GC-related slots of an object's type.
+///
+/// # Safety
+///
+/// - `obj_ptr` must be a valid pointer to a Python object laid out as the
+///   configured ABI describes
+/// - `info` must be a valid pointer to a writable `GCTypeInfo`
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_type_info(
+    obj_ptr: *mut c_void,
+    info: *mut GCTypeInfo,
+) -> GCReturnCode {
+    if obj_ptr.is_null() || info.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL pointer");
+    }
+
+    unsafe {
+        let py_type = object_type(obj_ptr);
+        if py_type.is_null() {
+            return fail(GCReturnCode::ErrorInternal, "Object has no type");
+        }
+        *info = GCTypeInfo {
+            has_gc: (type_flags(py_type) & PY_TPFLAGS_HAVE_GC != 0) as c_int,
+            has_traverse: type_traverse(py_type).is_some() as c_int,
+            has_clear: type_clear(py_type).is_some() as c_int,
+            has_finalize: type_finalize(py_type).is_some() as c_int,
+        };
+    }
+    GCReturnCode::Success
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_is_tracked_python(obj_ptr: *mut c_void) -> c_int {
+    if obj_ptr.is_null() {
+        return 0;
+    }
+
+    if !is_object_tracked(obj_ptr) {
+        return 0;
+    }
+    if let Some(api) = abi3_api()
+        && let Some(gc_is_tracked) = api.gc_is_tracked
+    {
+        return (gc_is_tracked(obj_ptr) != 0) as c_int;
+    }
+
+    unsafe {
+        let py_type = object_type(obj_ptr.cast_const());
+        (!py_type.is_null() && type_flags(py_type) & PY_TPFLAGS_HAVE_GC != 0) as c_int
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_track_python(obj_ptr: *mut c_void) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    if is_object_tracked(obj_ptr) {
+        return fail(
+            GCReturnCode::ErrorAlreadyTracked,
+            "Object is already tracked",
+        );
+    }
+
+    let obj = object_from_ptr(obj_ptr);
+
+    track_object_fast(obj_ptr, obj).into()
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_untrack_python(obj_ptr: *mut c_void) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    if untrack_object_fast(obj_ptr) {
+        GCReturnCode::Success
+    } else {
+        fail(GCReturnCode::ErrorNotTracked, "Object is not tracked")
+    }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_collection_counts() -> *mut c_int {
+    with_gc(|gc| {
+        let counts = Box::new([
+            gc.get_generation_count(0).unwrap_or(0) as c_int,
+            gc.get_generation_count(1).unwrap_or(0) as c_int,
+            gc.get_generation_count(2).unwrap_or(0) as c_int,
+        ]);
+        Box::into_raw(counts) as *mut c_int
+    })
+    .unwrap_or(std::ptr::null_mut())
+}
+
+/// Free memory allocated for collection counts
+///
+/// # Safety
+///
+/// - `counts` must be a valid pointer previously returned by a GC function
+/// - The pointer must not be used after this call
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_free_collection_counts(counts: *mut c_int) {
+    if !counts.is_null() {
+        unsafe {
+            let _ = Box::from_raw(counts);
+        }
+    }
+}
+
+/// Get uncollectable objects as a Python list
+///
+/// # Safety
+///
+/// - The returned pointer must be properly managed by the caller
+/// - The caller is responsible for decrementing the reference count when done
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_garbage() -> *mut c_void {
+    let uncollectable = get_uncollectable_objects();
+    unsafe { create_python_list_from_objects(uncollectable) }
+}
+
+/// Set the garbage list for uncollectable objects
+///
+/// # Safety
+///
+/// - `garbage_list` must be a valid pointer to a Python list or null
+/// - The list must contain valid object pointers
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_set_garbage(garbage_list: *mut c_void) -> GCReturnCode {
+    if garbage_list.is_null() {
+        clear_uncollectable_objects();
+        return GCReturnCode::Success;
+    }
+
+    clear_uncollectable_objects();
+
+    let _gil = GilGuard::acquire();
+    let api = python_api::python_api();
+    let list_size = api.list_size(garbage_list);
+    if list_size < 0 {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            "Garbage list has no valid size",
+        );
+    }
+
+    for i in 0..list_size {
+        let item = api.list_get_item(garbage_list, i);
+        if !item.is_null() {
+            api.incref(item);
+            add_uncollectable(item);
+        }
+    }
+
+    GCReturnCode::Success
+}
+
+/// Get the objects collections kept while `GC_DEBUG_SAVEALL` was set, as a
+/// Python list
+///
+/// # Safety
+///
+/// - The returned pointer must be properly managed by the caller
+/// - The caller is responsible for decrementing the reference count when done
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_saveall_garbage() -> *mut c_void {
+    let saved = SAVED_GARBAGE.with(|garbage| garbage.borrow().iter().map(|ptr| ptr.0).collect());
+    unsafe { create_python_list_from_objects(saved) }
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_saveall_garbage_count() -> c_int {
+    SAVED_GARBAGE.with(|garbage| garbage.borrow().len() as c_int)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_clear_saveall_garbage() -> GCReturnCode {
+    with_gc(|gc| {
+        gc.clear_saved_garbage();
+        SAVED_GARBAGE.with(|garbage| garbage.borrow_mut().clear());
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_set_debug_flags(flags: c_int) -> GCReturnCode {
+    with_gc(|gc| {
+        if flags < 0 {
+            return fail(
+                GCReturnCode::ErrorInternal,
+                "Debug flags must be non-negative",
+            );
+        }
+        gc.set_debug(flags as u32);
+        GCReturnCode::Success
+    })
+    .unwrap_or_else(not_initialized)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_debug_flags() -> c_int {
+    with_gc(|gc| gc.get_debug() as c_int).unwrap_or(0)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_has_finalizer(obj_ptr: *mut c_void) -> c_int {
+    if obj_ptr.is_null() {
+        return 0;
+    }
+
+    with_object_registry(|reg| {
+        if let Some(obj) = reg.get(&obj_ptr) {
+            if obj.has_finalizer() { 1 } else { 0 }
+        } else {
+            0 // Object not tracked, so no finalizer
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_set_finalizer(obj_ptr: *mut c_void, has_finalizer: c_int) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    with_object_registry(|reg| {
+        if let Some(obj) = reg.get_mut(&obj_ptr) {
+            obj.set_finalizer(has_finalizer != 0);
+            GCReturnCode::Success
+        } else {
+            fail(GCReturnCode::ErrorNotTracked, "Object is not tracked")
+        }
+    })
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_get_object_size(obj_ptr: *mut c_void) -> c_int {
+    if obj_ptr.is_null() {
+        return 0;
+    }
+
+    with_object_registry(|reg| {
+        reg.get(&obj_ptr)
+            .map_or(0, |obj| obj.data().payload_size() as c_int)
+    })
+}
+
+/// Get the type name of an object
+///
+/// Pass a null `buffer` to query the required size through
+/// `py_gc_get_required_buffer_size`.
+///
+/// # Safety
+///
+/// - `obj_ptr` must be a valid pointer to a tracked object or null
+/// - `buffer` must be a valid pointer to a buffer of at least `buffer_size` bytes, or null
+/// - The buffer must be writable and not overlap with any other memory being accessed
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn py_gc_get_object_type_name(
+    obj_ptr: *mut c_void,
+    buffer: *mut c_char,
+    buffer_size: usize,
+) -> GCReturnCode {
+    if obj_ptr.is_null() {
+        unsafe { write_c_string("NULL pointer", buffer, buffer_size) };
+        return fail(GCReturnCode::ErrorInternal, "NULL object pointer");
+    }
+
+    let type_name = with_object_registry(|reg| {
+        if let Some(obj) = reg.get(&obj_ptr) {
+            obj.name().to_string()
+        } else {
+            "unknown".to_string()
+        }
+    });
+
+    unsafe { fill_buffer(&type_name, buffer, buffer_size) }
+}
+
+/// Create a GC instance with its own collector and object registry, for an
+/// embedder running several interpreters. Returns its handle for the `_h`
+/// functions.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_create_instance() -> GCInstanceHandle {
+    let handle = NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed);
+    let instance = Instance::new();
+    *instance.gc.write() = Some(GarbageCollector::new());
+    INSTANCES.write().insert(handle, Arc::new(instance));
+    handle
+}
+
+/// Destroy an instance made by `py_gc_create_instance`, dropping its tracked
+/// objects. Without the `ft` feature, registry entries made on other threads
+/// are only freed when those threads exit.
+#[unsafe(no_mangle)]
+pub extern "C" fn py_gc_destroy_instance(handle: GCInstanceHandle) -> GCReturnCode {
+    if handle == DEFAULT_INSTANCE_HANDLE {
+        return fail(
+            GCReturnCode::ErrorInternal,
+            "The default GC instance cannot be destroyed",
+        );
+    }
+
+    let result = in_instance(handle, || py_gc_cleanup());
+    if result != GCReturnCode::Success {
+        return result;
+    }
+    OBJECT_REGISTRY.remove(handle);
+    OBJECT_POINTERS.remove(handle);
+    REFCOUNT_CALLBACKS.remove(handle);
+    REFERENCE_TRACKING.remove(handle);
+    REFERENCE_LABELS.remove(handle);
+    UNCOLLECTABLE_OBJECTS.remove(handle);
+    SAVED_GARBAGE.remove(handle);
+    INSTANCES.write().remove(&handle);
+    GCReturnCode::Success
+}
+
+/// Declares the `_h` variant of each instance-scoped function: the same call
+/// made against the instance `handle` instead of the default one. Unknown
+/// handles fail with `ErrorNotInitialized`, or return 0 or null.
+macro_rules! instance_variants {
+    ($($kind:ident fn $name:ident => $global:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {
+        $(instance_variants!(@one $kind $name $global($($arg: $ty),*) -> $ret);)*
+    };
+    (@one safe $name:ident $global:ident($($arg:ident: $ty:ty),*) -> $ret:ty) => {
+        #[doc = concat!("`", stringify!($global), "` on the instance `handle`.")]
+        #[unsafe(no_mangle)]
+        pub extern "C" fn $name(handle: GCInstanceHandle, $($arg: $ty),*) -> $ret {
+            in_instance(handle, || $global($($arg),*))
+        }
+    };
+    (@one unsafe $name:ident $global:ident($($arg:ident: $ty:ty),*) -> $ret:ty) => {
+        #[doc = concat!("`", stringify!($global), "` on the instance `handle`.")]
+        ///
+        /// # Safety
+        ///
+        #[doc = concat!("Same as `", stringify!($global), "`.")]
+        #[unsafe(no_mangle)]
+        pub unsafe extern "C" fn $name(handle: GCInstanceHandle, $($arg: $ty),*) -> $ret {
+            in_instance(handle, || unsafe { $global($($arg),*) })
+        }
+    };
+}
+
+instance_variants! {
+    safe fn py_gc_init_h => py_gc_init() -> GCReturnCode;
+    safe fn py_gc_cleanup_h => py_gc_cleanup() -> GCReturnCode;
+    safe fn py_gc_enable_h => py_gc_enable() -> GCReturnCode;
+    safe fn py_gc_disable_h => py_gc_disable() -> GCReturnCode;
+    safe fn py_gc_is_enabled_h => py_gc_is_enabled() -> c_int;
+    safe fn py_gc_is_initialized_h => py_gc_is_initialized() -> c_int;
+    unsafe fn py_gc_get_state_string_h => py_gc_get_state_string(buffer: *mut c_char, buffer_size: usize) -> GCReturnCode;
+    unsafe fn py_gc_track_h => py_gc_track(obj_ptr: *mut c_void) -> GCReturnCode;
+    unsafe fn py_gc_track_batch_h => py_gc_track_batch(objects: *const *mut c_void, count: usize, out_tracked: *mut usize) -> GCReturnCode;
+    unsafe fn py_gc_untrack_batch_h => py_gc_untrack_batch(objects: *const *mut c_void, count: usize, out_untracked: *mut usize) -> GCReturnCode;
+    safe fn py_gc_untrack_h => py_gc_untrack(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_collect_generation_h => py_gc_collect_generation(generation: c_int) -> GCReturnCode;
+    safe fn py_gc_collect_h => py_gc_collect() -> GCReturnCode;
+    unsafe fn py_gc_collect_generation_ex_h => py_gc_collect_generation_ex(generation: c_int, out_collected: *mut usize) -> GCReturnCode;
+    unsafe fn py_gc_collect_ex_h => py_gc_collect_ex(out_collected: *mut usize) -> GCReturnCode;
+    safe fn py_gc_needs_collection_h => py_gc_needs_collection() -> c_int;
+    safe fn py_gc_needs_collection_generation_h => py_gc_needs_collection_generation() -> c_int;
+    safe fn py_gc_collect_if_needed_h => py_gc_collect_if_needed() -> GCReturnCode;
+    safe fn py_gc_get_count_h => py_gc_get_count() -> c_int;
+    safe fn py_gc_get_generation_count_h => py_gc_get_generation_count(generation: c_int) -> c_int;
+    safe fn py_gc_get_object_generation_h => py_gc_get_object_generation(obj_ptr: *mut c_void) -> c_int;
+    safe fn py_gc_freeze_h => py_gc_freeze() -> GCReturnCode;
+    safe fn py_gc_unfreeze_h => py_gc_unfreeze() -> GCReturnCode;
+    safe fn py_gc_get_freeze_count_h => py_gc_get_freeze_count() -> c_int;
+    safe fn py_gc_set_threshold_h => py_gc_set_threshold(generation: c_int, threshold: c_int) -> GCReturnCode;
+    safe fn py_gc_get_threshold_h => py_gc_get_threshold(generation: c_int) -> c_int;
+    safe fn py_gc_set_thresholds_h => py_gc_set_thresholds(threshold0: c_int, threshold1: c_int, threshold2: c_int) -> GCReturnCode;
+    unsafe fn py_gc_get_thresholds_h => py_gc_get_thresholds(out: *mut c_int) -> GCReturnCode;
+    safe fn py_gc_set_large_object_threshold_h => py_gc_set_large_object_threshold(threshold: usize) -> GCReturnCode;
+    safe fn py_gc_get_large_object_threshold_h => py_gc_get_large_object_threshold() -> usize;
+    safe fn py_gc_set_debug_h => py_gc_set_debug(flags: c_int) -> GCReturnCode;
+    unsafe fn py_gc_get_stats_h => py_gc_get_stats(stats: *mut GCStats) -> GCReturnCode;
+    unsafe fn py_gc_get_stats_extended_h => py_gc_get_stats_extended(stats: *mut GCStatsExtended, size: usize) -> GCReturnCode;
+    unsafe fn py_gc_get_generation_stats_h => py_gc_get_generation_stats(generation: c_int, stats: *mut GCGenerationStats) -> GCReturnCode;
+    unsafe fn py_gc_add_pre_collect_callback_h => py_gc_add_pre_collect_callback(callback: Option<PreCollectCallback>, user_data: *mut c_void, out_hook_id: *mut u64) -> GCReturnCode;
+    safe fn py_gc_remove_pre_collect_callback_h => py_gc_remove_pre_collect_callback(hook_id: u64) -> GCReturnCode;
+    safe fn py_gc_is_tracked_h => py_gc_is_tracked(obj_ptr: *mut c_void) -> c_int;
+    safe fn py_gc_get_uncollectable_count_h => py_gc_get_uncollectable_count() -> c_int;
+    safe fn py_gc_get_registry_count_h => py_gc_get_registry_count() -> c_int;
+    safe fn py_gc_clear_uncollectable_h => py_gc_clear_uncollectable() -> GCReturnCode;
+    safe fn py_gc_clear_registry_h => py_gc_clear_registry() -> GCReturnCode;
+    safe fn py_gc_add_reference_h => py_gc_add_reference(from_obj: *mut c_void, to_obj: *mut c_void) -> GCReturnCode;
+    unsafe fn py_gc_add_reference_named_h => py_gc_add_reference_named(from_obj: *mut c_void, to_obj: *mut c_void, name: *const c_char) -> GCReturnCode;
+    safe fn py_gc_add_reference_typed_h => py_gc_add_reference_typed(from_obj: *mut c_void, to_obj: *mut c_void, ref_type: c_int) -> GCReturnCode;
+    safe fn py_gc_get_reference_type_h => py_gc_get_reference_type(from_obj: *mut c_void, to_obj: *mut c_void) -> c_int;
+    unsafe fn py_gc_add_references_batch_h => py_gc_add_references_batch(from_objects: *const *mut c_void, to_objects: *const *mut c_void, count: usize) -> GCReturnCode;
+    safe fn py_gc_remove_reference_h => py_gc_remove_reference(from_obj: *mut c_void, to_obj: *mut c_void) -> GCReturnCode;
+    unsafe fn py_gc_break_cycle_h => py_gc_break_cycle(obj_ptr: *mut c_void, out_freed: *mut usize) -> GCReturnCode;
+    safe fn py_gc_mark_uncollectable_h => py_gc_mark_uncollectable(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_unmark_uncollectable_h => py_gc_unmark_uncollectable(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_is_uncollectable_h => py_gc_is_uncollectable(obj_ptr: *mut c_void) -> c_int;
+    unsafe fn py_gc_get_tracked_info_h => py_gc_get_tracked_info(obj_ptr: *mut c_void, buffer: *mut c_char, buffer_size: usize) -> GCReturnCode;
+    unsafe fn py_gc_explain_object_h => py_gc_explain_object(obj_ptr: *mut c_void, buffer: *mut c_char, buffer_size: usize) -> GCReturnCode;
+    safe fn py_gc_set_reference_history_h => py_gc_set_reference_history(per_object: usize, max_objects: usize) -> GCReturnCode;
+    unsafe fn py_gc_get_reference_history_h => py_gc_get_reference_history(obj_ptr: *mut c_void, n: usize, buffer: *mut c_char, buffer_size: usize) -> GCReturnCode;
+    unsafe fn py_gc_watch_object_h => py_gc_watch_object(obj_ptr: *mut c_void, kind: c_int, threshold: usize, callback: Option<ObjectWatcher>, user_data: *mut c_void, out_watch_id: *mut u64) -> GCReturnCode;
+    safe fn py_gc_unwatch_object_h => py_gc_unwatch_object(watch_id: u64) -> GCReturnCode;
+    unsafe fn py_gc_dump_heap_h => py_gc_dump_heap(path: *const c_char) -> GCReturnCode;
+    unsafe fn py_gc_dump_heap_tagged_h => py_gc_dump_heap_tagged(path: *const c_char, tag: *const c_char) -> GCReturnCode;
+    unsafe fn py_gc_set_tag_h => py_gc_set_tag(obj_ptr: *mut c_void, tag: *const c_char) -> GCReturnCode;
+    unsafe fn py_gc_get_tag_stats_h => py_gc_get_tag_stats(tag: *const c_char, stats: *mut GCTagStats) -> GCReturnCode;
+    safe fn py_gc_debug_untrack_h => py_gc_debug_untrack(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_debug_state_h => py_gc_debug_state() -> GCReturnCode;
+    unsafe fn py_gc_verify_h => py_gc_verify(out_violations: *mut usize) -> GCReturnCode;
+    unsafe fn py_gc_audit_refcounts_h => py_gc_audit_refcounts(out_discrepancies: *mut usize) -> GCReturnCode;
+    safe fn py_gc_add_external_ref_h => py_gc_add_external_ref(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_release_external_ref_h => py_gc_release_external_ref(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_enable_automatic_tracking_h => py_gc_enable_automatic_tracking() -> GCReturnCode;
+    safe fn py_gc_disable_automatic_tracking_h => py_gc_disable_automatic_tracking() -> GCReturnCode;
+    safe fn py_gc_is_automatic_tracking_enabled_h => py_gc_is_automatic_tracking_enabled() -> c_int;
+    safe fn py_gc_object_created_h => py_gc_object_created(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_object_destroyed_h => py_gc_object_destroyed(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_refcount_changed_h => py_gc_refcount_changed(obj_ptr: *mut c_void, old_count: c_int, new_count: c_int) -> GCReturnCode;
+    safe fn py_gc_get_refcount_h => py_gc_get_refcount(obj_ptr: *mut c_void) -> c_int;
+    unsafe fn py_gc_set_refcount_h => py_gc_set_refcount(obj_ptr: *mut c_void, refcount: c_int) -> GCReturnCode;
+    unsafe fn py_gc_get_objects_h => py_gc_get_objects() -> *mut c_void;
+    unsafe fn py_gc_get_referrers_h => py_gc_get_referrers(obj_ptr: *mut c_void) -> *mut c_void;
+    safe fn py_gc_iterate_objects_h => py_gc_iterate_objects(callback: Option<ObjectVisitor>, user_data: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_iterate_referrers_h => py_gc_iterate_referrers(obj_ptr: *mut c_void, callback: Option<ObjectVisitor>, user_data: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_iterate_referents_h => py_gc_iterate_referents(obj_ptr: *mut c_void, callback: Option<ObjectVisitor>, user_data: *mut c_void) -> GCReturnCode;
+    unsafe fn py_gc_get_objects_by_type_h => py_gc_get_objects_by_type(type_name: *const c_char) -> *mut c_void;
+    unsafe fn py_gc_get_objects_by_tag_h => py_gc_get_objects_by_tag(tag: *const c_char) -> *mut c_void;
+    unsafe fn py_gc_get_referents_h => py_gc_get_referents(obj_ptr: *mut c_void) -> *mut c_void;
+    unsafe fn py_gc_find_cycles_h => py_gc_find_cycles() -> *mut c_void;
+    safe fn py_gc_count_cycles_h => py_gc_count_cycles() -> c_int;
+    safe fn py_gc_is_tracked_python_h => py_gc_is_tracked_python(obj_ptr: *mut c_void) -> c_int;
+    safe fn py_gc_track_python_h => py_gc_track_python(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_untrack_python_h => py_gc_untrack_python(obj_ptr: *mut c_void) -> GCReturnCode;
+    safe fn py_gc_get_collection_counts_h => py_gc_get_collection_counts() -> *mut c_int;
+    unsafe fn py_gc_get_garbage_h => py_gc_get_garbage() -> *mut c_void;
+    unsafe fn py_gc_set_garbage_h => py_gc_set_garbage(garbage_list: *mut c_void) -> GCReturnCode;
+    unsafe fn py_gc_get_saveall_garbage_h => py_gc_get_saveall_garbage() -> *mut c_void;
+    safe fn py_gc_get_saveall_garbage_count_h => py_gc_get_saveall_garbage_count() -> c_int;
+    safe fn py_gc_clear_saveall_garbage_h => py_gc_clear_saveall_garbage() -> GCReturnCode;
+    safe fn py_gc_set_debug_flags_h => py_gc_set_debug_flags(flags: c_int) -> GCReturnCode;
+    safe fn py_gc_get_debug_flags_h => py_gc_get_debug_flags() -> c_int;
+    safe fn py_gc_has_finalizer_h => py_gc_has_finalizer(obj_ptr: *mut c_void) -> c_int;
+    safe fn py_gc_set_finalizer_h => py_gc_set_finalizer(obj_ptr: *mut c_void, has_finalizer: c_int) -> GCReturnCode;
+    safe fn py_gc_get_object_size_h => py_gc_get_object_size(obj_ptr: *mut c_void) -> c_int;
+    unsafe fn py_gc_get_object_type_name_h => py_gc_get_object_type_name(obj_ptr: *mut c_void, buffer: *mut c_char, buffer_size: usize) -> GCReturnCode;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+    #[repr(C)]
+    struct MockObject {
+        head: PyObject_HEAD,
+        type_object: PyTypeObject,
+    }
+
+    fn mock_object() -> *mut c_void {
+        let mut obj: Box<MockObject> = Box::new(unsafe { std::mem::zeroed() });
+        obj.head.ob_refcnt = 1;
+        obj.type_object.tp_name = c"mock".as_ptr();
+        let obj_ptr = Box::into_raw(obj);
+        unsafe {
+            (*obj_ptr).head.ob_type = &mut (*obj_ptr).type_object;
+        }
+        obj_ptr as *mut c_void
+    }
+
+    fn free_mock_object(obj_ptr: *mut c_void) {
+        unsafe {
+            let _ = Box::from_raw(obj_ptr as *mut MockObject);
+        }
+    }
+
+    #[test]
+    fn test_gc_init_cleanup() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_gc_enable_disable() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
 
         assert_eq!(py_gc_disable() as i32, GCReturnCode::Success as i32);
         assert_eq!(py_gc_is_enabled(), 0);
 
-        assert_eq!(py_gc_enable() as i32, GCReturnCode::Success as i32);
-        assert_eq!(py_gc_is_enabled(), 1);
+        let obj_ptr = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_is_tracked(obj_ptr), 1);
+        let result = unsafe { py_gc_set_refcount(obj_ptr, 0) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(
+            py_gc_collect_if_needed() as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_is_tracked(obj_ptr), 1);
+        assert_eq!(py_gc_collect() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_is_tracked(obj_ptr), 0);
+        free_mock_object(obj_ptr);
+
+        assert_eq!(py_gc_enable() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_is_enabled(), 1);
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_gc_reinit_resets_state() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        assert_eq!(
+            py_gc_set_threshold(0, 5) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_get_threshold(0), 700);
+        assert_eq!(py_gc_is_initialized(), 1);
+
+        let mut thresholds = [0; 3];
+        assert_eq!(
+            py_gc_set_thresholds(50, 5, -1) as i32,
+            GCReturnCode::Success as i32
+        );
+        let result = unsafe { py_gc_get_thresholds(thresholds.as_mut_ptr()) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(thresholds, [50, 5, 10]);
+        assert_eq!(
+            py_gc_set_thresholds(-1, 1, 1) as i32,
+            GCReturnCode::ErrorInvalidGeneration as i32
+        );
+        assert_eq!(py_gc_get_threshold(1), 5);
+        let result = unsafe { py_gc_get_thresholds(std::ptr::null_mut()) };
+        assert_eq!(result as i32, GCReturnCode::ErrorInternal as i32);
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_is_initialized(), 0);
+        assert_eq!(
+            py_gc_collect() as i32,
+            GCReturnCode::ErrorNotInitialized as i32
+        );
+    }
+
+    #[test]
+    fn test_gc_collection() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let result = py_gc_collect();
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_zero_threshold0_keeps_manual_collection() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        assert_eq!(
+            py_gc_set_thresholds(0, -1, -1) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let obj_ptr = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let result = unsafe { py_gc_set_refcount(obj_ptr, 0) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_needs_collection(), 0);
+        assert_eq!(py_gc_needs_collection_generation(), -1);
+        assert_eq!(
+            py_gc_collect_if_needed() as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_is_tracked(obj_ptr), 1);
+
+        assert_eq!(py_gc_collect() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_is_tracked(obj_ptr), 0);
+
+        free_mock_object(obj_ptr);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_set_and_get_thresholds() {
+        let _guard = TEST_LOCK.lock();
+        let mut thresholds = [-1 as c_int; 3];
+        assert_eq!(
+            py_gc_set_thresholds(100, 5, 5) as i32,
+            GCReturnCode::ErrorNotInitialized as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_get_thresholds(thresholds.as_mut_ptr()) } as i32,
+            GCReturnCode::ErrorNotInitialized as i32
+        );
+        assert_eq!(thresholds, [-1; 3]);
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        assert_eq!(
+            py_gc_set_thresholds(500, 20, 30) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_get_thresholds(thresholds.as_mut_ptr()) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(thresholds, [500, 20, 30]);
+
+        // Negative optional thresholds leave their generations alone.
+        assert_eq!(
+            py_gc_set_thresholds(600, -1, 40) as i32,
+            GCReturnCode::Success as i32
+        );
+        unsafe { py_gc_get_thresholds(thresholds.as_mut_ptr()) };
+        assert_eq!(thresholds, [600, 20, 40]);
+        assert_eq!(
+            py_gc_set_thresholds(700, 25, -1) as i32,
+            GCReturnCode::Success as i32
+        );
+        unsafe { py_gc_get_thresholds(thresholds.as_mut_ptr()) };
+        assert_eq!(thresholds, [700, 25, 40]);
+
+        // A refused call changes nothing.
+        assert_eq!(
+            py_gc_set_thresholds(-1, 1, 1) as i32,
+            GCReturnCode::ErrorInvalidGeneration as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_get_thresholds(std::ptr::null_mut()) } as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+        unsafe { py_gc_get_thresholds(thresholds.as_mut_ptr()) };
+        assert_eq!(thresholds, [700, 25, 40]);
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_track_reaches_collector() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj1_ptr = mock_object();
+        let obj2_ptr = mock_object();
+
+        assert_eq!(
+            unsafe { py_gc_track(obj1_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_track(obj2_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_get_count(), 2);
+        assert_eq!(py_gc_get_registry_count(), 2);
+
+        assert_eq!(py_gc_untrack(obj1_ptr) as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_get_count(), 1);
+
+        assert_eq!(py_gc_collect() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_get_count(), 1);
+
+        let result = unsafe { py_gc_set_refcount(obj2_ptr, 0) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_collect() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_get_count(), 0);
+        assert_eq!(py_gc_get_registry_count(), 0);
+        assert_eq!(py_gc_is_tracked(obj2_ptr), 0);
+
+        free_mock_object(obj1_ptr);
+        free_mock_object(obj2_ptr);
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_track_reads_type_name() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj_ptr = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let mut buffer = [0 as c_char; 32];
+        let result =
+            unsafe { py_gc_get_object_type_name(obj_ptr, buffer.as_mut_ptr(), buffer.len()) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        let type_name = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(type_name.to_str().unwrap(), "mock");
+
+        free_mock_object(obj_ptr);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_collect_ex_reports_count() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let objects: Vec<*mut c_void> = (0..3).map(|_| mock_object()).collect();
+
+        for obj_ptr in &objects {
+            assert_eq!(
+                unsafe { py_gc_track(*obj_ptr) } as i32,
+                GCReturnCode::Success as i32
+            );
+            let result = unsafe { py_gc_set_refcount(*obj_ptr, 0) };
+            assert_eq!(result as i32, GCReturnCode::Success as i32);
+        }
+
+        let mut collected = 0usize;
+        let result = unsafe { py_gc_collect_ex(&mut collected) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(collected, 3);
+
+        let result = unsafe { py_gc_collect_generation_ex(3, &mut collected) };
+        assert_eq!(result as i32, GCReturnCode::ErrorInvalidGeneration as i32);
+
+        for obj_ptr in objects {
+            free_mock_object(obj_ptr);
+        }
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_saveall_garbage_keeps_pointers() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        assert_eq!(
+            py_gc_set_debug_flags(DEBUG_SAVEALL as c_int) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let garbage = mock_object();
+        let live = mock_object();
+        for obj_ptr in [garbage, live] {
+            assert_eq!(
+                unsafe { py_gc_track(obj_ptr) } as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+        let result = unsafe { py_gc_set_refcount(garbage, 0) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+
+        assert_eq!(py_gc_collect() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_get_saveall_garbage_count(), 1);
+        assert_eq!(
+            SAVED_GARBAGE.with(|saved| saved.borrow().clone()),
+            [ObjectPtr(garbage)]
+        );
+        assert_eq!(py_gc_is_tracked(live), 1);
+
+        assert_eq!(
+            py_gc_clear_saveall_garbage() as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_get_saveall_garbage_count(), 0);
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+        free_mock_object(garbage);
+        free_mock_object(live);
+    }
+
+    #[test]
+    fn test_break_cycle_frees_recorded_cycle() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let a = mock_object();
+        let b = mock_object();
+        for obj_ptr in [a, b] {
+            assert_eq!(
+                unsafe { py_gc_track(obj_ptr) } as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+        assert_eq!(
+            py_gc_add_reference(a, b) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_add_reference(b, a) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_collect() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_is_tracked(a), 1);
+
+        let mut freed = 0usize;
+        let result = unsafe { py_gc_break_cycle(a, &mut freed) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(freed, 2);
+        assert_eq!((py_gc_is_tracked(a), py_gc_is_tracked(b)), (0, 0));
+        assert_eq!(py_gc_count_cycles(), 0);
+
+        let result = unsafe { py_gc_break_cycle(a, &mut freed) };
+        assert_eq!(result as i32, GCReturnCode::ErrorNotTracked as i32);
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+        free_mock_object(a);
+        free_mock_object(b);
+    }
+
+    #[test]
+    fn test_last_error_reporting() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+        py_gc_clear_last_error();
+        assert_eq!(py_gc_get_last_error_code(), GCReturnCode::Success as c_int);
+
+        assert_eq!(
+            py_gc_collect() as i32,
+            GCReturnCode::ErrorNotInitialized as i32
+        );
+        assert_eq!(
+            py_gc_get_last_error_code(),
+            GCReturnCode::ErrorNotInitialized as c_int
+        );
+
+        let mut buffer = [0 as c_char; 64];
+        let result = unsafe { py_gc_get_last_error(buffer.as_mut_ptr(), buffer.len()) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        let message = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(message.to_str().unwrap(), "GC not initialized");
+
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        assert_eq!(
+            py_gc_collect_generation(7) as i32,
+            GCReturnCode::ErrorInvalidGeneration as i32
+        );
+        let result = unsafe { py_gc_get_last_error(buffer.as_mut_ptr(), buffer.len()) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        let message = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+        assert_eq!(message.to_str().unwrap(), "Invalid generation: 7");
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_log_callback_receives_debug_stats() {
+        static MESSAGES: Mutex<Vec<(c_int, String)>> = Mutex::new(Vec::new());
+
+        extern "C" fn record(level: c_int, message: *const c_char) {
+            let message = unsafe { std::ffi::CStr::from_ptr(message) };
+            MESSAGES
+                .lock()
+                .push((level, message.to_string_lossy().into_owned()));
+        }
+
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        assert_eq!(
+            py_gc_set_log_callback(Some(record)) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_set_debug(DEBUG_STATS as c_int) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(py_gc_collect() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_debug_state() as i32, GCReturnCode::Success as i32);
+
+        let messages = std::mem::take(&mut *MESSAGES.lock());
+        assert!(messages.iter().any(|(level, message)| {
+            *level == GCLogLevel::Debug as c_int && message.starts_with("gc: done")
+        }));
+        assert!(messages.iter().any(|(level, message)| {
+            *level == GCLogLevel::Info as c_int && message == "GC Debug State:"
+        }));
+
+        py_gc_set_log_callback(None);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_python_hooks_pair_gil_calls() {
+        use std::sync::atomic::AtomicUsize;
+
+        static ENSURED: AtomicUsize = AtomicUsize::new(0);
+        static RELEASED: AtomicUsize = AtomicUsize::new(0);
+
+        extern "C" fn ensure() -> c_int {
+            ENSURED.fetch_add(1, Ordering::SeqCst);
+            7
+        }
+
+        extern "C" fn release(state: c_int) {
+            assert_eq!(state, 7);
+            RELEASED.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let _guard = TEST_LOCK.lock();
+        let partial = GCPythonHooks {
+            gil_ensure: Some(ensure),
+            gil_release: None,
+        };
+        let result = unsafe { py_gc_set_python_hooks(&partial) };
+        assert_eq!(result as i32, GCReturnCode::ErrorInternal as i32);
+
+        let hooks = GCPythonHooks {
+            gil_ensure: Some(ensure),
+            gil_release: Some(release),
+        };
+        let result = unsafe { py_gc_set_python_hooks(&hooks) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+
+        {
+            let _gil = GilGuard::acquire();
+            assert_eq!(ENSURED.load(Ordering::SeqCst), 1);
+            assert_eq!(RELEASED.load(Ordering::SeqCst), 0);
+        }
+        assert_eq!(RELEASED.load(Ordering::SeqCst), 1);
+
+        let result = unsafe { py_gc_set_python_hooks(std::ptr::null()) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        drop(GilGuard::acquire());
+        assert_eq!(ENSURED.load(Ordering::SeqCst), 1);
+    }
+
+    struct FakeListApi;
+
+    impl PythonApi for FakeListApi {
+        fn list_new(&self, size: isize) -> *mut c_void {
+            let list = vec![std::ptr::null_mut::<c_void>(); size as usize];
+            Box::into_raw(Box::new(list)) as *mut c_void
+        }
+
+        fn list_set_item(&self, list: *mut c_void, index: isize, item: *mut c_void) -> c_int {
+            let list = unsafe { &mut *(list as *mut Vec<*mut c_void>) };
+            list[index as usize] = item;
+            0
+        }
+
+        fn list_get_item(&self, list: *mut c_void, index: isize) -> *mut c_void {
+            let list = unsafe { &*(list as *mut Vec<*mut c_void>) };
+            list[index as usize]
+        }
+
+        fn list_size(&self, list: *mut c_void) -> isize {
+            let list = unsafe { &*(list as *mut Vec<*mut c_void>) };
+            list.len() as isize
+        }
+
+        fn incref(&self, _obj: *mut c_void) {}
+
+        fn decref(&self, _obj: *mut c_void) {}
+
+        fn gc_new(&self, _type_obj: *mut c_void) -> *mut c_void {
+            mock_object()
+        }
+
+        fn gc_del(&self, obj: *mut c_void) {
+            free_mock_object(obj);
+        }
+    }
+
+    fn take_fake_list(list: *mut c_void) -> Vec<*mut c_void> {
+        assert!(!list.is_null());
+        *unsafe { Box::from_raw(list as *mut Vec<*mut c_void>) }
+    }
+
+    #[test]
+    fn test_python_api_backend() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let from = 0x1000 as *mut c_void;
+        let to = 0x2000 as *mut c_void;
+        assert_eq!(
+            py_gc_add_reference(from, to) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert!(unsafe { py_gc_get_referents(from) }.is_null());
+
+        python_api::set_python_api(Arc::new(FakeListApi));
+        let referents = take_fake_list(unsafe { py_gc_get_referents(from) });
+        assert_eq!(referents, vec![to]);
+
+        let garbage = FakeListApi.list_new(2);
+        FakeListApi.list_set_item(garbage, 0, from);
+        FakeListApi.list_set_item(garbage, 1, to);
+        let result = unsafe { py_gc_set_garbage(garbage) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_is_uncollectable(to), 1);
+        take_fake_list(garbage);
+
+        let incomplete = GCPythonApi {
+            list_new: None,
+            list_set_item: None,
+            list_get_item: None,
+            list_size: None,
+            incref: None,
+            decref: None,
+            gc_new: None,
+            gc_del: None,
+        };
+        let result = unsafe { py_gc_set_python_api(&incomplete) };
+        assert_eq!(result as i32, GCReturnCode::ErrorInternal as i32);
+
+        // gc_new and gc_del are only needed by py_gc_object_new/_del.
+        extern "C" fn list_new(_size: isize) -> *mut c_void {
+            std::ptr::null_mut()
+        }
+        extern "C" fn list_set_item(
+            _list: *mut c_void,
+            _index: isize,
+            _item: *mut c_void,
+        ) -> c_int {
+            -1
+        }
+        extern "C" fn list_get_item(_list: *mut c_void, _index: isize) -> *mut c_void {
+            std::ptr::null_mut()
+        }
+        extern "C" fn list_size(_list: *mut c_void) -> isize {
+            -1
+        }
+        extern "C" fn refcount_op(_obj: *mut c_void) {}
+        let without_gc_alloc = GCPythonApi {
+            list_new: Some(list_new),
+            list_set_item: Some(list_set_item),
+            list_get_item: Some(list_get_item),
+            list_size: Some(list_size),
+            incref: Some(refcount_op),
+            decref: Some(refcount_op),
+            gc_new: None,
+            gc_del: None,
+        };
+        let result = unsafe { py_gc_set_python_api(&without_gc_alloc) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert!(unsafe { py_gc_object_new(std::ptr::null_mut()) }.is_null());
+
+        let result = unsafe { py_gc_set_python_api(std::ptr::null()) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_allocation_reporting_tracks_allocations() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        python_api::set_python_api(Arc::new(FakeListApi));
+
+        assert_eq!(
+            py_gc_enable_allocation_reporting() as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_enable_allocation_reporting() as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_allocation_reporting_enabled(), 1);
+        assert_eq!(
+            py_gc_enable_automatic_tracking() as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let obj_ptr = unsafe { py_gc_object_new(std::ptr::null_mut()) };
+        assert_eq!(py_gc_is_tracked(obj_ptr), 1);
+        unsafe { py_gc_object_del(obj_ptr) };
+        assert_eq!(py_gc_get_count(), 0);
+
+        let mut stats: GCStatsExtended = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<GCStatsExtended>();
+        let result = unsafe { py_gc_get_stats_extended(&mut stats, size) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(stats.reported_allocations, 1);
+        assert_eq!(stats.reported_frees, 1);
+
+        assert_eq!(
+            py_gc_disable_allocation_reporting() as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_allocation_reporting_enabled(), 0);
+        let obj_ptr = unsafe { py_gc_object_new(std::ptr::null_mut()) };
+        assert_eq!(py_gc_is_tracked(obj_ptr), 0);
+        unsafe { py_gc_object_del(obj_ptr) };
+
+        python_api::clear_python_api();
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_find_reference_cycles() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let a = 0x1000 as *mut c_void;
+        let b = 0x2000 as *mut c_void;
+        let c = 0x3000 as *mut c_void;
+        let d = 0x4000 as *mut c_void;
+        for (from, to) in [(a, b), (b, a), (b, c), (d, d)] {
+            assert_eq!(
+                py_gc_add_reference(from, to) as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+        assert_eq!(py_gc_count_cycles(), 2);
+
+        python_api::set_python_api(Arc::new(FakeListApi));
+        let mut cycles: Vec<Vec<*mut c_void>> = take_fake_list(unsafe { py_gc_find_cycles() })
+            .into_iter()
+            .map(|cycle| {
+                let mut cycle = take_fake_list(cycle);
+                cycle.sort();
+                cycle
+            })
+            .collect();
+        cycles.sort();
+        assert_eq!(cycles, vec![vec![a, b], vec![d]]);
+
+        assert_eq!(
+            py_gc_remove_reference(b, a) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_count_cycles(), 1);
+
+        assert_eq!(
+            py_gc_add_reference_typed(c, b, 1) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_get_reference_type(c, b), 1);
+        assert_eq!(py_gc_get_reference_type(a, b), 0);
+        assert_eq!(py_gc_get_reference_type(b, a), -1);
+        assert_eq!(py_gc_count_cycles(), 1);
+        assert_eq!(
+            py_gc_add_reference_typed(b, a, 2) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_count_cycles(), 2);
+        assert_eq!(
+            py_gc_add_reference_typed(a, b, 7) as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+
+        python_api::clear_python_api();
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_weak_edge_does_not_close_a_cycle() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        python_api::set_python_api(Arc::new(FakeListApi));
+        let find_cycles = || -> Vec<Vec<*mut c_void>> {
+            take_fake_list(unsafe { py_gc_find_cycles() })
+                .into_iter()
+                .map(|cycle| {
+                    let mut cycle = take_fake_list(cycle);
+                    cycle.sort();
+                    cycle
+                })
+                .collect()
+        };
+
+        let a = 0x1000 as *mut c_void;
+        let b = 0x2000 as *mut c_void;
+        assert_eq!(
+            py_gc_add_reference_typed(a, b, 0) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_add_reference_typed(b, a, 1) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert!(find_cycles().is_empty());
+
+        assert_eq!(
+            py_gc_add_reference_typed(b, a, 0) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_get_reference_type(b, a), 0);
+        assert_eq!(find_cycles(), vec![vec![a, b]]);
+
+        python_api::clear_python_api();
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_get_objects_by_type() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        python_api::set_python_api(Arc::new(FakeListApi));
+
+        let mock_ptr = mock_object();
+        let other_ptr = mock_object();
+        unsafe {
+            (*(other_ptr as *mut MockObject)).type_object.tp_name = c"other".as_ptr();
+        }
+        assert_eq!(
+            unsafe { py_gc_track(mock_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_track(other_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let objects = take_fake_list(unsafe { py_gc_get_objects_by_type(c"other".as_ptr()) });
+        assert_eq!(objects, vec![other_ptr]);
+        assert!(unsafe { py_gc_get_objects_by_type(c"missing".as_ptr()) }.is_null());
+        assert!(unsafe { py_gc_get_objects_by_type(std::ptr::null()) }.is_null());
+
+        free_mock_object(mock_ptr);
+        free_mock_object(other_ptr);
+        python_api::clear_python_api();
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_buffer_size_query() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj_ptr = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let result = unsafe { py_gc_get_object_type_name(obj_ptr, std::ptr::null_mut(), 0) };
+        assert_eq!(result as i32, GCReturnCode::ErrorBufferTooSmall as i32);
+        assert_eq!(py_gc_get_required_buffer_size(), "mock".len() + 1);
+
+        let mut small = [0 as c_char; 3];
+        let result =
+            unsafe { py_gc_get_object_type_name(obj_ptr, small.as_mut_ptr(), small.len()) };
+        assert_eq!(result as i32, GCReturnCode::ErrorBufferTooSmall as i32);
+        let truncated = unsafe { std::ffi::CStr::from_ptr(small.as_ptr()) };
+        assert_eq!(truncated.to_str().unwrap(), "mo");
+
+        let result = unsafe { py_gc_get_state_string(std::ptr::null_mut(), 0) };
+        assert_eq!(result as i32, GCReturnCode::ErrorBufferTooSmall as i32);
+        let mut buffer = vec![0 as c_char; py_gc_get_required_buffer_size()];
+        let result = unsafe { py_gc_get_state_string(buffer.as_mut_ptr(), buffer.len()) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+
+        let result = unsafe { py_gc_get_last_error(std::ptr::null_mut(), 0) };
+        assert_eq!(result as i32, GCReturnCode::ErrorBufferTooSmall as i32);
+        assert_eq!(
+            py_gc_get_last_error_code(),
+            GCReturnCode::ErrorBufferTooSmall as c_int
+        );
+
+        free_mock_object(obj_ptr);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_iterate_objects() {
+        extern "C" fn collect(obj_ptr: *mut c_void, user_data: *mut c_void) -> c_int {
+            let visited = unsafe { &mut *(user_data as *mut Vec<*mut c_void>) };
+            visited.push(obj_ptr);
+            (visited.len() >= 2) as c_int
+        }
+
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let objects: Vec<*mut c_void> = (0..3).map(|_| mock_object()).collect();
+        for obj_ptr in &objects {
+            assert_eq!(
+                unsafe { py_gc_track(*obj_ptr) } as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+
+        let mut visited: Vec<*mut c_void> = Vec::new();
+        let user_data = &mut visited as *mut Vec<*mut c_void> as *mut c_void;
+        let result = py_gc_iterate_objects(Some(collect), user_data);
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(visited.len(), 2);
+        assert!(visited.iter().all(|obj_ptr| objects.contains(obj_ptr)));
+
+        assert_eq!(
+            py_gc_add_reference(objects[0], objects[1]) as i32,
+            GCReturnCode::Success as i32
+        );
+        visited.clear();
+        let user_data = &mut visited as *mut Vec<*mut c_void> as *mut c_void;
+        let result = py_gc_iterate_referents(objects[0], Some(collect), user_data);
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        let result = py_gc_iterate_referrers(objects[1], Some(collect), user_data);
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(visited, vec![objects[1], objects[0]]);
+
+        let result = py_gc_iterate_objects(None, std::ptr::null_mut());
+        assert_eq!(result as i32, GCReturnCode::ErrorInternal as i32);
+
+        for obj_ptr in objects {
+            free_mock_object(obj_ptr);
+        }
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_after_fork_child_preserves_objects() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj_ptr = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        std::mem::forget(DEFAULT_INSTANCE.gc.read());
+        std::mem::forget(LOG_CALLBACK.write());
+        assert_eq!(
+            unsafe { py_gc_after_fork_child() } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(py_gc_is_tracked(obj_ptr), 1);
+        assert_eq!(py_gc_get_count(), 1);
+        assert_eq!(
+            py_gc_set_log_callback(None) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        free_mock_object(obj_ptr);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_batch_operations() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let objects: Vec<*mut c_void> = (0..4).map(|_| mock_object()).collect();
+
+        let mut tracked = 0usize;
+        let result = unsafe { py_gc_track_batch(objects.as_ptr(), objects.len(), &mut tracked) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(tracked, 4);
+        assert_eq!(py_gc_get_count(), 4);
+        assert_eq!(py_gc_get_registry_count(), 4);
+
+        let result = unsafe { py_gc_track_batch(objects.as_ptr(), 2, &mut tracked) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(tracked, 0);
+
+        let from = [objects[0], objects[1]];
+        let to = [objects[1], objects[2]];
+        let result = unsafe { py_gc_add_references_batch(from.as_ptr(), to.as_ptr(), 2) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(get_references(objects[0]), vec![objects[1]]);
+        assert_eq!(get_referrers(objects[2]), vec![objects[1]]);
+
+        let mut untracked = 0usize;
+        let result = unsafe { py_gc_untrack_batch(objects.as_ptr(), 3, &mut untracked) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(untracked, 3);
+        assert_eq!(py_gc_get_count(), 1);
+        assert_eq!(py_gc_is_tracked(objects[3]), 1);
+
+        for obj_ptr in objects {
+            free_mock_object(obj_ptr);
+        }
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_stats_extended() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj_ptr = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let result = unsafe { py_gc_set_refcount(obj_ptr, 0) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(
+            py_gc_collect_generation(1) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let mut stats: GCStatsExtended = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<GCStatsExtended>();
+        let result = unsafe { py_gc_get_stats_extended(&mut stats, size) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(stats.size, size);
+        assert_eq!(stats.collections, [0, 1, 0]);
+        assert_eq!(stats.total_collected, 1);
+        assert_eq!(stats.collected, [0, 1, 0]);
+        assert_eq!(stats.thresholds, [700, 10, 10]);
+        assert_eq!(stats.long_lived_total, 0);
+        assert_eq!(stats.long_lived_pending, 0);
+        assert!(stats.stats_epoch > 0);
+        assert_eq!(stats.collection_in_progress, 0);
+
+        let mut prefix: GCStatsExtended = unsafe { std::mem::zeroed() };
+        let prefix_size = 2 * std::mem::size_of::<usize>();
+        let result = unsafe { py_gc_get_stats_extended(&mut prefix, prefix_size) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(prefix.size, prefix_size);
+        assert_eq!(prefix.generation_counts, [0, 0, 0]);
+        assert_eq!(prefix.thresholds, [0, 0, 0]);
+
+        let result = unsafe { py_gc_get_stats_extended(&mut prefix, 0) };
+        assert_eq!(result as i32, GCReturnCode::ErrorInternal as i32);
+
+        free_mock_object(obj_ptr);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_finalizer_behavior() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj1_ptr = mock_object();
+
+        assert_eq!(
+            unsafe { py_gc_track(obj1_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(py_gc_has_finalizer(obj1_ptr), 0);
+
+        assert_eq!(
+            py_gc_set_finalizer(obj1_ptr, 1) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(py_gc_has_finalizer(obj1_ptr), 1);
+
+        unsafe extern "C" fn finalize(_: *mut c_void) {}
+
+        let obj2_ptr = mock_object();
+        unsafe {
+            (*(obj2_ptr as *mut MockObject)).type_object.tp_finalize = Some(finalize);
+        }
+
+        assert_eq!(
+            unsafe { py_gc_track(obj2_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(py_gc_has_finalizer(obj2_ptr), 1);
+
+        free_mock_object(obj1_ptr);
+        free_mock_object(obj2_ptr);
+
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_verify() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(
+            unsafe { py_gc_verify(std::ptr::null_mut()) } as i32,
+            GCReturnCode::ErrorNotInitialized as i32
+        );
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj_ptr = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj_ptr) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let mut violations = usize::MAX;
+        assert_eq!(
+            unsafe { py_gc_verify(&mut violations) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(violations, 0);
+
+        free_mock_object(obj_ptr);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_audit_refcounts() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let mut discrepancies = usize::MAX;
+        unsafe {
+            assert_eq!(
+                py_gc_audit_refcounts(&mut discrepancies) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!(discrepancies, 1);
+
+            assert_eq!(
+                py_gc_add_external_ref(obj) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!(
+                py_gc_audit_refcounts(&mut discrepancies) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!(discrepancies, 0);
+
+            assert_eq!(
+                py_gc_release_external_ref(obj) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!(
+                py_gc_release_external_ref(obj) as i32,
+                GCReturnCode::ErrorInternal as i32
+            );
+        }
+        let untracked = mock_object();
+        assert_eq!(
+            py_gc_add_external_ref(untracked) as i32,
+            GCReturnCode::ErrorNotTracked as i32
+        );
+
+        free_mock_object(obj);
+        free_mock_object(untracked);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_reference_history() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let holder = mock_object();
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(holder) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_set_reference_history(4, 16) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_add_reference(holder, obj) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_add_reference(holder, obj) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_remove_reference(holder, obj) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let mut buffer = vec![0 as c_char; 256];
+        assert_eq!(
+            unsafe { py_gc_get_reference_history(obj, 10, buffer.as_mut_ptr(), buffer.len()) }
+                as i32,
+            GCReturnCode::Success as i32
+        );
+        let text = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_string_lossy();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(&format!("added from {holder:p}")));
+        assert!(lines[1].ends_with(&format!("removed from {holder:p}")));
+
+        assert_eq!(
+            py_gc_set_reference_history(0, 0) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_get_reference_history(obj, 10, buffer.as_mut_ptr(), buffer.len()) }
+                as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(buffer[0], 0);
+
+        free_mock_object(holder);
+        free_mock_object(obj);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_watch_object() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        extern "C" fn record(
+            obj_ptr: *mut c_void,
+            event: c_int,
+            refcount: usize,
+            user_data: *mut c_void,
+        ) {
+            let events = unsafe { &mut *(user_data as *mut Vec<(*mut c_void, c_int, usize)>) };
+            events.push((obj_ptr, event, refcount));
+        }
+
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let mut events: Vec<(*mut c_void, c_int, usize)> = Vec::new();
+        let user_data = &mut events as *mut Vec<(*mut c_void, c_int, usize)> as *mut c_void;
+        let mut watch_id = u64::MAX;
+        unsafe {
+            assert_eq!(
+                py_gc_watch_object(obj, 0, 2, Some(record), user_data, &mut watch_id) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!(
+                py_gc_watch_object(obj, 7, 0, Some(record), user_data, std::ptr::null_mut()) as i32,
+                GCReturnCode::ErrorInternal as i32
+            );
+            assert_eq!(
+                py_gc_set_refcount(obj, 2) as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+        assert_eq!(
+            py_gc_enable_automatic_tracking() as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_refcount_changed(obj, 2, 1) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(events, vec![(obj, 0, 2), (obj, 0, 1)]);
+
+        assert_eq!(
+            py_gc_unwatch_object(watch_id) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_unwatch_object(watch_id) as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+
+        free_mock_object(obj);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_pre_collect_callback() {
+        extern "C" fn count_and_veto(
+            generation: c_int,
+            stats: *const GCStatsExtended,
+            user_data: *mut c_void,
+        ) -> c_int {
+            let calls = unsafe { &*(user_data as *const AtomicUsize) };
+            assert_eq!(unsafe { (*stats).total_tracked }, 1);
+            calls.fetch_add(1, Ordering::Relaxed);
+            (generation == 0) as c_int
+        }
+
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let calls = AtomicUsize::new(0);
+        let mut hook_id = 0;
+        unsafe {
+            assert_eq!(
+                py_gc_add_pre_collect_callback(
+                    Some(count_and_veto),
+                    &calls as *const AtomicUsize as *mut c_void,
+                    &mut hook_id,
+                ) as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+        py_gc_collect_generation(0);
+        py_gc_collect_generation(1);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        let collections = |generation| {
+            let mut stats = GCGenerationStats::default();
+            unsafe { py_gc_get_generation_stats(generation, &mut stats) };
+            stats.collections
+        };
+        assert_eq!((collections(0), collections(1)), (0, 1));
+
+        assert_eq!(
+            py_gc_remove_pre_collect_callback(hook_id) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_remove_pre_collect_callback(hook_id) as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+        py_gc_collect_generation(0);
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
 
+        free_mock_object(obj);
         assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
     }
 
     #[test]
-    fn test_gc_collection() {
+    fn test_generation_stats() {
+        let _guard = TEST_LOCK.lock();
         assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
 
-        let result = py_gc_collect();
-        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_collect_generation(0) as i32,
+            GCReturnCode::Success as i32
+        );
+        let mut stats = GCGenerationStats::default();
+        unsafe {
+            assert_eq!(
+                py_gc_get_generation_stats(0, &mut stats) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!(
+                (
+                    stats.count,
+                    stats.collections,
+                    stats.scanned,
+                    stats.promoted
+                ),
+                (0, 1, 1, 1)
+            );
+            assert_eq!(stats.survivor_rate, 1.0);
+
+            py_gc_get_generation_stats(1, &mut stats);
+            assert_eq!((stats.count, stats.collections), (1, 0));
+            assert!(stats.bytes > 0);
+            assert_eq!(
+                py_gc_get_generation_stats(3, &mut stats) as i32,
+                GCReturnCode::ErrorInvalidGeneration as i32
+            );
+        }
 
+        free_mock_object(obj);
         assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
     }
 
     #[test]
-    fn test_finalizer_behavior() {
+    fn test_tag_stats() {
+        let _guard = TEST_LOCK.lock();
         assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
 
-        let obj1 = PyObject::new("regular_obj".to_string(), ObjectData::Integer(42));
-        let obj1_ptr = Box::into_raw(Box::new(obj1)) as *mut c_void;
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let mut stats = GCTagStats::default();
+        unsafe {
+            assert_eq!(
+                py_gc_set_tag(obj, c"session".as_ptr()) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!(
+                py_gc_get_tag_stats(c"session".as_ptr(), &mut stats) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!((stats.objects, stats.survivors), (1, 0));
+            assert!(stats.bytes > 0);
+
+            assert_eq!(
+                py_gc_set_tag(obj, std::ptr::null()) as i32,
+                GCReturnCode::Success as i32
+            );
+            py_gc_get_tag_stats(c"session".as_ptr(), &mut stats);
+            assert_eq!(stats.objects, 0);
+            let untracked = mock_object();
+            assert_eq!(
+                py_gc_set_tag(untracked, c"session".as_ptr()) as i32,
+                GCReturnCode::ErrorNotTracked as i32
+            );
+            free_mock_object(untracked);
+        }
 
-        assert_eq!(py_gc_track(obj1_ptr) as i32, GCReturnCode::Success as i32);
+        free_mock_object(obj);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
 
-        assert_eq!(py_gc_has_finalizer(obj1_ptr), 0);
+    #[test]
+    fn test_freeze_and_unfreeze() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
 
+        let obj = mock_object();
         assert_eq!(
-            py_gc_set_finalizer(obj1_ptr, 1) as i32,
+            unsafe { py_gc_track(obj) } as i32,
             GCReturnCode::Success as i32
         );
+        assert_eq!(py_gc_freeze() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_get_freeze_count(), 1);
+        assert_eq!(py_gc_get_object_generation(obj), -1);
+        let mut stats: GCStatsExtended = unsafe { std::mem::zeroed() };
+        let size = std::mem::size_of::<GCStatsExtended>();
+        unsafe {
+            assert_eq!(
+                py_gc_get_stats_extended(&mut stats, size) as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+        assert_eq!(stats.permanent_count, 1);
 
-        assert_eq!(py_gc_has_finalizer(obj1_ptr), 1);
+        assert_eq!(py_gc_unfreeze() as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_get_freeze_count(), 0);
+        assert_eq!(py_gc_get_object_generation(obj), 2);
+
+        free_mock_object(obj);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_named_references_label_the_retaining_path() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let (module, cache, entry) = (mock_object(), mock_object(), mock_object());
+        for obj in [module, cache, entry] {
+            assert_eq!(
+                unsafe { py_gc_track(obj) } as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+        unsafe {
+            assert_eq!(
+                py_gc_add_reference_named(module, cache, c"_cache".as_ptr()) as i32,
+                GCReturnCode::Success as i32
+            );
+            py_gc_add_reference_named(cache, entry, c"['key']".as_ptr());
+        }
+        let explain = || {
+            let mut buffer = vec![0 as c_char; 1024];
+            unsafe { py_gc_explain_object(entry, buffer.as_mut_ptr(), buffer.len()) };
+            unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }
+                .to_string_lossy()
+                .into_owned()
+        };
+        assert!(explain().contains(&format!(
+            "Retaining path: {module:p} -[_cache]-> {cache:p} -[['key']]-> {entry:p}"
+        )));
+
+        // Removing the reference forgets its name; a null name adds none.
+        py_gc_remove_reference(module, cache);
+        py_gc_add_reference(module, cache);
+        unsafe { py_gc_add_reference_named(cache, entry, std::ptr::null()) };
+        assert!(explain().contains(&format!(
+            "Retaining path: {module:p} -> {cache:p} -> {entry:p}"
+        )));
+
+        for obj in [module, cache, entry] {
+            free_mock_object(obj);
+        }
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_explain_object() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let holder = mock_object();
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(holder) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            py_gc_add_reference(holder, obj) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(
+            unsafe { py_gc_explain_object(obj, std::ptr::null_mut(), 0) } as i32,
+            GCReturnCode::ErrorBufferTooSmall as i32
+        );
+        let mut buffer = vec![0 as c_char; py_gc_get_required_buffer_size()];
+        assert_eq!(
+            unsafe { py_gc_explain_object(obj, buffer.as_mut_ptr(), buffer.len()) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let text = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) }.to_string_lossy();
+        assert!(text.contains("(mock, ID: "));
+        assert!(text.contains("Status: generation 0"));
+        assert_eq!(py_gc_get_object_generation(obj), 0);
+        assert!(text.contains(&format!("Retaining path: {holder:p} -> {obj:p}")));
 
-        let obj2 = PyObject::new_with_finalizer(
-            "finalizer_obj".to_string(),
-            ObjectData::String("test".to_string()),
+        let untracked = mock_object();
+        assert_eq!(
+            unsafe { py_gc_explain_object(untracked, buffer.as_mut_ptr(), buffer.len()) } as i32,
+            GCReturnCode::ErrorNotTracked as i32
         );
-        let obj2_ptr = Box::into_raw(Box::new(obj2)) as *mut c_void;
+        assert_eq!(py_gc_get_object_generation(untracked), -1);
+
+        free_mock_object(holder);
+        free_mock_object(obj);
+        free_mock_object(untracked);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
 
-        assert_eq!(py_gc_track(obj2_ptr) as i32, GCReturnCode::Success as i32);
+    #[test]
+    #[cfg(feature = "snapshot")]
+    fn test_dump_heap() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
 
-        assert_eq!(py_gc_has_finalizer(obj2_ptr), 1);
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let path = std::env::temp_dir().join(format!("python_gc_dump_{}.pygc", std::process::id()));
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        assert_eq!(
+            unsafe { py_gc_dump_heap(c_path.as_ptr()) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let snapshot = crate::snapshot::HeapSnapshot::load(&path).unwrap();
+        assert_eq!(snapshot.objects.len(), 1);
+        assert_eq!(snapshot.objects[0].type_name, "mock");
+        std::fs::remove_file(&path).unwrap();
+
+        free_mock_object(obj);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_refcount_changed_rejects_underflow() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        assert_eq!(
+            py_gc_enable_automatic_tracking() as i32,
+            GCReturnCode::Success as i32
+        );
+
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(
+            py_gc_refcount_changed(obj, 1, 3) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_get_refcount(obj), 3);
+
+        assert_eq!(
+            py_gc_refcount_changed(obj, 3, -2) as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+        // The rejected change is not applied in part.
+        assert_eq!(py_gc_get_refcount(obj), 3);
+        let mut buffer = [0 as c_char; 128];
+        unsafe { py_gc_get_last_error(buffer.as_mut_ptr(), buffer.len()) };
+        let message = unsafe { std::ffi::CStr::from_ptr(buffer.as_ptr()) };
+        assert!(
+            message
+                .to_string_lossy()
+                .starts_with("Reference count error on object ")
+        );
+
+        assert_eq!(
+            py_gc_disable_automatic_tracking() as i32,
+            GCReturnCode::Success as i32
+        );
+        free_mock_object(obj);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_abi_version_selects_layout() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(TypeLayout::cpython(13, 0), TypeLayout::BUILTIN);
+        assert_eq!(TypeLayout::cpython(3, 0).tp_finalize, None);
 
+        unsafe extern "C" fn traverse(_: *mut c_void, _: *mut c_void, _: *mut c_void) -> c_int {
+            0
+        }
+
+        let obj = mock_object();
         unsafe {
-            let _ = Box::from_raw(obj1_ptr as *mut PyObject);
-            let _ = Box::from_raw(obj2_ptr as *mut PyObject);
+            let type_object = &mut (*(obj as *mut MockObject)).type_object;
+            type_object.tp_flags = PY_TPFLAGS_HAVE_GC;
+            type_object.tp_traverse = Some(traverse);
+        }
+        let mut info = GCTypeInfo::default();
+        assert_eq!(
+            unsafe { py_gc_get_type_info(obj, &mut info) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            info,
+            GCTypeInfo {
+                has_gc: 1,
+                has_traverse: 1,
+                has_clear: 0,
+                has_finalize: 0,
+            }
+        );
+        free_mock_object(obj);
+
+        // A Py_TRACE_REFS build puts two list pointers ahead of every header.
+        assert_eq!(
+            py_gc_set_abi_version(3, 12, GC_ABI_TRACE_REFS) as i32,
+            GCReturnCode::Success as i32
+        );
+        let mut traced_type = [0usize; 64];
+        traced_type[2 + 3] = c"traced".as_ptr() as usize;
+        traced_type[2 + 21] = PY_TPFLAGS_HAVE_GC as usize;
+        let mut traced = [0, 0, 7, traced_type.as_ptr() as usize];
+        let traced_ptr = traced.as_mut_ptr() as *mut c_void;
+        assert_eq!(py_gc_get_refcount(traced_ptr), 7);
+        assert_eq!(read_type_name(traced_ptr), "traced");
+        assert_eq!(
+            unsafe { py_gc_get_type_info(traced_ptr, &mut info) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(info.has_gc, 1);
+
+        // 3.13t: thread id and flag bytes, then a split refcount.
+        #[repr(C)]
+        struct FtObject {
+            ob_tid: usize,
+            ob_flags: u16,
+            ob_mutex: u8,
+            ob_gc_bits: u8,
+            ob_ref_local: u32,
+            ob_ref_shared: isize,
+            ob_type: *const c_void,
+        }
+        #[repr(C)]
+        struct FtType {
+            head: FtObject,
+            ob_size: isize,
+            tp_name: *const c_char,
+        }
+        let ft_type = FtType {
+            head: unsafe { std::mem::zeroed() },
+            ob_size: 0,
+            tp_name: c"threaded".as_ptr(),
+        };
+        let mut ft_object = FtObject {
+            ob_ref_local: 3,
+            ob_ref_shared: 2 << PY_REF_SHARED_SHIFT,
+            ob_type: &ft_type as *const FtType as *const c_void,
+            ..unsafe { std::mem::zeroed() }
+        };
+        assert_eq!(
+            py_gc_set_abi_version(3, 12, GC_ABI_FREE_THREADED) as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+        assert_eq!(
+            py_gc_set_abi_version(3, 13, GC_ABI_FREE_THREADED) as i32,
+            GCReturnCode::Success as i32
+        );
+        let ft_ptr = &mut ft_object as *mut FtObject as *mut c_void;
+        assert_eq!(py_gc_get_refcount(ft_ptr), 5);
+        assert_eq!(read_type_name(ft_ptr), "threaded");
+
+        assert_eq!(
+            py_gc_set_abi_version(2, 7, 0) as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+        assert_eq!(
+            py_gc_set_abi_version(3, 13, 0) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(*TYPE_LAYOUT.read(), TypeLayout::BUILTIN);
+    }
+
+    #[test]
+    fn test_abi3_mode_uses_registered_functions() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        // Deliberately nothing like PyObject, so any struct read would fail.
+        #[repr(C)]
+        struct FakeObject {
+            gc_tracked: c_int,
+            refcnt: isize,
+        }
+        static FAKE_TYPE: u8 = 0;
+
+        extern "C" fn object_type(_: *mut c_void) -> *mut c_void {
+            &FAKE_TYPE as *const u8 as *mut c_void
+        }
+        extern "C" fn type_name(_: *mut c_void, buffer: *mut c_char, size: usize) -> isize {
+            unsafe { write_c_string("fake", buffer, size) };
+            4
+        }
+        extern "C" fn type_flags(_: *mut c_void) -> c_ulong {
+            PY_TPFLAGS_HAVE_GC
+        }
+        extern "C" fn type_get_slot(_: *mut c_void, slot: c_int) -> *mut c_void {
+            if slot == PY_TP_TRAVERSE {
+                type_get_slot as *mut c_void
+            } else {
+                std::ptr::null_mut()
+            }
+        }
+        extern "C" fn gc_is_tracked(obj: *mut c_void) -> c_int {
+            unsafe { (*(obj as *mut FakeObject)).gc_tracked }
+        }
+        extern "C" fn refcnt(obj: *mut c_void) -> isize {
+            unsafe { (*(obj as *mut FakeObject)).refcnt }
+        }
+
+        let incomplete = GCAbi3Api {
+            object_type: Some(object_type),
+            type_name: None,
+            type_flags: Some(type_flags),
+            type_get_slot: Some(type_get_slot),
+            gc_is_tracked: Some(gc_is_tracked),
+            refcnt: Some(refcnt),
+        };
+        let result = unsafe { py_gc_set_abi3_api(&incomplete) };
+        assert_eq!(result as i32, GCReturnCode::ErrorInternal as i32);
+        assert_eq!(py_gc_is_abi3_mode(), 0);
+
+        let api = GCAbi3Api {
+            type_name: Some(type_name),
+            ..incomplete
+        };
+        let result = unsafe { py_gc_set_abi3_api(&api) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_is_abi3_mode(), 1);
+
+        let mut fake = FakeObject {
+            gc_tracked: 1,
+            refcnt: 5,
+        };
+        let obj = &mut fake as *mut FakeObject as *mut c_void;
+        assert_eq!(py_gc_get_refcount(obj), 5);
+        assert_eq!(read_type_name(obj), "fake");
+
+        let mut info = GCTypeInfo::default();
+        unsafe { py_gc_get_type_info(obj, &mut info) };
+        assert_eq!(
+            info,
+            GCTypeInfo {
+                has_gc: 1,
+                has_traverse: 1,
+                has_clear: 0,
+                has_finalize: 0,
+            }
+        );
+
+        assert_eq!(py_gc_track_python(obj) as i32, GCReturnCode::Success as i32);
+        assert_eq!(py_gc_is_tracked_python(obj), 1);
+        unsafe { (*(obj as *mut FakeObject)).gc_tracked = 0 };
+        assert_eq!(py_gc_is_tracked_python(obj), 0);
+        assert_eq!(
+            py_gc_untrack_python(obj) as i32,
+            GCReturnCode::Success as i32
+        );
+
+        assert_eq!(
+            unsafe { py_gc_set_abi3_api(std::ptr::null()) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_is_abi3_mode(), 0);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_stop_the_world_holds_off_collection() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        static STOPS: AtomicUsize = AtomicUsize::new(0);
+        static STARTS: AtomicUsize = AtomicUsize::new(0);
+        extern "C" fn on_stop() {
+            STOPS.fetch_add(1, Ordering::SeqCst);
         }
+        extern "C" fn on_start() {
+            STARTS.fetch_add(1, Ordering::SeqCst);
+        }
+        let hooks = GCThreadHooks {
+            stop_the_world: Some(on_stop),
+            start_the_world: Some(on_start),
+        };
+        unsafe { py_gc_set_thread_hooks(&hooks) };
+
+        assert_eq!(
+            py_gc_resume_the_world() as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+
+        assert_eq!(py_gc_stop_the_world() as i32, GCReturnCode::Success as i32);
+        // The stopping thread can still collect.
+        assert_eq!(py_gc_collect() as i32, GCReturnCode::Success as i32);
+        assert_eq!(STOPS.load(Ordering::SeqCst), 1);
+
+        let collected = Arc::new(AtomicBool::new(false));
+        let other = std::thread::spawn({
+            let collected = collected.clone();
+            move || {
+                py_gc_collect();
+                collected.store(true, Ordering::SeqCst);
+            }
+        });
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        assert!(!collected.load(Ordering::SeqCst));
+
+        assert_eq!(
+            py_gc_resume_the_world() as i32,
+            GCReturnCode::Success as i32
+        );
+        other.join().unwrap();
+        assert!(collected.load(Ordering::SeqCst));
+        assert_eq!(STOPS.load(Ordering::SeqCst), 2);
+        assert_eq!(STARTS.load(Ordering::SeqCst), 2);
+
+        unsafe { py_gc_set_thread_hooks(std::ptr::null()) };
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[cfg(feature = "ft")]
+    #[test]
+    fn test_registry_is_shared_between_threads() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let obj = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track(obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let obj_addr = obj as usize;
+        let untracked = std::thread::spawn(move || {
+            let obj = obj_addr as *mut c_void;
+            (py_gc_is_tracked(obj), py_gc_untrack(obj) as i32)
+        })
+        .join()
+        .unwrap();
+        assert_eq!(untracked, (1, GCReturnCode::Success as i32));
+        assert_eq!(py_gc_is_tracked(obj), 0);
+
+        free_mock_object(obj);
+        assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
+    }
+
+    #[test]
+    fn test_instances_keep_separate_heaps() {
+        let _guard = TEST_LOCK.lock();
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+
+        let first = py_gc_create_instance();
+        let second = py_gc_create_instance();
+        assert_ne!(first, second);
+        assert_eq!(py_gc_is_initialized_h(first), 1);
+
+        let obj = mock_object();
+        let other = mock_object();
+        assert_eq!(
+            unsafe { py_gc_track_h(first, obj) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_track_h(second, other) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(py_gc_is_tracked_h(first, obj), 1);
+        assert_eq!(py_gc_is_tracked_h(second, obj), 0);
+        assert_eq!(py_gc_is_tracked(obj), 0);
+        assert_eq!(py_gc_get_registry_count_h(first), 1);
+        assert_eq!(py_gc_get_registry_count(), 0);
+
+        assert_eq!(
+            unsafe { py_gc_set_refcount_h(first, obj, 0) } as i32,
+            GCReturnCode::Success as i32
+        );
+        let mut collected = 0;
+        assert_eq!(
+            unsafe { py_gc_collect_ex_h(first, &mut collected) } as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(collected, 1);
+        assert_eq!(py_gc_is_tracked_h(first, obj), 0);
+        assert_eq!(py_gc_is_tracked_h(second, other), 1);
+
+        assert_eq!(
+            py_gc_destroy_instance(second) as i32,
+            GCReturnCode::Success as i32
+        );
+        assert_eq!(
+            unsafe { py_gc_track_h(second, other) } as i32,
+            GCReturnCode::ErrorNotInitialized as i32
+        );
+        assert!(unsafe { py_gc_get_objects_h(second) }.is_null());
+        assert_eq!(
+            py_gc_destroy_instance(DEFAULT_INSTANCE_HANDLE) as i32,
+            GCReturnCode::ErrorInternal as i32
+        );
+        assert_eq!(py_gc_get_registry_count_h(DEFAULT_INSTANCE_HANDLE), 0);
 
+        assert_eq!(
+            py_gc_destroy_instance(first) as i32,
+            GCReturnCode::Success as i32
+        );
+        free_mock_object(obj);
+        free_mock_object(other);
         assert_eq!(py_gc_cleanup() as i32, GCReturnCode::Success as i32);
     }
 }
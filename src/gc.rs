@@ -1,274 +1,4831 @@
 use crate::GCResult;
-use crate::collector::Collector;
+use crate::arena::{ArenaPool, CompactionStats, FragmentationStats, PooledCell};
+use crate::clock::Clock;
+use crate::collector::{
+    BarrierViolation, CollectionReport, CollectionStep, Collector, IntegrityViolation, LeakReport,
+    MarkSnapshot, ObjectExplanation, PromotionPolicy, PromotionStats, PublishedStats, Quota,
+    QuotaKey, RefcountDiscrepancy, StatsBoard, TagStats, TypeStats, UncollectablePolicy,
+};
 use crate::error::GCError;
-use crate::object::{ObjectId, PyObject};
-use parking_lot::RwLock;
-use std::sync::Arc;
+use crate::object::{IMMORTAL_REFCOUNT, ObjectData, ObjectId, PyObject};
+use crate::rss::{RssMonitor, trim_allocator};
+use crate::traversal::{Trace, visit_children};
+use parking_lot::{Condvar, Mutex, MutexGuard, RwLock, RwLockReadGuard};
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::{JoinHandle, ThreadId};
+use std::time::Duration;
+
+pub const DEBUG_STATS: u32 = 1 << 0;
+pub const DEBUG_COLLECTABLE: u32 = 1 << 1;
+pub const DEBUG_UNCOLLECTABLE: u32 = 1 << 2;
+pub const DEBUG_SAVEALL: u32 = 1 << 5;
+/// Audit refcounts against the object graph at the start of each
+/// collection; see [`GarbageCollector::last_refcount_audit`]. Walks the
+/// whole heap, so it is meant for debugging embedders.
+pub const DEBUG_AUDIT_REFS: u32 = 1 << 6;
+/// After each minor collection, check the whole heap for references to the
+/// objects it freed; see [`GarbageCollector::last_barrier_violations`].
+pub const DEBUG_VERIFY_BARRIERS: u32 = 1 << 7;
+pub const DEBUG_LEAK: u32 = DEBUG_COLLECTABLE | DEBUG_UNCOLLECTABLE | DEBUG_SAVEALL;
+
+/// Called once for each object with a finalizer just before it is freed.
+/// An object is finalized at most once, so one that was resurrected is
+/// freed without its finalizer running again.
+pub type Finalizer = Arc<dyn Fn(&FinalizerContext<'_>) + Send + Sync>;
+
+/// What a [`Finalizer`] gets: the object about to be freed, and the means to
+/// keep it, for caches that hand objects back out instead of losing them.
+pub struct FinalizerContext<'a> {
+    object: &'a PyObject,
+    can_resurrect: bool,
+    resurrected: Cell<bool>,
+}
+
+impl FinalizerContext<'_> {
+    pub fn object(&self) -> &PyObject {
+        self.object
+    }
+
+    /// Keep the object alive: once the finalizer returns it is tracked again
+    /// in the oldest generation, along with the garbage it references. The
+    /// finalizer should take a reference for whoever keeps the object, or
+    /// the next full collection frees it, this time without finalizing it.
+    /// Returns false, doing nothing, for an object freed by `decref` or
+    /// `break_cycle`, whose references were already dropped.
+    pub fn resurrect(&self) -> bool {
+        self.resurrected.set(self.can_resurrect);
+        self.can_resurrect
+    }
+}
+
+#[derive(Clone)]
+struct FinalizerHook(Finalizer);
+
+impl fmt::Debug for FinalizerHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("FinalizerHook")
+    }
+}
+
+/// Called with `(tracked_bytes, hard_limit)` when a full collection could not
+/// bring the heap back under the hard limit.
+pub type MemoryPressureCallback = Arc<dyn Fn(usize, usize) + Send + Sync>;
+
+#[derive(Clone)]
+struct PressureHook(MemoryPressureCallback);
+
+impl fmt::Debug for PressureHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PressureHook")
+    }
+}
+
+/// When to compact the pooled payload arenas after a collection: once
+/// compaction would free at least `min_reclaimable_bytes` of arenas.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionPolicy {
+    pub min_reclaimable_bytes: usize,
+}
+
+/// Called with a quota's key and the ids of its objects to evict, oldest
+/// first, when a collection leaves more under the key than the quota allows.
+pub type EvictionCallback = Arc<dyn Fn(&QuotaKey, &[ObjectId]) + Send + Sync>;
+
+#[derive(Clone)]
+struct EvictionHook(EvictionCallback);
+
+impl fmt::Debug for EvictionHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EvictionHook")
+    }
+}
+
+#[derive(Debug, Default)]
+struct QuotaState {
+    quotas: BTreeMap<QuotaKey, Quota>,
+    on_exceeded: Option<EvictionHook>,
+    /// Set while the callback runs, so a collection it starts doesn't
+    /// report the same quotas again.
+    enforcing: bool,
+}
+
+/// What a watch set with [`GarbageCollector::watch`] reports about its object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    /// The refcount crossing `threshold`, up or down: from below it to at
+    /// least it, or back.
+    Refcount { threshold: usize },
+    /// A collection finding the object unreachable.
+    Reachability,
+    /// The object being freed, by a collection or by its refcount reaching
+    /// zero.
+    Collection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchEvent {
+    Refcount { old: usize, new: usize },
+    Unreachable,
+    Collected,
+}
+
+/// Called with the watched object's id and what happened to it. Runs once
+/// the collector lock is released, so it may call back into the collector.
+pub type WatchCallback = Arc<dyn Fn(ObjectId, WatchEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WatchId(u64);
+
+impl WatchId {
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    pub fn from_u64(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+#[derive(Clone)]
+struct WatchHook(WatchCallback);
+
+impl fmt::Debug for WatchHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WatchHook")
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Watch {
+    obj_id: ObjectId,
+    kind: WatchKind,
+    callback: WatchHook,
+}
+
+#[derive(Debug, Default)]
+struct Watches {
+    next_id: u64,
+    watches: BTreeMap<WatchId, Watch>,
+}
+
+/// What a [`PreCollectHook`] wants done with the collection it is told of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreCollect {
+    Proceed,
+    /// Skip this collection, say during a latency-critical section.
+    Veto,
+}
+
+/// Called with the generation about to be collected and the current stats
+/// before a collection starts marking, like a `gc.callbacks` "start" call.
+/// It runs unlocked, so it may drop cached objects for the collection to
+/// find.
+pub type PreCollectHook = Arc<dyn Fn(usize, &crate::GCStats) -> PreCollect + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PreCollectHookId(u64);
+
+impl PreCollectHookId {
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+
+    pub fn from_u64(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+#[derive(Clone)]
+struct PreCollectEntry(PreCollectHook);
+
+impl fmt::Debug for PreCollectEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PreCollectEntry")
+    }
+}
+
+#[derive(Debug, Default)]
+struct PreCollectHooks {
+    next_id: u64,
+    hooks: BTreeMap<PreCollectHookId, PreCollectEntry>,
+}
+
+/// Byte limits on the shallow size of tracked objects. Crossing `soft` forces
+/// a full collection; staying above `hard` afterwards makes `allocate` fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimit {
+    pub soft: usize,
+    pub hard: usize,
+}
+
+#[derive(Debug, Default)]
+struct MemoryState {
+    limit: Option<MemoryLimit>,
+    on_pressure: Option<PressureHook>,
+    /// Heap size left over by the last forced collection. Another forced
+    /// collection waits until the heap grows past it, so a heap that settles
+    /// above the soft limit is not collected on every allocation.
+    collect_above: usize,
+    exhausted: bool,
+}
+
+/// An object a collection found unreachable while [`DEBUG_SAVEALL`] was set.
+/// Holding it keeps the object, and any cycle it is part of, alive.
+#[derive(Debug, Clone)]
+pub struct SavedGarbage {
+    pub object: PyObject,
+    pub type_name: &'static str,
+}
+
+/// Integers CPython keeps preallocated, and so does [`GarbageCollector::intern`].
+pub const SMALL_INT_RANGE: std::ops::RangeInclusive<i64> = -5..=256;
+
+/// Longest string, in bytes, that [`GarbageCollector::intern`] caches.
+pub const MAX_INTERNED_STRING_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InternStats {
+    /// Calls answered from the table.
+    pub hits: usize,
+    /// Calls that added a value to the table.
+    pub misses: usize,
+    /// Calls with values the table doesn't cache, such as large integers.
+    pub uncached: usize,
+    pub entries: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum InternKey {
+    Int(i64),
+    Str(String),
+}
+
+#[derive(Debug, Default)]
+struct InternTable {
+    values: HashMap<InternKey, PyObject>,
+    stats: InternStats,
+}
+
+/// When to give freed memory back to the OS after a collection. The heap
+/// must shrink by `min_shrink_bytes` from its peak since the last release,
+/// so a heap that hovers around one size doesn't trim on every collection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReleasePolicy {
+    pub min_shrink_bytes: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ReleaseStats {
+    /// Times the allocator was asked to give memory back.
+    pub releases: usize,
+    /// RSS given back, as measured by the RSS monitor. Zero without one.
+    pub bytes_returned: u64,
+}
+
+#[derive(Debug, Default)]
+struct ReleaseState {
+    policy: Option<ReleasePolicy>,
+    /// Largest tracked heap seen at a collection since the last release.
+    peak: usize,
+    stats: ReleaseStats,
+}
+
+/// Whether a reference into an object appeared or went away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeChange {
+    Added,
+    Removed,
+}
+
+/// One change to the references into an object, as kept by
+/// [`GarbageCollector::reference_history`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceEvent {
+    /// When it happened, on the collector's clock.
+    pub at: Duration,
+    pub from: ObjectId,
+    pub change: EdgeChange,
+}
+
+/// Memory bounds on reference history: the events kept per object, and the
+/// objects with a history. Once `max_objects` have one, the object whose
+/// history started first loses it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReferenceHistoryLimits {
+    pub per_object: usize,
+    pub max_objects: usize,
+}
+
+#[derive(Debug, Default)]
+struct ReferenceHistory {
+    limits: Option<ReferenceHistoryLimits>,
+    clock: Option<Arc<dyn Clock>>,
+    events: HashMap<ObjectId, VecDeque<ReferenceEvent>>,
+    /// Objects with a history, in the order it started.
+    order: VecDeque<ObjectId>,
+}
+
+impl ReferenceHistory {
+    fn record(&mut self, from: ObjectId, to: ObjectId, change: EdgeChange) {
+        let (Some(limits), Some(clock)) = (self.limits, &self.clock) else {
+            return;
+        };
+        if limits.per_object == 0 || limits.max_objects == 0 {
+            return;
+        }
+        let at = clock.now();
+        let events = match self.events.entry(to) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                self.order.push_back(to);
+                entry.insert(VecDeque::with_capacity(limits.per_object))
+            }
+        };
+        if events.len() == limits.per_object {
+            events.pop_front();
+        }
+        events.push_back(ReferenceEvent { at, from, change });
+        while self.order.len() > limits.max_objects {
+            if let Some(evicted) = self.order.pop_front() {
+                self.events.remove(&evicted);
+            }
+        }
+    }
+}
+
+/// Time application threads spent parked at safepoints.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SafepointStats {
+    /// Stop-the-world collections run from a safepoint or a request.
+    pub collections: usize,
+    /// Times a thread reached a safepoint while a collection was requested.
+    pub waits: usize,
+    pub total_wait: Duration,
+    pub max_wait: Duration,
+}
+
+#[derive(Debug, Default)]
+struct SafepointState {
+    attached: usize,
+    parked: usize,
+    /// Work waiting for every attached thread to park, if any.
+    requested: Option<StopTheWorld>,
+    /// Bumped after each stop-the-world collection so parked threads know
+    /// theirs has finished.
+    epoch: u64,
+    /// Thread running a stop-the-world collection. The lock is released
+    /// meanwhile so its callbacks can use the safepoint API.
+    running: Option<ThreadId>,
+    stats: SafepointStats,
+}
+
+#[derive(Debug)]
+enum StopTheWorld {
+    Collect(usize),
+    Remark(Box<PendingRemark>),
+}
+
+/// A finished concurrent mark waiting for its stop-the-world remark.
+#[derive(Debug)]
+struct PendingRemark {
+    step: CollectionStep,
+    snapshot: MarkSnapshot,
+    unreachable: Vec<ObjectId>,
+}
+
+thread_local! {
+    /// Write-barrier entries not yet handed to their collector, tagged with
+    /// the collector's id. Flushed at safepoints.
+    static SATB_BUFFER: RefCell<Vec<(u64, ObjectId)>> = const { RefCell::new(Vec::new()) };
+
+    /// New objects waiting to be tracked, by collector id. Use through
+    /// [`young_buffers`], which drops the buffers of collectors since gone.
+    static YOUNG_BUFFERS: RefCell<HashMap<u64, YoungBuffer>> = RefCell::new(HashMap::new());
+
+    /// Ids of the collectors this thread is collecting with the lock held.
+    static COLLECTING_HERE: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks the current thread as collecting with one collector until dropped.
+struct CollectingHere(u64);
+
+impl CollectingHere {
+    fn enter(id: u64) -> Self {
+        COLLECTING_HERE.with_borrow_mut(|ids| ids.push(id));
+        Self(id)
+    }
+}
+
+impl Drop for CollectingHere {
+    fn drop(&mut self) {
+        COLLECTING_HERE.with_borrow_mut(|ids| {
+            if let Some(index) = ids.iter().rposition(|&id| id == self.0) {
+                ids.remove(index);
+            }
+        });
+    }
+}
+
+/// A track or untrack asked for by a callback running inside a collection,
+/// applied once the collection has released the collector.
+#[derive(Debug)]
+enum DeferredOp {
+    Track(PyObject),
+    Untrack(ObjectId),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DeferredStats {
+    /// Operations queued because they came from inside a collection.
+    pub deferred: usize,
+    pub applied: usize,
+    /// Operations that failed once applied, such as untracking an object
+    /// the collection had already freed.
+    pub failed: usize,
+}
+
+#[derive(Debug, Default)]
+struct DeferredOps {
+    ops: Vec<DeferredOp>,
+    stats: DeferredStats,
+}
+
+static NEXT_COLLECTOR_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Objects a thread buffers before `track_buffered` flushes them, by default.
+pub const DEFAULT_THREAD_BUFFER_CAPACITY: usize = 64;
+
+/// One thread's allocation buffer for one collector.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ThreadBufferStats {
+    /// Objects waiting in the buffer now.
+    pub buffered: usize,
+    pub flushes: usize,
+    /// Objects handed to generation 0 over all flushes.
+    pub flushed: usize,
+    /// Flushes caused by the buffer filling up.
+    pub overflows: usize,
+}
+
+#[derive(Debug)]
+struct YoungBuffer {
+    objects: Vec<PyObject>,
+    stats: ThreadBufferStats,
+    /// Dead once the collector is dropped, from whichever thread.
+    owner: Weak<()>,
+}
+
+/// Run `f` on this thread's allocation buffers, first discarding those whose
+/// collector was dropped; a collector only clears its own entry on the
+/// thread that drops it.
+fn young_buffers<R>(f: impl FnOnce(&mut HashMap<u64, YoungBuffer>) -> R) -> R {
+    YOUNG_BUFFERS.with_borrow_mut(|buffers| {
+        buffers.retain(|_, buffer| buffer.owner.strong_count() > 0);
+        f(buffers)
+    })
+}
+
+/// A mark running on a background thread, from
+/// [`GarbageCollector::start_concurrent_mark`]. Mutators keep running and
+/// must call [`GarbageCollector::write_barrier`] before changing an
+/// object's references. Dropping it without [`finish`](Self::finish)
+/// still finishes the collection.
+#[derive(Debug)]
+pub struct ConcurrentMark<'a> {
+    gc: &'a GarbageCollector,
+    step: Option<CollectionStep>,
+    worker: Option<JoinHandle<(MarkSnapshot, Vec<ObjectId>)>>,
+}
+
+impl ConcurrentMark<'_> {
+    /// Whether the background mark is done, so `finish` won't wait for it.
+    pub fn is_marked(&self) -> bool {
+        self.worker.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Wait for the mark, then ask attached threads to stop for the remark
+    /// and sweep. As with `request_stw_collection`, these run at the last
+    /// thread's safepoint, or straight away with no threads attached.
+    pub fn finish(mut self) -> GCResult<()> {
+        self.queue_remark()
+    }
+
+    fn queue_remark(&mut self) -> GCResult<()> {
+        let (Some(step), Some(worker)) = (self.step.take(), self.worker.take()) else {
+            return Ok(());
+        };
+        // A mark that panicked found nothing, so the remark keeps every
+        // candidate and only clears the collection flags.
+        let (result, (snapshot, unreachable)) = match worker.join() {
+            Ok(marked) => (Ok(()), marked),
+            Err(_) => (
+                Err(GCError::internal("concurrent_mark", "mark thread panicked")),
+                (MarkSnapshot::default(), Vec::new()),
+            ),
+        };
+
+        let mut state = self.gc.safepoints.lock();
+        state.requested = Some(StopTheWorld::Remark(Box::new(PendingRemark {
+            step,
+            snapshot,
+            unreachable,
+        })));
+        self.gc.stw_requested.store(true, Ordering::Release);
+        if state.parked == state.attached {
+            self.gc.run_stop_the_world(&mut state)?;
+        }
+        result
+    }
+}
+
+impl Drop for ConcurrentMark<'_> {
+    fn drop(&mut self) {
+        let _ = self.queue_remark();
+    }
+}
+
+/// Registration of an application thread with the safepoint protocol,
+/// from [`GarbageCollector::attach_thread`]. Detaches on drop.
+#[derive(Debug)]
+pub struct AttachedThread<'a> {
+    gc: &'a GarbageCollector,
+}
+
+impl Drop for AttachedThread<'_> {
+    fn drop(&mut self) {
+        let mut state = self.gc.safepoints.lock();
+        state.attached -= 1;
+        // The detaching thread may have been the last one the request was
+        // waiting for.
+        if state.requested.is_some() && state.parked == state.attached {
+            let _ = self.gc.run_stop_the_world(&mut state);
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ViewScope {
+    Tracked,
+    Generation(usize),
+    Permanent,
+    Uncollectable,
+    Tag(String),
+}
+
+/// Objects seen through the collector's read lock, held for as long as the
+/// view lives. Calls that modify the collector block until it is dropped, so
+/// don't make them from the same thread while iterating.
+#[derive(Debug)]
+pub struct ObjectView<'a> {
+    collector: RwLockReadGuard<'a, Collector>,
+    scope: ViewScope,
+}
+
+impl ObjectView<'_> {
+    pub fn iter(&self) -> impl Iterator<Item = &PyObject> + '_ {
+        let collector = &*self.collector;
+        let scope = &self.scope;
+        let tracked = (!matches!(scope, ViewScope::Uncollectable))
+            .then(|| collector.tracked_objects.values())
+            .into_iter()
+            .flatten();
+        let uncollectable = matches!(scope, ViewScope::Uncollectable | ViewScope::Tag(_))
+            .then(|| collector.uncollectable.iter())
+            .into_iter()
+            .flatten();
+        tracked.chain(uncollectable).filter(move |obj| match scope {
+            ViewScope::Generation(generation) => {
+                collector.generation_of(&obj.id()) == Some(*generation)
+            }
+            ViewScope::Permanent => collector.permanent.contains(&obj.id()),
+            ViewScope::Tag(tag) => obj.tag().as_deref() == Some(tag.as_str()),
+            ViewScope::Tracked | ViewScope::Uncollectable => true,
+        })
+    }
+}
+
+impl<'v> IntoIterator for &'v ObjectView<'_> {
+    type Item = &'v PyObject;
+    type IntoIter = Box<dyn Iterator<Item = &'v PyObject> + 'v>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        Box::new(self.iter())
+    }
+}
+
+/// Completes a cooperative collection whose future was dropped, so the
+/// collector is not left with objects flagged as collecting.
+#[cfg(feature = "async")]
+struct PendingCollection<'a> {
+    gc: &'a GarbageCollector,
+    step: CollectionStep,
+}
+
+#[cfg(feature = "async")]
+impl Drop for PendingCollection<'_> {
+    fn drop(&mut self) {
+        let step = &mut self.step;
+        self.gc.with_collection(|collector| {
+            while !step.is_finished() {
+                if collector.step_collection(step).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Returns `Pending` once, waking the task straight away.
+#[cfg(feature = "async")]
+struct YieldNow(bool);
+
+#[cfg(feature = "async")]
+impl std::future::Future for YieldNow {
+    type Output = ();
+
+    fn poll(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<()> {
+        if self.0 {
+            return std::task::Poll::Ready(());
+        }
+        self.0 = true;
+        cx.waker().wake_by_ref();
+        std::task::Poll::Pending
+    }
+}
 
 #[derive(Debug)]
 pub struct GarbageCollector {
     collector: Arc<RwLock<Collector>>,
-    enabled: bool,
-    thresholds: [usize; 3],
-    debug_flags: u32,
+    /// The collector's stats board, reachable without its lock.
+    stats_board: Arc<StatsBoard>,
+    enabled: AtomicBool,
+    tracking_paused: AtomicBool,
+    finalizer: RwLock<Option<FinalizerHook>>,
+    memory: Mutex<MemoryState>,
+    quotas: Mutex<QuotaState>,
+    rss: Mutex<Option<RssMonitor>>,
+    release: Mutex<ReleaseState>,
+    /// Arenas holding the payloads of objects from `allocate_pooled`.
+    pool: Arc<ArenaPool>,
+    compaction: Mutex<Option<CompactionPolicy>>,
+    interned: Mutex<InternTable>,
+    /// Tags this collector's entries in the thread-local buffers.
+    id: u64,
+    /// Held only here, so the thread-local buffers can tell from their weak
+    /// handles that the collector is gone.
+    alive: Arc<()>,
+    thread_buffer_capacity: AtomicUsize,
+    stw_requested: AtomicBool,
+    marking: AtomicBool,
+    satb: Mutex<Vec<ObjectId>>,
+    safepoints: Mutex<SafepointState>,
+    safepoint_done: Condvar,
+    deferred: Mutex<DeferredOps>,
+    recording_references: AtomicBool,
+    reference_history: Mutex<ReferenceHistory>,
+    watching: AtomicBool,
+    watches: Mutex<Watches>,
+    pre_collect: Mutex<PreCollectHooks>,
 }
 
-unsafe impl Send for GarbageCollector {}
-unsafe impl Sync for GarbageCollector {}
+impl GarbageCollector {
+    pub fn new() -> Self {
+        let collector = Collector::new();
+        Self {
+            stats_board: collector.stats_board.clone(),
+            collector: Arc::new(RwLock::new(collector)),
+            enabled: AtomicBool::new(true),
+            tracking_paused: AtomicBool::new(false),
+            finalizer: RwLock::new(None),
+            memory: Mutex::new(MemoryState::default()),
+            quotas: Mutex::new(QuotaState::default()),
+            rss: Mutex::new(None),
+            release: Mutex::new(ReleaseState::default()),
+            pool: Arc::new(ArenaPool::new()),
+            compaction: Mutex::new(None),
+            interned: Mutex::new(InternTable::default()),
+            id: NEXT_COLLECTOR_ID.fetch_add(1, Ordering::Relaxed),
+            alive: Arc::new(()),
+            thread_buffer_capacity: AtomicUsize::new(DEFAULT_THREAD_BUFFER_CAPACITY),
+            stw_requested: AtomicBool::new(false),
+            marking: AtomicBool::new(false),
+            satb: Mutex::new(Vec::new()),
+            safepoints: Mutex::new(SafepointState::default()),
+            safepoint_done: Condvar::new(),
+            deferred: Mutex::new(DeferredOps::default()),
+            recording_references: AtomicBool::new(false),
+            reference_history: Mutex::new(ReferenceHistory::default()),
+            watching: AtomicBool::new(false),
+            watches: Mutex::new(Watches::default()),
+            pre_collect: Mutex::new(PreCollectHooks::default()),
+        }
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Release);
+    }
+
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Release);
+    }
+
+    /// Whether automatic collection is on. As with Python's `gc.disable()`,
+    /// turning it off leaves tracking and explicit collections working.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// Make track and untrack calls no-ops until `resume_tracking`.
+    pub fn pause_tracking(&self) {
+        self.tracking_paused.store(true, Ordering::Release);
+    }
+
+    pub fn resume_tracking(&self) {
+        self.tracking_paused.store(false, Ordering::Release);
+    }
+
+    pub fn is_tracking_paused(&self) -> bool {
+        self.tracking_paused.load(Ordering::Acquire)
+    }
+
+    /// Start tracking `obj`. Called from a callback inside a collection on
+    /// this thread, the object is tracked once the collection is over.
+    pub fn track(&self, obj: PyObject) -> GCResult<()> {
+        if self.is_tracking_paused() {
+            return Ok(());
+        }
+        if self.in_collection() {
+            self.defer([DeferredOp::Track(obj)]);
+            return Ok(());
+        }
+
+        {
+            let mut collector = self.collector.write();
+            collector.track_object_fast(obj)?;
+        }
+        self.enforce_memory_limit()
+    }
+
+    /// Create and track a new object. Fails with `AllocationFailed` while the
+    /// heap is still above the hard memory limit after a forced collection.
+    pub fn allocate(&self, name: String, data: ObjectData) -> GCResult<PyObject> {
+        self.check_allocation()?;
+        let obj = PyObject::new(name, data);
+        self.track(obj.clone())?;
+        Ok(obj)
+    }
+
+    /// [`allocate`](Self::allocate) an object with a zeroed payload of `len`
+    /// bytes in the collector's arenas. The payload is reached through
+    /// [`PyObject::with_pooled`], so [`compact`](Self::compact) can move it,
+    /// and is freed with the object.
+    pub fn allocate_pooled(
+        &self,
+        name: String,
+        data: ObjectData,
+        len: usize,
+    ) -> GCResult<PyObject> {
+        self.check_allocation()?;
+        let handle = self.pool.lock().alloc(len)?;
+        let cell = PooledCell::new(self.pool.clone(), handle, len);
+        let obj = PyObject::new_pooled(name, data, cell);
+        self.track(obj.clone())?;
+        Ok(obj)
+    }
+
+    fn check_allocation(&self) -> GCResult<()> {
+        if let Some(limit) = self.exhausted_limit() {
+            let tracked = self.tracked_bytes();
+            if tracked > limit.hard {
+                return Err(GCError::AllocationFailed(format!(
+                    "tracked heap of {tracked} bytes exceeds hard limit of {} bytes",
+                    limit.hard
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_memory_limit(&self, limit: Option<MemoryLimit>) -> GCResult<()> {
+        if let Some(limit) = limit
+            && limit.soft > limit.hard
+        {
+            return Err(GCError::internal(
+                "set_memory_limit",
+                "soft memory limit exceeds hard limit",
+            ));
+        }
+        let mut memory = self.memory.lock();
+        memory.limit = limit;
+        memory.collect_above = 0;
+        memory.exhausted = false;
+        Ok(())
+    }
+
+    pub fn get_memory_limit(&self) -> Option<MemoryLimit> {
+        self.memory.lock().limit
+    }
+
+    pub fn on_memory_pressure(&self, callback: Option<MemoryPressureCallback>) {
+        self.memory.lock().on_pressure = callback.map(PressureHook);
+    }
+
+    /// Limit the tracked objects under `key`, or lift the limit with `None`.
+    /// Quotas are checked after each collection; see [`Self::enforce_quotas`].
+    pub fn set_quota(&self, key: QuotaKey, quota: Option<Quota>) {
+        let mut state = self.quotas.lock();
+        match quota {
+            Some(quota) => state.quotas.insert(key, quota),
+            None => state.quotas.remove(&key),
+        };
+    }
+
+    pub fn get_quota(&self, key: &QuotaKey) -> Option<Quota> {
+        self.quotas.lock().quotas.get(key).copied()
+    }
+
+    pub fn on_quota_exceeded(&self, callback: Option<EvictionCallback>) {
+        self.quotas.lock().on_exceeded = callback.map(EvictionHook);
+    }
+
+    /// Check every quota and hand the eviction callback the objects to
+    /// evict for each one exceeded. The GC only suggests candidates; it is
+    /// up to the callback to drop or untrack them. Returns the number of
+    /// quotas exceeded.
+    pub fn enforce_quotas(&self) -> usize {
+        let (quotas, callback) = {
+            let mut state = self.quotas.lock();
+            if state.quotas.is_empty() || state.enforcing {
+                return 0;
+            }
+            state.enforcing = true;
+            (state.quotas.clone(), state.on_exceeded.clone())
+        };
+        let exceeded: Vec<(QuotaKey, Vec<ObjectId>)> = {
+            let collector = self.collector.read();
+            quotas
+                .into_iter()
+                .map(|(key, quota)| {
+                    let candidates = collector.quota_candidates(&key, &quota);
+                    (key, candidates)
+                })
+                .filter(|(_, candidates)| !candidates.is_empty())
+                .collect()
+        };
+        // Run outside the locks so the callback may untrack objects or
+        // change the quotas.
+        if let Some(EvictionHook(callback)) = callback {
+            for (key, candidates) in &exceeded {
+                callback(key, candidates);
+            }
+        }
+        self.quotas.lock().enforcing = false;
+        exceeded.len()
+    }
+
+    /// Shallow size of every tracked object, as used for the memory limit.
+    pub fn tracked_bytes(&self) -> usize {
+        self.collector.read().tracked_bytes
+    }
+
+    fn exhausted_limit(&self) -> Option<MemoryLimit> {
+        let memory = self.memory.lock();
+        memory.limit.filter(|_| memory.exhausted)
+    }
+
+    fn enforce_memory_limit(&self) -> GCResult<()> {
+        let (limit, collect_above) = {
+            let memory = self.memory.lock();
+            match memory.limit {
+                Some(limit) => (limit, memory.collect_above),
+                None => return Ok(()),
+            }
+        };
+
+        let mut tracked = self.tracked_bytes();
+        if tracked <= limit.soft {
+            let mut memory = self.memory.lock();
+            memory.collect_above = 0;
+            memory.exhausted = false;
+            return Ok(());
+        }
+        let forced = tracked > collect_above && self.is_enabled();
+        if forced {
+            self.collect()?;
+            tracked = self.tracked_bytes();
+        }
+
+        let callback = {
+            let mut memory = self.memory.lock();
+            if forced {
+                memory.collect_above = if tracked > limit.soft { tracked } else { 0 };
+            }
+            let newly_exhausted = tracked > limit.hard && !memory.exhausted;
+            memory.exhausted = tracked > limit.hard;
+            memory.on_pressure.clone().filter(|_| newly_exhausted)
+        };
+        // Run outside the lock so the callback may free objects or adjust
+        // the limit.
+        if let Some(PressureHook(callback)) = callback {
+            callback(tracked, limit.hard);
+        }
+        Ok(())
+    }
+
+    /// An object holding `data`. Small integers and short strings come from
+    /// an intern table, so equal values share one immortal object that is
+    /// never tracked; anything else gets a new, untracked object.
+    pub fn intern(&self, data: ObjectData) -> PyObject {
+        let name = data.type_name();
+        let key = match &data {
+            ObjectData::Integer(value) if SMALL_INT_RANGE.contains(value) => InternKey::Int(*value),
+            ObjectData::String(value) if value.len() <= MAX_INTERNED_STRING_LEN => {
+                InternKey::Str(value.clone())
+            }
+            _ => {
+                self.interned.lock().stats.uncached += 1;
+                return PyObject::new(name.to_string(), data);
+            }
+        };
+
+        let mut table = self.interned.lock();
+        let table = &mut *table;
+        match table.values.entry(key) {
+            Entry::Occupied(entry) => {
+                table.stats.hits += 1;
+                entry.get().clone()
+            }
+            Entry::Vacant(entry) => {
+                let obj = PyObject::new(name.to_string(), data);
+                obj.set_refcount(IMMORTAL_REFCOUNT);
+                entry.insert(obj.clone());
+                table.stats.misses += 1;
+                table.stats.entries = table.values.len();
+                obj
+            }
+        }
+    }
+
+    pub fn intern_stats(&self) -> InternStats {
+        self.interned.lock().stats
+    }
+
+    /// Track `obj` through this thread's allocation buffer, which joins
+    /// generation 0 under a single lock once it fills up, at a safepoint, at
+    /// a collection started from this thread, or on `flush_thread_buffers`.
+    /// Until then the object doesn't count as tracked. Errors tracking it
+    /// surface from the flush.
+    pub fn track_buffered(&self, obj: PyObject) -> GCResult<()> {
+        if self.is_tracking_paused() {
+            return Ok(());
+        }
+        let capacity = self.thread_buffer_capacity.load(Ordering::Relaxed);
+        if capacity == 0 {
+            return self.track(obj);
+        }
+
+        let full = young_buffers(|buffers| {
+            let buffer = buffers.entry(self.id).or_insert_with(|| YoungBuffer {
+                objects: Vec::new(),
+                stats: ThreadBufferStats::default(),
+                owner: Arc::downgrade(&self.alive),
+            });
+            buffer.objects.push(obj);
+            buffer.stats.buffered = buffer.objects.len();
+            let full = buffer.objects.len() >= capacity;
+            if full {
+                buffer.stats.overflows += 1;
+            }
+            full
+        });
+        if full {
+            self.flush_thread_buffers()?;
+        }
+        Ok(())
+    }
+
+    /// Track everything in this thread's allocation buffer. Returns how many
+    /// objects were flushed; the first error, if any, once all were tried.
+    pub fn flush_thread_buffers(&self) -> GCResult<usize> {
+        // The collection running on this thread holds the collector; the
+        // buffer waits for the next flush.
+        if self.in_collection() {
+            return Ok(0);
+        }
+        let objects = young_buffers(|buffers| {
+            let buffer = buffers.get_mut(&self.id)?;
+            if buffer.objects.is_empty() {
+                return None;
+            }
+            buffer.stats.flushes += 1;
+            buffer.stats.flushed += buffer.objects.len();
+            buffer.stats.buffered = 0;
+            Some(std::mem::take(&mut buffer.objects))
+        });
+        let Some(objects) = objects else {
+            return Ok(0);
+        };
+
+        let flushed = objects.len();
+        let mut result = Ok(flushed);
+        {
+            let mut collector = self.collector.write();
+            for obj in objects {
+                if let Err(err) = collector.track_object_fast(obj)
+                    && result.is_ok()
+                {
+                    result = Err(err);
+                }
+            }
+        }
+        self.enforce_memory_limit()?;
+        result
+    }
+
+    /// Stats for this thread's allocation buffer.
+    pub fn thread_buffer_stats(&self) -> ThreadBufferStats {
+        young_buffers(|buffers| {
+            buffers
+                .get(&self.id)
+                .map_or_else(ThreadBufferStats::default, |buffer| buffer.stats)
+        })
+    }
+
+    /// How many objects a thread buffers before flushing them; 0 makes
+    /// `track_buffered` track straight away.
+    pub fn set_thread_buffer_capacity(&self, capacity: usize) {
+        self.thread_buffer_capacity
+            .store(capacity, Ordering::Relaxed);
+    }
+
+    pub fn track_bulk(&self, objects: Vec<PyObject>) -> GCResult<()> {
+        if self.is_tracking_paused() {
+            return Ok(());
+        }
+        if self.in_collection() {
+            self.defer(objects.into_iter().map(DeferredOp::Track));
+            return Ok(());
+        }
+
+        {
+            let mut collector = self.collector.write();
+            collector.track_objects_bulk(objects)
+        }
+    }
+
+    /// Stop tracking `obj_id`, deferred as for `track` when called from
+    /// inside a collection.
+    pub fn untrack(&self, obj_id: &ObjectId) -> GCResult<()> {
+        if self.is_tracking_paused() {
+            return Ok(());
+        }
+        if self.in_collection() {
+            self.defer([DeferredOp::Untrack(*obj_id)]);
+            return Ok(());
+        }
+
+        {
+            let mut collector = self.collector.write();
+            collector.untrack_object_fast(obj_id)
+        }
+    }
+
+    /// Move `obj_id`, and with `closure` everything tracked here that it
+    /// reaches, into `to`. Returns the ids that changed heaps. Objects left
+    /// behind that the moved ones still reference stay alive: `to` cannot
+    /// see those edges, so `self` counts them as external references.
+    pub fn transfer(
+        &self,
+        obj_id: &ObjectId,
+        to: &GarbageCollector,
+        closure: bool,
+    ) -> GCResult<Vec<ObjectId>> {
+        if Arc::ptr_eq(&self.collector, &to.collector) {
+            return Err(GCError::internal(
+                "transfer",
+                "source and destination heaps are the same",
+            ));
+        }
+
+        // Both heaps stay locked for the whole move, taken in address order
+        // so two opposite transfers can't deadlock.
+        let (mut from, mut into) = if Arc::as_ptr(&self.collector) < Arc::as_ptr(&to.collector) {
+            let from = self.collector.write();
+            (from, to.collector.write())
+        } else {
+            let into = to.collector.write();
+            (self.collector.write(), into)
+        };
+
+        // Check everything before taking anything, so a refused transfer
+        // leaves both heaps as they were.
+        let moved = from.objects_to_take(obj_id, closure)?;
+        for obj_id in &moved {
+            into.check_trackable(obj_id)?;
+        }
+
+        let mut objects = from.take_objects(&moved).into_iter();
+        while let Some(obj) = objects.next() {
+            if let Err(err) = into.track_object_fast(obj.clone()) {
+                // Not reachable after the checks above, but never drop
+                // objects: whatever hasn't moved goes back where it was.
+                for obj in std::iter::once(obj).chain(objects) {
+                    let _ = from.track_object_fast(obj);
+                }
+                return Err(err);
+            }
+        }
+        drop((from, into));
+
+        // The move is done. A forced collection the limit can't run now is
+        // retried on the next allocation, so it doesn't fail the transfer.
+        let _ = to.enforce_memory_limit();
+        Ok(moved)
+    }
+
+    /// References from this heap into `other`, as `(referrer, referent)`
+    /// pairs. Each referent is an external root for `other`.
+    pub fn cross_heap_edges(&self, other: &GarbageCollector) -> Vec<(ObjectId, ObjectId)> {
+        if Arc::ptr_eq(&self.collector, &other.collector) {
+            return Vec::new();
+        }
+        let collector = self.collector.read();
+        collector.cross_heap_edges(&other.collector.read())
+    }
+
+    pub fn set_finalizer(&self, finalizer: Option<Finalizer>) {
+        *self.finalizer.write() = finalizer.map(FinalizerHook);
+    }
+
+    pub fn set_tracer(&self, tracer: Option<Arc<dyn Trace>>) {
+        self.collector.write().set_tracer(tracer);
+    }
+
+    /// Drop one reference to `obj_id`, freeing it and anything only it kept
+    /// alive once counts reach zero. Finalizers run after the collector lock
+    /// is released, so they may call back into the collector. Returns the
+    /// number of objects freed.
+    pub fn decref(&self, obj_id: &ObjectId) -> GCResult<usize> {
+        let (reclaimed, watched) = {
+            let mut collector = self.collector.write();
+            let watched = self.watched_refcounts(&collector);
+            (collector.decref(obj_id)?, watched)
+        };
+
+        self.notify_refcount_watches(watched);
+        self.notify_freed(&reclaimed);
+        self.run_finalizers(&reclaimed, false);
+        Ok(reclaimed.len())
+    }
+
+    /// Clear the references `obj_id` holds, the surgical fix for a known-bad
+    /// cycle such as one stuck in the uncollectable list: the rest of the
+    /// cycle is freed once nothing else holds it, with finalizers run as for
+    /// `decref`. Returns the number of objects freed.
+    pub fn break_cycle(&self, obj_id: &ObjectId) -> GCResult<usize> {
+        let (reclaimed, watched) = {
+            let mut collector = self.collector.write();
+            let watched = self.watched_refcounts(&collector);
+            (collector.break_cycle(obj_id)?, watched)
+        };
+        self.notify_refcount_watches(watched);
+        self.notify_freed(&reclaimed);
+        self.run_finalizers(&reclaimed, false);
+        Ok(reclaimed.len())
+    }
+
+    /// Call `visit` with the id of each object `obj_id` refers to, exactly as
+    /// marking sees them: list items, dict keys and values, what the tracer
+    /// reports for custom data, and the values of ephemerons keyed by
+    /// `obj_id`. An object held twice is visited twice. The children are
+    /// gathered first, so `visit` may call back into the collector. Fails
+    /// with `NotTracked` if the collector doesn't know `obj_id`.
+    pub fn visit_children(
+        &self,
+        obj_id: &ObjectId,
+        visit: &mut dyn FnMut(ObjectId),
+    ) -> GCResult<()> {
+        let mut children = Vec::new();
+        self.collector
+            .read()
+            .visit_children(obj_id, &mut |child_id| children.push(child_id))?;
+        children.into_iter().for_each(visit);
+        Ok(())
+    }
+
+    /// Make `value` live only as long as `key` is reachable without it, as an
+    /// ephemeron does: the pair holds a reference on `value`, but one that
+    /// marking follows from `key` rather than counting as a root. Values may
+    /// refer back to their keys, as a weak-key map's values often do, and
+    /// the pair is still collected once nothing else holds the key.
+    pub fn add_ephemeron(&self, key: &ObjectId, value: &ObjectId) -> GCResult<()> {
+        self.collector.write().add_ephemeron(key, value)
+    }
+
+    /// Undo one [`Self::add_ephemeron`] of `key` with `value`, dropping the
+    /// reference it held as `decref` does. Returns false if there was none.
+    pub fn remove_ephemeron(&self, key: &ObjectId, value: &ObjectId) -> GCResult<bool> {
+        let (reclaimed, watched) = {
+            let mut collector = self.collector.write();
+            if !collector.remove_ephemeron(key, value) {
+                return Ok(false);
+            }
+            let watched = self.watched_refcounts(&collector);
+            (collector.decref(value)?, watched)
+        };
+        self.notify_refcount_watches(watched);
+        self.notify_freed(&reclaimed);
+        self.run_finalizers(&reclaimed, false);
+        Ok(true)
+    }
+
+    /// Run `hook` before every collection until removed. Hooks run in the
+    /// order they were added.
+    pub fn add_pre_collect_hook(&self, hook: PreCollectHook) -> PreCollectHookId {
+        let mut state = self.pre_collect.lock();
+        let hook_id = PreCollectHookId(state.next_id);
+        state.next_id += 1;
+        state.hooks.insert(hook_id, PreCollectEntry(hook));
+        hook_id
+    }
+
+    /// Returns false if the hook was already gone.
+    pub fn remove_pre_collect_hook(&self, hook_id: PreCollectHookId) -> bool {
+        self.pre_collect.lock().hooks.remove(&hook_id).is_some()
+    }
+
+    /// Run the pre-collect hooks for a collection of `generation`. Returns
+    /// how long they took and whether one vetoed the collection; the rest
+    /// still run, since they may have caches to drop regardless.
+    fn run_pre_collect_hooks(&self, generation: usize) -> (Duration, bool) {
+        let hooks: Vec<PreCollectEntry> = self.pre_collect.lock().hooks.values().cloned().collect();
+        if hooks.is_empty() {
+            return (Duration::ZERO, false);
+        }
+        let clock = self.collector.read().clock.clone();
+        let started = clock.now();
+        let stats = self.get_stats();
+        let mut vetoed = false;
+        for PreCollectEntry(hook) in hooks {
+            vetoed |= hook(generation, &stats) == PreCollect::Veto;
+        }
+        (clock.now().saturating_sub(started), vetoed)
+    }
+
+    /// Watch `obj_id` for `kind` of change, calling `callback` each time it
+    /// happens until [`Self::unwatch`] or the object is freed.
+    pub fn watch(
+        &self,
+        obj_id: &ObjectId,
+        kind: WatchKind,
+        callback: WatchCallback,
+    ) -> GCResult<WatchId> {
+        if !self.is_tracked(obj_id) {
+            return Err(GCError::NotTracked { id: *obj_id });
+        }
+        let mut state = self.watches.lock();
+        let watch_id = WatchId(state.next_id);
+        state.next_id += 1;
+        state.watches.insert(
+            watch_id,
+            Watch {
+                obj_id: *obj_id,
+                kind,
+                callback: WatchHook(callback),
+            },
+        );
+        self.watching.store(true, Ordering::Release);
+        Ok(watch_id)
+    }
+
+    /// Remove a watch. Returns false if it was already gone.
+    pub fn unwatch(&self, watch_id: WatchId) -> bool {
+        let mut state = self.watches.lock();
+        let removed = state.watches.remove(&watch_id).is_some();
+        self.watching
+            .store(!state.watches.is_empty(), Ordering::Release);
+        removed
+    }
+
+    /// Report a refcount change made outside the collector, such as through
+    /// the C API, to the refcount watches on `obj_id`.
+    pub fn refcount_changed(&self, obj_id: &ObjectId, old: usize, new: usize) {
+        if self.watching.load(Ordering::Acquire) {
+            self.fire_watches(|watch| {
+                let WatchKind::Refcount { threshold } = watch.kind else {
+                    return None;
+                };
+                (watch.obj_id == *obj_id && (old < threshold) != (new < threshold))
+                    .then_some(WatchEvent::Refcount { old, new })
+            });
+        }
+    }
+
+    /// Handles to, and refcounts of, the objects with refcount watches, to
+    /// compare once an operation that may change several counts is done.
+    fn watched_refcounts(&self, collector: &Collector) -> Vec<(PyObject, usize)> {
+        if !self.watching.load(Ordering::Acquire) {
+            return Vec::new();
+        }
+        let state = self.watches.lock();
+        let mut watched: Vec<ObjectId> = state
+            .watches
+            .values()
+            .filter(|watch| matches!(watch.kind, WatchKind::Refcount { .. }))
+            .map(|watch| watch.obj_id)
+            .collect();
+        watched.sort_by_key(ObjectId::as_u64);
+        watched.dedup();
+        watched
+            .iter()
+            .filter_map(|obj_id| collector.find_object(obj_id).cloned())
+            .map(|obj| {
+                let refcount = obj.get_refcount();
+                (obj, refcount)
+            })
+            .collect()
+    }
+
+    fn notify_refcount_watches(&self, watched: Vec<(PyObject, usize)>) {
+        for (obj, old) in watched {
+            self.refcount_changed(&obj.id(), old, obj.get_refcount());
+        }
+    }
+
+    /// Report what a finished collection found to the reachability and
+    /// collection watches.
+    fn notify_collection(&self, step: &CollectionStep) {
+        if !self.watching.load(Ordering::Acquire) {
+            return;
+        }
+        self.fire_watches(|watch| {
+            (watch.kind == WatchKind::Reachability && step.unreachable.contains(&watch.obj_id))
+                .then_some(WatchEvent::Unreachable)
+        });
+        self.notify_collected(&step.collected);
+    }
+
+    fn notify_freed(&self, objects: &[PyObject]) {
+        if self.watching.load(Ordering::Acquire) {
+            let freed: Vec<ObjectId> = objects.iter().map(PyObject::id).collect();
+            self.notify_collected(&freed);
+        }
+    }
+
+    /// Fire the collection watches on `freed`, then drop every watch on
+    /// them: the objects are gone.
+    fn notify_collected(&self, freed: &[ObjectId]) {
+        self.fire_watches(|watch| {
+            (watch.kind == WatchKind::Collection && freed.contains(&watch.obj_id))
+                .then_some(WatchEvent::Collected)
+        });
+        let mut state = self.watches.lock();
+        state
+            .watches
+            .retain(|_, watch| !freed.contains(&watch.obj_id));
+        self.watching
+            .store(!state.watches.is_empty(), Ordering::Release);
+    }
+
+    /// Call each watch `event` picks an event for, outside the watch lock so
+    /// callbacks may add or remove watches.
+    fn fire_watches(&self, event: impl Fn(&Watch) -> Option<WatchEvent>) {
+        let fired: Vec<(ObjectId, WatchEvent, WatchHook)> = self
+            .watches
+            .lock()
+            .watches
+            .values()
+            .filter_map(|watch| Some((watch.obj_id, event(watch)?, watch.callback.clone())))
+            .collect();
+        for (obj_id, event, WatchHook(callback)) in fired {
+            callback(obj_id, event);
+        }
+    }
+
+    /// Call the finalizer hook on each of `objects` that has a finalizer not
+    /// yet run, marking it finalized first. Returns how many it ran on and
+    /// the objects it resurrected, which only a collection allows.
+    fn run_finalizers(&self, objects: &[PyObject], can_resurrect: bool) -> (usize, Vec<PyObject>) {
+        let finalizer = self.finalizer.read().clone();
+        let Some(FinalizerHook(finalizer)) = finalizer else {
+            return (0, Vec::new());
+        };
+        let mut run = 0;
+        let mut resurrected = Vec::new();
+        for obj in objects.iter().filter(|obj| obj.has_finalizer()) {
+            {
+                let mut head = obj.gc_head();
+                if head.is_finalized() {
+                    continue;
+                }
+                head.set_finalized();
+            }
+            let ctx = FinalizerContext {
+                object: obj,
+                can_resurrect,
+                resurrected: Cell::new(false),
+            };
+            finalizer(&ctx);
+            run += 1;
+            if ctx.resurrected.get() {
+                resurrected.push(obj.clone());
+            }
+        }
+        (run, resurrected)
+    }
+
+    /// Run `f` with the collector locked for a collection, then apply the
+    /// tracking changes callbacks asked for meanwhile. Taking the lock again
+    /// from one of those callbacks would deadlock.
+    fn with_collection<R>(&self, f: impl FnOnce(&mut Collector) -> R) -> R {
+        let result = {
+            let _here = CollectingHere::enter(self.id);
+            let mut collector = self.collector.write();
+            f(&mut collector)
+        };
+        self.apply_deferred();
+        result
+    }
+
+    /// Whether this thread is inside a collection holding the collector.
+    fn in_collection(&self) -> bool {
+        COLLECTING_HERE.with_borrow(|ids| ids.contains(&self.id))
+    }
+
+    fn defer(&self, ops: impl IntoIterator<Item = DeferredOp>) {
+        let mut deferred = self.deferred.lock();
+        let queued = deferred.ops.len();
+        deferred.ops.extend(ops);
+        deferred.stats.deferred += deferred.ops.len() - queued;
+    }
+
+    /// Apply the queued tracking changes in the order they were asked for.
+    fn apply_deferred(&self) {
+        if self.in_collection() {
+            return;
+        }
+        let ops = std::mem::take(&mut self.deferred.lock().ops);
+        if ops.is_empty() {
+            return;
+        }
+
+        let (mut applied, mut failed) = (0, 0);
+        {
+            let mut collector = self.collector.write();
+            for op in ops {
+                let result = match op {
+                    DeferredOp::Track(obj) => collector.track_object_fast(obj),
+                    DeferredOp::Untrack(obj_id) => collector.untrack_object_fast(&obj_id),
+                };
+                match result {
+                    Ok(()) => applied += 1,
+                    Err(_) => failed += 1,
+                }
+            }
+        }
+        let stats = &mut self.deferred.lock().stats;
+        stats.applied += applied;
+        stats.failed += failed;
+    }
+
+    /// Tracking changes queued from inside collections and what became of
+    /// them.
+    pub fn deferred_stats(&self) -> DeferredStats {
+        self.deferred.lock().stats
+    }
+
+    /// Overwrite the refcount the collector holds for `obj_id`, for callers
+    /// that mirror counts kept elsewhere.
+    pub fn set_refcount(&self, obj_id: &ObjectId, refcount: usize) -> GCResult<()> {
+        let old = {
+            let mut collector = self.collector.write();
+            let obj = collector
+                .find_object_mut(obj_id)
+                .ok_or(GCError::NotTracked { id: *obj_id })?;
+            let old = obj.get_refcount();
+            obj.set_refcount(refcount);
+            old
+        };
+        self.refcount_changed(obj_id, old, refcount);
+        Ok(())
+    }
+
+    /// Untrack each of `obj_ids` and return how many were. From inside a
+    /// collection they are deferred, and none count as untracked yet.
+    pub fn untrack_bulk(&self, obj_ids: &[ObjectId]) -> GCResult<usize> {
+        if self.is_tracking_paused() {
+            return Ok(0);
+        }
+        if self.in_collection() {
+            self.defer(obj_ids.iter().copied().map(DeferredOp::Untrack));
+            return Ok(0);
+        }
+
+        {
+            let mut collector = self.collector.write();
+            collector.untrack_objects_bulk(obj_ids)
+        }
+    }
+
+    pub fn collect_generation(&self, generation: usize) -> GCResult<usize> {
+        Ok(self.collect_generation_with_report(generation)?.collected)
+    }
+
+    pub fn collect(&self) -> GCResult<usize> {
+        Ok(self.collect_with_report()?.collected)
+    }
+
+    /// Full collection that reports what it scanned, found and freed.
+    pub fn collect_with_report(&self) -> GCResult<CollectionReport> {
+        self.collect_generation_with_report(2)
+    }
+
+    /// Collect `generation` and all younger ones. Finalizers of collected
+    /// objects run once the collector lock is released, as for `decref`.
+    pub fn collect_generation_with_report(&self, generation: usize) -> GCResult<CollectionReport> {
+        let (pre_collect_time, vetoed) = self.run_pre_collect_hooks(generation);
+        if vetoed {
+            return Ok(CollectionReport {
+                generation,
+                pre_collect_time,
+                vetoed,
+                ..CollectionReport::default()
+            });
+        }
+        self.flush_thread_buffers()?;
+        let (step, before, after) = self.with_collection(|collector| {
+            let before = collector.tracked_bytes;
+            let step = collector.run_collection(generation)?;
+            GCResult::Ok((step, before, collector.tracked_bytes))
+        })?;
+        self.notify_collection(&step);
+        let mut report = step.report();
+        report.pre_collect_time = pre_collect_time;
+        let (run, resurrected) = self.run_finalizers(&step.finalizable, true);
+        report.finalizers_run = run;
+        if !resurrected.is_empty() {
+            self.collector
+                .write()
+                .resurrect(&resurrected, &step.finalizable);
+        }
+        // Finalized garbage kept its references for the finalizers; clear
+        // what stayed dead now so cycles among it are freed.
+        for obj in step.finalizable.iter().filter(|obj| !obj.is_gc_tracked()) {
+            drop(obj.take_data());
+        }
+        self.compact_after_collection();
+        self.release_after_collection(before, after);
+        self.enforce_quotas();
+        Ok(report)
+    }
+
+    /// Ask the allocator to return free pages to the OS. Returns the drop in
+    /// RSS, as read by the RSS monitor, or 0 without a monitor or where the
+    /// allocator can't do it (only glibc can so far).
+    pub fn release_free_memory(&self) -> usize {
+        let rss = self.rss.lock();
+        let before = rss.as_ref().and_then(RssMonitor::current_rss);
+        let trimmed = trim_allocator();
+        let after = rss.as_ref().and_then(RssMonitor::current_rss);
+        drop(rss);
+
+        let returned = match (before, after) {
+            (Some(before), Some(after)) if trimmed => before.saturating_sub(after),
+            _ => 0,
+        };
+        let mut release = self.release.lock();
+        release.stats.releases += 1;
+        release.stats.bytes_returned += returned;
+        returned as usize
+    }
+
+    /// Release free memory automatically after collections that shrink the
+    /// heap enough; `None` turns it off.
+    pub fn set_release_policy(&self, policy: Option<ReleasePolicy>) {
+        let mut release = self.release.lock();
+        release.policy = policy;
+        release.peak = self.tracked_bytes();
+    }
+
+    pub fn get_release_policy(&self) -> Option<ReleasePolicy> {
+        self.release.lock().policy
+    }
+
+    pub fn release_stats(&self) -> ReleaseStats {
+        self.release.lock().stats
+    }
+
+    fn release_after_collection(&self, before: usize, after: usize) {
+        let due = {
+            let mut release = self.release.lock();
+            let Some(policy) = release.policy else {
+                return;
+            };
+            release.peak = release.peak.max(before);
+            let due = release.peak.saturating_sub(after) >= policy.min_shrink_bytes;
+            if due {
+                release.peak = after;
+            }
+            due
+        };
+        if due {
+            self.release_free_memory();
+        }
+    }
+
+    /// Move pooled payloads into as few arenas as hold them and free the
+    /// rest, then ask the allocator to return the freed memory to the OS.
+    /// Handles to the moved payloads stay valid. Only payloads from
+    /// [`allocate_pooled`](Self::allocate_pooled) are pooled; every object
+    /// itself is still a separate allocation that compaction doesn't move.
+    pub fn compact(&self) -> CompactionStats {
+        let run = self.pool.lock().compact();
+        if run.arenas_released > 0 {
+            self.release_free_memory();
+        }
+        run
+    }
+
+    /// How much of the pooled arenas is free. Covers only payloads from
+    /// [`allocate_pooled`](Self::allocate_pooled), not the objects themselves.
+    pub fn fragmentation(&self) -> FragmentationStats {
+        self.pool.lock().fragmentation()
+    }
+
+    /// Compact automatically after collections that leave enough of the
+    /// arenas free; `None` turns it off.
+    pub fn set_compaction_policy(&self, policy: Option<CompactionPolicy>) {
+        *self.compaction.lock() = policy;
+    }
+
+    pub fn get_compaction_policy(&self) -> Option<CompactionPolicy> {
+        *self.compaction.lock()
+    }
+
+    /// Compaction work summed over every run, automatic or not.
+    pub fn compaction_stats(&self) -> CompactionStats {
+        self.pool.lock().stats()
+    }
+
+    fn compact_after_collection(&self) {
+        let Some(policy) = self.get_compaction_policy() else {
+            return;
+        };
+        let stats = self.fragmentation();
+        if stats.reclaimable_arenas > 0 && stats.reclaimable_bytes >= policy.min_reclaimable_bytes {
+            self.compact();
+        }
+    }
+
+    /// Full collection that runs at most `budget_per_poll` phases each time
+    /// it is polled and yields to the executor in between, so it can run on
+    /// an async runtime without blocking other tasks for the whole pause.
+    /// Dropping the future part way finishes the collection synchronously.
+    /// Returns the number of objects collected.
+    #[cfg(feature = "async")]
+    pub async fn collect_cooperative(&self, budget_per_poll: usize) -> GCResult<usize> {
+        if self.run_pre_collect_hooks(2).1 {
+            return Ok(0);
+        }
+        let step = self.collector.write().begin_collection(2)?;
+        let mut pending = PendingCollection { gc: self, step };
+        loop {
+            let step = &mut pending.step;
+            self.with_collection(|collector| {
+                for _ in 0..budget_per_poll.max(1) {
+                    if collector.step_collection(step)?.is_none() {
+                        break;
+                    }
+                }
+                GCResult::Ok(())
+            })?;
+            if pending.step.is_finished() {
+                self.notify_collection(&pending.step);
+                return Ok(pending.step.collected.len());
+            }
+            YieldNow(false).await;
+        }
+    }
+
+    /// Register the calling thread as one that mutates the heap and calls
+    /// [`safepoint`](Self::safepoint). A requested stop-the-world collection
+    /// runs once every attached thread is parked at a safepoint.
+    pub fn attach_thread(&self) -> AttachedThread<'_> {
+        let mut state = self.safepoints.lock();
+        // A thread attaching during a stop-the-world collection waits for it,
+        // unless it is a callback of that collection.
+        let current = std::thread::current().id();
+        while state.running.is_some_and(|runner| runner != current) {
+            self.safepoint_done.wait(&mut state);
+        }
+        state.attached += 1;
+        AttachedThread { gc: self }
+    }
+
+    /// Ask attached threads to stop for a collection of `generation`. The
+    /// last thread to reach a safepoint runs it while the others wait; with
+    /// no threads attached it runs straight away. A second request before
+    /// the first has run widens it to the older generation. Fails while a
+    /// concurrent mark is in progress.
+    pub fn request_stw_collection(&self, generation: usize) -> GCResult<()> {
+        if generation >= 3 {
+            return Err(GCError::InvalidGeneration(generation));
+        }
+        let mut state = self.safepoints.lock();
+        if self.marking.load(Ordering::Acquire) {
+            return Err(GCError::CollectionInProgress);
+        }
+        let generation = match state.requested {
+            Some(StopTheWorld::Collect(requested)) => requested.max(generation),
+            _ => generation,
+        };
+        state.requested = Some(StopTheWorld::Collect(generation));
+        self.stw_requested.store(true, Ordering::Release);
+        if state.parked == state.attached {
+            self.run_stop_the_world(&mut state)?;
+        }
+        Ok(())
+    }
+
+    /// Start a collection of `generation` whose mark runs on a background
+    /// thread while mutators keep going. Only taking the candidates and
+    /// the final remark need the collector to themselves.
+    pub fn start_concurrent_mark(&self, generation: usize) -> GCResult<ConcurrentMark<'_>> {
+        let state = self.safepoints.lock();
+        if state.requested.is_some() || state.running.is_some() {
+            return Err(GCError::CollectionInProgress);
+        }
+        let (step, snapshot) = self.collector.write().begin_concurrent_mark(generation)?;
+        self.marking.store(true, Ordering::Release);
+        drop(state);
+
+        let worker = std::thread::spawn(move || {
+            let unreachable = snapshot.find_unreachable();
+            (snapshot, unreachable)
+        });
+        Ok(ConcurrentMark {
+            gc: self,
+            step: Some(step),
+            worker: Some(worker),
+        })
+    }
+
+    /// Call before changing which objects `obj` refers to. While a
+    /// concurrent mark runs this logs `obj` and everything it refers to now,
+    /// so the remark keeps whatever the mark could have missed.
+    pub fn write_barrier(&self, obj: &PyObject) {
+        if !self.marking.load(Ordering::Acquire) {
+            return;
+        }
+        let tracer = self.collector.read().tracer.clone();
+        let key = self.id;
+        SATB_BUFFER.with_borrow_mut(|buffer| {
+            buffer.push((key, obj.id()));
+            visit_children(obj, tracer.as_deref(), &mut |child_id| {
+                buffer.push((key, child_id))
+            });
+        });
+    }
+
+    /// Replace `obj`'s data, going through the write barrier. With reference
+    /// history on, the references gained and lost are recorded against the
+    /// objects they point to. Returns the old data.
+    pub fn replace_data(&self, obj: &PyObject, data: ObjectData) -> ObjectData {
+        self.write_barrier(obj);
+        if !self.is_recording_references() {
+            return std::mem::replace(&mut *obj.data_mut(), data);
+        }
+
+        let tracer = self.collector.read().tracer.clone();
+        let mut delta: HashMap<ObjectId, isize> = HashMap::new();
+        visit_children(obj, tracer.as_deref(), &mut |child_id| {
+            *delta.entry(child_id).or_default() -= 1;
+        });
+        let old = std::mem::replace(&mut *obj.data_mut(), data);
+        visit_children(obj, tracer.as_deref(), &mut |child_id| {
+            *delta.entry(child_id).or_default() += 1;
+        });
+
+        let mut changes: Vec<(ObjectId, isize)> =
+            delta.into_iter().filter(|&(_, n)| n != 0).collect();
+        changes.sort_by_key(|(child_id, _)| child_id.as_u64());
+        let mut history = self.reference_history.lock();
+        for (child_id, n) in changes {
+            let change = if n > 0 {
+                EdgeChange::Added
+            } else {
+                EdgeChange::Removed
+            };
+            for _ in 0..n.unsigned_abs() {
+                history.record(obj.id(), child_id, change);
+            }
+        }
+        old
+    }
+
+    /// Start recording the references added to and removed from each
+    /// object, within `limits`, or stop with `None`. Drops any history
+    /// recorded so far.
+    pub fn set_reference_history(&self, limits: Option<ReferenceHistoryLimits>) {
+        let clock = limits.map(|_| self.collector.read().clock.clone());
+        let mut history = self.reference_history.lock();
+        *history = ReferenceHistory {
+            limits,
+            clock,
+            ..ReferenceHistory::default()
+        };
+        self.recording_references
+            .store(limits.is_some(), Ordering::Release);
+    }
+
+    pub fn is_recording_references(&self) -> bool {
+        self.recording_references.load(Ordering::Acquire)
+    }
+
+    /// Record a reference from `from` to `to` appearing or going away, for
+    /// embedders that keep edges outside [`ObjectData`]. Does nothing while
+    /// reference history is off.
+    pub fn record_reference_change(&self, from: &ObjectId, to: &ObjectId, change: EdgeChange) {
+        if self.is_recording_references() {
+            self.reference_history.lock().record(*from, *to, change);
+        }
+    }
+
+    /// The last `n` changes to the references into `obj_id`, oldest first.
+    pub fn reference_history(&self, obj_id: &ObjectId, n: usize) -> Vec<ReferenceEvent> {
+        let history = self.reference_history.lock();
+        let Some(events) = history.events.get(obj_id) else {
+            return Vec::new();
+        };
+        events
+            .iter()
+            .skip(events.len().saturating_sub(n))
+            .copied()
+            .collect()
+    }
+
+    /// Whether a concurrent mark has started and not been remarked yet.
+    pub fn is_marking(&self) -> bool {
+        self.marking.load(Ordering::Acquire)
+    }
+
+    /// Hand this thread's write-barrier entries to the collector.
+    fn flush_satb(&self) {
+        let key = self.id;
+        SATB_BUFFER.with_borrow_mut(|buffer| {
+            if buffer.is_empty() {
+                return;
+            }
+            let mut satb = self.satb.lock();
+            buffer.retain(|&(owner, obj_id)| {
+                if owner == key {
+                    satb.push(obj_id);
+                }
+                owner != key
+            });
+        });
+    }
+
+    /// Called periodically by attached threads. Returns at once unless a
+    /// stop-the-world collection is pending, in which case it blocks until
+    /// that collection has finished. The thread that runs the collection
+    /// gets its error, if any.
+    pub fn safepoint(&self) -> GCResult<()> {
+        self.flush_thread_buffers()?;
+        if self.marking.load(Ordering::Acquire) {
+            self.flush_satb();
+        }
+        if !self.stw_requested.load(Ordering::Acquire) {
+            return Ok(());
+        }
+
+        let started = self.collector.read().clock.now();
+        let mut state = self.safepoints.lock();
+        // A callback of the running collection has nothing to wait for.
+        if state.requested.is_none() || state.running == Some(std::thread::current().id()) {
+            return Ok(());
+        }
+        state.parked += 1;
+        let result = if state.parked >= state.attached {
+            self.run_stop_the_world(&mut state).map(drop)
+        } else {
+            let epoch = state.epoch;
+            while state.epoch == epoch {
+                self.safepoint_done.wait(&mut state);
+            }
+            Ok(())
+        };
+
+        let waited = self.collector.read().clock.now().saturating_sub(started);
+        let stats = &mut state.stats;
+        stats.waits += 1;
+        stats.total_wait += waited;
+        stats.max_wait = stats.max_wait.max(waited);
+        result
+    }
+
+    pub fn safepoint_stats(&self) -> SafepointStats {
+        self.safepoints.lock().stats
+    }
+
+    /// Run the pending collection, then release parked threads. The lock
+    /// is dropped while it runs so hooks and callbacks may attach, detach
+    /// or request another collection; `running` keeps other threads that
+    /// attach meanwhile waiting. A request made during the run is left for
+    /// the next safepoint, or run here once no thread is attached.
+    fn run_stop_the_world(&self, state: &mut MutexGuard<'_, SafepointState>) -> GCResult<usize> {
+        if state.running.is_some() {
+            return Ok(0);
+        }
+        let mut collected = 0;
+        while let Some(work) = state.requested.take() {
+            self.stw_requested.store(false, Ordering::Release);
+            state.running = Some(std::thread::current().id());
+            let result = MutexGuard::unlocked(state, || match work {
+                StopTheWorld::Collect(generation) => self.collect_generation(generation),
+                StopTheWorld::Remark(remark) => self.remark(*remark),
+            });
+            state.running = None;
+            state.stats.collections += 1;
+            state.parked = 0;
+            state.epoch += 1;
+            self.safepoint_done.notify_all();
+            collected += result?;
+            if state.parked != state.attached {
+                break;
+            }
+        }
+        Ok(collected)
+    }
+
+    fn remark(&self, remark: PendingRemark) -> GCResult<usize> {
+        let PendingRemark {
+            mut step,
+            snapshot,
+            unreachable,
+        } = remark;
+        self.marking.store(false, Ordering::Release);
+        self.flush_satb();
+        let logged = std::mem::take(&mut *self.satb.lock());
+
+        self.with_collection(|collector| {
+            collector.remark(&mut step, &snapshot, &unreachable, &logged);
+            drop(snapshot);
+            while !step.is_finished() {
+                collector.step_collection(&mut step)?;
+            }
+            GCResult::Ok(())
+        })?;
+        self.notify_collection(&step);
+        Ok(step.collected.len())
+    }
+
+    /// The oldest generation over its threshold, which `collect_if_needed`
+    /// would collect now. Always `None` while the generation 0 threshold is 0.
+    pub fn needs_collection(&self) -> Option<usize> {
+        self.collector
+            .read()
+            .generation_manager
+            .generation_to_collect()
+    }
+
+    /// False once the generation 0 threshold is set to 0. Tracking and
+    /// explicit collections keep working; only the automatic triggers stop.
+    pub fn is_automatic_collection_enabled(&self) -> bool {
+        self.get_threshold(0).is_some_and(|threshold| threshold > 0)
+    }
+
+    pub fn get_stats(&self) -> crate::GCStats {
+        let mut stats = self.collector.read().get_stats();
+        self.add_rss_sample(&mut stats);
+        stats
+    }
+
+    /// Stats that never wait on a collection. With the collector lock free
+    /// they are current; while a collection holds it they are what the last
+    /// finished collection published.
+    pub fn published_stats(&self) -> PublishedStats {
+        let mut published = match self.collector.try_read() {
+            Some(collector) => PublishedStats {
+                stats: collector.get_stats(),
+                epoch: self.stats_board.epoch(),
+                collection_in_progress: self.stats_board.is_collecting(),
+            },
+            None => self.stats_board.read(),
+        };
+        self.add_rss_sample(&mut published.stats);
+        published
+    }
+
+    fn add_rss_sample(&self, stats: &mut crate::GCStats) {
+        if let Some(monitor) = self.rss.lock().as_ref() {
+            let sample = monitor.sample();
+            stats.rss_bytes = sample.rss_bytes;
+            stats.rss_growth_per_sec = sample.growth_per_sec;
+        }
+    }
+
+    pub fn set_rss_monitor(&self, monitor: Option<RssMonitor>) {
+        *self.rss.lock() = monitor;
+    }
+
+    /// Sample process RSS and, if it is growing faster than the monitor's
+    /// policy allows, collect the generation the monitor escalates to.
+    /// Returns the number of objects collected.
+    pub fn poll_rss(&self) -> GCResult<usize> {
+        if !self.is_enabled() {
+            return Ok(0);
+        }
+        let now = self.collector.read().clock.now();
+        let generation = match self.rss.lock().as_mut() {
+            Some(monitor) => monitor.observe(now),
+            None => return Ok(0),
+        };
+        match generation {
+            Some(generation) => self.collect_generation(generation),
+            None => Ok(0),
+        }
+    }
+
+    pub fn set_debug(&self, flags: u32) {
+        let mut collector = self.collector.write();
+        collector.set_debug_flags(flags);
+    }
+
+    pub fn get_debug(&self) -> u32 {
+        let collector = self.collector.read();
+        collector.get_debug_flags()
+    }
+
+    pub fn get_count(&self) -> usize {
+        let collector = self.collector.read();
+        collector.get_count()
+    }
+
+    pub fn type_histogram(&self) -> BTreeMap<String, TypeStats> {
+        self.collector.read().type_histogram()
+    }
+
+    /// Put `obj_id` in an operational group such as `"cache"` or
+    /// `"plugin:X"`, so heap usage can be attributed by subsystem rather
+    /// than by type. Replaces any earlier tag.
+    pub fn set_tag(&self, obj_id: &ObjectId, tag: &str) -> GCResult<()> {
+        self.collector.write().set_tag(obj_id, Some(tag))
+    }
+
+    pub fn clear_tag(&self, obj_id: &ObjectId) -> GCResult<()> {
+        self.collector.write().set_tag(obj_id, None)
+    }
+
+    pub fn tag_stats(&self) -> BTreeMap<String, TagStats> {
+        self.collector.read().tag_stats()
+    }
+
+    pub fn total_heap_bytes(&self) -> usize {
+        self.collector.read().total_heap_bytes()
+    }
+
+    pub fn is_tracked(&self, obj_id: &ObjectId) -> bool {
+        let collector = self.collector.read();
+        collector.is_tracked(obj_id)
+    }
+
+    /// Whether `obj_id` still names a live object. It stops being valid once
+    /// the last handle to the object is dropped, and stays invalid after its
+    /// slot is reused, so a stale id is never mistaken for the new object.
+    pub fn is_valid(&self, obj_id: &ObjectId) -> bool {
+        obj_id.is_live()
+    }
+
+    pub fn get_generation_count(&self, generation: usize) -> Option<usize> {
+        if generation >= 3 {
+            return None;
+        }
+
+        let collector = self.collector.read();
+        collector
+            .generation_manager
+            .get_generation(generation)
+            .map(|g| g.count)
+    }
+
+    /// The generation `obj_id` is in; `None` if it isn't tracked or is
+    /// uncollectable.
+    pub fn generation_of(&self, obj_id: &ObjectId) -> Option<usize> {
+        self.collector.read().generation_of(obj_id)
+    }
+
+    /// Move `obj_id` one generation older, so young collections stop
+    /// counting it. Returns its new generation.
+    pub fn promote(&self, obj_id: &ObjectId) -> GCResult<usize> {
+        self.collector.write().promote(obj_id)
+    }
+
+    /// Move `obj_id` one generation younger. Returns its new generation.
+    pub fn demote(&self, obj_id: &ObjectId) -> GCResult<usize> {
+        self.collector.write().demote(obj_id)
+    }
+
+    /// Move every tracked object into the permanent generation, which no
+    /// collection looks at, as Python's `gc.freeze()` does. Returns how many
+    /// objects were moved.
+    pub fn freeze(&self) -> GCResult<usize> {
+        self.collector.write().freeze()
+    }
+
+    /// Move the permanent generation back into the oldest generation, as
+    /// `gc.unfreeze()` does. Returns how many objects were moved.
+    pub fn unfreeze(&self) -> GCResult<usize> {
+        self.collector.write().unfreeze()
+    }
+
+    /// Move one object into the permanent generation.
+    pub fn move_to_permanent(&self, obj_id: &ObjectId) -> GCResult<()> {
+        self.collector.write().move_to_permanent(obj_id)
+    }
+
+    pub fn get_freeze_count(&self) -> usize {
+        self.collector.read().freeze_count()
+    }
+
+    pub fn set_threshold(&self, generation: usize, threshold: usize) -> GCResult<()> {
+        let mut collector = self.collector.write();
+        let generation_ref = collector
+            .generation_manager
+            .get_generation_mut(generation)
+            .ok_or(GCError::InvalidGeneration(generation))?;
+
+        generation_ref.threshold = threshold;
+        Ok(())
+    }
+
+    /// Objects whose shallow size reaches `threshold` bytes when tracked go
+    /// to the large object space, which only full collections look at. 0
+    /// turns the space off. Objects already tracked stay where they are.
+    pub fn set_large_object_threshold(&self, threshold: usize) {
+        let mut collector = self.collector.write();
+        collector.generation_manager.large_objects.threshold = threshold;
+    }
+
+    pub fn get_large_object_threshold(&self) -> usize {
+        let collector = self.collector.read();
+        collector.generation_manager.large_objects.threshold
+    }
+
+    pub fn get_threshold(&self, generation: usize) -> Option<usize> {
+        let collector = self.collector.read();
+        collector
+            .generation_manager
+            .get_generation(generation)
+            .map(|g| g.threshold)
+    }
+
+    /// Set thresholds the way Python's `gc.set_threshold` does: `threshold0`
+    /// always, the others only when given. All three change under one lock.
+    pub fn set_thresholds(
+        &self,
+        threshold0: usize,
+        threshold1: Option<usize>,
+        threshold2: Option<usize>,
+    ) {
+        let mut collector = self.collector.write();
+        let generations = &mut collector.generation_manager.generations;
+        for (generation, threshold) in
+            generations
+                .iter_mut()
+                .zip([Some(threshold0), threshold1, threshold2])
+        {
+            if let Some(threshold) = threshold {
+                generation.threshold = threshold;
+            }
+        }
+    }
+
+    pub fn get_thresholds(&self) -> [usize; 3] {
+        let collector = self.collector.read();
+        let generations = &collector.generation_manager.generations;
+        [0, 1, 2].map(|generation| generations[generation].threshold)
+    }
+
+    pub fn collect_if_needed(&self) -> GCResult<usize> {
+        if !self.is_enabled() || !self.is_automatic_collection_enabled() {
+            return Ok(0);
+        }
+
+        if let Some(generation) = self.needs_collection() {
+            return self.collect_generation(generation);
+        }
+
+        self.poll_rss()
+    }
+
+    pub fn detect_probable_leaks(&self, min_generations_survived: usize) -> Vec<LeakReport> {
+        let collector = self.collector.read();
+        collector.detect_probable_leaks(min_generations_survived)
+    }
+
+    pub fn explain_object(&self, obj_id: &ObjectId) -> Option<ObjectExplanation> {
+        let collector = self.collector.read();
+        collector.explain_object(obj_id)
+    }
+
+    pub fn verify_heap(&self) -> Vec<IntegrityViolation> {
+        let collector = self.collector.read();
+        collector.verify_heap()
+    }
+
+    /// Objects whose refcount doesn't match their referrers plus declared
+    /// external references. Runs the audit now, whatever the debug flags.
+    pub fn audit_refcounts(&self) -> Vec<RefcountDiscrepancy> {
+        self.collector.read().audit_refcounts()
+    }
+
+    /// What the audit found at the start of the last collection run with
+    /// [`DEBUG_AUDIT_REFS`] set.
+    pub fn last_refcount_audit(&self) -> Vec<RefcountDiscrepancy> {
+        self.collector.read().refcount_discrepancies.clone()
+    }
+
+    /// Old-to-young references missed by the last minor collection run with
+    /// [`DEBUG_VERIFY_BARRIERS`] set, found by scanning the heap after it.
+    pub fn last_barrier_violations(&self) -> Vec<BarrierViolation> {
+        self.collector.read().barrier_violations.clone()
+    }
+
+    /// Declare a reference to `obj_id` held from outside the object graph,
+    /// such as by a C global, so the refcount audit accounts for it.
+    pub fn add_external_ref(&self, obj_id: &ObjectId) -> GCResult<()> {
+        let mut collector = self.collector.write();
+        let obj = collector
+            .find_object_mut(obj_id)
+            .ok_or(GCError::NotTracked { id: *obj_id })?;
+        obj.add_external_ref();
+        Ok(())
+    }
+
+    pub fn release_external_ref(&self, obj_id: &ObjectId) -> GCResult<()> {
+        let mut collector = self.collector.write();
+        let obj = collector
+            .find_object_mut(obj_id)
+            .ok_or(GCError::NotTracked { id: *obj_id })?;
+        if !obj.release_external_ref() {
+            return Err(GCError::internal(
+                "release_external_ref",
+                "object has no declared external references",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Tracked objects, without cloning the collector's map.
+    pub fn iter_objects(&self) -> ObjectView<'_> {
+        ObjectView {
+            collector: self.collector.read(),
+            scope: ViewScope::Tracked,
+        }
+    }
+
+    /// Tracked objects in `generation`, or `None` for an unknown generation.
+    pub fn iter_generation(&self, generation: usize) -> Option<ObjectView<'_>> {
+        (generation < 3).then(|| ObjectView {
+            collector: self.collector.read(),
+            scope: ViewScope::Generation(generation),
+        })
+    }
+
+    /// Tracked and uncollectable objects tagged `tag`.
+    pub fn iter_tagged(&self, tag: &str) -> ObjectView<'_> {
+        ObjectView {
+            collector: self.collector.read(),
+            scope: ViewScope::Tag(tag.to_string()),
+        }
+    }
+
+    /// Objects in the permanent generation.
+    pub fn iter_permanent(&self) -> ObjectView<'_> {
+        ObjectView {
+            collector: self.collector.read(),
+            scope: ViewScope::Permanent,
+        }
+    }
+
+    pub fn iter_uncollectable(&self) -> ObjectView<'_> {
+        ObjectView {
+            collector: self.collector.read(),
+            scope: ViewScope::Uncollectable,
+        }
+    }
+
+    pub fn get_uncollectable(&self) -> Vec<PyObject> {
+        let collector = self.collector.read();
+        collector.uncollectable.clone()
+    }
+
+    pub fn clear_uncollectable(&self) {
+        self.collector.write().clear_uncollectable();
+    }
+
+    /// Garbage kept by collections run with [`DEBUG_SAVEALL`], oldest first.
+    /// Like `gc.garbage`, it grows until cleared.
+    pub fn saved_garbage(&self) -> Vec<SavedGarbage> {
+        let collector = self.collector.read();
+        collector
+            .saved_garbage
+            .iter()
+            .map(|obj| SavedGarbage {
+                object: obj.clone(),
+                type_name: obj.data().type_name(),
+            })
+            .collect()
+    }
+
+    pub fn clear_saved_garbage(&self) {
+        self.collector.write().clear_saved_garbage();
+    }
+
+    /// Choose what full collections do with unreachable objects that have
+    /// finalizers. The default, `ReportOnly`, leaves them uncollectable.
+    pub fn set_uncollectable_policy(&self, policy: UncollectablePolicy) {
+        self.collector.write().set_uncollectable_policy(policy);
+    }
+
+    pub fn get_uncollectable_policy(&self) -> UncollectablePolicy {
+        self.collector.read().uncollectable_policy.clone()
+    }
+
+    /// Choose when survivors of young collections are promoted. The
+    /// default promotes every survivor; [`PromotionPolicy::Aged`] keeps the
+    /// young ones back so short-lived objects don't pile up in generation 2.
+    /// The promotion stats start over.
+    pub fn set_promotion_policy(&self, policy: PromotionPolicy) {
+        self.collector.write().set_promotion_policy(policy);
+    }
+
+    pub fn promotion_policy(&self) -> PromotionPolicy {
+        self.collector.read().promotion_policy
+    }
+
+    /// What the promotion policy has done since it was set.
+    pub fn promotion_stats(&self) -> PromotionStats {
+        self.collector.read().promotion_stats
+    }
+
+    #[cfg(feature = "snapshot")]
+    pub fn snapshot(&self) -> crate::snapshot::HeapSnapshot {
+        let collector = self.collector.read();
+        crate::snapshot::HeapSnapshot::capture(&collector)
+    }
+
+    #[cfg(feature = "snapshot")]
+    pub fn save_snapshot(&self, path: impl AsRef<std::path::Path>) -> GCResult<()> {
+        self.snapshot().save(path)
+    }
+
+    #[cfg(feature = "snapshot")]
+    pub fn load_snapshot(
+        path: impl AsRef<std::path::Path>,
+    ) -> GCResult<crate::snapshot::HeapSnapshot> {
+        crate::snapshot::HeapSnapshot::load(path)
+    }
+
+    /// Serve collect/stats/snapshot/threshold requests on a Unix socket at
+    /// `path` until the returned server is shut down or dropped.
+    #[cfg(all(unix, feature = "control"))]
+    pub fn serve_control_socket(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> GCResult<crate::control::ControlServer> {
+        crate::control::ControlServer::bind(self.collector.clone(), path)
+    }
+
+    /// Restore the collector in a forked child process. Tracked objects are kept.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called in the child right after `fork()`, before any other
+    /// thread is started, so no lock guard held at fork time is still in use.
+    pub unsafe fn after_fork(&self) {
+        unsafe { reset_lock_after_fork(&self.collector) };
+        let mut collector = self.collector.write();
+        collector.collecting_objects.clear();
+        collector.generation_manager.reset_collection();
+    }
+}
+
+/// Release a lock that may have been held by a thread that no longer exists
+/// in a forked child.
+///
+/// # Safety
+///
+/// No live guard for `lock` may exist in the calling process.
+pub(crate) unsafe fn reset_lock_after_fork<T: ?Sized>(lock: &RwLock<T>) {
+    while lock.is_locked_exclusive() {
+        unsafe { lock.force_unlock_write() };
+    }
+    while lock.is_locked() {
+        unsafe { lock.force_unlock_read() };
+    }
+}
+
+impl Default for GarbageCollector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for GarbageCollector {
+    /// Discard what this thread still buffers for the collector. Other
+    /// threads' buffers go the next time they touch theirs.
+    fn drop(&mut self) {
+        let _ = YOUNG_BUFFERS.try_with(|buffers| buffers.borrow_mut().remove(&self.id));
+        let _ = SATB_BUFFER.try_with(|buffer| buffer.borrow_mut().retain(|&(id, _)| id != self.id));
+    }
+}
+
+pub mod global {
+    use super::*;
+    use std::sync::OnceLock;
+
+    static GC: OnceLock<GarbageCollector> = OnceLock::new();
+
+    pub fn get_gc() -> &'static GarbageCollector {
+        GC.get_or_init(GarbageCollector::new)
+    }
+
+    pub fn track(obj: PyObject) -> GCResult<()> {
+        get_gc().track(obj)
+    }
+
+    pub fn untrack(obj_id: &ObjectId) -> GCResult<()> {
+        get_gc().untrack(obj_id)
+    }
+
+    pub fn collect() -> GCResult<usize> {
+        get_gc().collect()
+    }
+
+    pub fn get_stats() -> crate::GCStats {
+        get_gc().get_stats()
+    }
+}
+
+/// Named collectors for subsystems that each want their own heap, such as
+/// plugin sandboxes. Separate from the collector in [`global`].
+pub mod registry {
+    use super::*;
+    use std::time::Duration;
+
+    static HEAPS: RwLock<BTreeMap<String, Arc<GarbageCollector>>> = RwLock::new(BTreeMap::new());
+
+    /// Totals over every heap in the registry.
+    #[derive(Debug, Clone, Default, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize))]
+    pub struct RegistryStats {
+        pub heaps: usize,
+        pub collections: usize,
+        pub collected: usize,
+        pub uncollectable: usize,
+        pub total_tracked: usize,
+        pub tracked_bytes: usize,
+        pub generation_counts: [usize; 3],
+        /// Longest last pause of any heap.
+        pub max_last_pause: Duration,
+    }
+
+    /// The heap called `name`, created on first use.
+    pub fn get_or_create(name: &str) -> Arc<GarbageCollector> {
+        if let Some(heap) = HEAPS.read().get(name) {
+            return heap.clone();
+        }
+        HEAPS
+            .write()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(GarbageCollector::new()))
+            .clone()
+    }
+
+    pub fn get(name: &str) -> Option<Arc<GarbageCollector>> {
+        HEAPS.read().get(name).cloned()
+    }
+
+    /// Take a heap out of the registry. Holders of its `Arc` keep it alive.
+    pub fn remove(name: &str) -> Option<Arc<GarbageCollector>> {
+        HEAPS.write().remove(name)
+    }
+
+    /// Every heap, in name order.
+    pub fn enumerate() -> Vec<(String, Arc<GarbageCollector>)> {
+        HEAPS
+            .read()
+            .iter()
+            .map(|(name, heap)| (name.clone(), heap.clone()))
+            .collect()
+    }
+
+    /// Stats for every heap, in name order.
+    pub fn stats() -> Vec<(String, crate::GCStats)> {
+        enumerate()
+            .into_iter()
+            .map(|(name, heap)| (name, heap.get_stats()))
+            .collect()
+    }
+
+    pub fn aggregate_stats() -> RegistryStats {
+        let mut total = RegistryStats::default();
+        for (_, heap) in enumerate() {
+            let stats = heap.get_stats();
+            total.heaps += 1;
+            total.collections += stats.collections;
+            total.collected += stats.collected;
+            total.uncollectable += stats.uncollectable;
+            total.total_tracked += stats.total_tracked;
+            total.tracked_bytes += heap.tracked_bytes();
+            for (sum, count) in total
+                .generation_counts
+                .iter_mut()
+                .zip(stats.generation_counts)
+            {
+                *sum += count;
+            }
+            total.max_last_pause = total.max_last_pause.max(stats.last_pause);
+        }
+        total
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{CustomData, PyDict};
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_gc_creation() {
+        let gc = GarbageCollector::new();
+        assert!(gc.is_enabled());
+        assert_eq!(gc.get_count(), 0);
+    }
+
+    #[test]
+    fn test_object_tracking() {
+        let gc = GarbageCollector::new();
+
+        let obj = PyObject::new("test".to_string(), ObjectData::Integer(42));
+        let obj_id = obj.id();
+
+        assert!(gc.track(obj).is_ok());
+        assert_eq!(gc.get_count(), 1);
+
+        assert!(gc.untrack(&obj_id).is_ok());
+        assert_eq!(gc.get_count(), 0);
+    }
+
+    #[test]
+    fn test_after_fork_releases_stale_lock() {
+        let gc = GarbageCollector::new();
+        let obj = PyObject::new("test".to_string(), ObjectData::Integer(42));
+        assert!(gc.track(obj).is_ok());
+
+        std::mem::forget(gc.collector.write());
+        unsafe { gc.after_fork() };
+
+        assert_eq!(gc.get_count(), 1);
+        assert!(gc.collect().is_ok());
+    }
+
+    #[test]
+    fn test_stale_ids_are_invalid_after_slot_reuse() {
+        let gc = GarbageCollector::new();
+        let obj = PyObject::new("old".to_string(), ObjectData::List(Vec::new()));
+        let stale = obj.id();
+        gc.track(obj.clone()).unwrap();
+        assert!(gc.is_valid(&stale));
+
+        obj.set_refcount(0);
+        drop(obj);
+        assert_eq!(gc.collect().unwrap(), 1);
+        assert!(!gc.is_valid(&stale));
+
+        // Whoever gets the slot next gets it under a newer generation.
+        let next = ObjectId::from_raw(stale.index, stale.generation + 1);
+        let reused = PyObject::with_id(next, "new".to_string(), ObjectData::None);
+        gc.track(reused).unwrap();
+        assert!(gc.is_tracked(&next) && !gc.is_tracked(&stale));
+        assert!(!gc.is_valid(&stale));
+    }
+
+    #[test]
+    fn test_detect_probable_leaks() {
+        let gc = GarbageCollector::new();
+
+        let a = PyObject::new_with_finalizer("A".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new_with_finalizer("B".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        let external = PyObject::new_with_finalizer("C".to_string(), ObjectData::None);
+        let (a_id, b_id) = (a.id(), b.id());
+
+        gc.track(a).unwrap();
+        gc.track(b).unwrap();
+        gc.track(external).unwrap();
+
+        gc.collect().unwrap();
+        assert!(gc.detect_probable_leaks(2).is_empty());
+
+        gc.collect().unwrap();
+        let leaks = gc.detect_probable_leaks(2);
+        assert_eq!(leaks.len(), 2);
+        let leak = &leaks[0];
+        assert_eq!(leak.object_id, a_id);
+        assert_eq!(leak.collections_survived, 2);
+        assert_eq!(leak.internal_refs, 1);
+        assert_eq!(leak.retaining_path, vec![a_id, b_id, a_id]);
+        assert_eq!(
+            leak.retaining_labels,
+            vec![Some("[0]".to_string()), Some("[0]".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_explanations_name_the_fields_on_the_retaining_path() {
+        let gc = GarbageCollector::new();
+        let key = PyObject::new("Key".to_string(), ObjectData::Custom(CustomData::new(())));
+        let value = PyObject::new("Value".to_string(), ObjectData::Custom(CustomData::new(())));
+        let table = PyObject::new("dict".to_string(), ObjectData::Dict(PyDict::new()));
+        table
+            .dict_set(
+                PyObject::new("str".to_string(), ObjectData::String("k".to_string())),
+                key.clone(),
+            )
+            .unwrap();
+        let module = PyObject::new("list".to_string(), ObjectData::List(vec![table.clone()]));
+        for obj in [&module, &table, &key, &value] {
+            gc.track(obj.clone()).unwrap();
+        }
+        gc.add_ephemeron(&key.id(), &value.id()).unwrap();
+        value.dec_ref();
+
+        let explanation = gc.explain_object(&value.id()).unwrap();
+        assert_eq!(
+            explanation.retaining_path,
+            vec![module.id(), table.id(), key.id(), value.id()]
+        );
+        let labels: Vec<Option<&str>> = explanation
+            .retaining_labels
+            .iter()
+            .map(Option::as_deref)
+            .collect();
+        assert_eq!(labels, vec![Some("[0]"), Some("['k']"), Some("ephemeron")]);
+    }
+
+    #[test]
+    fn test_survivors_move_through_generations() {
+        let gc = GarbageCollector::new();
+        let obj = PyObject::new("kept".to_string(), ObjectData::Custom(CustomData::new(())));
+        obj.set_refcount(1);
+        gc.track(obj.clone()).unwrap();
+        assert_eq!(gc.generation_of(&obj.id()), Some(0));
+
+        gc.collect_generation(0).unwrap();
+        assert_eq!(gc.generation_of(&obj.id()), Some(1));
+        let young = PyObject::new("young".to_string(), ObjectData::Custom(CustomData::new(())));
+        young.set_refcount(1);
+        gc.track(young.clone()).unwrap();
+        gc.collect_generation(1).unwrap();
+        assert_eq!(gc.generation_of(&obj.id()), Some(2));
+        assert_eq!(gc.generation_of(&young.id()), Some(2));
+        gc.collect().unwrap();
+        assert_eq!(gc.generation_of(&obj.id()), Some(2));
+
+        assert_eq!(gc.demote(&obj.id()).unwrap(), 1);
+        assert_eq!(gc.get_generation_count(1), Some(1));
+        assert_eq!(gc.demote(&obj.id()).unwrap(), 0);
+        assert_eq!(gc.demote(&obj.id()).unwrap(), 0);
+        assert_eq!(gc.promote(&obj.id()).unwrap(), 1);
+        gc.untrack(&obj.id()).unwrap();
+        assert_eq!(gc.get_generation_count(1), Some(0));
+        assert_eq!(gc.generation_of(&obj.id()), None);
+        assert!(matches!(
+            gc.promote(&obj.id()),
+            Err(GCError::NotTracked { .. })
+        ));
+    }
+
+    #[test]
+    fn test_exceeded_quota_offers_oldest_objects_for_eviction() {
+        let gc = Arc::new(GarbageCollector::new());
+        let entry = |tag: &str| {
+            let obj = PyObject::new("Entry".to_string(), ObjectData::Custom(CustomData::new(())));
+            gc.track(obj.clone()).unwrap();
+            gc.set_tag(&obj.id(), tag).unwrap();
+            obj
+        };
+        let oldest = entry("cache");
+        gc.collect().unwrap();
+        let older = entry("cache");
+        gc.collect().unwrap();
+        let newer = entry("cache");
+        let other = entry("session");
+
+        let offered = Arc::new(Mutex::new(Vec::new()));
+        let (hook_gc, hook_offered) = (Arc::downgrade(&gc), offered.clone());
+        gc.on_quota_exceeded(Some(Arc::new(move |key, candidates| {
+            hook_offered.lock().push((key.clone(), candidates.to_vec()));
+            let gc = hook_gc.upgrade().unwrap();
+            for id in candidates {
+                gc.untrack(id).unwrap();
+            }
+            gc.collect().unwrap();
+        })));
+        let cache = QuotaKey::Tag("cache".to_string());
+        let quota = Quota {
+            max_objects: Some(1),
+            max_bytes: None,
+        };
+        gc.set_quota(cache.clone(), Some(quota));
+        assert_eq!(gc.get_quota(&cache), Some(quota));
+
+        gc.collect().unwrap();
+        assert_eq!(
+            *offered.lock(),
+            vec![(cache.clone(), vec![oldest.id(), older.id()])]
+        );
+        assert!(!gc.is_tracked(&oldest.id()) && !gc.is_tracked(&older.id()));
+        assert!(gc.is_tracked(&newer.id()) && gc.is_tracked(&other.id()));
+        assert_eq!(gc.enforce_quotas(), 0);
+
+        let by_type = QuotaKey::Type("Entry".to_string());
+        gc.set_quota(cache.clone(), None);
+        gc.set_quota(
+            by_type.clone(),
+            Some(Quota {
+                max_objects: None,
+                max_bytes: Some(newer.shallow_size()),
+            }),
+        );
+        offered.lock().clear();
+        assert_eq!(gc.enforce_quotas(), 1);
+        assert_eq!(offered.lock().len(), 1);
+        assert_eq!(offered.lock()[0].0, by_type);
+        assert_eq!(offered.lock()[0].1.len(), 1);
+        assert_eq!(gc.tag_stats().values().map(|s| s.objects).sum::<usize>(), 1);
+    }
+
+    #[test]
+    fn test_permanent_generation_is_left_out_of_collections() {
+        let gc = GarbageCollector::new();
+        let a = PyObject::new("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        gc.track(a.clone()).unwrap();
+        gc.track(b.clone()).unwrap();
+
+        assert_eq!(gc.freeze().unwrap(), 2);
+        assert_eq!(gc.get_generation_count(0), Some(0));
+        assert_eq!(gc.generation_of(&a.id()), None);
+        assert_eq!(gc.collect().unwrap(), 0);
+        assert!(gc.is_tracked(&a.id()));
+        let stats = gc.get_stats();
+        assert_eq!((stats.permanent_count, stats.total_tracked), (2, 2));
+        assert_eq!(stats.permanent_bytes, a.shallow_size() + b.shallow_size());
+        assert_eq!(gc.iter_permanent().iter().count(), 2);
+
+        let late = PyObject::new("late".to_string(), ObjectData::Custom(CustomData::new(())));
+        late.set_refcount(1);
+        gc.track(late.clone()).unwrap();
+        gc.move_to_permanent(&late.id()).unwrap();
+        assert_eq!(gc.get_freeze_count(), 3);
+        assert_eq!(gc.freeze().unwrap(), 0);
+        gc.untrack(&late.id()).unwrap();
+        assert_eq!(gc.get_freeze_count(), 2);
+
+        assert_eq!(gc.unfreeze().unwrap(), 2);
+        assert_eq!(gc.get_generation_count(2), Some(2));
+        assert_eq!(gc.generation_of(&a.id()), Some(2));
+        assert_eq!(gc.collect().unwrap(), 2);
+        assert_eq!(gc.get_stats().permanent_count, 0);
+    }
+
+    #[test]
+    fn test_tag_stats_group_objects_by_tag() {
+        let gc = GarbageCollector::new();
+        let tagged = |name: &str, tag: &str| {
+            let obj = PyObject::new(name.to_string(), ObjectData::Custom(CustomData::new(())));
+            gc.track(obj.clone()).unwrap();
+            gc.set_tag(&obj.id(), tag).unwrap();
+            obj
+        };
+        let entry = tagged("Entry", "cache");
+        let survivor = tagged("Entry", "cache");
+        let session = tagged("Entry", "session");
+        let untagged = PyObject::new("Entry".to_string(), ObjectData::Custom(CustomData::new(())));
+        gc.track(untagged.clone()).unwrap();
+        gc.collect().unwrap();
+        let late = tagged("Blob", "cache");
+
+        let stats = gc.tag_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(
+            stats["cache"],
+            TagStats {
+                objects: 3,
+                bytes: entry.shallow_size() + survivor.shallow_size() + late.shallow_size(),
+                survivors: 2,
+            }
+        );
+        assert_eq!(stats["session"].objects, 1);
+        let mut cache: Vec<ObjectId> = gc.iter_tagged("cache").iter().map(PyObject::id).collect();
+        cache.sort_by_key(ObjectId::as_u64);
+        let mut expected = vec![entry.id(), survivor.id(), late.id()];
+        expected.sort_by_key(ObjectId::as_u64);
+        assert_eq!(cache, expected);
+
+        gc.clear_tag(&session.id()).unwrap();
+        assert!(!gc.tag_stats().contains_key("session"));
+        gc.untrack(&late.id()).unwrap();
+        assert!(matches!(
+            gc.set_tag(&late.id(), "cache"),
+            Err(GCError::NotTracked { .. })
+        ));
+        assert_eq!(gc.tag_stats()["cache"].objects, 2);
+    }
+
+    #[test]
+    fn test_reentrant_collection_is_refused() {
+        let gc = GarbageCollector::new();
+        let garbage = PyObject::new("garbage".to_string(), ObjectData::List(Vec::new()));
+        garbage.set_refcount(0);
+        gc.track(garbage).unwrap();
+
+        let mut collector = gc.collector.write();
+        let step = collector.begin_collection(2).unwrap();
+        // Nothing is flagged as collecting before Mark runs.
+        assert!(collector.collecting_objects.is_empty());
+        assert!(matches!(
+            collector.begin_collection(0),
+            Err(GCError::CollectionInProgress)
+        ));
+        assert!(matches!(
+            collector.collect(),
+            Err(GCError::CollectionInProgress)
+        ));
+        drop(step);
+
+        assert!(!collector.is_collecting());
+        assert_eq!(collector.collect().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_collects_do_not_interleave() {
+        let gc = Arc::new(GarbageCollector::new());
+        let mut step = gc.collector.write().begin_collection(2).unwrap();
+        let refused = |gc: &Arc<GarbageCollector>| {
+            let gc = Arc::clone(gc);
+            std::thread::spawn(move || gc.collect())
+        };
+        for worker in [refused(&gc), refused(&gc)] {
+            assert!(matches!(
+                worker.join().unwrap(),
+                Err(GCError::CollectionInProgress)
+            ));
+        }
+        while !step.is_finished() {
+            gc.collector.write().step_collection(&mut step).unwrap();
+        }
+
+        let workers: Vec<_> = (0..4)
+            .map(|i| {
+                let gc = Arc::clone(&gc);
+                std::thread::spawn(move || {
+                    let mut collected = 0;
+                    for j in 0..50 {
+                        let a = PyObject::new(format!("a{i}-{j}"), ObjectData::List(Vec::new()));
+                        let b =
+                            PyObject::new(format!("b{i}-{j}"), ObjectData::List(vec![a.clone()]));
+                        a.set_data(ObjectData::List(vec![b.clone()]));
+                        gc.track_bulk(vec![a, b]).unwrap();
+                        collected += gc.collect().unwrap();
+                    }
+                    collected
+                })
+            })
+            .collect();
+        let collected: usize = workers.into_iter().map(|w| w.join().unwrap()).sum();
+
+        assert_eq!(collected, 4 * 50 * 2);
+        assert_eq!(gc.get_count(), 0);
+        assert!(!gc.collector.read().is_collecting());
+    }
+
+    #[test]
+    fn test_published_stats_do_not_wait_for_collection() {
+        let gc = GarbageCollector::new();
+        gc.track(PyObject::new("kept".to_string(), ObjectData::None))
+            .unwrap();
+        gc.collect().unwrap();
+        let before = gc.published_stats();
+        assert_eq!(before.epoch, 1);
+
+        let garbage = PyObject::new("garbage".to_string(), ObjectData::List(Vec::new()));
+        garbage.set_refcount(0);
+        gc.track(garbage).unwrap();
+        // Reading with the lock free publishes nothing.
+        assert_eq!(gc.published_stats().stats.total_tracked, 2);
+        assert_eq!(gc.published_stats().epoch, 1);
+
+        let mut collector = gc.collector.write();
+        let mut step = collector.begin_collection(2).unwrap();
+        collector.step_collection(&mut step).unwrap();
+        let during = gc.published_stats();
+        assert!(during.collection_in_progress);
+        assert_eq!(during.epoch, 1);
+        assert_eq!(during.stats.total_tracked, 1);
+        while !step.is_finished() {
+            collector.step_collection(&mut step).unwrap();
+        }
+        drop(collector);
+
+        let after = gc.published_stats();
+        assert!(!after.collection_in_progress);
+        assert_eq!(after.epoch, 2);
+        assert_eq!(after.stats.collected, 1);
+        assert_eq!(after.stats.total_tracked, 1);
+    }
+
+    #[test]
+    fn test_collect_with_report_counts_each_stage() {
+        use crate::clock::ManualClock;
+
+        let gc = GarbageCollector::new();
+        gc.collector.write().set_clock(Arc::new(ManualClock::new()));
+        let finalized = Arc::new(AtomicUsize::new(0));
+        let counter = finalized.clone();
+        gc.set_finalizer(Some(Arc::new(move |ctx: &FinalizerContext<'_>| {
+            assert!(!ctx.object().children().is_empty());
+            counter.fetch_add(1, Ordering::Relaxed);
+        })));
+
+        let a = PyObject::new("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        let root = PyObject::new("root".to_string(), ObjectData::Custom(CustomData::new(())));
+        let resource = PyObject::new_with_finalizer("res".to_string(), ObjectData::None);
+        for obj in [&a, &b, &root, &resource] {
+            gc.track(obj.clone()).unwrap();
+        }
+        // Gaining a finalizer after tracking leaves `a` collectable.
+        a.set_finalizer(true);
+
+        let report = gc.collect_with_report().unwrap();
+        assert_eq!(
+            report,
+            CollectionReport {
+                generation: 2,
+                scanned: 3,
+                unreachable_found: 2,
+                collected: 2,
+                uncollectable: 1,
+                finalizers_run: 1,
+                duration: Duration::ZERO,
+                promoted: 1,
+                pre_collect_time: Duration::ZERO,
+                vetoed: false,
+            }
+        );
+        assert_eq!(finalized.load(Ordering::Relaxed), 1);
+        assert_eq!(gc.collect().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_uncollectable_policy_decides_per_cycle() {
+        use crate::collector::UncollectableAction;
+
+        let gc = GarbageCollector::new();
+        let finalized = Arc::new(AtomicUsize::new(0));
+        let counter = finalized.clone();
+        gc.set_finalizer(Some(Arc::new(move |_ctx: &FinalizerContext<'_>| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        })));
+        let cycle = |name: &str| {
+            let a = PyObject::new_with_finalizer(format!("{name}-a"), ObjectData::List(Vec::new()));
+            let b = PyObject::new_with_finalizer(
+                format!("{name}-b"),
+                ObjectData::List(vec![a.clone()]),
+            );
+            a.set_data(ObjectData::List(vec![b.clone()]));
+            gc.track(a.clone()).unwrap();
+            gc.track(b).unwrap();
+            a
+        };
+
+        let kept = cycle("kept");
+        assert_eq!(gc.collect().unwrap(), 0);
+        assert_eq!(gc.get_uncollectable().len(), 2);
+
+        let leaked = cycle("leaked");
+        gc.set_uncollectable_policy(UncollectablePolicy::Callback(Arc::new(|members| {
+            assert_eq!(members.len(), 2);
+            if members[0].name().starts_with("kept") {
+                UncollectableAction::Keep
+            } else {
+                UncollectableAction::Leak
+            }
+        })));
+        assert_eq!(gc.collect().unwrap(), 0);
+        assert_eq!(gc.get_uncollectable().len(), 2);
+        assert!(!gc.is_tracked(&leaked.id()));
+        assert_eq!(leaked.children().len(), 1);
+
+        gc.set_uncollectable_policy(UncollectablePolicy::BreakCyclesAnyway);
+        let report = gc.collect_with_report().unwrap();
+        assert_eq!((report.collected, report.finalizers_run), (2, 2));
+        assert_eq!(finalized.load(Ordering::Relaxed), 2);
+        assert!(gc.get_uncollectable().is_empty());
+        assert!(kept.children().is_empty());
+        assert!(gc.verify_heap().is_empty());
+    }
+
+    #[test]
+    fn test_tracking_from_collection_callback_is_deferred() {
+        use crate::collector::UncollectableAction;
+
+        let gc = Arc::new(GarbageCollector::new());
+        let a = PyObject::new_with_finalizer("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new_with_finalizer("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        let bystander = PyObject::new(
+            "bystander".to_string(),
+            ObjectData::Custom(CustomData::new(())),
+        );
+        bystander.set_refcount(1);
+        for obj in [a, b, bystander.clone()] {
+            gc.track(obj).unwrap();
+        }
+
+        let fresh = PyObject::new("fresh".to_string(), ObjectData::Custom(CustomData::new(())));
+        fresh.set_refcount(1);
+        let weak = Arc::downgrade(&gc);
+        let (added, removed) = (fresh.clone(), bystander.id());
+        gc.set_uncollectable_policy(UncollectablePolicy::Callback(Arc::new(move |_| {
+            let gc = weak.upgrade().unwrap();
+            gc.track(added.clone()).unwrap();
+            gc.untrack(&removed).unwrap();
+            gc.untrack(&removed).unwrap();
+            UncollectableAction::Keep
+        })));
+
+        assert_eq!(gc.collect().unwrap(), 0);
+        assert!(gc.is_tracked(&fresh.id()));
+        assert!(!gc.is_tracked(&bystander.id()));
+        assert_eq!(
+            gc.deferred_stats(),
+            DeferredStats {
+                deferred: 3,
+                applied: 2,
+                failed: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_break_cycle_frees_uncollectable_cycle() {
+        let gc = GarbageCollector::new();
+        let a = PyObject::new_with_finalizer("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new_with_finalizer("b".to_string(), ObjectData::List(vec![a.clone()]));
+        let c = PyObject::new_with_finalizer("c".to_string(), ObjectData::List(vec![b.clone()]));
+        a.set_data(ObjectData::List(vec![c.clone()]));
+        for obj in [&a, &b, &c] {
+            gc.track(obj.clone()).unwrap();
+        }
+        assert_eq!(gc.collect().unwrap(), 0);
+        assert_eq!(gc.get_uncollectable().len(), 3);
+
+        // Breaking a -> c leaves c unreferenced, and freeing it frees b and
+        // then a in turn.
+        assert_eq!(gc.break_cycle(&a.id()).unwrap(), 3);
+        assert!(a.children().is_empty());
+        assert!(gc.get_uncollectable().is_empty());
+        assert!(gc.verify_heap().is_empty());
+        assert!(matches!(
+            gc.break_cycle(&a.id()),
+            Err(GCError::NotTracked { .. })
+        ));
+
+        let custom = PyObject::new(
+            "custom".to_string(),
+            ObjectData::Custom(CustomData::new(())),
+        );
+        gc.track(custom.clone()).unwrap();
+        assert!(matches!(
+            gc.break_cycle(&custom.id()),
+            Err(GCError::Internal { .. })
+        ));
+    }
+
+    #[test]
+    fn test_decref_reclaims_long_chain() {
+        use std::sync::atomic::AtomicUsize;
+
+        let gc = GarbageCollector::new();
+        let finalized = Arc::new(AtomicUsize::new(0));
+        let counter = finalized.clone();
+        gc.set_finalizer(Some(Arc::new(move |_ctx: &FinalizerContext<'_>| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        })));
+
+        let objects: Vec<PyObject> = (0..100_000)
+            .map(|i| {
+                let obj = PyObject::new("link".to_string(), ObjectData::List(Vec::new()));
+                obj.set_finalizer(i % 10 == 0);
+                obj
+            })
+            .collect();
+        for pair in objects.windows(2) {
+            pair[0].set_data(ObjectData::List(vec![pair[1].clone()]));
+        }
+        let (head, second) = (objects[0].id(), objects[1].id());
+        for obj in objects {
+            gc.track(obj).unwrap();
+        }
+
+        {
+            let mut collector = gc.collector.write();
+            collector.find_object_mut(&second).unwrap().inc_ref();
+        }
+        assert_eq!(gc.decref(&head).unwrap(), 1);
+        assert_eq!(gc.decref(&second).unwrap(), 99_999);
+
+        assert_eq!(gc.get_count(), 0);
+        assert!(gc.get_uncollectable().is_empty());
+        assert_eq!(finalized.load(Ordering::Relaxed), 10_000);
+        assert!(gc.verify_heap().is_empty());
+        assert!(matches!(gc.decref(&head), Err(GCError::NotTracked { .. })));
+    }
+
+    #[test]
+    fn test_containers_keep_contents_alive() {
+        #[derive(Debug)]
+        struct HoldsOne(ObjectId);
+
+        impl Trace for HoldsOne {
+            fn trace(&self, _data: &CustomData, visit: &mut dyn FnMut(ObjectId)) {
+                visit(self.0);
+            }
+        }
+
+        fn garbage(name: &str, data: ObjectData) -> PyObject {
+            let obj = PyObject::new(name.to_string(), data);
+            obj.set_refcount(0);
+            obj
+        }
+
+        let gc = GarbageCollector::new();
+        let key = garbage("str", ObjectData::String("key".to_string()));
+        let value = garbage("int", ObjectData::Integer(1));
+        let dict = garbage("dict", ObjectData::Dict(Default::default()));
+        dict.dict_set(key.clone(), value.clone()).unwrap();
+        let list = PyObject::new("list".to_string(), ObjectData::List(vec![dict.clone()]));
+        let held = garbage("held", ObjectData::None);
+        let custom = PyObject::new(
+            "custom".to_string(),
+            ObjectData::Custom(CustomData::new(())),
+        );
+        let cycle_a = garbage("cycle", ObjectData::List(Vec::new()));
+        let cycle_b = garbage("cycle", ObjectData::List(vec![cycle_a.clone()]));
+        cycle_a.set_data(ObjectData::List(vec![cycle_b.clone()]));
+
+        let ids = [
+            key.id(),
+            value.id(),
+            dict.id(),
+            list.id(),
+            held.id(),
+            custom.id(),
+        ];
+        for obj in [key, value, dict, held, cycle_a, cycle_b] {
+            obj.set_refcount(obj.get_refcount() + 1);
+            gc.track(obj).unwrap();
+        }
+        gc.track(list).unwrap();
+        gc.track(custom).unwrap();
+        gc.set_tracer(Some(Arc::new(HoldsOne(ids[4]))));
+
+        assert_eq!(gc.collect().unwrap(), 2);
+        assert!(ids.iter().all(|obj_id| gc.is_tracked(obj_id)));
+
+        gc.set_refcount(&ids[5], 0).unwrap();
+        assert_eq!(gc.collect().unwrap(), 2);
+        assert!(!gc.is_tracked(&ids[4]));
+        assert!(gc.verify_heap().is_empty());
+    }
+
+    #[test]
+    fn test_total_heap_bytes_counts_shared_objects_once() {
+        let gc = GarbageCollector::new();
+        let shared = PyObject::new("str".to_string(), ObjectData::String("x".repeat(64)));
+        let a = PyObject::new("list".to_string(), ObjectData::List(vec![shared.clone()]));
+        let b = PyObject::new("list".to_string(), ObjectData::List(vec![shared.clone()]));
+        let expected = shared.shallow_size() + a.shallow_size() + b.shallow_size();
+
+        gc.track(shared).unwrap();
+        gc.track(a).unwrap();
+        assert!(gc.total_heap_bytes() < expected);
+        gc.track(b).unwrap();
+        assert_eq!(gc.total_heap_bytes(), expected);
+    }
+
+    #[test]
+    fn test_memory_limit_forces_collection_and_fails_allocation() {
+        let gc = GarbageCollector::new();
+        let pressure = Arc::new(AtomicUsize::new(0));
+        let seen = pressure.clone();
+        gc.on_memory_pressure(Some(Arc::new(move |_, _| {
+            seen.fetch_add(1, Ordering::SeqCst);
+        })));
+
+        let garbage = gc
+            .allocate("str".to_string(), ObjectData::String("x".repeat(256)))
+            .unwrap();
+        garbage.set_refcount(0);
+        let garbage_bytes = gc.tracked_bytes();
+        gc.set_memory_limit(Some(MemoryLimit {
+            soft: garbage_bytes,
+            hard: garbage_bytes * 2,
+        }))
+        .unwrap();
+
+        // Crossing the soft limit collects the garbage string.
+        let live = gc
+            .allocate("str".to_string(), ObjectData::String("y".repeat(256)))
+            .unwrap();
+        assert!(!gc.is_tracked(&garbage.id()));
+        assert!(gc.is_tracked(&live.id()));
+        assert_eq!(pressure.load(Ordering::SeqCst), 0);
+
+        // Live data past the hard limit reports pressure once, then refuses
+        // further allocations.
+        let big = gc
+            .allocate("str".to_string(), ObjectData::String("z".repeat(1024)))
+            .unwrap();
+        assert_eq!(pressure.load(Ordering::SeqCst), 1);
+        assert!(matches!(
+            gc.allocate("int".to_string(), ObjectData::Integer(1)),
+            Err(GCError::AllocationFailed(_))
+        ));
+        assert_eq!(pressure.load(Ordering::SeqCst), 1);
+
+        gc.untrack(&big.id()).unwrap();
+        assert!(
+            gc.allocate("int".to_string(), ObjectData::Integer(1))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_rss_growth_triggers_collection() {
+        use crate::clock::ManualClock;
+        use crate::rss::{ManualRss, RssPolicy};
+        use std::time::Duration;
+
+        let gc = GarbageCollector::new();
+        let clock = Arc::new(ManualClock::new());
+        gc.collector.write().set_clock(clock.clone());
+        let rss = Arc::new(ManualRss::new());
+        gc.set_rss_monitor(Some(RssMonitor::new(
+            rss.clone(),
+            RssPolicy {
+                max_growth_per_sec: 1 << 20,
+                min_interval: Duration::from_millis(100),
+            },
+        )));
+
+        rss.set(64 << 20);
+        assert_eq!(gc.poll_rss().unwrap(), 0);
+        let garbage = PyObject::new("str".to_string(), ObjectData::String("x".into()));
+        garbage.set_refcount(0);
+        gc.track(garbage.clone()).unwrap();
+
+        clock.advance(Duration::from_secs(1));
+        rss.set(72 << 20);
+        assert_eq!(gc.poll_rss().unwrap(), 1);
+        assert!(!gc.is_tracked(&garbage.id()));
+
+        let stats = gc.get_stats();
+        assert_eq!(stats.rss_bytes, 72 << 20);
+        assert_eq!(stats.rss_growth_per_sec, 8 << 20);
+    }
+
+    #[test]
+    fn test_object_views() {
+        let gc = GarbageCollector::new();
+        let plain = PyObject::new("int".to_string(), ObjectData::Integer(1));
+        let garbage = PyObject::new("int".to_string(), ObjectData::Integer(2));
+        garbage.set_refcount(0);
+        let finalized = PyObject::new_with_finalizer("res".to_string(), ObjectData::None);
+        gc.track(plain.clone()).unwrap();
+        gc.track(garbage.clone()).unwrap();
+        gc.track(finalized.clone()).unwrap();
+
+        let ids: std::collections::HashSet<_> =
+            gc.iter_objects().iter().map(PyObject::id).collect();
+        assert_eq!(ids, [plain.id(), garbage.id()].into());
+        let uncollectable: Vec<_> = gc.iter_uncollectable().iter().map(PyObject::id).collect();
+        assert_eq!(uncollectable, [finalized.id()]);
+
+        gc.collect().unwrap();
+        let view = gc.iter_objects();
+        let ids: Vec<_> = (&view).into_iter().map(PyObject::id).collect();
+        assert_eq!(ids, [plain.id()]);
+        drop(view);
+        // The survivor of a full collection is in the oldest generation.
+        assert_eq!(gc.iter_generation(0).unwrap().iter().count(), 0);
+        assert_eq!(gc.iter_generation(2).unwrap().iter().count(), 1);
+        assert!(gc.iter_generation(3).is_none());
+    }
+
+    #[test]
+    fn test_collect_generation_merges_younger_generations() {
+        let gc = GarbageCollector::new();
+        let counts = |gc: &GarbageCollector| {
+            [0, 1, 2].map(|generation| gc.get_generation_count(generation).unwrap())
+        };
+        let track = |gc: &GarbageCollector, refcount| {
+            let obj = PyObject::new("int".to_string(), ObjectData::Integer(0));
+            obj.set_refcount(refcount);
+            gc.track(obj).unwrap();
+        };
+
+        for _ in 0..3 {
+            track(&gc, 1);
+        }
+        gc.collect_generation(0).unwrap();
+        assert_eq!(counts(&gc), [0, 3, 0]);
+
+        track(&gc, 1);
+        track(&gc, 0);
+        assert_eq!(gc.collect_generation(1).unwrap(), 1);
+        assert_eq!(counts(&gc), [0, 0, 4]);
+
+        track(&gc, 0);
+        track(&gc, 1);
+        assert_eq!(gc.collect().unwrap(), 1);
+        assert_eq!(counts(&gc), [0, 0, 5]);
+    }
+
+    #[test]
+    fn test_young_collection_leaves_the_oldest_generation_alone() {
+        let gc = GarbageCollector::new();
+        let old: Vec<PyObject> = (0..4)
+            .map(|_| PyObject::new("list".to_string(), ObjectData::List(Vec::new())))
+            .collect();
+        let anchor = PyObject::new(
+            "anchor".to_string(),
+            ObjectData::Custom(CustomData::new(())),
+        );
+        gc.track(anchor.clone()).unwrap();
+        for obj in &old {
+            obj.set_data(ObjectData::List(vec![anchor.clone()]));
+            anchor.inc_ref();
+            gc.track(obj.clone()).unwrap();
+        }
+        gc.collect().unwrap();
+        assert_eq!(gc.get_generation_count(2).unwrap(), 5);
+
+        let young = PyObject::new("list".to_string(), ObjectData::List(vec![anchor.clone()]));
+        anchor.inc_ref();
+        gc.track(young.clone()).unwrap();
+        old.iter().for_each(|obj| obj.set_refcount(0));
+
+        let report = gc.collect_generation_with_report(0).unwrap();
+        assert_eq!(
+            (report.scanned, report.collected, report.promoted),
+            (1, 0, 1)
+        );
+        assert!(old.iter().all(|obj| gc.is_tracked(&obj.id())));
+        assert_eq!(gc.generation_of(&young.id()), Some(1));
+        let stats = gc.get_stats();
+        assert_eq!(stats.generation_scanned[0], 1);
+        assert_eq!(stats.generation_counts, [0, 1, 5]);
+
+        assert_eq!(gc.collect().unwrap(), 4);
+    }
+
+    #[test]
+    fn test_freed_objects_leave_the_generation_they_were_in() {
+        let gc = GarbageCollector::new();
+        let counts = |gc: &GarbageCollector| {
+            [0, 1, 2].map(|generation| gc.get_generation_count(generation).unwrap())
+        };
+        let old = PyObject::new("list".to_string(), ObjectData::List(Vec::new()));
+        let middle = PyObject::new("list".to_string(), ObjectData::List(Vec::new()));
+        let anchor = PyObject::new(
+            "anchor".to_string(),
+            ObjectData::Custom(CustomData::new(())),
+        );
+        old.set_data(ObjectData::List(vec![anchor.clone()]));
+        middle.set_data(ObjectData::List(vec![anchor.clone()]));
+        gc.track(anchor.clone()).unwrap();
+        gc.track(old.clone()).unwrap();
+        gc.collect().unwrap();
+        gc.track(middle.clone()).unwrap();
+        gc.collect_generation(0).unwrap();
+        assert_eq!(counts(&gc), [0, 1, 2]);
+
+        // Dead but old, so a young collection leaves it alone.
+        old.set_refcount(0);
+        assert_eq!(gc.collect_generation(0).unwrap(), 0);
+        assert_eq!(counts(&gc), [0, 1, 2]);
+
+        middle.set_refcount(0);
+        assert_eq!(gc.collect_generation(1).unwrap(), 1);
+        assert_eq!(counts(&gc), [0, 0, 2]);
+        assert!(gc.verify_heap().is_empty());
+
+        gc.untrack(&old.id()).unwrap();
+        assert_eq!(counts(&gc), [0, 0, 1]);
+        assert!(gc.verify_heap().is_empty());
+    }
+
+    #[test]
+    fn test_full_collection_waits_for_long_lived_pending() {
+        let gc = GarbageCollector::new();
+        let track = |count| {
+            for _ in 0..count {
+                gc.track(PyObject::new("int".to_string(), ObjectData::Integer(0)))
+                    .unwrap();
+            }
+        };
+        let full_collections = |gc: &GarbageCollector| gc.get_stats().generation_collections[2];
+
+        track(8);
+        gc.collect().unwrap();
+        let stats = gc.get_stats();
+        assert_eq!((stats.long_lived_total, stats.long_lived_pending), (8, 0));
+
+        gc.set_threshold(2, 1).unwrap();
+        gc.collect_if_needed().unwrap();
+        assert_eq!(full_collections(&gc), 1);
+
+        track(2);
+        gc.collect_generation(1).unwrap();
+        assert_eq!(gc.get_stats().long_lived_pending, 2);
+        gc.collect_if_needed().unwrap();
+        assert_eq!(full_collections(&gc), 2);
+        let stats = gc.get_stats();
+        assert_eq!((stats.long_lived_total, stats.long_lived_pending), (10, 0));
+    }
+
+    #[test]
+    fn test_needs_collection_reports_oldest_due_generation() {
+        let gc = GarbageCollector::new();
+        gc.set_thresholds(1, Some(1), Some(100));
+        let track = || {
+            let obj = PyObject::new("node".to_string(), ObjectData::Custom(CustomData::new(())));
+            gc.track(obj).unwrap();
+        };
+        assert_eq!(gc.needs_collection(), None);
+
+        track();
+        assert_eq!(gc.needs_collection(), Some(0));
+        gc.collect_if_needed().unwrap();
+        assert_eq!(gc.needs_collection(), Some(1));
+
+        track();
+        assert_eq!(gc.needs_collection(), Some(1));
+        gc.collect_if_needed().unwrap();
+        assert_eq!(gc.get_stats().generation_collections, [1, 1, 0]);
+    }
+
+    #[test]
+    fn test_zero_threshold0_disables_automatic_collection() {
+        let gc = GarbageCollector::new();
+        gc.set_thresholds(0, None, None);
+        assert!(!gc.is_automatic_collection_enabled());
+
+        let garbage = PyObject::new("int".to_string(), ObjectData::Integer(0));
+        garbage.set_refcount(0);
+        gc.track(garbage.clone()).unwrap();
+        assert!(gc.is_tracked(&garbage.id()));
+        assert_eq!(gc.needs_collection(), None);
+        assert_eq!(gc.collect_if_needed().unwrap(), 0);
+        assert!(gc.is_tracked(&garbage.id()));
+
+        assert_eq!(gc.collect().unwrap(), 1);
+        gc.set_thresholds(1, None, None);
+        assert!(gc.is_automatic_collection_enabled());
+    }
 
-impl GarbageCollector {
-    pub fn new() -> Self {
-        Self {
-            collector: Arc::new(RwLock::new(Collector::new())),
-            enabled: true,
-            thresholds: [700, 10, 10],
-            debug_flags: 0,
-        }
+    #[test]
+    fn test_disable_stops_only_automatic_collection() {
+        let gc = GarbageCollector::new();
+        gc.set_thresholds(1, None, None);
+        gc.disable();
+
+        let garbage = PyObject::new("int".to_string(), ObjectData::Integer(0));
+        garbage.set_refcount(0);
+        gc.track(garbage.clone()).unwrap();
+        assert!(gc.is_tracked(&garbage.id()));
+        assert_eq!(gc.needs_collection(), Some(0));
+        assert_eq!(gc.collect_if_needed().unwrap(), 0);
+        assert!(gc.is_tracked(&garbage.id()));
+        assert_eq!(gc.collect().unwrap(), 1);
+        assert!(!gc.is_tracked(&garbage.id()));
+
+        gc.pause_tracking();
+        let skipped = PyObject::new("int".to_string(), ObjectData::Integer(1));
+        gc.track(skipped.clone()).unwrap();
+        assert!(!gc.is_tracked(&skipped.id()));
+        gc.resume_tracking();
+        gc.track(skipped.clone()).unwrap();
+        assert!(gc.is_tracked(&skipped.id()));
     }
 
-    pub fn enable(&mut self) {
-        self.enabled = true;
+    #[test]
+    fn test_invalid_generation_errors() {
+        let gc = GarbageCollector::new();
+        assert!(matches!(
+            gc.collect_generation(5),
+            Err(GCError::InvalidGeneration(5))
+        ));
+        assert!(matches!(
+            gc.set_threshold(3, 10),
+            Err(GCError::InvalidGeneration(3))
+        ));
+        let mut collector = gc.collector.write();
+        assert!(matches!(
+            collector.generation_manager.promote_generation(1, 4),
+            Err(GCError::InvalidGeneration(4))
+        ));
+        assert!(matches!(
+            collector.begin_collection(3),
+            Err(GCError::InvalidGeneration(3))
+        ));
     }
 
-    pub fn disable(&mut self) {
-        self.enabled = false;
+    #[test]
+    fn test_errors_name_object_and_code() {
+        use std::error::Error;
+
+        let gc = GarbageCollector::new();
+        let obj = PyObject::new("int".to_string(), ObjectData::Integer(0));
+        let id = obj.id();
+        gc.track(obj.clone()).unwrap();
+
+        let err = gc.track(obj).unwrap_err();
+        assert!(matches!(err, GCError::AlreadyTracked { id: got } if got == id));
+        assert_eq!(err.to_string(), format!("Object {id} is already tracked"));
+        assert_eq!(err.code(), -1);
+
+        gc.untrack(&id).unwrap();
+        let err = gc.untrack(&id).unwrap_err();
+        assert!(matches!(err, GCError::NotTracked { id: got } if got == id));
+        assert_eq!(err.code(), -2);
+
+        let io = std::io::Error::other("disk gone");
+        let err = GCError::from(io);
+        assert_eq!(err.code(), -5);
+        assert_eq!(err.source().unwrap().to_string(), "disk gone");
     }
 
-    pub fn is_enabled(&self) -> bool {
-        self.enabled
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_telemetry_serializes_to_json() {
+        let gc = GarbageCollector::new();
+        let a = PyObject::new("list".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("list".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        a.set_refcount(2);
+        let list_bytes = a.shallow_size() + b.shallow_size();
+        gc.track(a.clone()).unwrap();
+        let b_id = b.id();
+        gc.track(b).unwrap();
+        gc.collect().unwrap();
+
+        let stats = serde_json::to_value(gc.get_stats()).unwrap();
+        assert_eq!(stats["total_tracked"], 2);
+        assert_eq!(stats["generation_counts"], serde_json::json!([0, 0, 2]));
+
+        let histogram = serde_json::to_value(gc.type_histogram()).unwrap();
+        assert_eq!(
+            histogram,
+            serde_json::json!({"list": {"count": 2, "bytes": list_bytes}})
+        );
+
+        let leaks = serde_json::to_value(gc.detect_probable_leaks(1)).unwrap();
+        assert_eq!(leaks[0]["object_id"], b_id.as_u64());
+        assert_eq!(leaks[0]["retaining_path"][0], b_id.as_u64());
+        assert_eq!(
+            leaks[0]["retaining_labels"],
+            serde_json::json!(["[0]", "[0]"])
+        );
+
+        let error = serde_json::to_value(gc.track(a.clone()).unwrap_err()).unwrap();
+        assert_eq!(
+            error,
+            serde_json::json!({"code": -1, "message": format!("Object {} is already tracked", a.id())})
+        );
     }
 
-    pub fn track(&mut self, obj: PyObject) -> GCResult<()> {
-        if !self.enabled {
-            return Ok(());
-        }
+    #[test]
+    fn test_verify_heap() {
+        let gc = GarbageCollector::new();
+        let obj = PyObject::new("test".to_string(), ObjectData::None);
+        let obj_id = obj.id();
+        gc.track(obj).unwrap();
+        assert!(gc.verify_heap().is_empty());
 
         {
-            let mut collector = self.collector.write();
-            collector.track_object_fast(obj)
+            let mut collector = gc.collector.write();
+            let obj = collector.tracked_objects.get_mut(&obj_id).unwrap();
+            obj.gc_head().set_collecting();
+            obj.gc_head().set_refs(2);
+            collector.generation_manager.generations[1].count += 1;
         }
+
+        let violations = gc.verify_heap();
+        assert_eq!(
+            violations,
+            vec![
+                IntegrityViolation::GenerationCountMismatch {
+                    generation_total: 2,
+                    tracked: 1,
+                },
+                IntegrityViolation::GcRefsAboveRefcount {
+                    object: obj_id,
+                    gc_refs: 2,
+                    refcount: 1,
+                },
+                IntegrityViolation::StaleCollectingFlag(obj_id),
+            ]
+        );
     }
 
-    pub fn track_bulk(&mut self, objects: Vec<PyObject>) -> GCResult<()> {
-        if !self.enabled {
-            return Ok(());
+    #[test]
+    fn test_refcount_audit_reports_unaccounted_references() {
+        let gc = GarbageCollector::new();
+        let custom =
+            |name: &str| PyObject::new(name.to_string(), ObjectData::Custom(CustomData::new(())));
+        let (known, unreported) = (custom("known"), custom("unreported"));
+        unreported.set_refcount(2);
+        let owner = PyObject::new(
+            "owner".to_string(),
+            ObjectData::List(vec![known.clone(), unreported.clone()]),
+        );
+        for obj in [&owner, &known, &unreported] {
+            gc.track(obj.clone()).unwrap();
         }
+        gc.add_external_ref(&owner.id()).unwrap();
+        assert_eq!(
+            gc.audit_refcounts(),
+            vec![RefcountDiscrepancy {
+                object: unreported.id(),
+                type_name: "unreported".to_string(),
+                refcount: 2,
+                internal_refs: 1,
+                external_refs: 0,
+                referrers: vec![owner.id()],
+            }]
+        );
 
-        {
-            let mut collector = self.collector.write();
-            collector.track_objects_bulk(objects)
+        gc.collect().unwrap();
+        assert!(gc.last_refcount_audit().is_empty());
+        gc.set_debug(DEBUG_AUDIT_REFS);
+        gc.release_external_ref(&owner.id()).unwrap();
+        gc.collect().unwrap();
+        let found: Vec<ObjectId> = gc.last_refcount_audit().iter().map(|d| d.object).collect();
+        assert_eq!(found.len(), 2);
+        assert!(found.contains(&owner.id()) && found.contains(&unreported.id()));
+        assert!(gc.release_external_ref(&owner.id()).is_err());
+    }
+
+    #[test]
+    fn test_reference_history_records_edges_into_each_object() {
+        use crate::clock::ManualClock;
+
+        let gc = GarbageCollector::new();
+        let clock = Arc::new(ManualClock::new());
+        gc.collector.write().set_clock(clock.clone());
+        let target = PyObject::new("target".to_string(), ObjectData::None);
+        let holder = PyObject::new("holder".to_string(), ObjectData::List(Vec::new()));
+        gc.replace_data(&holder, ObjectData::List(vec![target.clone()]));
+        assert!(gc.reference_history(&target.id(), 10).is_empty());
+
+        gc.set_reference_history(Some(ReferenceHistoryLimits {
+            per_object: 2,
+            max_objects: 1,
+        }));
+        clock.advance(Duration::from_secs(1));
+        gc.replace_data(&holder, ObjectData::List(Vec::new()));
+        clock.advance(Duration::from_secs(1));
+        gc.replace_data(
+            &holder,
+            ObjectData::List(vec![target.clone(), target.clone()]),
+        );
+        let event = |secs, change| ReferenceEvent {
+            at: Duration::from_secs(secs),
+            from: holder.id(),
+            change,
+        };
+        assert_eq!(
+            gc.reference_history(&target.id(), 10),
+            vec![event(2, EdgeChange::Added), event(2, EdgeChange::Added)]
+        );
+        assert_eq!(gc.reference_history(&target.id(), 1).len(), 1);
+
+        let other = PyObject::new("other".to_string(), ObjectData::None);
+        gc.record_reference_change(&holder.id(), &other.id(), EdgeChange::Removed);
+        assert!(gc.reference_history(&target.id(), 10).is_empty());
+        assert_eq!(gc.reference_history(&other.id(), 10).len(), 1);
+
+        gc.set_reference_history(None);
+        assert!(gc.reference_history(&other.id(), 10).is_empty());
+    }
+
+    #[test]
+    fn test_finalizer_resurrects_an_object_only_once() {
+        let gc = GarbageCollector::new();
+        let finalized = Arc::new(AtomicUsize::new(0));
+        let cache = Arc::new(Mutex::new(Vec::new()));
+        let (counter, kept) = (finalized.clone(), cache.clone());
+        gc.set_finalizer(Some(Arc::new(move |ctx: &FinalizerContext<'_>| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            assert!(ctx.resurrect());
+            ctx.object().inc_ref();
+            kept.lock().push(ctx.object().clone());
+        })));
+
+        let entry = PyObject::new("entry".to_string(), ObjectData::List(Vec::new()));
+        let value = PyObject::new("value".to_string(), ObjectData::List(vec![entry.clone()]));
+        entry.set_data(ObjectData::List(vec![value.clone()]));
+        gc.track(entry.clone()).unwrap();
+        gc.track(value.clone()).unwrap();
+        entry.set_finalizer(true);
+
+        let report = gc.collect_with_report().unwrap();
+        assert_eq!(report.finalizers_run, 1);
+        assert!(entry.gc_head().is_finalized());
+        // The entry comes back whole, with the garbage it references.
+        for obj in [&entry, &value] {
+            assert!(obj.is_gc_tracked());
+            assert_eq!(gc.generation_of(&obj.id()), Some(2));
         }
+        assert_eq!(entry.children(), vec![value.clone()]);
+        assert_eq!(value.children(), vec![entry.clone()]);
+
+        // Evicted from the cache, it dies again without being finalized.
+        cache.lock().clear();
+        entry.dec_ref();
+        let report = gc.collect_with_report().unwrap();
+        assert_eq!(report.finalizers_run, 0);
+        assert_eq!(finalized.load(Ordering::Relaxed), 1);
+        assert!(!entry.is_gc_tracked());
+        assert!(entry.children().is_empty());
     }
 
-    pub fn untrack(&mut self, obj_id: &ObjectId) -> GCResult<()> {
-        if !self.enabled {
-            return Ok(());
+    #[test]
+    fn test_ephemeron_value_lives_only_as_long_as_its_key() {
+        let gc = GarbageCollector::new();
+        let held = |name: &str| {
+            let obj = PyObject::new(name.to_string(), ObjectData::Custom(CustomData::new(())));
+            gc.track(obj.clone()).unwrap();
+            obj
+        };
+
+        // The value refers back to its key, as a weak-key cache's often do.
+        let key = held("key");
+        let value = PyObject::new("value".to_string(), ObjectData::List(vec![key.clone()]));
+        key.inc_ref();
+        gc.track(value.clone()).unwrap();
+        gc.add_ephemeron(&key.id(), &value.id()).unwrap();
+        value.dec_ref();
+        gc.collect().unwrap();
+        assert!(gc.is_tracked(&key.id()) && gc.is_tracked(&value.id()));
+
+        key.dec_ref();
+        assert_eq!(gc.collect().unwrap(), 2);
+        assert!(!gc.is_tracked(&value.id()));
+
+        // A value held elsewhere only loses the ephemeron's reference.
+        let (key, value) = (held("key"), held("value"));
+        gc.add_ephemeron(&key.id(), &value.id()).unwrap();
+        assert_eq!(value.get_refcount(), 2);
+        gc.decref(&key.id()).unwrap();
+        assert_eq!(value.get_refcount(), 1);
+        assert!(gc.is_tracked(&value.id()));
+
+        let key = held("key");
+        gc.add_ephemeron(&key.id(), &value.id()).unwrap();
+        value.dec_ref();
+        assert!(gc.remove_ephemeron(&key.id(), &value.id()).unwrap());
+        assert!(!gc.remove_ephemeron(&key.id(), &value.id()).unwrap());
+        assert!(!gc.is_tracked(&value.id()));
+        assert!(gc.collector.read().ephemerons.is_empty());
+        assert!(matches!(
+            gc.add_ephemeron(&key.id(), &value.id()),
+            Err(GCError::NotTracked { .. })
+        ));
+    }
+
+    #[test]
+    fn test_visit_children_walks_the_graph_marking_sees() {
+        #[derive(Debug)]
+        struct Pairs;
+        impl Trace for Pairs {
+            fn trace(&self, data: &CustomData, visit: &mut dyn FnMut(ObjectId)) {
+                if let Some((a, b)) = data.downcast_ref::<(ObjectId, ObjectId)>() {
+                    visit(*a);
+                    visit(*b);
+                }
+            }
         }
 
-        {
-            let mut collector = self.collector.write();
-            collector.untrack_object_fast(obj_id)
+        let gc = GarbageCollector::new();
+        gc.set_tracer(Some(Arc::new(Pairs)));
+        let leaf = PyObject::new("leaf".to_string(), ObjectData::Integer(1));
+        let list = PyObject::new(
+            "list".to_string(),
+            ObjectData::List(vec![leaf.clone(), leaf.clone()]),
+        );
+        let pair = PyObject::new(
+            "pair".to_string(),
+            ObjectData::Custom(CustomData::new((list.id(), leaf.id()))),
+        );
+        let value = PyObject::new("value".to_string(), ObjectData::Custom(CustomData::new(())));
+        for obj in [&list, &pair, &value] {
+            gc.track(obj.clone()).unwrap();
         }
+        gc.add_ephemeron(&pair.id(), &value.id()).unwrap();
+
+        let children = |obj: &PyObject| {
+            let mut children = Vec::new();
+            gc.visit_children(&obj.id(), &mut |child_id| children.push(child_id))
+                .unwrap();
+            children
+        };
+        assert_eq!(children(&list), vec![leaf.id(), leaf.id()]);
+        assert_eq!(children(&pair), vec![list.id(), leaf.id(), value.id()]);
+        assert!(children(&value).is_empty());
+
+        // The visitor may use the collector while it walks.
+        let mut tracked = 0;
+        gc.visit_children(&pair.id(), &mut |child_id| {
+            tracked += gc.is_tracked(&child_id) as usize;
+        })
+        .unwrap();
+        assert_eq!(tracked, 2);
+        assert!(matches!(
+            gc.visit_children(&leaf.id(), &mut |_| {}),
+            Err(GCError::NotTracked { .. })
+        ));
     }
 
-    pub fn collect_generation(&self, generation: usize) -> GCResult<usize> {
-        if !self.enabled {
-            return Ok(0);
+    #[test]
+    fn test_stats_report_bytes_promotions_and_survivor_rates() {
+        let gc = GarbageCollector::new();
+        let kept: Vec<PyObject> = (0..3)
+            .map(|i| PyObject::new(format!("kept{i}"), ObjectData::Custom(CustomData::new(()))))
+            .collect();
+        let a = PyObject::new("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        for obj in kept.iter().chain([&a, &b]) {
+            gc.track(obj.clone()).unwrap();
         }
+        let bytes: usize = kept.iter().map(PyObject::shallow_size).sum();
+        assert_eq!(
+            gc.get_stats().generation_bytes[0],
+            bytes + 2 * a.shallow_size()
+        );
+        assert_eq!(gc.get_stats().survivor_rate(0), 0.0);
 
-        let mut collector = self.collector.write();
-        collector.collect_generation(generation)
+        assert_eq!(gc.collect_generation(0).unwrap(), 2);
+        let stats = gc.get_stats();
+        assert_eq!(stats.generation_bytes, [0, bytes, 0]);
+        assert_eq!(stats.generation_scanned, [5, 0, 0]);
+        assert_eq!(stats.generation_promoted, [3, 0, 0]);
+        assert_eq!(stats.survivor_rate(0), 0.6);
+
+        gc.collect().unwrap();
+        let stats = gc.get_stats();
+        assert_eq!(stats.generation_bytes, [0, 0, bytes]);
+        // The oldest generation keeps its survivors rather than promoting them.
+        assert_eq!(stats.generation_promoted, [3, 0, 0]);
+        assert_eq!(stats.survivor_rate(2), 1.0);
     }
 
-    pub fn collect(&self) -> GCResult<usize> {
-        if !self.enabled {
-            return Ok(0);
+    #[test]
+    fn test_aged_promotion_holds_back_young_survivors() {
+        let gc = GarbageCollector::new();
+        let held =
+            |name: &str| PyObject::new(name.to_string(), ObjectData::Custom(CustomData::new(())));
+        let (young, anchor) = (held("young"), held("anchor"));
+        let big = PyObject::new(
+            "big".to_string(),
+            ObjectData::List(vec![anchor.clone(); 64]),
+        );
+        anchor.set_refcount(65);
+        for obj in [&young, &anchor, &big] {
+            gc.track(obj.clone()).unwrap();
         }
+        gc.set_promotion_policy(PromotionPolicy::Aged {
+            survivals: 2,
+            early_bytes: Some(big.shallow_size()),
+        });
+        let generations = |gc: &GarbageCollector| {
+            [&young, &anchor, &big].map(|obj| gc.generation_of(&obj.id()).unwrap())
+        };
 
-        let mut collector = self.collector.write();
-        collector.collect_generation(2)
-    }
+        gc.collect_generation(0).unwrap();
+        assert_eq!(generations(&gc), [0, 0, 1]);
+        assert_eq!(gc.get_stats().generation_counts, [2, 1, 0]);
+        gc.collect_generation(0).unwrap();
+        assert_eq!(generations(&gc), [1, 1, 1]);
+        gc.collect_generation(1).unwrap();
+        assert_eq!(generations(&gc), [1, 1, 2]);
+        assert_eq!(gc.get_stats().generation_counts, [0, 2, 1]);
+        assert_eq!(
+            gc.promotion_stats(),
+            PromotionStats {
+                promoted: 4,
+                into_oldest: 1,
+                held_back: 4,
+                promoted_early: 2,
+            }
+        );
 
-    pub fn needs_collection(&self) -> bool {
-        let collector = self.collector.read();
-        collector.generation_manager.should_collect_generation(0)
+        gc.set_promotion_policy(PromotionPolicy::EverySurvival);
+        gc.collect_generation(1).unwrap();
+        assert_eq!(generations(&gc), [2, 2, 2]);
+        assert_eq!(gc.get_stats().generation_counts, [0, 0, 3]);
+        assert_eq!(
+            gc.promotion_stats(),
+            PromotionStats {
+                promoted: 2,
+                into_oldest: 2,
+                ..PromotionStats::default()
+            }
+        );
     }
 
-    pub fn get_stats(&self) -> crate::GCStats {
-        let collector = self.collector.read();
-        collector.get_stats()
+    #[test]
+    fn test_pre_collect_hooks_drop_caches_and_can_veto() {
+        use crate::clock::ManualClock;
+
+        let gc = Arc::new(GarbageCollector::new());
+        let clock = Arc::new(ManualClock::new());
+        gc.collector.write().set_clock(clock.clone());
+        let a = PyObject::new("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        gc.track(a.clone()).unwrap();
+        gc.track(b.clone()).unwrap();
+        // A memo table's reference keeps the cycle alive until the hook
+        // drops it.
+        a.inc_ref();
+        let cache = Arc::new(Mutex::new(vec![a.clone()]));
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let drop_cache = {
+            let (weak, cache, seen) = (Arc::downgrade(&gc), cache.clone(), seen.clone());
+            let clock = clock.clone();
+            Arc::new(move |generation, stats: &crate::GCStats| {
+                seen.lock().push((generation, stats.total_tracked));
+                let gc = weak.upgrade().unwrap();
+                for obj in cache.lock().drain(..) {
+                    gc.decref(&obj.id()).unwrap();
+                }
+                clock.advance(Duration::from_millis(3));
+                PreCollect::Proceed
+            })
+        };
+        let dropper = gc.add_pre_collect_hook(drop_cache);
+
+        let report = gc.collect_with_report().unwrap();
+        assert_eq!(report.collected, 2);
+        assert_eq!(report.pre_collect_time, Duration::from_millis(3));
+        assert_eq!(*seen.lock(), vec![(2, 2)]);
+
+        let veto = gc.add_pre_collect_hook(Arc::new(|_, _| PreCollect::Veto));
+        let report = gc.collect_generation_with_report(0).unwrap();
+        assert!(report.vetoed);
+        assert_eq!(report.generation, 0);
+        assert_eq!(gc.get_stats().generation_collections, [0, 0, 1]);
+        // Every hook runs, whether or not another vetoes.
+        assert_eq!(seen.lock().len(), 2);
+
+        assert!(gc.remove_pre_collect_hook(veto));
+        assert!(gc.remove_pre_collect_hook(dropper));
+        assert!(!gc.remove_pre_collect_hook(dropper));
+        let report = gc.collect_with_report().unwrap();
+        assert!(!report.vetoed);
+        assert_eq!(report.pre_collect_time, Duration::ZERO);
     }
 
-    pub fn set_debug(&mut self, flags: u32) {
-        self.debug_flags = flags;
-        let mut collector = self.collector.write();
-        collector.set_debug_flags(flags);
+    #[test]
+    fn test_verify_barriers_reports_missed_old_to_young_edges() {
+        let gc = GarbageCollector::new();
+        gc.set_debug(DEBUG_VERIFY_BARRIERS);
+        let anchor = PyObject::new(
+            "anchor".to_string(),
+            ObjectData::Custom(CustomData::new(())),
+        );
+        let old = PyObject::new("old".to_string(), ObjectData::List(vec![anchor.clone()]));
+        gc.track(anchor.clone()).unwrap();
+        gc.track(old.clone()).unwrap();
+        // A full collection moves both into the oldest generation, which
+        // minor collections don't scan: only refcounts tell them about its
+        // references into younger ones.
+        assert_eq!(gc.collect().unwrap(), 0);
+        assert_eq!(gc.generation_of(&old.id()), Some(2));
+
+        // A young cycle, stored into the old list once through the barrier's
+        // incref and once without.
+        let a = PyObject::new("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        gc.track(a.clone()).unwrap();
+        gc.track(b.clone()).unwrap();
+        a.inc_ref();
+        old.set_data(ObjectData::List(vec![anchor.clone(), a.clone()]));
+        assert_eq!(gc.collect_generation(0).unwrap(), 0);
+        assert!(gc.last_barrier_violations().is_empty());
+
+        old.set_data(ObjectData::List(vec![anchor.clone(), a.clone(), b.clone()]));
+        a.dec_ref();
+        assert_eq!(gc.collect_generation(1).unwrap(), 2);
+        let violations = gc.last_barrier_violations();
+        let missed = |to: &PyObject| BarrierViolation {
+            from: old.id(),
+            from_type: "old".to_string(),
+            from_generation: Some(2),
+            to: to.id(),
+            generation: 1,
+        };
+        let mut expected = vec![missed(&a), missed(&b)];
+        expected.sort_by_key(|v| v.to.as_u64());
+        assert_eq!(violations, expected);
+        assert!(
+            violations[0]
+                .to_string()
+                .contains("freed by a generation 1 collection")
+        );
     }
 
-    pub fn get_debug(&self) -> u32 {
-        self.debug_flags
+    #[test]
+    fn test_watches_report_refcount_reachability_and_collection() {
+        let gc = Arc::new(GarbageCollector::new());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorder = |gc: &Arc<GarbageCollector>| -> WatchCallback {
+            let (gc, events) = (Arc::downgrade(gc), events.clone());
+            Arc::new(move |obj_id, event| {
+                // Runs unlocked, so it may look at the collector.
+                let tracked = gc.upgrade().unwrap().is_tracked(&obj_id);
+                events.lock().push((obj_id, event, tracked));
+            })
+        };
+
+        let held = PyObject::new("held".to_string(), ObjectData::Custom(CustomData::new(())));
+        gc.track(held.clone()).unwrap();
+        let watch = gc
+            .watch(
+                &held.id(),
+                WatchKind::Refcount { threshold: 3 },
+                recorder(&gc),
+            )
+            .unwrap();
+        gc.set_refcount(&held.id(), 3).unwrap();
+        gc.set_refcount(&held.id(), 4).unwrap();
+        gc.decref(&held.id()).unwrap();
+        gc.decref(&held.id()).unwrap();
+        let refcount = |old, new| (held.id(), WatchEvent::Refcount { old, new }, true);
+        assert_eq!(*events.lock(), vec![refcount(1, 3), refcount(3, 2)]);
+        assert!(gc.unwatch(watch));
+        assert!(!gc.unwatch(watch));
+
+        let a = PyObject::new("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        gc.track(a.clone()).unwrap();
+        gc.track(b.clone()).unwrap();
+        let reachability = gc
+            .watch(&a.id(), WatchKind::Reachability, recorder(&gc))
+            .unwrap();
+        gc.watch(&a.id(), WatchKind::Collection, recorder(&gc))
+            .unwrap();
+        events.lock().clear();
+        gc.collect().unwrap();
+        assert_eq!(
+            *events.lock(),
+            vec![
+                (a.id(), WatchEvent::Unreachable, false),
+                (a.id(), WatchEvent::Collected, false),
+            ]
+        );
+        assert!(!gc.unwatch(reachability));
+        assert!(matches!(
+            gc.watch(&a.id(), WatchKind::Collection, recorder(&gc)),
+            Err(GCError::NotTracked { .. })
+        ));
     }
 
-    pub fn get_count(&self) -> usize {
-        let collector = self.collector.read();
-        collector.get_count()
+    #[test]
+    fn test_generation_thresholds() {
+        let gc = GarbageCollector::new();
+
+        assert_eq!(gc.get_threshold(0), Some(700));
+        assert_eq!(gc.get_threshold(1), Some(10));
+        assert_eq!(gc.get_threshold(2), Some(10));
+
+        assert!(gc.set_threshold(0, 1000).is_ok());
+        assert_eq!(gc.get_threshold(0), Some(1000));
     }
 
-    pub fn get_generation_count(&self, generation: usize) -> Option<usize> {
-        if generation >= 3 {
-            return None;
+    #[test]
+    fn test_shared_reference_api() {
+        let gc = Arc::new(GarbageCollector::new());
+
+        let handles: Vec<_> = (0..4)
+            .map(|i| {
+                let gc = Arc::clone(&gc);
+                std::thread::spawn(move || {
+                    let obj = PyObject::new(format!("obj{i}"), ObjectData::Integer(i));
+                    gc.track(obj).unwrap();
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            handle.join().unwrap();
         }
 
-        let collector = self.collector.read();
-        collector
-            .generation_manager
-            .get_generation(generation)
-            .map(|g| g.count)
+        assert_eq!(gc.get_count(), 4);
+
+        gc.disable();
+        assert!(!gc.is_enabled());
+        let obj = PyObject::new("late".to_string(), ObjectData::None);
+        obj.set_refcount(0);
+        gc.track(obj.clone()).unwrap();
+        assert!(gc.is_tracked(&obj.id()));
+        assert_eq!(gc.collect().unwrap(), 1);
+        gc.enable();
+
+        gc.pause_tracking();
+        let paused = PyObject::new("paused".to_string(), ObjectData::None);
+        gc.track(paused.clone()).unwrap();
+        assert!(!gc.is_tracked(&paused.id()));
+        gc.resume_tracking();
+        gc.track(paused.clone()).unwrap();
+        assert!(gc.is_tracked(&paused.id()));
+
+        gc.set_debug(0x01);
+        assert_eq!(gc.get_debug(), 0x01);
     }
 
-    pub fn set_threshold(&mut self, generation: usize, threshold: usize) -> GCResult<()> {
-        if generation >= 3 {
-            return Err(GCError::Internal(format!(
-                "Invalid generation: {generation}"
-            )));
-        }
+    #[test]
+    fn test_collection() {
+        let gc = GarbageCollector::new();
 
-        self.thresholds[generation] = threshold;
-        Ok(())
+        assert!(gc.collect().is_ok());
+        assert_eq!(gc.get_count(), 0);
     }
 
-    pub fn get_threshold(&self, generation: usize) -> Option<usize> {
-        self.thresholds.get(generation).copied()
+    #[test]
+    fn test_cross_heap_edges_keep_referents_alive() {
+        let app = GarbageCollector::new();
+        let cache = GarbageCollector::new();
+
+        let entry = PyObject::new("entry".to_string(), ObjectData::None);
+        let holder = PyObject::new("holder".to_string(), ObjectData::List(vec![entry.clone()]));
+        app.track(holder.clone()).unwrap();
+        cache.track(entry.clone()).unwrap();
+
+        assert_eq!(app.cross_heap_edges(&cache), [(holder.id(), entry.id())]);
+        assert!(cache.cross_heap_edges(&app).is_empty());
+        cache.collect().unwrap();
+        assert!(cache.is_tracked(&entry.id()));
+
+        let moved = cache.transfer(&entry.id(), &app, false).unwrap();
+        assert_eq!(moved, [entry.id()]);
+        assert!(!cache.is_tracked(&entry.id()));
+        assert!(app.is_tracked(&entry.id()));
+        assert!(app.cross_heap_edges(&cache).is_empty());
+        assert!(matches!(
+            app.transfer(&entry.id(), &app, false),
+            Err(GCError::Internal { .. })
+        ));
+
+        let mut moved = app.transfer(&holder.id(), &cache, true).unwrap();
+        moved.sort_by_key(|obj_id| obj_id.as_u64());
+        let mut expected = vec![holder.id(), entry.id()];
+        expected.sort_by_key(|obj_id| obj_id.as_u64());
+        assert_eq!(moved, expected);
+        assert_eq!(app.get_count(), 0);
+        assert_eq!(cache.get_count(), 2);
+        assert!(matches!(
+            app.transfer(&holder.id(), &cache, true),
+            Err(GCError::NotTracked { .. })
+        ));
     }
 
-    pub fn collect_if_needed(&self) -> GCResult<usize> {
-        if !self.enabled {
-            return Ok(0);
-        }
+    #[test]
+    fn test_refused_transfer_leaves_both_heaps_intact() {
+        let app = GarbageCollector::new();
+        let cache = GarbageCollector::new();
+        let entry = PyObject::new("entry".to_string(), ObjectData::None);
+        let holder = PyObject::new("holder".to_string(), ObjectData::List(vec![entry.clone()]));
+        app.track(holder.clone()).unwrap();
+        app.track(entry.clone()).unwrap();
 
-        let mut collector = self.collector.write();
+        let intact = |app: &GarbageCollector, cache_count: usize| {
+            assert!(app.is_tracked(&holder.id()) && app.is_tracked(&entry.id()));
+            assert!(holder.is_gc_tracked() && entry.is_gc_tracked());
+            assert_eq!(app.get_count(), 2);
+            assert_eq!(cache.get_count(), cache_count);
+        };
 
-        for gen_idx in (0..3).rev() {
-            if collector
-                .generation_manager
-                .get_generation(gen_idx)
-                .map(|g| g.should_collect())
-                .unwrap_or(false)
-            {
-                return collector.collect_generation(gen_idx);
-            }
+        let mut step = cache.collector.write().begin_collection(2).unwrap();
+        assert!(matches!(
+            app.transfer(&holder.id(), &cache, true),
+            Err(GCError::CollectionInProgress)
+        ));
+        intact(&app, 0);
+        while !step.is_finished() {
+            cache.collector.write().step_collection(&mut step).unwrap();
         }
 
-        Ok(0)
+        // `cache` already holds an object under the id of the second one.
+        let twin = PyObject::with_id(entry.id(), "twin".to_string(), ObjectData::None);
+        cache.track(twin).unwrap();
+        assert!(matches!(
+            app.transfer(&holder.id(), &cache, true),
+            Err(GCError::AlreadyTracked { .. })
+        ));
+        intact(&app, 1);
     }
 
-    pub fn get_uncollectable(&self) -> Vec<PyObject> {
-        let collector = self.collector.read();
-        collector.uncollectable.clone()
+    #[test]
+    fn test_safepoint_parks_threads_for_requested_collection() {
+        let gc = Arc::new(GarbageCollector::new());
+        gc.request_stw_collection(0).unwrap();
+        assert_eq!(gc.safepoint_stats().collections, 1);
+        assert!(matches!(
+            gc.request_stw_collection(3),
+            Err(GCError::InvalidGeneration(3))
+        ));
+
+        let a = PyObject::new("A".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("B".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        gc.track(a).unwrap();
+        gc.track(b).unwrap();
+
+        let attached = gc.attach_thread();
+        let worker = {
+            let gc = gc.clone();
+            std::thread::spawn(move || {
+                let _attached = gc.attach_thread();
+                while gc.safepoint_stats().collections < 2 {
+                    gc.safepoint().unwrap();
+                    std::thread::yield_now();
+                }
+            })
+        };
+        gc.request_stw_collection(2).unwrap();
+        assert_eq!(gc.get_count(), 2);
+        gc.safepoint().unwrap();
+        assert_eq!(gc.get_count(), 0);
+        worker.join().unwrap();
+
+        let stats = gc.safepoint_stats();
+        assert_eq!(stats.collections, 2);
+        assert!(stats.waits >= 1);
+        assert!(stats.max_wait <= stats.total_wait);
+        drop(attached);
+        gc.safepoint().unwrap();
+        assert_eq!(gc.safepoint_stats().waits, stats.waits);
     }
 
-    pub fn clear_uncollectable(&self) {
-        let mut collector = self.collector.write();
-        collector.uncollectable.clear();
+    #[test]
+    fn test_stop_the_world_callbacks_can_use_the_safepoint_api() {
+        let gc = Arc::new(GarbageCollector::new());
+        let runs = Arc::new(AtomicUsize::new(0));
+        let hook: PreCollectHook = {
+            let gc = Arc::downgrade(&gc);
+            let runs = runs.clone();
+            Arc::new(move |_, _| {
+                let gc = gc.upgrade().unwrap();
+                drop(gc.attach_thread());
+                gc.safepoint().unwrap();
+                let _ = gc.safepoint_stats();
+                if runs.fetch_add(1, Ordering::SeqCst) == 0 {
+                    gc.request_stw_collection(0).unwrap();
+                }
+                PreCollect::Proceed
+            })
+        };
+        gc.add_pre_collect_hook(hook);
+
+        // Unattached, the request made by the hook runs straight after.
+        gc.request_stw_collection(0).unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+        assert_eq!(gc.safepoint_stats().collections, 2);
+
+        let attached = gc.attach_thread();
+        gc.request_stw_collection(0).unwrap();
+        gc.safepoint().unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 3);
+        drop(attached);
+        assert_eq!(gc.safepoint_stats().collections, 3);
     }
-}
 
-impl Default for GarbageCollector {
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn test_concurrent_mark_remarks_logged_objects() {
+        let gc = GarbageCollector::new();
+        let a = PyObject::new("A".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("B".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        let leaf = PyObject::new("leaf".to_string(), ObjectData::None);
+        let holder = PyObject::new("holder".to_string(), ObjectData::List(vec![leaf.clone()]));
+        for obj in [&a, &b, &leaf, &holder] {
+            gc.track(obj.clone()).unwrap();
+        }
+
+        let mark = gc.start_concurrent_mark(2).unwrap();
+        assert!(gc.is_marking());
+        assert!(matches!(
+            gc.request_stw_collection(0),
+            Err(GCError::CollectionInProgress)
+        ));
+        // Move the leaf to an object the mark doesn't know about.
+        gc.write_barrier(&holder);
+        holder.set_data(ObjectData::List(Vec::new()));
+        let fresh = PyObject::new("fresh".to_string(), ObjectData::List(vec![leaf.clone()]));
+        gc.track(fresh).unwrap();
+        mark.finish().unwrap();
+
+        assert!(!gc.is_marking());
+        assert!(!gc.is_tracked(&a.id()) && !gc.is_tracked(&b.id()));
+        assert!(gc.is_tracked(&leaf.id()));
+        // The emptied holder only holds atomic values now.
+        assert!(!gc.is_tracked(&holder.id()));
+        assert_eq!(gc.get_stats().elided, 1);
+        assert_eq!(gc.get_count(), 2);
+        assert!(gc.verify_heap().is_empty());
+
+        // A mark that ran while the leaf was moving can report it as
+        // garbage; the barrier's log keeps it and what it refers to.
+        let child = PyObject::new("child".to_string(), ObjectData::None);
+        leaf.set_data(ObjectData::List(vec![child.clone()]));
+        gc.track(child.clone()).unwrap();
+        let mut collector = gc.collector.write();
+        let (mut step, snapshot) = collector.begin_concurrent_mark(2).unwrap();
+        let racy = vec![leaf.id(), child.id()];
+        collector.remark(&mut step, &snapshot, &racy, &[leaf.id()]);
+        assert!(step.unreachable.is_empty());
+        while !step.is_finished() {
+            collector.step_collection(&mut step).unwrap();
+        }
+        assert!(collector.is_tracked(&child.id()));
     }
-}
 
-pub mod global {
-    use super::*;
-    use parking_lot::RwLock;
-    use std::sync::Once;
+    #[test]
+    fn test_release_policy_waits_for_heap_to_shrink() {
+        let gc = GarbageCollector::new();
+        let cycle = |gc: &GarbageCollector, len: usize| {
+            let a = PyObject::new("A".to_string(), ObjectData::List(Vec::new()));
+            let b = PyObject::new("B".to_string(), ObjectData::List(vec![a.clone(); len]));
+            a.set_data(ObjectData::List(vec![b.clone()]));
+            a.set_refcount(len);
+            let size = a.shallow_size() + b.shallow_size();
+            gc.track(a).unwrap();
+            gc.track(b).unwrap();
+            size
+        };
 
-    static INIT: Once = Once::new();
-    static mut GC: Option<Arc<RwLock<GarbageCollector>>> = None;
+        let big = cycle(&gc, 64);
+        gc.set_release_policy(Some(ReleasePolicy {
+            min_shrink_bytes: big,
+        }));
+        assert_eq!(gc.collect().unwrap(), 2);
+        assert_eq!(gc.release_stats().releases, 1);
 
-    pub fn get_gc() -> Arc<RwLock<GarbageCollector>> {
-        unsafe {
-            INIT.call_once(|| {
-                GC = Some(Arc::new(RwLock::new(GarbageCollector::new())));
-            });
+        let small = cycle(&gc, 1);
+        assert!(small < big);
+        gc.collect().unwrap();
+        assert_eq!(gc.release_stats().releases, 1);
 
-            let gc_ptr = &raw const GC;
-            match *gc_ptr {
-                Some(ref gc) => gc.clone(),
-                None => unreachable!("GC should be initialized by INIT.call_once"),
-            }
+        gc.set_release_policy(None);
+        cycle(&gc, 64);
+        gc.collect().unwrap();
+        assert_eq!(gc.release_stats().releases, 1);
+
+        let rss = Arc::new(crate::rss::ManualRss::new());
+        rss.set(1 << 20);
+        gc.set_rss_monitor(Some(RssMonitor::new(
+            rss,
+            crate::rss::RssPolicy {
+                max_growth_per_sec: u64::MAX,
+                min_interval: std::time::Duration::ZERO,
+            },
+        )));
+        assert_eq!(gc.release_free_memory(), 0);
+        assert_eq!(gc.release_stats().releases, 2);
+    }
+
+    #[test]
+    fn test_thread_buffers_batch_into_generation0() {
+        let gc = GarbageCollector::new();
+        gc.set_thread_buffer_capacity(3);
+        let objects: Vec<PyObject> = (0..4)
+            .map(|i| PyObject::new(format!("young{i}"), ObjectData::None))
+            .collect();
+        for obj in &objects[..2] {
+            gc.track_buffered(obj.clone()).unwrap();
         }
+        assert_eq!(gc.get_count(), 0);
+        assert_eq!(gc.thread_buffer_stats().buffered, 2);
+
+        gc.track_buffered(objects[2].clone()).unwrap();
+        assert_eq!(gc.get_count(), 3);
+        let stats = gc.thread_buffer_stats();
+        assert_eq!((stats.buffered, stats.flushes, stats.overflows), (0, 1, 1));
+
+        gc.track_buffered(objects[3].clone()).unwrap();
+        let other =
+            std::thread::scope(|scope| scope.spawn(|| gc.thread_buffer_stats()).join().unwrap());
+        assert_eq!(other, ThreadBufferStats::default());
+        gc.collect().unwrap();
+        assert!(gc.is_tracked(&objects[3].id()));
+
+        let stats = gc.thread_buffer_stats();
+        assert_eq!((stats.flushes, stats.flushed, stats.overflows), (2, 4, 1));
+        gc.track_buffered(objects[0].clone()).unwrap();
+        assert!(matches!(
+            gc.flush_thread_buffers(),
+            Err(GCError::AlreadyTracked { .. })
+        ));
     }
 
-    pub fn track(obj: PyObject) -> GCResult<()> {
-        let binding = get_gc();
-        let mut gc = binding.write();
-        gc.track(obj)
+    #[test]
+    fn test_dropped_collectors_leave_no_thread_buffers_behind() {
+        let gc = Arc::new(GarbageCollector::new());
+        let id = gc.id;
+        let (here, there) = (
+            PyObject::new("here".to_string(), ObjectData::None),
+            PyObject::new("there".to_string(), ObjectData::None),
+        );
+        let (here_id, there_id) = (here.id(), there.id());
+        gc.track_buffered(here).unwrap();
+
+        let (buffered, dropped) = (
+            Arc::new(std::sync::Barrier::new(2)),
+            Arc::new(std::sync::Barrier::new(2)),
+        );
+        let other = {
+            let (gc, buffered, dropped) = (gc.clone(), buffered.clone(), dropped.clone());
+            std::thread::spawn(move || {
+                gc.track_buffered(there).unwrap();
+                drop(gc);
+                buffered.wait();
+                dropped.wait();
+                // The stale entry goes once this thread touches its buffers.
+                let next = GarbageCollector::new();
+                next.track_buffered(PyObject::new("next".to_string(), ObjectData::None))
+                    .unwrap();
+                YOUNG_BUFFERS.with_borrow(|buffers| buffers.len())
+            })
+        };
+
+        buffered.wait();
+        drop(gc);
+        assert!(!YOUNG_BUFFERS.with_borrow(|buffers| buffers.contains_key(&id)));
+        assert!(!here_id.is_live());
+        dropped.wait();
+        assert_eq!(other.join().unwrap(), 1);
+        assert!(!there_id.is_live());
     }
 
-    pub fn untrack(obj_id: &ObjectId) -> GCResult<()> {
-        let binding = get_gc();
-        let mut gc = binding.write();
-        gc.untrack(obj_id)
+    #[test]
+    #[cfg(feature = "async")]
+    fn test_collect_cooperative_yields_between_phases() {
+        use std::future::Future;
+        use std::task::{Context, Poll, Waker};
+
+        let gc = GarbageCollector::new();
+        let a = PyObject::new("A".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("B".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        gc.track(a).unwrap();
+        gc.track(b).unwrap();
+
+        let mut polls = 0;
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut future = std::pin::pin!(gc.collect_cooperative(1));
+        let collected = loop {
+            polls += 1;
+            if let Poll::Ready(result) = future.as_mut().poll(&mut cx) {
+                break result.unwrap();
+            }
+        };
+        assert_eq!(collected, 2);
+        assert_eq!(polls, 4);
+        assert_eq!(gc.get_count(), 0);
+
+        let c = PyObject::new("C".to_string(), ObjectData::None);
+        gc.track(c).unwrap();
+        {
+            let mut future = std::pin::pin!(gc.collect_cooperative(1));
+            assert!(future.as_mut().poll(&mut cx).is_pending());
+        }
+        assert!(gc.verify_heap().is_empty());
+        assert_eq!(gc.collect().unwrap(), 0);
     }
 
-    pub fn collect() -> GCResult<usize> {
-        let binding = get_gc();
-        let gc = binding.read();
-        gc.collect()
+    #[test]
+    fn test_large_objects_wait_for_full_collections() {
+        let gc = GarbageCollector::new();
+        let small_cycle = |gc: &GarbageCollector| {
+            let a = PyObject::new("A".to_string(), ObjectData::List(Vec::new()));
+            let b = PyObject::new("B".to_string(), ObjectData::List(vec![a.clone()]));
+            a.set_data(ObjectData::List(vec![b.clone()]));
+            gc.track(a).unwrap();
+            gc.track(b).unwrap();
+        };
+        let blob = PyObject::new("blob".to_string(), ObjectData::List(Vec::new()));
+        blob.set_data(ObjectData::List(vec![blob.clone(); 512]));
+        blob.set_refcount(512);
+        let threshold = blob.shallow_size();
+        gc.set_large_object_threshold(threshold);
+        assert_eq!(gc.get_large_object_threshold(), threshold);
+
+        gc.track(blob.clone()).unwrap();
+        small_cycle(&gc);
+        let stats = gc.get_stats();
+        assert_eq!(stats.generation_counts, [2, 0, 0]);
+        assert_eq!(
+            (stats.large_object_count, stats.large_object_bytes),
+            (1, threshold)
+        );
+
+        assert_eq!(gc.collect_generation(1).unwrap(), 2);
+        assert!(gc.is_tracked(&blob.id()));
+        assert!(gc.verify_heap().is_empty());
+
+        small_cycle(&gc);
+        assert_eq!(gc.collect().unwrap(), 3);
+        let stats = gc.get_stats();
+        assert_eq!(stats.large_object_count, 0);
+        assert_eq!(stats.generation_counts, [0, 0, 0]);
+        assert!(gc.verify_heap().is_empty());
     }
 
-    pub fn get_stats() -> crate::GCStats {
-        let binding = get_gc();
-        let gc = binding.read();
-        gc.get_stats()
+    #[test]
+    fn test_intern_shares_small_values() {
+        let gc = GarbageCollector::new();
+        let one = gc.intern(ObjectData::Integer(1));
+        assert!(one.ptr_eq(&gc.intern(ObjectData::Integer(1))));
+        assert!(one.is_immortal());
+        assert!(!one.is_gc_tracked());
+        assert!(!one.dec_ref());
+
+        let name = gc.intern(ObjectData::String("__init__".to_string()));
+        assert!(name.ptr_eq(&gc.intern(ObjectData::String("__init__".to_string()))));
+        assert_eq!(name.name(), "str");
+
+        let big = gc.intern(ObjectData::Integer(1 << 20));
+        assert!(!big.ptr_eq(&gc.intern(ObjectData::Integer(1 << 20))));
+        assert!(!big.is_immortal());
+        let long = "x".repeat(MAX_INTERNED_STRING_LEN + 1);
+        assert!(
+            !gc.intern(ObjectData::String(long.clone()))
+                .ptr_eq(&gc.intern(ObjectData::String(long)))
+        );
+
+        assert_eq!(
+            gc.intern_stats(),
+            InternStats {
+                hits: 2,
+                misses: 2,
+                uncached: 4,
+                entries: 2,
+            }
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::object::{ObjectData, PyObject};
+    #[test]
+    fn test_atomic_containers_are_elided_until_written() {
+        let gc = GarbageCollector::new();
+        let key = gc.intern(ObjectData::String("size".to_string()));
+        let table = PyObject::new("dict".to_string(), ObjectData::Dict(PyDict::new()));
+        table
+            .dict_set(key.clone(), gc.intern(ObjectData::Integer(3)))
+            .unwrap();
+        let row = PyObject::new("list".to_string(), ObjectData::List(vec![table.clone()]));
+        gc.track(table.clone()).unwrap();
+        gc.track(row.clone()).unwrap();
+
+        assert_eq!(gc.collect().unwrap(), 0);
+        assert!(!gc.is_tracked(&table.id()));
+        assert!(gc.is_tracked(&row.id()));
+        gc.collect().unwrap();
+        assert!(!gc.is_tracked(&row.id()));
+        let stats = gc.get_stats();
+        assert_eq!((stats.elided, stats.total_tracked), (2, 0));
+        assert_eq!(stats.generation_counts, [0, 0, 0]);
+
+        // Writing a cycle through the dict brings both containers back, so
+        // the cycle can be collected.
+        let node = PyObject::new("node".to_string(), ObjectData::List(vec![table.clone()]));
+        table.dict_set(key, node.clone()).unwrap();
+        gc.track(node).unwrap();
+        row.set_data(ObjectData::List(Vec::new()));
+        assert_eq!(gc.collect().unwrap(), 2);
+        assert_eq!(gc.get_stats().elided, 1);
+        assert!(gc.verify_heap().is_empty());
+    }
 
     #[test]
-    fn test_gc_creation() {
+    fn test_dead_elided_containers_are_collected() {
         let gc = GarbageCollector::new();
-        assert!(gc.is_enabled());
-        assert_eq!(gc.get_count(), 0);
+        let row = PyObject::new(
+            "list".to_string(),
+            ObjectData::List(vec![gc.intern(ObjectData::Integer(1))]),
+        );
+        let kept = PyObject::new(
+            "list".to_string(),
+            ObjectData::List(vec![gc.intern(ObjectData::Integer(2))]),
+        );
+        gc.track(row.clone()).unwrap();
+        gc.track(kept.clone()).unwrap();
+        assert_eq!(gc.collect().unwrap(), 0);
+        assert_eq!(gc.get_stats().elided, 2);
+
+        row.set_refcount(0);
+        assert_eq!(gc.collect_generation(0).unwrap(), 1);
+        let stats = gc.get_stats();
+        assert_eq!((stats.elided, stats.total_tracked), (1, 0));
+        assert_eq!(stats.generation_counts, [0, 0, 0]);
+        assert!(!gc.is_tracked(&row.id()) && !gc.is_tracked(&kept.id()));
+        assert!(gc.verify_heap().is_empty());
     }
 
     #[test]
-    fn test_object_tracking() {
-        let mut gc = GarbageCollector::new();
+    fn test_saveall_keeps_unreachable_cycles() {
+        let gc = GarbageCollector::new();
+        gc.set_debug(DEBUG_SAVEALL);
+        let a = PyObject::new("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        let root = PyObject::new("root".to_string(), ObjectData::Custom(CustomData::new(())));
+        for obj in [&a, &b, &root] {
+            gc.track(obj.clone()).unwrap();
+        }
 
-        let obj = PyObject::new("test".to_string(), ObjectData::Integer(42));
-        let obj_id = obj.id;
+        assert_eq!(gc.collect().unwrap(), 2);
+        let saved = gc.saved_garbage();
+        let mut found: Vec<(&str, &str)> = saved
+            .iter()
+            .map(|garbage| (garbage.object.name(), garbage.type_name))
+            .collect();
+        found.sort();
+        assert_eq!(found, [("a", "list"), ("b", "list")]);
+        assert!(a.children().iter().any(|child| child.ptr_eq(&b)));
+        assert!(!gc.is_tracked(&a.id()) && gc.is_tracked(&root.id()));
 
-        assert!(gc.track(obj).is_ok());
-        assert_eq!(gc.get_count(), 1);
+        gc.clear_saved_garbage();
+        assert!(gc.saved_garbage().is_empty());
+        assert!(gc.verify_heap().is_empty());
+    }
 
-        assert!(gc.untrack(&obj_id).is_ok());
-        assert_eq!(gc.get_count(), 0);
+    #[test]
+    fn test_named_heaps_are_independent() {
+        let plugins = registry::get_or_create("test-plugins");
+        let sandbox = registry::get_or_create("test-sandbox");
+        assert!(Arc::ptr_eq(
+            &plugins,
+            &registry::get_or_create("test-plugins")
+        ));
+
+        let obj = PyObject::new("plugin".to_string(), ObjectData::None);
+        plugins.track(obj.clone()).unwrap();
+        assert!(plugins.is_tracked(&obj.id()));
+        assert!(!sandbox.is_tracked(&obj.id()));
+
+        let names: Vec<String> = registry::enumerate()
+            .into_iter()
+            .map(|(name, _)| name)
+            .filter(|name| name.starts_with("test-"))
+            .collect();
+        assert_eq!(names, ["test-plugins", "test-sandbox"]);
+
+        let totals = registry::aggregate_stats();
+        assert!(totals.heaps >= 2);
+        assert!(totals.total_tracked >= 1);
+        let plugin_stats = registry::stats()
+            .into_iter()
+            .find(|(name, _)| name == "test-plugins")
+            .unwrap()
+            .1;
+        assert_eq!(plugin_stats.total_tracked, 1);
+
+        assert!(registry::remove("test-sandbox").is_some());
+        assert!(registry::get("test-sandbox").is_none());
+        registry::remove("test-plugins");
     }
 
     #[test]
-    fn test_generation_thresholds() {
-        let mut gc = GarbageCollector::new();
+    fn test_compaction_moves_pooled_payloads_and_frees_arenas() {
+        let gc = GarbageCollector::new();
+        let per_arena = crate::arena::ARENA_BYTES / 64;
+        let objects: Vec<_> = (0..per_arena * 4)
+            .map(|i| {
+                let obj = gc
+                    .allocate_pooled("P".to_string(), ObjectData::None, 64)
+                    .unwrap();
+                obj.with_pooled(|bytes| bytes.fill(i as u8)).unwrap();
+                (i, obj)
+            })
+            .collect();
+        // Keep a quarter of each arena.
+        let mut kept = Vec::new();
+        for (i, obj) in objects {
+            if i % 4 == 0 {
+                kept.push((i, obj));
+            } else {
+                gc.untrack(&obj.id()).unwrap();
+            }
+        }
+        let before = gc.fragmentation();
+        assert_eq!(before.arenas, 4);
+        assert_eq!(before.reclaimable_arenas, 3);
 
-        assert_eq!(gc.get_threshold(0), Some(700));
-        assert_eq!(gc.get_threshold(1), Some(10));
-        assert_eq!(gc.get_threshold(2), Some(10));
+        gc.set_compaction_policy(Some(CompactionPolicy {
+            min_reclaimable_bytes: 4 * crate::arena::ARENA_BYTES,
+        }));
+        gc.collect().unwrap();
+        assert_eq!(gc.compaction_stats().compactions, 0);
 
-        assert!(gc.set_threshold(0, 1000).is_ok());
-        assert_eq!(gc.get_threshold(0), Some(1000));
+        gc.set_compaction_policy(Some(CompactionPolicy {
+            min_reclaimable_bytes: crate::arena::ARENA_BYTES,
+        }));
+        gc.collect().unwrap();
+        let stats = gc.compaction_stats();
+        assert_eq!(stats.compactions, 1);
+        assert_eq!(stats.arenas_released, 3);
+        assert_eq!(gc.fragmentation().arenas, 1);
+        for (i, obj) in &kept {
+            assert_eq!(
+                obj.with_pooled(|bytes| bytes.to_vec()).unwrap(),
+                vec![*i as u8; 64]
+            );
+        }
+
+        let (_, obj) = kept.pop().unwrap();
+        let handle = obj.pooled_handle().unwrap();
+        gc.untrack(&obj.id()).unwrap();
+        drop(obj);
+        assert!(!gc.pool.lock().contains(handle));
+        assert_eq!(gc.compact().arenas_released, 0);
     }
 
     #[test]
-    fn test_collection() {
+    fn test_pooled_closure_can_use_the_pool() {
         let gc = GarbageCollector::new();
+        let obj = gc
+            .allocate_pooled("P".to_string(), ObjectData::None, 32)
+            .unwrap();
+        let other = gc
+            .allocate_pooled("Q".to_string(), ObjectData::None, 32)
+            .unwrap();
+        gc.untrack(&other.id()).unwrap();
 
-        assert!(gc.collect().is_ok());
-        assert_eq!(gc.get_count(), 0);
+        let made = obj
+            .with_pooled(|bytes| {
+                bytes.fill(7);
+                drop(other);
+                gc.compact();
+                gc.allocate_pooled("R".to_string(), ObjectData::None, 32)
+                    .unwrap()
+            })
+            .unwrap();
+        assert_eq!(
+            obj.with_pooled(|bytes| bytes.to_vec()).unwrap(),
+            vec![7; 32]
+        );
+        assert_eq!(
+            made.with_pooled(|bytes| bytes.to_vec()).unwrap(),
+            vec![0; 32]
+        );
     }
 }
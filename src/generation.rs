@@ -1,11 +1,23 @@
 use crate::GCResult;
 use crate::error::GCError;
 use crate::object::{ObjectId, PyObject};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug)]
 pub struct Generation {
     pub count: usize,
+    /// Shallow size of the counted objects, as of when each was counted in.
+    pub bytes: usize,
     pub threshold: usize,
+    pub collections: usize,
+    /// Objects freed by collections of this generation.
+    pub collected: usize,
+    /// Candidates examined by collections of this generation.
+    pub scanned: usize,
+    /// Survivors its collections moved on to the next older generation.
+    pub promoted: usize,
     pub head: crate::object::PyGCHead,
 }
 
@@ -18,7 +30,12 @@ impl Generation {
 
         Self {
             count: 0,
+            bytes: 0,
             threshold,
+            collections: 0,
+            collected: 0,
+            scanned: 0,
+            promoted: 0,
             head,
         }
     }
@@ -46,12 +63,50 @@ impl Generation {
 
     pub fn clear(&mut self) {
         self.count = 0;
+        self.bytes = 0;
+    }
+}
+
+/// Objects too big to be worth moving through the generations. They skip
+/// the generation counts and are only collected by full collections.
+#[derive(Debug, Default)]
+pub struct LargeObjectSpace {
+    /// Shallow size from which an object is large; 0 turns the space off.
+    pub threshold: usize,
+    pub objects: HashSet<ObjectId>,
+    /// Shallow size of `objects`, as of when each was put in.
+    pub bytes: usize,
+}
+
+impl LargeObjectSpace {
+    pub fn is_large(&self, size: usize) -> bool {
+        self.threshold > 0 && size >= self.threshold
+    }
+}
+
+/// Proof that a collection is running, from
+/// [`GenerationManager::start_collection`]. Dropping it ends the collection,
+/// so one abandoned part way doesn't block every later one.
+#[derive(Debug)]
+pub struct CollectionGuard {
+    collecting: Arc<AtomicBool>,
+}
+
+impl Drop for CollectionGuard {
+    fn drop(&mut self) {
+        self.collecting.store(false, Ordering::Release);
     }
 }
 
 #[derive(Debug)]
 pub struct GenerationManager {
     pub generations: Vec<Generation>,
+    /// Objects that survived the last full collection.
+    pub long_lived_total: usize,
+    /// Objects promoted into the oldest generation since then.
+    pub long_lived_pending: usize,
+    pub large_objects: LargeObjectSpace,
+    collecting: Arc<AtomicBool>,
 }
 
 impl Default for GenerationManager {
@@ -68,14 +123,52 @@ impl GenerationManager {
             Generation::new(10),
         ];
 
-        Self { generations }
+        Self {
+            generations,
+            long_lived_total: 0,
+            long_lived_pending: 0,
+            large_objects: LargeObjectSpace::default(),
+            collecting: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Claim the generations for a collection. Fails with
+    /// `CollectionInProgress` while another collection holds its guard.
+    pub fn start_collection(&self) -> GCResult<CollectionGuard> {
+        self.collecting
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .map_err(|_| GCError::CollectionInProgress)?;
+        Ok(CollectionGuard {
+            collecting: Arc::clone(&self.collecting),
+        })
+    }
+
+    /// End the collection `guard` was claimed for.
+    pub fn end_collection(&self, guard: CollectionGuard) {
+        drop(guard);
+    }
+
+    pub fn is_collecting(&self) -> bool {
+        self.collecting.load(Ordering::Acquire)
+    }
+
+    /// The flag behind [`is_collecting`](Self::is_collecting), for readers
+    /// that can't take the collector lock.
+    pub fn collecting_flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.collecting)
+    }
+
+    /// Forget a collection whose guard will never be dropped, as after
+    /// `fork()` in the child.
+    pub fn reset_collection(&self) {
+        self.collecting.store(false, Ordering::Release);
     }
 
     pub fn add_to_generation0(&mut self, obj: PyObject) -> GCResult<()> {
         if let Some(generation) = self.generations.get_mut(0) {
             generation.add_object(obj)
         } else {
-            Err(GCError::Internal("Generation 0 not found".to_string()))
+            Err(GCError::InvalidGeneration(0))
         }
     }
 
@@ -83,19 +176,79 @@ impl GenerationManager {
         if let Some(generation) = self.generations.get_mut(0) {
             generation.add_object_fast(obj_id)
         } else {
-            Err(GCError::Internal("Generation 0 not found".to_string()))
+            Err(GCError::InvalidGeneration(0))
         }
     }
 
+    /// Count a newly tracked object in generation 0, or put it in the large
+    /// object space if `size` makes it large.
+    pub fn add_object(&mut self, obj_id: ObjectId, size: usize) -> GCResult<()> {
+        if self.large_objects.is_large(size) {
+            self.large_objects.objects.insert(obj_id);
+            self.large_objects.bytes += size;
+            Ok(())
+        } else {
+            self.add_to_generation0_fast(obj_id)?;
+            self.generations[0].bytes += size;
+            Ok(())
+        }
+    }
+
+    /// Forget an untracked object of `size` bytes: drop it from the large
+    /// object space, or take it off the count of `generation`, the one it
+    /// was in.
+    pub fn remove_object(&mut self, obj_id: &ObjectId, generation: usize, size: usize) {
+        if self.large_objects.objects.remove(obj_id) {
+            self.large_objects.bytes = self.large_objects.bytes.saturating_sub(size);
+        } else if let Some(generation) = self.generations.get_mut(generation) {
+            generation.count = generation.count.saturating_sub(1);
+            generation.bytes = generation.bytes.saturating_sub(size);
+        }
+    }
+
+    /// Move `count` objects of `bytes` in all from one generation to another.
+    pub fn move_objects(&mut self, from: usize, to: usize, count: usize, bytes: usize) {
+        let from = &mut self.generations[from];
+        from.count = from.count.saturating_sub(count);
+        from.bytes = from.bytes.saturating_sub(bytes);
+        let to = &mut self.generations[to];
+        to.count += count;
+        to.bytes += bytes;
+    }
+
     pub fn promote_generation(&mut self, from_gen: usize, to_gen: usize) -> GCResult<()> {
-        if from_gen >= self.generations.len() || to_gen >= self.generations.len() {
-            return Err(GCError::Internal("Invalid generation index".to_string()));
+        if let Some(&generation) = [from_gen, to_gen]
+            .iter()
+            .find(|&&generation| generation >= self.generations.len())
+        {
+            return Err(GCError::InvalidGeneration(generation));
         }
 
-        let from_count = self.generations[from_gen].count;
+        let (from_count, from_bytes) = (
+            self.generations[from_gen].count,
+            self.generations[from_gen].bytes,
+        );
         self.generations[from_gen].clear();
         self.generations[to_gen].count += from_count;
+        self.generations[to_gen].bytes += from_bytes;
+
+        Ok(())
+    }
+
+    /// Fold the counts of every generation younger than `generation` into
+    /// it, as CPython does before collecting `generation`.
+    pub fn merge_younger(&mut self, generation: usize) -> GCResult<()> {
+        if generation >= self.generations.len() {
+            return Err(GCError::InvalidGeneration(generation));
+        }
 
+        let (mut count, mut bytes) = (0, 0);
+        for younger in &mut self.generations[..generation] {
+            count += std::mem::take(&mut younger.count);
+            bytes += std::mem::take(&mut younger.bytes);
+        }
+        self.generations[generation].count += count;
+        self.generations[generation].bytes += bytes;
         Ok(())
     }
 
@@ -111,6 +264,22 @@ impl GenerationManager {
         self.generations.iter().map(|g| g.count).sum()
     }
 
+    /// Oldest generation over its threshold. A full collection is put off
+    /// until the objects pending promotion reach a quarter of those that
+    /// survived the last one, so steady allocation doesn't make full
+    /// collections quadratic. A generation 0 threshold of 0 turns automatic
+    /// collection off, as in CPython.
+    pub fn generation_to_collect(&self) -> Option<usize> {
+        if self.generations.first()?.threshold == 0 {
+            return None;
+        }
+        let oldest = self.generations.len() - 1;
+        (0..=oldest).rev().find(|&generation| {
+            self.generations[generation].should_collect()
+                && (generation != oldest || self.long_lived_pending >= self.long_lived_total / 4)
+        })
+    }
+
     pub fn should_collect_generation(&self, generation: usize) -> bool {
         self.generations
             .get(generation)
@@ -4,21 +4,73 @@
 //! with cycle detection. It provides the core functionality for managing object
 //! lifecycles and detecting reference cycles.
 
+pub mod arena;
+pub mod clock;
 pub mod collector;
+#[cfg(all(unix, feature = "control"))]
+pub mod control;
 pub mod error;
 pub mod ffi;
 pub mod gc;
 pub mod generation;
 pub mod object;
+#[cfg(feature = "snapshot")]
+pub mod pickle;
+pub mod python_api;
+pub mod rss;
+pub mod slab;
+#[cfg(feature = "snapshot")]
+pub mod snapshot;
+pub mod testing;
 pub mod traversal;
+pub mod weakmap;
 
-#[derive(Debug, Clone)]
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct GCStats {
     pub collections: usize,
     pub collected: usize,
     pub uncollectable: usize,
     pub total_tracked: usize,
     pub generation_counts: [usize; 3],
+    pub generation_collections: [usize; 3],
+    pub generation_collected: [usize; 3],
+    pub thresholds: [usize; 3],
+    /// Shallow size of the objects each generation counts.
+    pub generation_bytes: [usize; 3],
+    /// Candidates examined by collections of each generation, and the
+    /// survivors they moved on to the next older one.
+    pub generation_scanned: [usize; 3],
+    pub generation_promoted: [usize; 3],
+    pub last_pause: Duration,
+    pub long_lived_total: usize,
+    pub long_lived_pending: usize,
+    /// Atomic-only containers currently untracked.
+    pub elided: usize,
+    /// Objects in the large object space, and their shallow size.
+    pub large_object_count: usize,
+    pub large_object_bytes: usize,
+    /// Objects in the permanent generation, and their shallow size. They are
+    /// part of `total_tracked` but of no generation count.
+    pub permanent_count: usize,
+    pub permanent_bytes: usize,
+    /// Latest RSS reading and growth rate; zero unless an RSS monitor is set.
+    pub rss_bytes: u64,
+    pub rss_growth_per_sec: u64,
+}
+
+impl GCStats {
+    /// Share of the objects collections of `generation` examined that
+    /// survived them; 0 before its first collection.
+    pub fn survivor_rate(&self, generation: usize) -> f64 {
+        let scanned = self.generation_scanned[generation];
+        if scanned == 0 {
+            return 0.0;
+        }
+        scanned.saturating_sub(self.generation_collected[generation]) as f64 / scanned as f64
+    }
 }
 
 pub use error::GCError;
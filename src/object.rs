@@ -1,9 +1,125 @@
+use crate::GCResult;
+use crate::arena::{GcHandle, PooledCell};
+use crate::error::GCError;
+use indexmap::IndexMap;
+use parking_lot::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::any::Any;
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::collections::hash_map::DefaultHasher;
 use std::ffi::c_void;
+use std::fmt;
 use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU32, AtomicU64, AtomicUsize, Ordering};
 
+/// Names an object by its slot in the id slab and the generation the slot
+/// was on when the id was handed out. A slot is reused once the object
+/// holding it is dropped, under the next generation, so a stale id never
+/// names the new occupant and [`ObjectId::is_live`] can tell it is stale.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ObjectId {
-    pub id: usize,
+    pub index: u32,
+    pub generation: u32,
+}
+
+/// Current generation of every id slot, in buckets that double in size so a
+/// slot never moves once allocated and is read and bumped without a lock.
+/// Bucket `b` holds slots `2^b - 1` up to `2^(b+1) - 2`.
+static SLOT_GENERATIONS: [AtomicPtr<AtomicU32>; 32] =
+    [const { AtomicPtr::new(std::ptr::null_mut()) }; 32];
+
+/// The next slot never handed out. Slot 0 is never handed out.
+static NEXT_SLOT: AtomicU32 = AtomicU32::new(1);
+
+/// Generation of a slot that has been reused as often as it can be. No id
+/// is handed out on it, so every id ever handed out for the slot is stale.
+const RETIRED_GENERATION: u32 = u32::MAX;
+
+/// Freed slots beyond what each thread keeps for itself. Threads take and
+/// give back slots here in batches, so allocation rarely touches the lock.
+static FREE_SLOTS: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+
+/// Freed slots a thread keeps to itself; twice this many and it hands half
+/// of them back to [`FREE_SLOTS`].
+const LOCAL_FREE_SLOTS: usize = 256;
+
+/// A thread's own free slots, handed back when the thread exits.
+struct LocalFreeSlots(Vec<u32>);
+
+impl Drop for LocalFreeSlots {
+    fn drop(&mut self) {
+        FREE_SLOTS.lock().append(&mut self.0);
+    }
+}
+
+thread_local! {
+    static LOCAL_FREE: RefCell<LocalFreeSlots> = const { RefCell::new(LocalFreeSlots(Vec::new())) };
+}
+
+/// Order in which objects were created, oldest first.
+static NEXT_ALLOCATION: AtomicU64 = AtomicU64::new(0);
+
+fn slot_position(index: u32) -> (usize, usize) {
+    let position = index as usize + 1;
+    let bucket = position.ilog2() as usize;
+    (bucket, position - (1 << bucket))
+}
+
+/// Generation cell of a slot, if its bucket was ever allocated.
+fn slot_generation(index: u32) -> Option<&'static AtomicU32> {
+    let (bucket, offset) = slot_position(index);
+    let slots = SLOT_GENERATIONS[bucket].load(Ordering::Acquire);
+    // SAFETY: buckets are never freed and hold `1 << bucket` cells.
+    (!slots.is_null()).then(|| unsafe { &*slots.add(offset) })
+}
+
+/// Generation cell of a slot, allocating its bucket if need be.
+fn slot_generation_or_alloc(index: u32) -> &'static AtomicU32 {
+    if let Some(generation) = slot_generation(index) {
+        return generation;
+    }
+
+    let (bucket, _) = slot_position(index);
+    let len = 1usize << bucket;
+    let fresh = Box::into_raw((0..len).map(|_| AtomicU32::new(0)).collect::<Box<[_]>>());
+    if let Err(_installed) = SLOT_GENERATIONS[bucket].compare_exchange(
+        std::ptr::null_mut(),
+        fresh.cast::<AtomicU32>(),
+        Ordering::AcqRel,
+        Ordering::Acquire,
+    ) {
+        // Another thread installed the bucket first.
+        drop(unsafe { Box::from_raw(fresh) });
+    }
+    slot_generation(index).unwrap_or_else(|| unreachable!("bucket {bucket} was just installed"))
+}
+
+fn take_free_slot() -> Option<u32> {
+    let take = |local: &mut Vec<u32>| {
+        if local.is_empty() {
+            let mut shared = FREE_SLOTS.lock();
+            let from = shared.len().saturating_sub(LOCAL_FREE_SLOTS);
+            local.extend(shared.drain(from..));
+        }
+        local.pop()
+    };
+    LOCAL_FREE
+        .try_with(|local| take(&mut local.borrow_mut().0))
+        .unwrap_or_else(|_| FREE_SLOTS.lock().pop())
+}
+
+fn give_back_slot(index: u32) {
+    let given = LOCAL_FREE.try_with(|local| {
+        let local = &mut local.borrow_mut().0;
+        local.push(index);
+        if local.len() >= 2 * LOCAL_FREE_SLOTS {
+            FREE_SLOTS.lock().extend(local.drain(LOCAL_FREE_SLOTS..));
+        }
+    });
+    if given.is_err() {
+        FREE_SLOTS.lock().push(index);
+    }
 }
 
 impl Default for ObjectId {
@@ -12,17 +128,85 @@ impl Default for ObjectId {
     }
 }
 
+impl fmt::Display for ObjectId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.generation {
+            0 => self.index.fmt(f),
+            generation => write!(f, "{}/{generation}", self.index),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ObjectId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.as_u64())
+    }
+}
+
 impl ObjectId {
+    /// Take a free slot, or a new one when none is free.
     pub fn new() -> Self {
-        static mut COUNTER: usize = 0;
-        unsafe {
-            COUNTER += 1;
-            Self { id: COUNTER }
+        if let Some(index) = take_free_slot() {
+            return Self {
+                index,
+                generation: slot_generation_or_alloc(index).load(Ordering::Acquire),
+            };
+        }
+        let index = NEXT_SLOT
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |next| {
+                (next < u32::MAX).then(|| next + 1)
+            })
+            .expect("object id slab is full");
+        Self {
+            index,
+            generation: slot_generation_or_alloc(index).load(Ordering::Acquire),
         }
     }
 
-    pub fn as_usize(&self) -> usize {
-        self.id
+    /// An id for a slot taken by hand, such as a test's or a saved one. It is
+    /// not backed by the slab, so it says nothing about liveness.
+    pub const fn from_raw(index: u32, generation: u32) -> Self {
+        Self { index, generation }
+    }
+
+    /// Whether the object this id was handed out for is still alive. Ids
+    /// made with [`from_raw`](Self::from_raw) are only live by coincidence.
+    pub fn is_live(&self) -> bool {
+        self.index < NEXT_SLOT.load(Ordering::Acquire)
+            && slot_generation(self.index)
+                .is_some_and(|generation| generation.load(Ordering::Acquire) == self.generation)
+    }
+
+    /// Give the slot back under the next generation, unless it has moved on.
+    /// A slot that reaches [`RETIRED_GENERATION`] is never handed out again,
+    /// so its generation can't wrap round to one a stale id still carries.
+    fn release(&self) {
+        let Some(next) = self.generation.checked_add(1) else {
+            return;
+        };
+        if let Some(generation) = slot_generation(self.index)
+            && generation
+                .compare_exchange(self.generation, next, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            && next != RETIRED_GENERATION
+        {
+            give_back_slot(self.index);
+        }
+    }
+
+    /// Index and generation packed into one number, generation high.
+    pub fn as_u64(&self) -> u64 {
+        (u64::from(self.generation) << 32) | u64::from(self.index)
+    }
+}
+
+/// # Safety
+///
+/// Must only be called in a forked child before any other thread is started.
+pub(crate) unsafe fn after_fork() {
+    if FREE_SLOTS.is_locked() {
+        unsafe { FREE_SLOTS.force_unlock() };
     }
 }
 
@@ -108,111 +292,543 @@ impl PyGCHead {
     }
 }
 
+/// Refcount given to immortal objects, such as interned values. Far enough
+/// from the top that increments can't wrap it.
+pub const IMMORTAL_REFCOUNT: usize = u32::MAX as usize;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ObjectData {
     Integer(i64),
     Float(f64),
+    Bool(bool),
+    /// Real and imaginary parts.
+    Complex(f64, f64),
     String(String),
+    Bytes(Vec<u8>),
     List(Vec<PyObject>),
-    Dict(Vec<(PyObject, PyObject)>),
-    Custom(*mut c_void),
+    Dict(PyDict),
+    Custom(CustomData),
     None,
 }
 
-unsafe impl Send for ObjectData {}
-unsafe impl Sync for ObjectData {}
+impl ObjectData {
+    /// Bytes held by the payload itself, not counting the objects it refers
+    /// to.
+    pub fn payload_size(&self) -> usize {
+        match self {
+            ObjectData::Integer(_) | ObjectData::Float(_) => 8,
+            ObjectData::Bool(_) => 1,
+            ObjectData::Complex(..) => 16,
+            ObjectData::String(s) => s.len(),
+            ObjectData::Bytes(bytes) => bytes.len(),
+            ObjectData::List(items) => items.len() * std::mem::size_of::<PyObject>(),
+            ObjectData::Dict(entries) => {
+                entries.len() * std::mem::size_of::<(PyObjectKey, (PyObject, PyObject))>()
+            }
+            ObjectData::Custom(_) => std::mem::size_of::<CustomData>(),
+            ObjectData::None => 0,
+        }
+    }
 
-#[repr(C)]
-#[derive(Debug, Clone)]
-pub struct PyObject {
-    pub gc_head: PyGCHead,
-    pub name: String,
-    pub data: ObjectData,
-    pub refcount: usize,
-    pub gc_tracked: bool,
-    pub has_finalizer: bool,
+    /// Whether the data can refer to other objects. Like CPython, which only
+    /// tracks containers, the collector gains nothing from tracking objects
+    /// whose data is atomic. Custom data counts as a container since a
+    /// tracer may find references in it.
+    pub fn should_track(&self) -> bool {
+        match self {
+            ObjectData::List(_) | ObjectData::Dict(_) | ObjectData::Custom(_) => true,
+            ObjectData::Integer(_)
+            | ObjectData::Float(_)
+            | ObjectData::Bool(_)
+            | ObjectData::Complex(..)
+            | ObjectData::String(_)
+            | ObjectData::Bytes(_)
+            | ObjectData::None => false,
+        }
+    }
+
+    /// Python type name for the data.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            ObjectData::Integer(_) => "int",
+            ObjectData::Float(_) => "float",
+            ObjectData::Bool(_) => "bool",
+            ObjectData::Complex(..) => "complex",
+            ObjectData::String(_) => "str",
+            ObjectData::Bytes(_) => "bytes",
+            ObjectData::List(_) => "list",
+            ObjectData::Dict(_) => "dict",
+            ObjectData::Custom(_) => "object",
+            ObjectData::None => "NoneType",
+        }
+    }
+}
+
+/// Payload of an [`ObjectData::Custom`] object: any value the embedder
+/// shares between threads, found again by downcasting. Clones share the
+/// value, and two payloads are equal only when they share it. The collector
+/// cannot see into it; a [`Trace`](crate::traversal::Trace) impl reports the
+/// objects it refers to.
+#[derive(Clone)]
+pub struct CustomData(Arc<dyn Any + Send + Sync>);
+
+impl CustomData {
+    pub fn new<T: Any + Send + Sync>(value: T) -> Self {
+        Self(Arc::new(value))
+    }
+
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> {
+        self.0.downcast_ref()
+    }
+}
+
+impl PartialEq for CustomData {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for CustomData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "CustomData({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+/// Dict storage: insertion-ordered, keyed by [`PyObjectKey`], holding the key
+/// object alongside its value so both stay reachable.
+pub type PyDict = IndexMap<PyObjectKey, (PyObject, PyObject)>;
+
+/// Dict key for a [`PyObject`]. Keys compare by object identity, like
+/// `PyObject` itself; the hash is cached from the key's value so integer and
+/// string keys spread the same way they would in Python.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PyObjectKey {
+    pub hash: u64,
     pub id: ObjectId,
 }
 
-unsafe impl Send for PyObject {}
-unsafe impl Sync for PyObject {}
+impl PyObjectKey {
+    pub fn new(key: &PyObject) -> Self {
+        let mut hasher = DefaultHasher::new();
+        match &*key.data() {
+            ObjectData::Integer(value) => value.hash(&mut hasher),
+            // True and False hash as 1 and 0, as in Python.
+            ObjectData::Bool(value) => i64::from(*value).hash(&mut hasher),
+            ObjectData::Float(value) | ObjectData::Complex(value, 0.0) => {
+                value.to_bits().hash(&mut hasher)
+            }
+            ObjectData::Complex(re, im) => (re.to_bits(), im.to_bits()).hash(&mut hasher),
+            ObjectData::String(value) => value.hash(&mut hasher),
+            ObjectData::Bytes(value) => value.hash(&mut hasher),
+            _ => key.id().hash(&mut hasher),
+        }
+        Self {
+            hash: hasher.finish(),
+            id: key.id(),
+        }
+    }
+}
+
+impl Hash for PyObjectKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
+}
+
+/// GC-visible state of one object, shared by every [`PyObject`] handle to it.
+#[derive(Debug)]
+pub struct GcBox {
+    id: ObjectId,
+    name: String,
+    gc_head: Mutex<PyGCHead>,
+    data: RwLock<ObjectData>,
+    refcount: AtomicUsize,
+    gc_tracked: AtomicBool,
+    has_finalizer: AtomicBool,
+    /// Set whenever the data is written, so the collector can tell which
+    /// untracked containers may have gained references.
+    mutated: AtomicBool,
+    /// Whether `id` came from the slab, which gets the slot back on drop.
+    owns_id: bool,
+    /// Position in creation order. Ids are reused, so they don't give one.
+    allocation: u64,
+    /// Operational group the embedder put the object in, if any.
+    tag: RwLock<Option<Arc<str>>>,
+    /// References the embedder holds from outside the object graph, as
+    /// declared for the refcount audit.
+    external_refs: AtomicUsize,
+    /// Shallow size the collector counted the object at when tracking it,
+    /// so the same amount comes off however the data changed since.
+    counted_size: AtomicUsize,
+    /// Payload bytes held in the collector's arenas, if any.
+    pooled: Option<PooledCell>,
+}
+
+impl Drop for GcBox {
+    fn drop(&mut self) {
+        if self.owns_id {
+            self.id.release();
+        }
+    }
+}
+
+/// Handle to a [`GcBox`]. Cloning yields another handle to the same object,
+/// so tracking state, refcount and data are seen identically through every
+/// clone. The refcount is the Python-level count and is not touched by
+/// cloning or dropping handles.
+#[derive(Clone)]
+pub struct PyObject {
+    inner: Arc<GcBox>,
+}
 
 impl PyObject {
     pub fn new(name: String, data: ObjectData) -> Self {
+        Self::build(ObjectId::new(), true, name, data, None)
+    }
+
+    /// Create an object whose payload bytes live in `cell`.
+    pub(crate) fn new_pooled(name: String, data: ObjectData, cell: PooledCell) -> Self {
+        Self::build(ObjectId::new(), true, name, data, Some(cell))
+    }
+
+    /// Create an object with a caller-chosen id, for deterministic tests and
+    /// for rebuilding objects from saved ids. The id's slot is left alone.
+    pub fn with_id(id: ObjectId, name: String, data: ObjectData) -> Self {
+        Self::build(id, false, name, data, None)
+    }
+
+    fn build(
+        id: ObjectId,
+        owns_id: bool,
+        name: String,
+        data: ObjectData,
+        pooled: Option<PooledCell>,
+    ) -> Self {
         Self {
-            gc_head: PyGCHead::new(),
-            name,
-            data,
-            refcount: 1,
-            gc_tracked: false,
-            has_finalizer: false,
-            id: ObjectId::new(),
+            inner: Arc::new(GcBox {
+                id,
+                name,
+                gc_head: Mutex::new(PyGCHead::new()),
+                data: RwLock::new(data),
+                refcount: AtomicUsize::new(1),
+                gc_tracked: AtomicBool::new(false),
+                has_finalizer: AtomicBool::new(false),
+                mutated: AtomicBool::new(false),
+                owns_id,
+                allocation: NEXT_ALLOCATION.fetch_add(1, Ordering::Relaxed),
+                tag: RwLock::new(None),
+                external_refs: AtomicUsize::new(0),
+                counted_size: AtomicUsize::new(0),
+                pooled,
+            }),
         }
     }
 
     pub fn new_ffi(name: &str, data: ObjectData, _ptr: *mut c_void) -> Self {
-        Self {
-            gc_head: PyGCHead::new(),
-            name: name.to_string(),
-            data,
-            refcount: 1,
-            gc_tracked: false,
-            has_finalizer: false,
-            id: ObjectId::new(),
-        }
+        Self::new(name.to_string(), data)
     }
 
     pub fn new_with_finalizer(name: String, data: ObjectData) -> Self {
-        Self {
-            gc_head: PyGCHead::new(),
-            name,
-            data,
-            refcount: 1,
-            gc_tracked: false,
-            has_finalizer: true,
-            id: ObjectId::new(),
-        }
+        let obj = Self::new(name, data);
+        obj.set_finalizer(true);
+        obj
+    }
+
+    pub fn id(&self) -> ObjectId {
+        self.inner.id
+    }
+
+    /// Record the current shallow size as the one the collector counts.
+    pub(crate) fn count_size(&self) -> usize {
+        let size = self.shallow_size();
+        self.inner.counted_size.store(size, Ordering::Relaxed);
+        size
+    }
+
+    /// Shallow size as of the last [`count_size`](Self::count_size).
+    pub(crate) fn counted_size(&self) -> usize {
+        self.inner.counted_size.load(Ordering::Relaxed)
+    }
+
+    /// Position of the object in creation order, oldest first.
+    pub fn allocation(&self) -> u64 {
+        self.inner.allocation
+    }
+
+    pub fn name(&self) -> &str {
+        &self.inner.name
+    }
+
+    pub fn data(&self) -> RwLockReadGuard<'_, ObjectData> {
+        self.inner.data.read()
+    }
+
+    pub fn data_mut(&self) -> RwLockWriteGuard<'_, ObjectData> {
+        self.inner.mutated.store(true, Ordering::Release);
+        self.inner.data.write()
+    }
+
+    pub fn set_data(&self, data: ObjectData) {
+        *self.data_mut() = data;
+    }
+
+    /// Whether the data was written since the last call, clearing the flag.
+    pub fn take_mutated(&self) -> bool {
+        self.inner.mutated.swap(false, Ordering::AcqRel)
+    }
+
+    /// Replace the data with [`ObjectData::None`] and return the old value,
+    /// dropping this object's references the way `tp_clear` does.
+    pub fn take_data(&self) -> ObjectData {
+        std::mem::replace(&mut *self.inner.data.write(), ObjectData::None)
+    }
+
+    pub fn tag(&self) -> Option<Arc<str>> {
+        self.inner.tag.read().clone()
+    }
+
+    pub fn set_tag(&self, tag: Option<&str>) {
+        *self.inner.tag.write() = tag.map(Arc::from);
+    }
+
+    pub fn external_refs(&self) -> usize {
+        self.inner.external_refs.load(Ordering::Acquire)
+    }
+
+    pub fn add_external_ref(&self) {
+        self.inner.external_refs.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Drop one declared external reference. Returns false if none were
+    /// declared.
+    pub fn release_external_ref(&self) -> bool {
+        self.inner
+            .external_refs
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| n.checked_sub(1))
+            .is_ok()
+    }
+
+    pub fn gc_head(&self) -> MutexGuard<'_, PyGCHead> {
+        self.inner.gc_head.lock()
+    }
+
+    pub fn is_gc_tracked(&self) -> bool {
+        self.inner.gc_tracked.load(Ordering::Acquire)
+    }
+
+    pub fn set_gc_tracked(&self, tracked: bool) {
+        self.inner.gc_tracked.store(tracked, Ordering::Release);
+    }
+
+    /// Whether `self` and `other` are handles to the same object.
+    pub fn ptr_eq(&self, other: &PyObject) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
     }
 
     pub fn get_refcount(&self) -> usize {
-        self.refcount
+        self.inner.refcount.load(Ordering::Acquire)
     }
 
-    pub fn set_refcount(&mut self, count: usize) {
-        self.refcount = count;
+    /// Whether the object is immortal: its count is so high that reference
+    /// counting can never free it, as with CPython's immortal objects.
+    pub fn is_immortal(&self) -> bool {
+        self.get_refcount() >= IMMORTAL_REFCOUNT
     }
 
-    pub fn inc_ref(&mut self) {
-        self.refcount += 1;
+    pub fn set_refcount(&self, count: usize) {
+        self.inner.refcount.store(count, Ordering::Release);
     }
 
-    pub fn dec_ref(&mut self) -> bool {
-        if self.refcount > 0 {
-            self.refcount -= 1;
-            self.refcount == 0
-        } else {
-            false
+    pub fn inc_ref(&self) {
+        let _ = self
+            .inner
+            .refcount
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                Some(count.saturating_add(1))
+            });
+    }
+
+    /// Like [`inc_ref`](Self::inc_ref), but reports overflow instead of
+    /// saturating silently.
+    pub fn try_inc_ref(&self) -> GCResult<()> {
+        self.inner
+            .refcount
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                count.checked_add(1)
+            })
+            .map_err(|_| GCError::ReferenceCountError {
+                id: self.id(),
+                message: "refcount overflowed".to_string(),
+            })?;
+        Ok(())
+    }
+
+    /// Decrement the refcount and return whether it reached zero. Underflow is
+    /// an error and leaves the count at zero.
+    pub fn try_dec_ref(&self) -> GCResult<bool> {
+        match self
+            .inner
+            .refcount
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                count.checked_sub(1)
+            }) {
+            Ok(previous) => Ok(previous == 1),
+            Err(_) => Err(GCError::ReferenceCountError {
+                id: self.id(),
+                message: format!("refcount of {} dropped below zero", self.name()),
+            }),
         }
     }
 
-    pub fn set_finalizer(&mut self, has_finalizer: bool) {
-        self.has_finalizer = has_finalizer;
+    /// Add `delta` to the refcount in one step and return the count it had
+    /// before. Overflow and underflow are errors and leave the count as it was.
+    pub fn try_adjust_ref(&self, delta: isize) -> GCResult<usize> {
+        self.inner
+            .refcount
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                count.checked_add_signed(delta)
+            })
+            .map_err(|count| GCError::ReferenceCountError {
+                id: self.id(),
+                message: if delta < 0 {
+                    format!(
+                        "refcount of {} dropped below zero ({count} {delta})",
+                        self.name()
+                    )
+                } else {
+                    "refcount overflowed".to_string()
+                },
+            })
+    }
+
+    pub fn dec_ref(&self) -> bool {
+        self.try_dec_ref().unwrap_or(false)
+    }
+
+    pub fn set_finalizer(&self, has_finalizer: bool) {
+        self.inner
+            .has_finalizer
+            .store(has_finalizer, Ordering::Release);
     }
 
     pub fn has_finalizer(&self) -> bool {
-        self.has_finalizer
+        self.inner.has_finalizer.load(Ordering::Acquire)
+    }
+
+    pub fn children(&self) -> Vec<PyObject> {
+        match &*self.data() {
+            ObjectData::List(items) => items.clone(),
+            ObjectData::Dict(entries) => entries
+                .values()
+                .flat_map(|(k, v)| [k.clone(), v.clone()])
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// Size of this object alone: its [`GcBox`] plus its payload.
+    pub fn shallow_size(&self) -> usize {
+        let pooled = self.inner.pooled.as_ref().map_or(0, PooledCell::len);
+        std::mem::size_of::<GcBox>() + self.data().payload_size() + pooled
+    }
+
+    /// Handle to the object's pooled payload, for objects allocated with one.
+    pub fn pooled_handle(&self) -> Option<GcHandle> {
+        self.inner.pooled.as_ref().map(PooledCell::handle)
+    }
+
+    /// Run `f` on the object's pooled payload, wherever compaction has put
+    /// it. `None` for objects without one. `f` works on a copy that is stored
+    /// back when it returns, so it may allocate, drop pooled objects or
+    /// compact; of two overlapping calls on one object, the later store wins.
+    pub fn with_pooled<R>(&self, f: impl FnOnce(&mut [u8]) -> R) -> Option<R> {
+        self.inner.pooled.as_ref()?.with(f)
+    }
+
+    /// Size of this object and everything reachable from it, each object
+    /// counted once however many paths lead to it.
+    pub fn deep_size(&self) -> usize {
+        self.deep_size_excluding(&mut HashSet::new())
+    }
+
+    /// [`deep_size`](Self::deep_size) skipping objects already in `visited`,
+    /// which is extended with everything counted. Sharing one set across
+    /// calls sums several roots without double counting.
+    pub fn deep_size_excluding(&self, visited: &mut HashSet<ObjectId>) -> usize {
+        let mut total = 0;
+        let mut worklist = vec![self.clone()];
+        while let Some(obj) = worklist.pop() {
+            if !visited.insert(obj.id()) {
+                continue;
+            }
+            total += obj.shallow_size();
+            worklist.extend(
+                obj.children()
+                    .into_iter()
+                    .filter(|child| !visited.contains(&child.id())),
+            );
+        }
+        total
+    }
+
+    /// Look up `key` in dict data. Returns `None` for missing keys and
+    /// non-dict objects.
+    pub fn dict_get(&self, key: &PyObject) -> Option<PyObject> {
+        // Hash before locking: the key may be this very object.
+        let key = PyObjectKey::new(key);
+        match &*self.data() {
+            ObjectData::Dict(entries) => entries.get(&key).map(|(_, v)| v.clone()),
+            _ => None,
+        }
+    }
+
+    /// Insert `key` into dict data, returning the value it replaced.
+    pub fn dict_set(&self, key: PyObject, value: PyObject) -> GCResult<Option<PyObject>> {
+        let hashed = PyObjectKey::new(&key);
+        let mut data = self.data_mut();
+        let ObjectData::Dict(entries) = &mut *data else {
+            return Err(GCError::internal(
+                "dict_set",
+                format!("object {} ({}) is not a dict", self.id(), self.name()),
+            ));
+        };
+        let previous = entries.insert(hashed, (key, value));
+        Ok(previous.map(|(_, v)| v))
+    }
+
+    /// Remove `key` from dict data, keeping the order of the remaining
+    /// entries, and return its value.
+    pub fn dict_remove(&self, key: &PyObject) -> Option<PyObject> {
+        let key = PyObjectKey::new(key);
+        match &mut *self.data_mut() {
+            ObjectData::Dict(entries) => entries.shift_remove(&key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+/// Prints identity and counts only; following `data` could loop on cycles.
+impl fmt::Debug for PyObject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PyObject")
+            .field("id", &self.id())
+            .field("name", &self.name())
+            .field("refcount", &self.get_refcount())
+            .field("gc_tracked", &self.is_gc_tracked())
+            .field("has_finalizer", &self.has_finalizer())
+            .finish()
     }
 }
 
 impl Hash for PyObject {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.id.hash(state);
+        self.id().hash(state);
     }
 }
 
 impl PartialEq for PyObject {
     fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+        self.id() == other.id()
     }
 }
 
@@ -278,3 +894,183 @@ impl Drop for PyObjectPtr {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dict_accessors() {
+        let dict = PyObject::new("dict".to_string(), ObjectData::Dict(PyDict::new()));
+        let a = PyObject::new("str".to_string(), ObjectData::String("a".to_string()));
+        let b = PyObject::new("str".to_string(), ObjectData::String("b".to_string()));
+        let one = PyObject::new("int".to_string(), ObjectData::Integer(1));
+        let two = PyObject::new("int".to_string(), ObjectData::Integer(2));
+
+        assert!(dict.dict_set(a.clone(), one.clone()).unwrap().is_none());
+        assert!(dict.dict_set(b.clone(), one.clone()).unwrap().is_none());
+        assert_eq!(
+            dict.dict_set(a.clone(), two.clone()).unwrap(),
+            Some(one.clone())
+        );
+        assert_eq!(dict.dict_get(&a), Some(two.clone()));
+
+        let children: Vec<ObjectId> = dict.children().iter().map(PyObject::id).collect();
+        assert_eq!(children, vec![a.id(), two.id(), b.id(), one.id()]);
+
+        assert_eq!(dict.dict_remove(&a), Some(two));
+        assert_eq!(dict.dict_get(&a), None);
+        assert_eq!(dict.children().len(), 2);
+
+        assert!(matches!(one.dict_set(a, b), Err(GCError::Internal { .. })));
+
+        assert!(dict.dict_set(dict.clone(), one.clone()).unwrap().is_none());
+        assert_eq!(dict.dict_get(&dict), Some(one));
+    }
+
+    #[test]
+    fn test_atomic_data_is_not_tracked_or_traversed() {
+        let atoms = [
+            ObjectData::Bool(true),
+            ObjectData::Complex(1.0, -2.0),
+            ObjectData::Bytes(vec![0; 32]),
+            ObjectData::Float(0.5),
+        ];
+        let names: Vec<&str> = atoms.iter().map(ObjectData::type_name).collect();
+        assert_eq!(names, ["bool", "complex", "bytes", "float"]);
+        assert!(atoms.iter().all(|data| !data.should_track()));
+        assert_eq!(ObjectData::Bytes(vec![0; 32]).payload_size(), 32);
+        assert!(ObjectData::List(Vec::new()).should_track());
+
+        let flag = PyObject::new("bool".to_string(), ObjectData::Bool(true));
+        let one = PyObject::new("int".to_string(), ObjectData::Integer(1));
+        assert_eq!(PyObjectKey::new(&flag).hash, PyObjectKey::new(&one).hash);
+        let real = PyObject::new("complex".to_string(), ObjectData::Complex(0.5, 0.0));
+        let half = PyObject::new("float".to_string(), ObjectData::Float(0.5));
+        assert_eq!(PyObjectKey::new(&real).hash, PyObjectKey::new(&half).hash);
+
+        let blob = PyObject::new("bytes".to_string(), ObjectData::Bytes(b"abc".to_vec()));
+        assert!(blob.children().is_empty());
+    }
+
+    #[test]
+    fn test_deep_size_counts_shared_and_cyclic_objects_once() {
+        let text = PyObject::new("str".to_string(), ObjectData::String("x".repeat(100)));
+        let list = PyObject::new(
+            "list".to_string(),
+            ObjectData::List(vec![text.clone(), text.clone()]),
+        );
+        let outer = PyObject::new("list".to_string(), ObjectData::List(vec![list.clone()]));
+        if let ObjectData::List(items) = &mut *list.data_mut() {
+            items.push(outer.clone());
+        }
+
+        let expected = text.shallow_size() + list.shallow_size() + outer.shallow_size();
+        assert_eq!(text.shallow_size(), std::mem::size_of::<GcBox>() + 100);
+        assert_eq!(outer.deep_size(), expected);
+        assert_eq!(list.deep_size(), expected);
+
+        let mut visited = HashSet::new();
+        assert_eq!(outer.deep_size_excluding(&mut visited), expected);
+        assert_eq!(text.deep_size_excluding(&mut visited), 0);
+    }
+
+    #[test]
+    fn test_clones_share_state() {
+        let obj = PyObject::new("test".to_string(), ObjectData::Integer(1));
+        let handle = obj.clone();
+
+        obj.set_gc_tracked(true);
+        obj.inc_ref();
+        obj.gc_head().set_collecting();
+        handle.set_data(ObjectData::Integer(2));
+
+        assert!(handle.is_gc_tracked());
+        assert_eq!(handle.get_refcount(), 2);
+        assert!(handle.gc_head().is_collecting());
+        assert_eq!(*obj.data(), ObjectData::Integer(2));
+        assert!(obj.ptr_eq(&handle));
+
+        let twin = PyObject::with_id(obj.id(), "test".to_string(), ObjectData::None);
+        assert_eq!(twin, obj);
+        assert!(!twin.ptr_eq(&obj));
+        assert!(!twin.is_gc_tracked());
+    }
+
+    #[test]
+    fn test_ids_from_many_threads_are_unique_and_go_stale() {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                std::thread::spawn(|| {
+                    (0..2 * LOCAL_FREE_SLOTS + 10)
+                        .map(|_| PyObject::new("t".to_string(), ObjectData::None))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let objects: Vec<PyObject> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+
+        let ids: HashSet<ObjectId> = objects.iter().map(PyObject::id).collect();
+        assert_eq!(ids.len(), objects.len());
+        assert!(ids.iter().all(ObjectId::is_live));
+
+        // Freed on this thread, though the slots were taken on others.
+        drop(objects);
+        assert!(!ids.iter().any(ObjectId::is_live));
+        let reused = PyObject::new("t".to_string(), ObjectData::None);
+        assert!(!ids.contains(&reused.id()));
+    }
+
+    #[test]
+    fn test_slot_retires_instead_of_wrapping() {
+        let index = ObjectId::new().index;
+        let slot = slot_generation(index).unwrap();
+        slot.store(RETIRED_GENERATION - 1, Ordering::Release);
+        let last = ObjectId::from_raw(index, RETIRED_GENERATION - 1);
+        assert!(last.is_live());
+
+        last.release();
+        assert!(!last.is_live());
+        assert_eq!(slot.load(Ordering::Acquire), RETIRED_GENERATION);
+        assert!(!LOCAL_FREE.with(|local| local.borrow().0.contains(&index)));
+        assert!(!FREE_SLOTS.lock().contains(&index));
+
+        ObjectId::from_raw(index, RETIRED_GENERATION).release();
+        assert_eq!(slot.load(Ordering::Acquire), RETIRED_GENERATION);
+    }
+
+    #[test]
+    fn test_checked_refcounting() {
+        let obj = PyObject::new("test".to_string(), ObjectData::None);
+        obj.try_inc_ref().unwrap();
+        assert_eq!(obj.get_refcount(), 2);
+        assert!(!obj.try_dec_ref().unwrap());
+        assert!(obj.try_dec_ref().unwrap());
+
+        assert!(matches!(
+            obj.try_dec_ref(),
+            Err(GCError::ReferenceCountError { .. })
+        ));
+        assert_eq!(obj.get_refcount(), 0);
+
+        obj.set_refcount(usize::MAX);
+        assert!(matches!(
+            obj.try_inc_ref(),
+            Err(GCError::ReferenceCountError { .. })
+        ));
+        obj.inc_ref();
+        assert_eq!(obj.get_refcount(), usize::MAX);
+
+        obj.set_refcount(3);
+        assert_eq!(obj.try_adjust_ref(4).unwrap(), 3);
+        assert_eq!(obj.try_adjust_ref(-6).unwrap(), 7);
+        assert!(matches!(
+            obj.try_adjust_ref(-2),
+            Err(GCError::ReferenceCountError { .. })
+        ));
+        assert_eq!(obj.get_refcount(), 1);
+    }
+}
@@ -0,0 +1,314 @@
+//! Pickle-like serialization of object graphs, for checkpointing embedder
+//! state and building test fixtures. [`dump`] walks everything reachable from
+//! a set of roots and writes it as one self-contained record: each object is
+//! written once, and every reference is the index of its target in the
+//! record, so shared objects and cycles come back as they were. [`load`]
+//! recreates the objects under fresh ids and tracks again the ones that were
+//! tracked. Custom data cannot be written, as nothing can see inside it.
+
+use crate::GCResult;
+use crate::error::GCError;
+use crate::gc::GarbageCollector;
+use crate::object::{ObjectData, ObjectId, PyObject, PyObjectKey};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+const MAGIC: &[u8; 4] = b"PYPK";
+pub const PICKLE_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Pickle {
+    objects: Vec<PickledObject>,
+    /// Indices into `objects`, in the order the roots were given.
+    roots: Vec<usize>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PickledObject {
+    name: String,
+    tracked: bool,
+    has_finalizer: bool,
+    tag: Option<String>,
+    data: PickledData,
+}
+
+/// [`ObjectData`] with every object it holds replaced by its index.
+#[derive(Debug, Serialize, Deserialize)]
+enum PickledData {
+    Integer(i64),
+    Float(f64),
+    Bool(bool),
+    Complex(f64, f64),
+    String(String),
+    Bytes(Vec<u8>),
+    List(Vec<usize>),
+    /// Key and value indices, in insertion order.
+    Dict(Vec<(usize, usize)>),
+    None,
+}
+
+/// The objects met so far, in the order they were first met, which is the
+/// order they are written in.
+#[derive(Default)]
+struct Memo {
+    index: HashMap<ObjectId, usize>,
+    objects: Vec<PyObject>,
+}
+
+impl Memo {
+    fn index_of(&mut self, obj: &PyObject) -> usize {
+        *self.index.entry(obj.id()).or_insert_with(|| {
+            self.objects.push(obj.clone());
+            self.objects.len() - 1
+        })
+    }
+}
+
+/// Write `roots` and everything they reach to `writer`. Fails without
+/// writing anything if one of those objects holds custom data.
+pub fn dump(roots: &[PyObject], mut writer: impl Write) -> GCResult<()> {
+    let pickle = Pickle::walk(roots)?;
+    writer.write_all(MAGIC)?;
+    writer.write_all(&PICKLE_VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, &pickle).map_err(|e| GCError::Snapshot(e.to_string()))
+}
+
+pub fn dumps(roots: &[PyObject]) -> GCResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    dump(roots, &mut bytes)?;
+    Ok(bytes)
+}
+
+/// Recreate the objects written to `reader` and return the roots in the
+/// order they were dumped. Each object's refcount is the number of
+/// references to it within the record, plus one for each returned root
+/// handle, so the graph is garbage once the caller lets go of the roots.
+/// Objects that were tracked are tracked by `gc`.
+pub fn load(gc: &GarbageCollector, mut reader: impl Read) -> GCResult<Vec<PyObject>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(GCError::Snapshot("Not a pickled object graph".to_string()));
+    }
+
+    let mut version = [0u8; 4];
+    reader.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != PICKLE_VERSION {
+        return Err(GCError::Snapshot(format!(
+            "Unsupported pickle version: {version}"
+        )));
+    }
+
+    let pickle: Pickle =
+        bincode::deserialize_from(reader).map_err(|e| GCError::Snapshot(e.to_string()))?;
+    pickle.rebuild(gc)
+}
+
+pub fn loads(gc: &GarbageCollector, bytes: &[u8]) -> GCResult<Vec<PyObject>> {
+    load(gc, bytes)
+}
+
+impl Pickle {
+    fn walk(roots: &[PyObject]) -> GCResult<Self> {
+        let mut memo = Memo::default();
+        let roots = roots.iter().map(|root| memo.index_of(root)).collect();
+
+        let mut objects = Vec::new();
+        while let Some(obj) = memo.objects.get(objects.len()).cloned() {
+            let data = match &*obj.data() {
+                ObjectData::Integer(value) => PickledData::Integer(*value),
+                ObjectData::Float(value) => PickledData::Float(*value),
+                ObjectData::Bool(value) => PickledData::Bool(*value),
+                ObjectData::Complex(re, im) => PickledData::Complex(*re, *im),
+                ObjectData::String(value) => PickledData::String(value.clone()),
+                ObjectData::Bytes(value) => PickledData::Bytes(value.clone()),
+                ObjectData::List(items) => {
+                    PickledData::List(items.iter().map(|item| memo.index_of(item)).collect())
+                }
+                ObjectData::Dict(entries) => PickledData::Dict(
+                    entries
+                        .values()
+                        .map(|(key, value)| (memo.index_of(key), memo.index_of(value)))
+                        .collect(),
+                ),
+                ObjectData::Custom(_) => {
+                    return Err(GCError::Snapshot(format!(
+                        "object {} ({}) holds custom data, which cannot be pickled",
+                        obj.id(),
+                        obj.name()
+                    )));
+                }
+                ObjectData::None => PickledData::None,
+            };
+            objects.push(PickledObject {
+                name: obj.name().to_string(),
+                tracked: obj.is_gc_tracked(),
+                has_finalizer: obj.has_finalizer(),
+                tag: obj.tag().map(|tag| tag.to_string()),
+                data,
+            });
+        }
+
+        Ok(Self { objects, roots })
+    }
+
+    /// Every index the record holds, so a damaged one is caught before any
+    /// object is created.
+    fn indices(&self) -> impl Iterator<Item = usize> + '_ {
+        let held = self.objects.iter().flat_map(|obj| match &obj.data {
+            PickledData::List(items) => items.clone(),
+            PickledData::Dict(entries) => entries.iter().flat_map(|&(k, v)| [k, v]).collect(),
+            _ => Vec::new(),
+        });
+        self.roots.iter().copied().chain(held)
+    }
+
+    fn rebuild(self, gc: &GarbageCollector) -> GCResult<Vec<PyObject>> {
+        let count = self.objects.len();
+        if let Some(index) = self.indices().find(|&index| index >= count) {
+            return Err(GCError::Snapshot(format!(
+                "Reference to object {index} of {count}"
+            )));
+        }
+
+        let mut objects = Vec::with_capacity(count);
+        let mut tracked = Vec::with_capacity(count);
+        let mut lists = Vec::new();
+        let mut dicts = Vec::new();
+        for pickled in self.objects {
+            let obj = PyObject::new(pickled.name, ObjectData::None);
+            obj.set_refcount(0);
+            obj.set_finalizer(pickled.has_finalizer);
+            obj.set_tag(pickled.tag.as_deref());
+            // Atomic data goes in first: dict keys hash by their value.
+            obj.set_data(match pickled.data {
+                PickledData::Integer(value) => ObjectData::Integer(value),
+                PickledData::Float(value) => ObjectData::Float(value),
+                PickledData::Bool(value) => ObjectData::Bool(value),
+                PickledData::Complex(re, im) => ObjectData::Complex(re, im),
+                PickledData::String(value) => ObjectData::String(value),
+                PickledData::Bytes(value) => ObjectData::Bytes(value),
+                PickledData::List(items) => {
+                    lists.push((objects.len(), items));
+                    ObjectData::None
+                }
+                PickledData::Dict(entries) => {
+                    dicts.push((objects.len(), entries));
+                    ObjectData::None
+                }
+                PickledData::None => ObjectData::None,
+            });
+            objects.push(obj);
+            tracked.push(pickled.tracked);
+        }
+
+        let hold = |index: usize| {
+            let obj = &objects[index];
+            obj.inc_ref();
+            obj.clone()
+        };
+        for (index, items) in lists {
+            objects[index].set_data(ObjectData::List(items.into_iter().map(hold).collect()));
+        }
+        for (index, entries) in dicts {
+            let entries = entries
+                .into_iter()
+                .map(|(key, value)| {
+                    let key = hold(key);
+                    (PyObjectKey::new(&key), (key, hold(value)))
+                })
+                .collect();
+            objects[index].set_data(ObjectData::Dict(entries));
+        }
+        let roots: Vec<PyObject> = self.roots.into_iter().map(hold).collect();
+
+        for (obj, tracked) in objects.into_iter().zip(tracked) {
+            if tracked {
+                gc.track(obj)?;
+            }
+        }
+        Ok(roots)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::{CustomData, PyDict};
+
+    #[test]
+    fn test_round_trip_keeps_sharing_and_cycles() {
+        let gc = GarbageCollector::new();
+        let shared = PyObject::new("str".to_string(), ObjectData::String("x".to_string()));
+        let key = PyObject::new("int".to_string(), ObjectData::Integer(7));
+        let dict = PyObject::new("dict".to_string(), ObjectData::Dict(PyDict::new()));
+        let list = PyObject::new(
+            "list".to_string(),
+            ObjectData::List(vec![shared.clone(), shared.clone(), dict.clone()]),
+        );
+        dict.dict_set(key.clone(), list.clone()).unwrap();
+        list.set_tag(Some("cache"));
+        list.set_finalizer(true);
+        gc.track(list.clone()).unwrap();
+        gc.track(dict.clone()).unwrap();
+
+        let bytes = dumps(&[list.clone(), shared.clone()]).unwrap();
+        let loaded_gc = GarbageCollector::new();
+        let roots = loads(&loaded_gc, &bytes).unwrap();
+
+        let (list, shared) = (&roots[0], &roots[1]);
+        assert_eq!(list.name(), "list");
+        assert_eq!(list.tag().as_deref(), Some("cache"));
+        assert!(list.has_finalizer());
+        let ObjectData::List(items) = &*list.data() else {
+            panic!("not a list");
+        };
+        assert!(items[0].ptr_eq(shared) && items[1].ptr_eq(shared));
+        assert_eq!(*shared.data(), ObjectData::String("x".to_string()));
+
+        // The dict holds the list again, under an equal key.
+        let dict = &items[2];
+        let key = PyObject::new("int".to_string(), ObjectData::Integer(7));
+        let ObjectData::Dict(entries) = &*dict.data() else {
+            panic!("not a dict");
+        };
+        let (_, (loaded_key, value)) = entries.first().unwrap();
+        assert_eq!(*loaded_key.data(), *key.data());
+        assert!(value.ptr_eq(list));
+
+        // Two list slots and the root handle; one list slot and the dict.
+        assert_eq!(shared.get_refcount(), 3);
+        assert_eq!(list.get_refcount(), 2);
+        assert_eq!(dict.get_refcount(), 1);
+        assert!(loaded_gc.is_tracked(&list.id()) && loaded_gc.is_tracked(&dict.id()));
+        assert!(!loaded_gc.is_tracked(&shared.id()));
+    }
+
+    #[test]
+    fn test_dump_rejects_custom_data_and_load_rejects_damage() {
+        let custom = PyObject::new("Foo".to_string(), ObjectData::Custom(CustomData::new(1)));
+        let list = PyObject::new("list".to_string(), ObjectData::List(vec![custom]));
+        assert!(matches!(dumps(&[list]), Err(GCError::Snapshot(_))));
+
+        let gc = GarbageCollector::new();
+        assert!(loads(&gc, b"PYGC\x01\x00\x00\x00").is_err());
+
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(PICKLE_VERSION.to_le_bytes());
+        let damaged = Pickle {
+            objects: vec![PickledObject {
+                name: "list".to_string(),
+                tracked: true,
+                has_finalizer: false,
+                tag: None,
+                data: PickledData::List(vec![3]),
+            }],
+            roots: vec![0],
+        };
+        bincode::serialize_into(&mut bytes, &damaged).unwrap();
+        assert!(matches!(loads(&gc, &bytes), Err(GCError::Snapshot(_))));
+        assert_eq!(gc.get_stats().total_tracked, 0);
+    }
+}
@@ -0,0 +1,143 @@
+use parking_lot::RwLock;
+use std::ffi::{c_int, c_void};
+use std::sync::Arc;
+
+static PYTHON_API: RwLock<Option<Arc<dyn PythonApi>>> = RwLock::new(None);
+
+pub trait PythonApi: Send + Sync {
+    fn list_new(&self, size: isize) -> *mut c_void;
+    fn list_set_item(&self, list: *mut c_void, index: isize, item: *mut c_void) -> c_int;
+    fn list_get_item(&self, list: *mut c_void, index: isize) -> *mut c_void;
+    fn list_size(&self, list: *mut c_void) -> isize;
+    fn incref(&self, obj: *mut c_void);
+    fn decref(&self, obj: *mut c_void);
+    fn gc_new(&self, type_obj: *mut c_void) -> *mut c_void;
+    fn gc_del(&self, obj: *mut c_void);
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct StubPythonApi;
+
+impl PythonApi for StubPythonApi {
+    fn list_new(&self, _size: isize) -> *mut c_void {
+        std::ptr::null_mut()
+    }
+
+    fn list_set_item(&self, _list: *mut c_void, _index: isize, _item: *mut c_void) -> c_int {
+        -1
+    }
+
+    fn list_get_item(&self, _list: *mut c_void, _index: isize) -> *mut c_void {
+        std::ptr::null_mut()
+    }
+
+    fn list_size(&self, _list: *mut c_void) -> isize {
+        -1
+    }
+
+    fn incref(&self, _obj: *mut c_void) {}
+
+    fn decref(&self, _obj: *mut c_void) {}
+
+    fn gc_new(&self, _type_obj: *mut c_void) -> *mut c_void {
+        std::ptr::null_mut()
+    }
+
+    fn gc_del(&self, _obj: *mut c_void) {}
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct GCPythonApi {
+    pub list_new: Option<extern "C" fn(isize) -> *mut c_void>,
+    pub list_set_item: Option<extern "C" fn(*mut c_void, isize, *mut c_void) -> c_int>,
+    pub list_get_item: Option<extern "C" fn(*mut c_void, isize) -> *mut c_void>,
+    pub list_size: Option<extern "C" fn(*mut c_void) -> isize>,
+    pub incref: Option<extern "C" fn(*mut c_void)>,
+    pub decref: Option<extern "C" fn(*mut c_void)>,
+    /// Optional, like `gc_del`: only `py_gc_object_new` and
+    /// `py_gc_object_del` use them.
+    pub gc_new: Option<extern "C" fn(*mut c_void) -> *mut c_void>,
+    pub gc_del: Option<extern "C" fn(*mut c_void)>,
+}
+
+impl GCPythonApi {
+    pub fn is_complete(&self) -> bool {
+        self.list_new.is_some()
+            && self.list_set_item.is_some()
+            && self.list_get_item.is_some()
+            && self.list_size.is_some()
+            && self.incref.is_some()
+            && self.decref.is_some()
+    }
+}
+
+impl PythonApi for GCPythonApi {
+    fn list_new(&self, size: isize) -> *mut c_void {
+        self.list_new
+            .map(|f| f(size))
+            .unwrap_or(std::ptr::null_mut())
+    }
+
+    fn list_set_item(&self, list: *mut c_void, index: isize, item: *mut c_void) -> c_int {
+        self.list_set_item
+            .map(|f| f(list, index, item))
+            .unwrap_or(-1)
+    }
+
+    fn list_get_item(&self, list: *mut c_void, index: isize) -> *mut c_void {
+        self.list_get_item
+            .map(|f| f(list, index))
+            .unwrap_or(std::ptr::null_mut())
+    }
+
+    fn list_size(&self, list: *mut c_void) -> isize {
+        self.list_size.map(|f| f(list)).unwrap_or(-1)
+    }
+
+    fn incref(&self, obj: *mut c_void) {
+        if let Some(f) = self.incref {
+            f(obj);
+        }
+    }
+
+    fn decref(&self, obj: *mut c_void) {
+        if let Some(f) = self.decref {
+            f(obj);
+        }
+    }
+
+    fn gc_new(&self, type_obj: *mut c_void) -> *mut c_void {
+        self.gc_new
+            .map(|f| f(type_obj))
+            .unwrap_or(std::ptr::null_mut())
+    }
+
+    fn gc_del(&self, obj: *mut c_void) {
+        if let Some(f) = self.gc_del {
+            f(obj);
+        }
+    }
+}
+
+pub fn set_python_api(api: Arc<dyn PythonApi>) {
+    *PYTHON_API.write() = Some(api);
+}
+
+pub fn clear_python_api() {
+    *PYTHON_API.write() = None;
+}
+
+/// # Safety
+///
+/// Must only be called in a forked child before any other thread is started.
+pub(crate) unsafe fn after_fork() {
+    unsafe { crate::gc::reset_lock_after_fork(&PYTHON_API) };
+}
+
+pub fn python_api() -> Arc<dyn PythonApi> {
+    PYTHON_API
+        .read()
+        .clone()
+        .unwrap_or_else(|| Arc::new(StubPythonApi))
+}
@@ -0,0 +1,217 @@
+//! Resident set size sampling, for collection triggers driven by process
+//! memory rather than object counts. In containers the limit that matters is
+//! usually cgroup memory, which tracked object counts say little about.
+
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Source of process RSS readings.
+pub trait RssSource: Debug + Send + Sync {
+    /// Current resident set size in bytes, or `None` if it can't be read.
+    fn rss_bytes(&self) -> Option<u64>;
+}
+
+/// Reads this process's RSS through `sysinfo`.
+#[cfg(feature = "rss")]
+#[derive(Debug)]
+pub struct SysinfoRss {
+    system: parking_lot::Mutex<sysinfo::System>,
+    pid: sysinfo::Pid,
+}
+
+#[cfg(feature = "rss")]
+impl SysinfoRss {
+    /// Returns `None` on platforms where `sysinfo` can't name the current
+    /// process.
+    pub fn new() -> Option<Self> {
+        let pid = sysinfo::get_current_pid().ok()?;
+        Some(Self {
+            system: parking_lot::Mutex::new(sysinfo::System::new()),
+            pid,
+        })
+    }
+}
+
+#[cfg(feature = "rss")]
+impl RssSource for SysinfoRss {
+    fn rss_bytes(&self) -> Option<u64> {
+        let mut system = self.system.lock();
+        system.refresh_processes_specifics(
+            sysinfo::ProcessesToUpdate::Some(&[self.pid]),
+            false,
+            sysinfo::ProcessRefreshKind::nothing().with_memory(),
+        );
+        system.process(self.pid).map(sysinfo::Process::memory)
+    }
+}
+
+/// RSS that only changes when told to.
+#[derive(Debug, Default)]
+pub struct ManualRss {
+    bytes: AtomicU64,
+}
+
+impl ManualRss {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, bytes: u64) {
+        self.bytes.store(bytes, Ordering::Relaxed);
+    }
+}
+
+impl RssSource for ManualRss {
+    fn rss_bytes(&self) -> Option<u64> {
+        Some(self.bytes.load(Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RssPolicy {
+    /// Growth rate, in bytes per second, above which a collection runs.
+    pub max_growth_per_sec: u64,
+    /// Readings closer together than this are skipped.
+    pub min_interval: Duration,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RssSample {
+    pub rss_bytes: u64,
+    pub growth_per_sec: u64,
+}
+
+/// Samples an [`RssSource`] and decides which generation to collect.
+#[derive(Debug)]
+pub struct RssMonitor {
+    source: Arc<dyn RssSource>,
+    policy: RssPolicy,
+    last_reading: Option<(Duration, u64)>,
+    sample: RssSample,
+    escalation: usize,
+}
+
+impl RssMonitor {
+    pub fn new(source: Arc<dyn RssSource>, policy: RssPolicy) -> Self {
+        Self {
+            source,
+            policy,
+            last_reading: None,
+            sample: RssSample::default(),
+            escalation: 0,
+        }
+    }
+
+    pub fn policy(&self) -> RssPolicy {
+        self.policy
+    }
+
+    /// Read the source now, leaving the sample and escalation alone.
+    pub fn current_rss(&self) -> Option<u64> {
+        self.source.rss_bytes()
+    }
+
+    /// Most recent reading and the growth rate measured against the one
+    /// before it.
+    pub fn sample(&self) -> RssSample {
+        self.sample
+    }
+
+    /// Take a reading at `now`. When growth since the previous reading is
+    /// over the policy's rate, returns the generation to collect: 0 at first,
+    /// then one older generation for each further reading still over it.
+    pub fn observe(&mut self, now: Duration) -> Option<usize> {
+        if let Some((at, _)) = self.last_reading
+            && now.saturating_sub(at) < self.policy.min_interval
+        {
+            return None;
+        }
+        let rss_bytes = self.source.rss_bytes()?;
+        let Some((at, previous)) = self.last_reading.replace((now, rss_bytes)) else {
+            self.sample = RssSample {
+                rss_bytes,
+                growth_per_sec: 0,
+            };
+            return None;
+        };
+
+        let growth = rss_bytes.saturating_sub(previous);
+        let elapsed = now.saturating_sub(at).as_secs_f64();
+        let growth_per_sec = if elapsed > 0.0 {
+            (growth as f64 / elapsed) as u64
+        } else {
+            growth
+        };
+        self.sample = RssSample {
+            rss_bytes,
+            growth_per_sec,
+        };
+
+        if growth_per_sec > self.policy.max_growth_per_sec {
+            let generation = self.escalation;
+            self.escalation = (self.escalation + 1).min(2);
+            Some(generation)
+        } else {
+            self.escalation = 0;
+            None
+        }
+    }
+}
+
+/// Ask the system allocator to hand free pages back to the OS. Freed
+/// objects go back to malloc's free lists, which otherwise keep them
+/// resident. Returns false where the allocator offers no way to do this.
+pub fn trim_allocator() -> bool {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    {
+        // SAFETY: malloc_trim only walks the allocator's own free lists.
+        unsafe { libc::malloc_trim(0) == 1 }
+    }
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_observe_escalates_while_growth_persists() {
+        let rss = Arc::new(ManualRss::new());
+        let mut monitor = RssMonitor::new(
+            rss.clone(),
+            RssPolicy {
+                max_growth_per_sec: 1000,
+                min_interval: Duration::from_secs(1),
+            },
+        );
+
+        rss.set(10_000);
+        assert_eq!(monitor.observe(Duration::from_secs(0)), None);
+        rss.set(20_000);
+        assert_eq!(monitor.observe(Duration::from_millis(500)), None);
+
+        let mut generations = Vec::new();
+        for second in 1..=4 {
+            rss.set(10_000 + second * 5_000);
+            generations.push(monitor.observe(Duration::from_secs(second)));
+        }
+        assert_eq!(generations, [Some(0), Some(1), Some(2), Some(2)]);
+        assert_eq!(monitor.sample().growth_per_sec, 5_000);
+
+        assert_eq!(monitor.observe(Duration::from_secs(5)), None);
+        rss.set(40_000);
+        assert_eq!(monitor.observe(Duration::from_secs(6)), Some(0));
+    }
+
+    #[cfg(feature = "rss")]
+    #[test]
+    fn test_sysinfo_reads_own_rss() {
+        let rss = SysinfoRss::new().expect("current pid");
+        assert!(rss.rss_bytes().unwrap() > 0);
+    }
+}
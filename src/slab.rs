@@ -0,0 +1,257 @@
+//! Tracked object storage. Objects sit densely in a vector, so marking walks
+//! them in memory order, and are found by indexing a paged table with their
+//! id slot, so lookups never hash.
+
+use crate::GCResult;
+use crate::error::GCError;
+use crate::object::{ObjectId, PyObject};
+use std::ops::Index;
+
+/// Slots per page of the position table.
+const PAGE_SLOTS: usize = 1024;
+
+/// Position of a slot that holds nothing here.
+const VACANT: u32 = u32::MAX;
+
+/// Where in `entries` the objects of `PAGE_SLOTS` consecutive slots are.
+#[derive(Debug)]
+struct Page {
+    positions: [u32; PAGE_SLOTS],
+    /// Slots on this page that hold an object, so an empty page is freed.
+    held: usize,
+}
+
+/// Objects keyed by their [`ObjectId`]. Only one generation of a slot is
+/// held at a time, and a lookup must match it, so a stale id finds nothing
+/// even once its slot holds a newer object. Slots are global, so the table
+/// is paged: a collector pays for the pages its own objects fall on, not
+/// for every slot below its highest.
+#[derive(Debug, Default)]
+pub struct ObjectSlab {
+    entries: Vec<(ObjectId, PyObject)>,
+    pages: Vec<Option<Box<Page>>>,
+}
+
+fn page_of(index: u32) -> (usize, usize) {
+    let index = index as usize;
+    (index / PAGE_SLOTS, index % PAGE_SLOTS)
+}
+
+impl ObjectSlab {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn contains_key(&self, obj_id: &ObjectId) -> bool {
+        self.position(obj_id).is_some()
+    }
+
+    /// Position of whatever the slot holds, under any generation.
+    fn slot(&self, index: u32) -> Option<usize> {
+        let (page, offset) = page_of(index);
+        let position = self.pages.get(page)?.as_ref()?.positions[offset];
+        (position != VACANT).then_some(position as usize)
+    }
+
+    fn set_slot(&mut self, index: u32, position: usize) {
+        let (page, offset) = page_of(index);
+        if page >= self.pages.len() {
+            self.pages.resize_with(page + 1, || None);
+        }
+        let page = self.pages[page].get_or_insert_with(|| {
+            Box::new(Page {
+                positions: [VACANT; PAGE_SLOTS],
+                held: 0,
+            })
+        });
+        if page.positions[offset] == VACANT {
+            page.held += 1;
+        }
+        page.positions[offset] = position as u32;
+    }
+
+    fn clear_slot(&mut self, index: u32) {
+        let (page_index, offset) = page_of(index);
+        let Some(Some(page)) = self.pages.get_mut(page_index) else {
+            return;
+        };
+        if page.positions[offset] != VACANT {
+            page.positions[offset] = VACANT;
+            page.held -= 1;
+        }
+        if page.held == 0 {
+            self.pages[page_index] = None;
+            while self.pages.last().is_some_and(Option::is_none) {
+                self.pages.pop();
+            }
+        }
+    }
+
+    fn position(&self, obj_id: &ObjectId) -> Option<usize> {
+        let position = self.slot(obj_id.index)?;
+        (self.entries[position].0 == *obj_id).then_some(position)
+    }
+
+    pub fn get(&self, obj_id: &ObjectId) -> Option<&PyObject> {
+        self.position(obj_id)
+            .map(|position| &self.entries[position].1)
+    }
+
+    pub fn get_mut(&mut self, obj_id: &ObjectId) -> Option<&mut PyObject> {
+        self.position(obj_id)
+            .map(|position| &mut self.entries[position].1)
+    }
+
+    /// Store `obj` under `obj_id`, returning the object it replaces. Fails if
+    /// the slot is held under another generation, which only ids made by
+    /// hand can run into.
+    pub fn insert(&mut self, obj_id: ObjectId, obj: PyObject) -> GCResult<Option<PyObject>> {
+        if let Some(position) = self.slot(obj_id.index) {
+            let (held, previous) = &mut self.entries[position];
+            if *held != obj_id {
+                return Err(slot_clash(&obj_id, held));
+            }
+            return Ok(Some(std::mem::replace(previous, obj)));
+        }
+        self.set_slot(obj_id.index, self.entries.len());
+        self.entries.push((obj_id, obj));
+        Ok(None)
+    }
+
+    /// Fail as [`insert`](Self::insert) would if `obj_id`'s slot is held
+    /// under another generation.
+    pub fn check_vacant(&self, obj_id: &ObjectId) -> GCResult<()> {
+        match self.slot(obj_id.index) {
+            Some(position) if self.entries[position].0 != *obj_id => {
+                Err(slot_clash(obj_id, &self.entries[position].0))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn remove(&mut self, obj_id: &ObjectId) -> Option<PyObject> {
+        let position = self.position(obj_id)?;
+        self.clear_slot(obj_id.index);
+        let (_, obj) = self.entries.swap_remove(position);
+        if let Some((moved, _)) = self.entries.get(position) {
+            self.set_slot(moved.index, position);
+        }
+        Some(obj)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&ObjectId, &PyObject)> {
+        self.entries.iter().map(|(id, obj)| (id, obj))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &ObjectId> {
+        self.iter().map(|(id, _)| id)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &PyObject> {
+        self.iter().map(|(_, obj)| obj)
+    }
+}
+
+fn slot_clash(obj_id: &ObjectId, held: &ObjectId) -> GCError {
+    GCError::internal(
+        "insert",
+        format!(
+            "slot {} already holds object {held}, not {obj_id}",
+            obj_id.index
+        ),
+    )
+}
+
+impl Index<&ObjectId> for ObjectSlab {
+    type Output = PyObject;
+
+    fn index(&self, obj_id: &ObjectId) -> &PyObject {
+        self.get(obj_id)
+            .unwrap_or_else(|| panic!("object {obj_id} is not in the slab"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::ObjectData;
+
+    #[test]
+    fn test_stale_ids_miss_reused_slots() {
+        let old = ObjectId::from_raw(3, 0);
+        let new = ObjectId::from_raw(3, 1);
+        let mut slab = ObjectSlab::new();
+        slab.insert(
+            old,
+            PyObject::with_id(old, "old".to_string(), ObjectData::None),
+        )
+        .unwrap();
+        assert!(slab.remove(&new).is_none());
+        assert_eq!(slab.remove(&old).map(|obj| obj.id()), Some(old));
+        assert!(slab.is_empty());
+
+        slab.insert(
+            new,
+            PyObject::with_id(new, "new".to_string(), ObjectData::None),
+        )
+        .unwrap();
+        assert!(slab.get(&old).is_none());
+        assert_eq!(slab[&new].name(), "new");
+        assert_eq!(slab.keys().copied().collect::<Vec<_>>(), [new]);
+        assert_eq!(slab.len(), 1);
+    }
+
+    #[test]
+    fn test_insert_rejects_another_generation_of_a_held_slot() {
+        let old = ObjectId::from_raw(3, 0);
+        let new = ObjectId::from_raw(3, 1);
+        let mut slab = ObjectSlab::new();
+        slab.insert(
+            old,
+            PyObject::with_id(old, "old".to_string(), ObjectData::None),
+        )
+        .unwrap();
+
+        let clash = slab.insert(
+            new,
+            PyObject::with_id(new, "new".to_string(), ObjectData::None),
+        );
+        assert!(matches!(clash, Err(GCError::Internal { .. })));
+        assert_eq!(slab[&old].name(), "old");
+        assert!(!slab.contains_key(&new));
+    }
+
+    #[test]
+    fn test_remove_keeps_the_rest_reachable() {
+        // Storage follows how many objects there are, not how high the slots go.
+        let ids: Vec<ObjectId> = [7, 1_000_000, 42]
+            .into_iter()
+            .map(|index| ObjectId::from_raw(index, 0))
+            .collect();
+        let mut slab = ObjectSlab::new();
+        for &id in &ids {
+            slab.insert(id, PyObject::with_id(id, "t".to_string(), ObjectData::None))
+                .unwrap();
+        }
+        assert!(slab.entries.capacity() <= 4);
+        assert_eq!(slab.pages.iter().flatten().count(), 2);
+
+        assert!(slab.remove(&ids[0]).is_some());
+        assert_eq!(slab.len(), 2);
+        assert!(slab.contains_key(&ids[1]) && slab.contains_key(&ids[2]));
+        assert!(slab.remove(&ids[2]).is_some());
+        assert_eq!(slab.keys().copied().collect::<Vec<_>>(), [ids[1]]);
+        assert_eq!(slab.pages.iter().flatten().count(), 1);
+
+        assert!(slab.remove(&ids[1]).is_some());
+        assert!(slab.pages.is_empty());
+    }
+}
@@ -0,0 +1,447 @@
+use crate::GCResult;
+use crate::collector::Collector;
+use crate::error::GCError;
+use crate::traversal::{cyclic_components, visit_labeled_children};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"PYGC";
+pub const SNAPSHOT_VERSION: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeapSnapshot {
+    pub objects: Vec<SnapshotObject>,
+    pub edges: Vec<(u64, u64)>,
+    /// The field or key behind each edge that has a name, sorted like `edges`.
+    pub edge_labels: Vec<(u64, u64, String)>,
+    pub stats: SnapshotStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotObject {
+    pub id: u64,
+    pub type_name: String,
+    pub refcount: usize,
+    pub has_finalizer: bool,
+    pub uncollectable: bool,
+    pub tag: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotStats {
+    pub collections: usize,
+    pub collected: usize,
+    pub uncollectable: usize,
+    pub total_tracked: usize,
+    pub generation_counts: [usize; 3],
+    pub generation_collections: [usize; 3],
+    pub thresholds: [usize; 3],
+    pub last_pause_ns: u64,
+}
+
+impl HeapSnapshot {
+    pub fn capture(collector: &Collector) -> Self {
+        let mut objects = Vec::new();
+        let mut edges = Vec::new();
+        let mut edge_labels = Vec::new();
+
+        let tracked = collector.tracked_objects.values().map(|obj| (obj, false));
+        let uncollectable = collector.uncollectable.iter().map(|obj| (obj, true));
+        for (obj, uncollectable) in tracked.chain(uncollectable) {
+            objects.push(SnapshotObject {
+                id: obj.id().as_u64(),
+                type_name: obj.name().to_string(),
+                refcount: obj.get_refcount(),
+                has_finalizer: obj.has_finalizer(),
+                uncollectable,
+                tag: obj.tag().map(|tag| tag.to_string()),
+            });
+            let from = obj.id().as_u64();
+            visit_labeled_children(obj, None, &mut |child_id, label| {
+                let to = child_id.as_u64();
+                edges.push((from, to));
+                if let Some(label) = label {
+                    edge_labels.push((from, to, label));
+                }
+            });
+        }
+        objects.sort_by_key(|obj| obj.id);
+        edges.sort_unstable();
+        edge_labels.sort_unstable();
+
+        let stats = collector.get_stats();
+        Self {
+            objects,
+            edges,
+            edge_labels,
+            stats: SnapshotStats {
+                collections: stats.collections,
+                collected: stats.collected,
+                uncollectable: stats.uncollectable,
+                total_tracked: stats.total_tracked,
+                generation_counts: stats.generation_counts,
+                generation_collections: stats.generation_collections,
+                thresholds: stats.thresholds,
+                last_pause_ns: stats.last_pause.as_nanos() as u64,
+            },
+        }
+    }
+
+    /// Only the objects tagged `tag` and the edges between them. The stats
+    /// are still those of the whole heap.
+    pub fn filter_tag(&self, tag: &str) -> Self {
+        let objects: Vec<SnapshotObject> = self
+            .objects
+            .iter()
+            .filter(|obj| obj.tag.as_deref() == Some(tag))
+            .cloned()
+            .collect();
+        let ids: HashSet<u64> = objects.iter().map(|obj| obj.id).collect();
+        let edges = self
+            .edges
+            .iter()
+            .copied()
+            .filter(|(from, to)| ids.contains(from) && ids.contains(to))
+            .collect();
+        let edge_labels = self
+            .edge_labels
+            .iter()
+            .filter(|(from, to, _)| ids.contains(from) && ids.contains(to))
+            .cloned()
+            .collect();
+        Self {
+            objects,
+            edges,
+            edge_labels,
+            stats: self.stats.clone(),
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> GCResult<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> GCResult<Self> {
+        Self::read_from(BufReader::new(File::open(path)?))
+    }
+
+    /// Write the snapshot in the same format `save` uses for files.
+    pub fn write_to(&self, mut writer: impl Write) -> GCResult<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&SNAPSHOT_VERSION.to_le_bytes())?;
+        bincode::serialize_into(&mut writer, self).map_err(|e| GCError::Snapshot(e.to_string()))
+    }
+
+    pub fn read_from(mut reader: impl Read) -> GCResult<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(GCError::Snapshot("Not a heap snapshot".to_string()));
+        }
+
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version)?;
+        let version = u32::from_le_bytes(version);
+        if version != SNAPSHOT_VERSION {
+            return Err(GCError::Snapshot(format!(
+                "Unsupported snapshot version: {version}"
+            )));
+        }
+
+        bincode::deserialize_from(reader).map_err(|e| GCError::Snapshot(e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotSide {
+    First,
+    Second,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeGrowth {
+    pub type_name: String,
+    pub before: usize,
+    pub after: usize,
+    pub delta: isize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retainer {
+    pub id: u64,
+    pub type_name: String,
+    pub retained: usize,
+}
+
+/// Compares two heap snapshots, typically taken some time apart from the same
+/// process.
+#[derive(Debug, Clone)]
+pub struct Analyzer {
+    first: HeapSnapshot,
+    second: HeapSnapshot,
+}
+
+impl Analyzer {
+    pub fn new(first: HeapSnapshot, second: HeapSnapshot) -> Self {
+        Self { first, second }
+    }
+
+    pub fn from_files(first: impl AsRef<Path>, second: impl AsRef<Path>) -> GCResult<Self> {
+        Ok(Self::new(
+            HeapSnapshot::load(first)?,
+            HeapSnapshot::load(second)?,
+        ))
+    }
+
+    /// Per-type object counts that changed between the snapshots, largest growth first.
+    pub fn growth_by_type(&self) -> Vec<TypeGrowth> {
+        let mut counts: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        for obj in &self.first.objects {
+            counts.entry(&obj.type_name).or_default().0 += 1;
+        }
+        for obj in &self.second.objects {
+            counts.entry(&obj.type_name).or_default().1 += 1;
+        }
+
+        let mut growth: Vec<TypeGrowth> = counts
+            .into_iter()
+            .filter(|(_, (before, after))| before != after)
+            .map(|(type_name, (before, after))| TypeGrowth {
+                type_name: type_name.to_string(),
+                before,
+                after,
+                delta: after as isize - before as isize,
+            })
+            .collect();
+        growth.sort_by_key(|growth| std::cmp::Reverse(growth.delta));
+        growth
+    }
+
+    /// Cycles in the second snapshot whose member set does not appear in the first.
+    pub fn new_cycles(&self) -> Vec<Vec<u64>> {
+        let known: HashSet<Vec<u64>> = self.first.cycles().into_iter().collect();
+        self.second
+            .cycles()
+            .into_iter()
+            .filter(|cycle| !known.contains(cycle))
+            .collect()
+    }
+
+    /// The `n` objects in the second snapshot that keep the most other objects reachable.
+    pub fn biggest_retainers(&self, n: usize) -> Vec<Retainer> {
+        let successors = self.second.successors();
+        let mut retainers: Vec<Retainer> = self
+            .second
+            .objects
+            .iter()
+            .map(|obj| Retainer {
+                id: obj.id,
+                type_name: obj.type_name.clone(),
+                retained: reachable_from(obj.id, &successors).len() - 1,
+            })
+            .filter(|retainer| retainer.retained > 0)
+            .collect();
+        retainers.sort_by(|a, b| b.retained.cmp(&a.retained).then(a.id.cmp(&b.id)));
+        retainers.truncate(n);
+        retainers
+    }
+
+    pub fn objects_only_in(&self, side: SnapshotSide) -> Vec<&SnapshotObject> {
+        let (this, other) = match side {
+            SnapshotSide::First => (&self.first, &self.second),
+            SnapshotSide::Second => (&self.second, &self.first),
+        };
+        let other_ids: HashSet<u64> = other.objects.iter().map(|obj| obj.id).collect();
+        this.objects
+            .iter()
+            .filter(|obj| !other_ids.contains(&obj.id))
+            .collect()
+    }
+}
+
+impl HeapSnapshot {
+    fn successors(&self) -> HashMap<u64, Vec<u64>> {
+        let mut successors: HashMap<u64, Vec<u64>> = HashMap::new();
+        for (from, to) in &self.edges {
+            successors.entry(*from).or_default().push(*to);
+        }
+        successors
+    }
+
+    /// Member ids of every cycle, each sorted ascending.
+    pub fn cycles(&self) -> Vec<Vec<u64>> {
+        let successors = self.successors();
+        let nodes: Vec<u64> = self.objects.iter().map(|obj| obj.id).collect();
+        let mut cycles: Vec<Vec<u64>> =
+            cyclic_components(&nodes, |id| successors.get(id).cloned().unwrap_or_default());
+        for cycle in &mut cycles {
+            cycle.sort_unstable();
+        }
+        cycles.sort();
+        cycles
+    }
+}
+
+fn reachable_from(root: u64, successors: &HashMap<u64, Vec<u64>>) -> HashSet<u64> {
+    let mut reachable = HashSet::from([root]);
+    let mut stack = vec![root];
+    while let Some(id) = stack.pop() {
+        for next in successors.get(&id).into_iter().flatten() {
+            if reachable.insert(*next) {
+                stack.push(*next);
+            }
+        }
+    }
+    reachable
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GarbageCollector;
+    use crate::object::{ObjectData, PyObject};
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let gc = GarbageCollector::new();
+
+        let item = PyObject::new("int".to_string(), ObjectData::Integer(1));
+        let item_id = item.id().as_u64();
+        let list = PyObject::new("list".to_string(), ObjectData::List(vec![item.clone()]));
+        let list_id = list.id().as_u64();
+        gc.track(item).unwrap();
+        gc.track(list).unwrap();
+        gc.track(PyObject::new_with_finalizer(
+            "Foo".to_string(),
+            ObjectData::None,
+        ))
+        .unwrap();
+
+        let path = std::env::temp_dir().join(format!("python_gc_{}.snap", std::process::id()));
+        gc.save_snapshot(&path).unwrap();
+        let loaded = GarbageCollector::load_snapshot(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded, gc.snapshot());
+        assert_eq!(loaded.objects.len(), 3);
+        assert_eq!(loaded.edges, vec![(list_id, item_id)]);
+        assert_eq!(
+            loaded.edge_labels,
+            vec![(list_id, item_id, "[0]".to_string())]
+        );
+        assert_eq!(loaded.stats.total_tracked, 2);
+        assert!(loaded.objects.iter().any(|obj| obj.uncollectable));
+    }
+
+    #[test]
+    fn test_filter_tag_keeps_tagged_objects_and_their_edges() {
+        let gc = GarbageCollector::new();
+        let cached = PyObject::new("int".to_string(), ObjectData::Integer(1));
+        let cache = PyObject::new("dict".to_string(), ObjectData::List(vec![cached.clone()]));
+        let session = PyObject::new("list".to_string(), ObjectData::List(vec![cached.clone()]));
+        for obj in [&cached, &cache, &session] {
+            gc.track(obj.clone()).unwrap();
+        }
+        gc.set_tag(&cached.id(), "cache").unwrap();
+        gc.set_tag(&cache.id(), "cache").unwrap();
+        gc.set_tag(&session.id(), "session").unwrap();
+
+        let tagged = gc.snapshot().filter_tag("cache");
+        let ids: Vec<u64> = tagged.objects.iter().map(|obj| obj.id).collect();
+        let mut expected = vec![cached.id().as_u64(), cache.id().as_u64()];
+        expected.sort_unstable();
+        assert_eq!(ids, expected);
+        assert_eq!(
+            tagged.edges,
+            vec![(cache.id().as_u64(), cached.id().as_u64())]
+        );
+        assert_eq!(tagged.stats.total_tracked, 3);
+    }
+
+    fn object(id: u64, type_name: &str) -> SnapshotObject {
+        SnapshotObject {
+            id,
+            type_name: type_name.to_string(),
+            refcount: 1,
+            has_finalizer: false,
+            uncollectable: false,
+            tag: None,
+        }
+    }
+
+    fn snapshot(objects: Vec<SnapshotObject>, edges: Vec<(u64, u64)>) -> HeapSnapshot {
+        HeapSnapshot {
+            objects,
+            edges,
+            edge_labels: Vec::new(),
+            stats: SnapshotStats {
+                collections: 0,
+                collected: 0,
+                uncollectable: 0,
+                total_tracked: 0,
+                generation_counts: [0; 3],
+                generation_collections: [0; 3],
+                thresholds: [700, 10, 10],
+                last_pause_ns: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn test_analyzer_queries() {
+        let first = snapshot(
+            vec![object(1, "list"), object(2, "Foo"), object(3, "Foo")],
+            vec![(2, 3), (3, 2)],
+        );
+        let second = snapshot(
+            vec![
+                object(1, "list"),
+                object(2, "Foo"),
+                object(3, "Foo"),
+                object(4, "Foo"),
+                object(5, "Bar"),
+                object(6, "Bar"),
+            ],
+            vec![(2, 3), (3, 2), (1, 4), (4, 5), (5, 6), (6, 5)],
+        );
+        let analyzer = Analyzer::new(first, second);
+
+        let growth = analyzer.growth_by_type();
+        assert_eq!(growth.len(), 2);
+        assert_eq!(growth[0].type_name, "Bar");
+        assert_eq!(growth[0].delta, 2);
+        assert_eq!(growth[1].type_name, "Foo");
+        assert_eq!((growth[1].before, growth[1].after), (2, 3));
+
+        assert_eq!(analyzer.new_cycles(), vec![vec![5, 6]]);
+
+        let retainers = analyzer.biggest_retainers(2);
+        assert_eq!(retainers[0].id, 1);
+        assert_eq!(retainers[0].retained, 3);
+        assert_eq!(retainers[1].id, 4);
+
+        let added: Vec<u64> = analyzer
+            .objects_only_in(SnapshotSide::Second)
+            .iter()
+            .map(|obj| obj.id)
+            .collect();
+        assert_eq!(added, vec![4, 5, 6]);
+        assert!(analyzer.objects_only_in(SnapshotSide::First).is_empty());
+    }
+
+    #[test]
+    fn test_load_rejects_foreign_file() {
+        let path = std::env::temp_dir().join(format!("python_gc_{}.bad", std::process::id()));
+        std::fs::write(&path, b"not a snapshot").unwrap();
+        let result = HeapSnapshot::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(GCError::Snapshot(_))));
+    }
+}
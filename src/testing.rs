@@ -0,0 +1,456 @@
+//! Deterministic helpers for tests, benchmarks and fuzzing.
+
+use crate::GCResult;
+use crate::clock::ManualClock;
+use crate::collector::{CollectionPhase, CollectionStep, Collector};
+use crate::error::GCError;
+use crate::object::{ObjectData, ObjectId, PyObject};
+use crate::traversal::cyclic_components;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Small seeded PRNG (splitmix64) so generated workloads replay exactly.
+#[derive(Debug, Clone)]
+pub struct TestRng {
+    state: u64,
+}
+
+impl TestRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `0..bound`; `bound` must be non-zero.
+    pub fn below(&mut self, bound: usize) -> usize {
+        (self.next_u64() % bound as u64) as usize
+    }
+
+    /// `true` with the given probability in `0.0..=1.0`.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        let unit = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        unit < probability
+    }
+}
+
+/// A collector wired to a manual clock, with its own ObjectId sequence and a
+/// phase-by-phase collection driver.
+#[derive(Debug)]
+pub struct TestHarness {
+    collector: Collector,
+    clock: Arc<ManualClock>,
+    rng: TestRng,
+    next_id: u32,
+    step: Option<CollectionStep>,
+}
+
+impl Default for TestHarness {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl TestHarness {
+    pub fn new(seed: u64) -> Self {
+        let clock = Arc::new(ManualClock::new());
+        let mut collector = Collector::new();
+        collector.set_clock(clock.clone());
+
+        Self {
+            collector,
+            clock,
+            rng: TestRng::new(seed),
+            next_id: 0,
+            step: None,
+        }
+    }
+
+    /// Create an object whose id comes from this harness (1, 2, 3, ...)
+    /// rather than the process-wide counter.
+    pub fn object(&mut self, name: &str, data: ObjectData) -> PyObject {
+        self.next_id += 1;
+        PyObject::with_id(ObjectId::from_raw(self.next_id, 0), name.to_string(), data)
+    }
+
+    pub fn track(&mut self, obj: PyObject) -> GCResult<ObjectId> {
+        let obj_id = obj.id();
+        self.collector.track_object(obj)?;
+        Ok(obj_id)
+    }
+
+    /// Make tracked object `from` hold a reference to `to`, bumping the
+    /// refcount of `to` when it is tracked too.
+    pub fn add_reference(&mut self, from: ObjectId, to: ObjectId) -> GCResult<()> {
+        let collector = &mut self.collector;
+        let target = collector.find_object_mut(&to).map(|obj| {
+            obj.inc_ref();
+            obj.clone()
+        });
+        let target =
+            target.unwrap_or_else(|| PyObject::with_id(to, String::new(), ObjectData::None));
+
+        let source = collector
+            .find_object_mut(&from)
+            .ok_or(GCError::NotTracked { id: from })?;
+        match &mut *source.data_mut() {
+            ObjectData::List(items) => items.push(target),
+            data => *data = ObjectData::List(vec![target]),
+        }
+        Ok(())
+    }
+
+    pub fn clock(&self) -> &ManualClock {
+        &self.clock
+    }
+
+    pub fn advance_clock(&self, by: Duration) {
+        self.clock.advance(by);
+    }
+
+    pub fn rng(&mut self) -> &mut TestRng {
+        &mut self.rng
+    }
+
+    pub fn collector(&self) -> &Collector {
+        &self.collector
+    }
+
+    pub fn collector_mut(&mut self) -> &mut Collector {
+        &mut self.collector
+    }
+
+    /// Run the next phase of a collection of `generation`, starting one if
+    /// none is in progress. Returns the phase that ran.
+    pub fn step_collection(&mut self, generation: usize) -> GCResult<CollectionPhase> {
+        let mut step = match self.step.take() {
+            Some(step) => step,
+            None => self.collector.begin_collection(generation)?,
+        };
+
+        let phase = self
+            .collector
+            .step_collection(&mut step)?
+            .expect("a pending collection always has a next phase");
+        if !step.is_finished() {
+            self.step = Some(step);
+        }
+
+        Ok(phase)
+    }
+
+    /// The collection currently in progress, if any.
+    pub fn pending_collection(&self) -> Option<&CollectionStep> {
+        self.step.as_ref()
+    }
+
+    pub fn marked(&self) -> &[ObjectId] {
+        self.step.as_ref().map_or(&[], |step| &step.marked)
+    }
+
+    pub fn promoted(&self) -> &[ObjectId] {
+        self.step.as_ref().map_or(&[], |step| &step.promoted)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GraphConfig {
+    pub nodes: usize,
+    /// Probability of each backward edge `i -> j` (`j < i`); these alone
+    /// never form a cycle.
+    pub edge_density: f64,
+    /// Probability that a node is joined into a two-node cycle with a
+    /// random peer.
+    pub cycle_probability: f64,
+    pub finalizer_probability: f64,
+    /// Probability that a node holds one reference from outside the heap.
+    pub external_probability: f64,
+}
+
+impl Default for GraphConfig {
+    fn default() -> Self {
+        Self {
+            nodes: 32,
+            edge_density: 0.1,
+            cycle_probability: 0.2,
+            finalizer_probability: 0.05,
+            external_probability: 0.1,
+        }
+    }
+}
+
+/// A random object graph whose refcounts equal internal plus external
+/// references.
+#[derive(Debug, Clone)]
+pub struct GeneratedGraph {
+    pub objects: Vec<PyObject>,
+    pub edges: Vec<(usize, usize)>,
+    pub external_refs: Vec<usize>,
+}
+
+impl GeneratedGraph {
+    pub fn generate(config: &GraphConfig, rng: &mut TestRng) -> Self {
+        let n = config.nodes;
+        let mut edges = HashSet::new();
+        for from in 0..n {
+            for to in 0..from {
+                if rng.chance(config.edge_density) {
+                    edges.insert((from, to));
+                }
+            }
+        }
+        if n > 1 {
+            for from in 0..n {
+                if rng.chance(config.cycle_probability) {
+                    let to = (from + 1 + rng.below(n - 1)) % n;
+                    edges.insert((from, to));
+                    edges.insert((to, from));
+                }
+            }
+        }
+        let mut edges: Vec<(usize, usize)> = edges.into_iter().collect();
+        edges.sort_unstable();
+
+        let external_refs: Vec<usize> = (0..n)
+            .map(|_| usize::from(rng.chance(config.external_probability)))
+            .collect();
+
+        let objects: Vec<PyObject> = (0..n)
+            .map(|index| {
+                let obj = PyObject::new(format!("node{index}"), ObjectData::List(Vec::new()));
+                obj.set_finalizer(rng.chance(config.finalizer_probability));
+                obj.set_refcount(external_refs[index]);
+                obj
+            })
+            .collect();
+        for &(from, to) in &edges {
+            objects[to].inc_ref();
+            if let ObjectData::List(items) = &mut *objects[from].data_mut() {
+                items.push(objects[to].clone());
+            }
+        }
+
+        Self {
+            objects,
+            edges,
+            external_refs,
+        }
+    }
+
+    pub fn track_all(&self, collector: &mut Collector) -> GCResult<()> {
+        for obj in &self.objects {
+            collector.track_object(obj.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Indices of objects reachable from an external reference or from an
+    /// object with a finalizer, which the collector keeps as uncollectable.
+    pub fn externally_reachable(&self) -> HashSet<usize> {
+        let successors = self.successors();
+        let mut reachable = HashSet::new();
+        let mut stack: Vec<usize> = (0..self.objects.len())
+            .filter(|&index| self.external_refs[index] > 0 || self.objects[index].has_finalizer())
+            .collect();
+        while let Some(index) = stack.pop() {
+            if reachable.insert(index) {
+                stack.extend(&successors[&index]);
+            }
+        }
+        reachable
+    }
+
+    /// Cycles that nothing outside the heap can reach.
+    pub fn garbage_cycles(&self) -> Vec<Vec<usize>> {
+        let reachable = self.externally_reachable();
+        let successors = self.successors();
+        let nodes: Vec<usize> = (0..self.objects.len()).collect();
+        cyclic_components(&nodes, |index| successors[index].clone())
+            .into_iter()
+            .filter(|cycle| !cycle.iter().any(|index| reachable.contains(index)))
+            .collect()
+    }
+
+    fn successors(&self) -> HashMap<usize, Vec<usize>> {
+        let mut successors: HashMap<usize, Vec<usize>> = (0..self.objects.len())
+            .map(|index| (index, Vec::new()))
+            .collect();
+        for &(from, to) in &self.edges {
+            successors.entry(from).or_default().push(to);
+        }
+        successors
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyViolation {
+    /// An object reachable from outside the heap is no longer tracked.
+    LiveObjectCollected(ObjectId),
+    /// A finalizer-free cycle unreachable from outside is still tracked.
+    GarbageCycleSurvived(Vec<ObjectId>),
+}
+
+/// Check a collector after a full collection of `graph` against the two
+/// collector safety/liveness properties.
+pub fn check_collection(graph: &GeneratedGraph, collector: &Collector) -> Vec<PropertyViolation> {
+    let mut violations = Vec::new();
+
+    let mut reachable: Vec<usize> = graph.externally_reachable().into_iter().collect();
+    reachable.sort_unstable();
+    for index in reachable {
+        let obj_id = graph.objects[index].id();
+        // Elided containers are untracked but still alive.
+        if !collector.is_tracked(&obj_id) && !collector.elided.contains_key(&obj_id) {
+            violations.push(PropertyViolation::LiveObjectCollected(obj_id));
+        }
+    }
+
+    for cycle in graph.garbage_cycles() {
+        let has_finalizer = cycle
+            .iter()
+            .any(|&index| graph.objects[index].has_finalizer());
+        let survivors: Vec<ObjectId> = cycle
+            .iter()
+            .map(|&index| graph.objects[index].id())
+            .filter(|obj_id| collector.tracked_objects.contains_key(obj_id))
+            .collect();
+        if !has_finalizer && !survivors.is_empty() {
+            violations.push(PropertyViolation::GarbageCycleSurvived(survivors));
+        }
+    }
+
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_rng_is_reproducible() {
+        let mut a = TestRng::new(42);
+        let mut b = TestRng::new(42);
+        let first: Vec<u64> = (0..8).map(|_| a.next_u64()).collect();
+        let second: Vec<u64> = (0..8).map(|_| b.next_u64()).collect();
+        assert_eq!(first, second);
+        assert_ne!(TestRng::new(7).next_u64(), first[0]);
+    }
+
+    #[test]
+    fn test_step_collection_phases() {
+        let mut harness = TestHarness::new(0);
+        let a = harness.object("a", ObjectData::None);
+        let b = harness.object("b", ObjectData::None);
+        a.set_refcount(0);
+        b.set_refcount(0);
+        assert_eq!(harness.track(a).unwrap(), ObjectId::from_raw(1, 0));
+        assert_eq!(harness.track(b).unwrap(), ObjectId::from_raw(2, 0));
+
+        assert_eq!(harness.step_collection(0).unwrap(), CollectionPhase::Mark);
+        assert_eq!(
+            harness.marked(),
+            &[ObjectId::from_raw(1, 0), ObjectId::from_raw(2, 0)]
+        );
+        assert_eq!(harness.collector().collecting_objects.len(), 2);
+        assert!(harness.collector().verify_heap().is_empty());
+
+        harness.advance_clock(Duration::from_millis(5));
+        assert_eq!(harness.step_collection(0).unwrap(), CollectionPhase::Sweep);
+        assert_eq!(harness.collector().get_count(), 0);
+
+        assert_eq!(
+            harness.step_collection(0).unwrap(),
+            CollectionPhase::Promote
+        );
+        assert!(harness.promoted().is_empty());
+
+        assert_eq!(harness.step_collection(0).unwrap(), CollectionPhase::Finish);
+        assert!(harness.pending_collection().is_none());
+
+        let stats = harness.collector().get_stats();
+        assert_eq!(stats.collected, 2);
+        assert_eq!(stats.generation_collections[0], 1);
+        assert_eq!(stats.last_pause, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_add_reference() {
+        let mut harness = TestHarness::new(0);
+        let a = harness.object("a", ObjectData::None);
+        let b = harness.object("b", ObjectData::None);
+        let a = harness.track(a).unwrap();
+        let b = harness.track(b).unwrap();
+
+        harness.add_reference(a, b).unwrap();
+        harness.add_reference(b, a).unwrap();
+        let collector = harness.collector();
+        assert_eq!(collector.tracked_objects[&a].children()[0].id(), b);
+        assert_eq!(collector.tracked_objects[&b].get_refcount(), 2);
+        assert!(collector.verify_heap().is_empty());
+
+        let missing = ObjectId::from_raw(99, 0);
+        assert!(matches!(
+            harness.add_reference(missing, a),
+            Err(GCError::NotTracked { id }) if id == missing
+        ));
+    }
+
+    #[test]
+    fn test_checker_flags_collected_live_object() {
+        let config = GraphConfig {
+            nodes: 3,
+            edge_density: 1.0,
+            cycle_probability: 0.0,
+            finalizer_probability: 0.0,
+            external_probability: 1.0,
+        };
+        let graph = GeneratedGraph::generate(&config, &mut TestRng::new(1));
+        let mut collector = Collector::new();
+        graph.track_all(&mut collector).unwrap();
+        assert!(check_collection(&graph, &collector).is_empty());
+
+        let victim = graph.objects[1].id();
+        collector.untrack_object(&victim).unwrap();
+        assert_eq!(
+            check_collection(&graph, &collector),
+            vec![PropertyViolation::LiveObjectCollected(victim)]
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_unreferenced_graphs_are_fully_collected(
+            seed in any::<u64>(),
+            nodes in 1usize..48,
+            edge_density in 0.0f64..0.3,
+            cycle_probability in 0.0f64..1.0,
+            finalizer_probability in 0.0f64..0.3,
+        ) {
+            let config = GraphConfig {
+                nodes,
+                edge_density,
+                cycle_probability,
+                finalizer_probability,
+                external_probability: 0.0,
+            };
+            let graph = GeneratedGraph::generate(&config, &mut TestRng::new(seed));
+            for (index, obj) in graph.objects.iter().enumerate() {
+                let incoming = graph.edges.iter().filter(|&&(_, to)| to == index).count();
+                prop_assert_eq!(obj.get_refcount(), incoming);
+            }
+
+            let mut collector = Collector::new();
+            graph.track_all(&mut collector).unwrap();
+            collector.collect_generation(2).unwrap();
+            prop_assert_eq!(check_collection(&graph, &collector), Vec::new());
+        }
+    }
+}
@@ -1,16 +1,131 @@
 use crate::GCResult;
+use crate::collector::IntegrityViolation;
 use crate::error::GCError;
-use crate::object::{ObjectId, PyObject};
+use crate::object::{CustomData, ObjectData, ObjectId, PyObject};
+use indexmap::IndexMap;
+use parking_lot::{Mutex, RwLock};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// Reports the objects held by an [`ObjectData::Custom`] payload, playing the
+/// role of CPython's `tp_traverse` for data the collector cannot see into.
+pub trait Trace: Debug + Send + Sync {
+    fn trace(&self, data: &CustomData, visit: &mut dyn FnMut(ObjectId));
+
+    /// As [`Trace::trace`], naming the field or slot each reference is held
+    /// in. Only reports ask for names, so marking never pays for them; the
+    /// default names nothing.
+    fn trace_labeled(&self, data: &CustomData, visit: &mut dyn FnMut(ObjectId, Option<&str>)) {
+        self.trace(data, &mut |child_id| visit(child_id, None));
+    }
+}
+
+/// Call `visit` with the id of every object `obj` holds: list items, dict
+/// keys and values, and whatever `tracer` reports for custom data.
+pub(crate) fn visit_children(
+    obj: &PyObject,
+    tracer: Option<&dyn Trace>,
+    visit: &mut dyn FnMut(ObjectId),
+) {
+    match &*obj.data() {
+        ObjectData::List(items) => items.iter().for_each(|item| visit(item.id())),
+        ObjectData::Dict(entries) => {
+            for (key, value) in entries.values() {
+                visit(key.id());
+                visit(value.id());
+            }
+        }
+        ObjectData::Custom(data) => {
+            if let Some(tracer) = tracer {
+                tracer.trace(data, visit);
+            }
+        }
+        ObjectData::Integer(_)
+        | ObjectData::Float(_)
+        | ObjectData::Bool(_)
+        | ObjectData::Complex(..)
+        | ObjectData::Bytes(_)
+        | ObjectData::String(_)
+        | ObjectData::None => {}
+    }
+}
+
+/// As [`visit_children`], also naming where `obj` holds each child: `[i]`
+/// for list items, `key k` and `[k]` for a dict entry's key and value, and
+/// whatever `tracer` names for custom data.
+pub(crate) fn visit_labeled_children(
+    obj: &PyObject,
+    tracer: Option<&dyn Trace>,
+    visit: &mut dyn FnMut(ObjectId, Option<String>),
+) {
+    match &*obj.data() {
+        ObjectData::List(items) => {
+            for (index, item) in items.iter().enumerate() {
+                visit(item.id(), Some(format!("[{index}]")));
+            }
+        }
+        ObjectData::Dict(entries) => {
+            for (key, value) in entries.values() {
+                let key_repr = repr(key);
+                visit(key.id(), Some(format!("key {key_repr}")));
+                visit(value.id(), Some(format!("[{key_repr}]")));
+            }
+        }
+        ObjectData::Custom(data) => {
+            if let Some(tracer) = tracer {
+                tracer.trace_labeled(data, &mut |child_id, label| {
+                    visit(child_id, label.map(str::to_string))
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The name of the first place `obj` holds `child`, if it names one.
+pub(crate) fn edge_label(
+    obj: &PyObject,
+    tracer: Option<&dyn Trace>,
+    child: &ObjectId,
+) -> Option<String> {
+    let mut found = None;
+    visit_labeled_children(obj, tracer, &mut |child_id, label| {
+        if found.is_none() && child_id == *child {
+            found = Some(label);
+        }
+    });
+    found.flatten()
+}
+
+/// Python-style repr of a dict key, for edge labels.
+fn repr(obj: &PyObject) -> String {
+    match &*obj.data() {
+        ObjectData::String(s) => format!("'{s}'"),
+        ObjectData::Integer(i) => i.to_string(),
+        ObjectData::Float(f) => f.to_string(),
+        ObjectData::Bool(true) => "True".to_string(),
+        ObjectData::Bool(false) => "False".to_string(),
+        ObjectData::None => "None".to_string(),
+        data => format!("<{} object>", data.type_name()),
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct Reference {
     pub from: ObjectId,
     pub to: ObjectId,
     pub reference_type: ReferenceType,
+    /// Field, slot or key the reference is held in, when known.
+    pub label: Option<String>,
+    /// How many times `from` holds `to` this way, as a list holding the same
+    /// item twice does. Each one accounts for a count on `to`.
+    pub count: usize,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum ReferenceType {
     Direct,
     Weak,
@@ -19,41 +134,242 @@ pub enum ReferenceType {
 
 #[derive(Debug)]
 pub struct ObjectGraph {
-    objects: HashMap<ObjectId, PyObject>,
+    // The maps sit behind `Arc`s so snapshots can share them; every change
+    // goes through `Arc::make_mut`, which copies a map a snapshot still holds.
+    objects: Arc<HashMap<ObjectId, PyObject>>,
 
-    references: HashMap<ObjectId, Vec<Reference>>,
+    references: Arc<HashMap<ObjectId, Vec<Reference>>>,
 
-    reverse_references: HashMap<ObjectId, Vec<ObjectId>>,
+    /// For each object, who refers to it and how many times, kept in step
+    /// with `references` so a removal is a lookup rather than a scan.
+    reverse_references: Arc<HashMap<ObjectId, IndexMap<ObjectId, usize>>>,
+
+    reachability: Mutex<ReachabilityCache>,
+}
+
+/// How many root sets [`ObjectGraph`] remembers the reachable objects of.
+const REACHABILITY_CACHE_SIZE: usize = 32;
+
+/// Reachable objects per root set, kept across queries. Each set is its own
+/// region: adding a reference extends only the sets holding its source, and
+/// removing one drops only the sets it could shrink.
+#[derive(Debug, Default)]
+struct ReachabilityCache {
+    sets: HashMap<Vec<ObjectId>, HashSet<ObjectId>>,
+}
+
+impl ReachabilityCache {
+    fn key(roots: &[ObjectId]) -> Vec<ObjectId> {
+        let mut key = roots.to_vec();
+        key.sort_by_key(|obj_id| obj_id.as_u64());
+        key.dedup();
+        key
+    }
+
+    /// Drop every set `obj_id` is in.
+    fn invalidate(&mut self, obj_id: &ObjectId) {
+        self.sets.retain(|_, reachable| !reachable.contains(obj_id));
+    }
+}
+
+/// Add to `reachable` everything reachable from `queue` through `references`.
+fn reach(
+    references: &HashMap<ObjectId, Vec<Reference>>,
+    reachable: &mut HashSet<ObjectId>,
+    mut queue: VecDeque<ObjectId>,
+) {
+    while let Some(current_id) = queue.pop_front() {
+        for reference in references.get(&current_id).into_iter().flatten() {
+            if reachable.insert(reference.to) {
+                queue.push_back(reference.to);
+            }
+        }
+    }
+}
+
+/// Record one more reference from `from` to `to` in `from`'s references and
+/// `to`'s referrers, bumping the count of an identical one if there is one.
+fn push_reference(
+    refs: &mut Vec<Reference>,
+    referrers: &mut IndexMap<ObjectId, usize>,
+    from: ObjectId,
+    to: ObjectId,
+    reference_type: ReferenceType,
+    label: Option<String>,
+) {
+    *referrers.entry(from).or_default() += 1;
+    if let Some(reference) = refs
+        .iter_mut()
+        .find(|r| r.to == to && r.reference_type == reference_type && r.label == label)
+    {
+        reference.count += 1;
+        return;
+    }
+    refs.push(Reference {
+        from,
+        to,
+        reference_type,
+        label,
+        count: 1,
+    });
+}
+
+/// Undo one [`push_reference`] of `from` to `to`, the one added last.
+/// Returns whether `from` still refers to `to`, or `None` if it never did.
+fn pop_reference(
+    refs: &mut Vec<Reference>,
+    referrers: &mut IndexMap<ObjectId, usize>,
+    from: ObjectId,
+    to: ObjectId,
+) -> Option<bool> {
+    let index = refs.iter().rposition(|r| r.to == to)?;
+    refs[index].count -= 1;
+    if refs[index].count == 0 {
+        refs.remove(index);
+    }
+
+    let reverse_count = referrers.get_mut(&from);
+    debug_assert!(
+        reverse_count.is_some(),
+        "{from:?} refers to {to:?} but is not recorded as its referrer"
+    );
+    match reverse_count {
+        Some(count) if *count > 1 => {
+            *count -= 1;
+            Some(true)
+        }
+        _ => {
+            referrers.swap_remove(&from);
+            Some(false)
+        }
+    }
 }
 
 impl ObjectGraph {
     pub fn new() -> Self {
         Self {
-            objects: HashMap::new(),
-            references: HashMap::new(),
-            reverse_references: HashMap::new(),
+            objects: Arc::default(),
+            references: Arc::default(),
+            reverse_references: Arc::default(),
+            reachability: Mutex::default(),
         }
     }
 
+    /// A second graph over the same maps, with a cache of its own.
+    fn share(&self) -> Self {
+        Self {
+            objects: Arc::clone(&self.objects),
+            references: Arc::clone(&self.references),
+            reverse_references: Arc::clone(&self.reverse_references),
+            reachability: Mutex::default(),
+        }
+    }
+
+    /// Freeze the graph as it is now. See [`GraphSnapshot`].
+    pub fn snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot {
+            graph: self.share(),
+        }
+    }
+
+    /// Build a graph from `objects` and the `edges` between them in one pass,
+    /// as an analyzer reloading a dump would. Each adjacency list is sized
+    /// from the edge counts before it is filled, so large graphs avoid the
+    /// regrowth of one [`Self::add_reference`] at a time. Repeated edges
+    /// become one reference with a count. Fails with `NotTracked` if an edge
+    /// names an object not in `objects`.
+    pub fn from_edges(
+        objects: impl IntoIterator<Item = PyObject>,
+        edges: impl IntoIterator<Item = (ObjectId, ObjectId, ReferenceType)>,
+    ) -> GCResult<Self> {
+        let objects = objects.into_iter();
+        let mut graph = Self {
+            objects: Arc::new(HashMap::with_capacity(objects.size_hint().0)),
+            references: Arc::default(),
+            reverse_references: Arc::default(),
+            reachability: Mutex::default(),
+        };
+        Arc::make_mut(&mut graph.objects).extend(objects.map(|obj| (obj.id(), obj)));
+
+        let mut counts: IndexMap<(ObjectId, ObjectId, ReferenceType), usize> = IndexMap::new();
+        for edge in edges {
+            *counts.entry(edge).or_default() += 1;
+        }
+        let mut out_degree: HashMap<ObjectId, usize> = HashMap::with_capacity(graph.objects.len());
+        let mut in_degree: HashMap<ObjectId, usize> = HashMap::with_capacity(graph.objects.len());
+        for (from, to, _) in counts.keys() {
+            if let Some(&id) = [from, to]
+                .into_iter()
+                .find(|obj_id| !graph.objects.contains_key(obj_id))
+            {
+                return Err(GCError::NotTracked { id });
+            }
+            *out_degree.entry(*from).or_default() += 1;
+            *in_degree.entry(*to).or_default() += 1;
+        }
+
+        let degree_of = |degree: &HashMap<ObjectId, usize>, obj_id: &ObjectId| {
+            degree.get(obj_id).copied().unwrap_or_default()
+        };
+        let mut references: HashMap<ObjectId, Vec<Reference>> = graph
+            .objects
+            .keys()
+            .map(|obj_id| (*obj_id, Vec::with_capacity(degree_of(&out_degree, obj_id))))
+            .collect();
+        let mut reverse_references: HashMap<ObjectId, IndexMap<ObjectId, usize>> = graph
+            .objects
+            .keys()
+            .map(|obj_id| {
+                let referrers = IndexMap::with_capacity(degree_of(&in_degree, obj_id));
+                (*obj_id, referrers)
+            })
+            .collect();
+        for ((from, to, reference_type), count) in counts {
+            if let Some(refs) = references.get_mut(&from) {
+                refs.push(Reference {
+                    from,
+                    to,
+                    reference_type,
+                    label: None,
+                    count,
+                });
+            }
+            if let Some(referrers) = reverse_references.get_mut(&to) {
+                *referrers.entry(from).or_default() += count;
+            }
+        }
+        graph.references = Arc::new(references);
+        graph.reverse_references = Arc::new(reverse_references);
+        Ok(graph)
+    }
+
     pub fn add_object(&mut self, obj: PyObject) {
-        let obj_id = obj.id;
-        self.objects.insert(obj_id, obj);
-        self.references.insert(obj_id, Vec::new());
-        self.reverse_references.insert(obj_id, Vec::new());
+        let obj_id = obj.id();
+        Arc::make_mut(&mut self.objects).insert(obj_id, obj);
+        Arc::make_mut(&mut self.references).insert(obj_id, Vec::new());
+        Arc::make_mut(&mut self.reverse_references).insert(obj_id, IndexMap::new());
     }
 
     pub fn remove_object(&mut self, obj_id: &ObjectId) -> Option<PyObject> {
-        if let Some(refs) = self.reverse_references.remove(obj_id) {
-            for from_id in refs {
-                if let Some(from_refs) = self.references.get_mut(&from_id) {
-                    from_refs.retain(|r| r.to != *obj_id);
-                }
+        self.reachability.get_mut().invalidate(obj_id);
+        let references = Arc::make_mut(&mut self.references);
+        let reverse_references = Arc::make_mut(&mut self.reverse_references);
+        for from_id in reverse_references
+            .remove(obj_id)
+            .unwrap_or_default()
+            .into_keys()
+        {
+            if let Some(from_refs) = references.get_mut(&from_id) {
+                from_refs.retain(|r| r.to != *obj_id);
+            }
+        }
+        for reference in references.remove(obj_id).unwrap_or_default() {
+            if let Some(referrers) = reverse_references.get_mut(&reference.to) {
+                referrers.swap_remove(obj_id);
             }
         }
 
-        self.references.remove(obj_id);
-
-        self.objects.remove(obj_id)
+        Arc::make_mut(&mut self.objects).remove(obj_id)
     }
 
     pub fn add_reference(
@@ -62,42 +378,145 @@ impl ObjectGraph {
         to: ObjectId,
         ref_type: ReferenceType,
     ) -> GCResult<()> {
-        if !self.objects.contains_key(&from) || !self.objects.contains_key(&to) {
-            return Err(GCError::Internal("Object not found in graph".to_string()));
+        self.insert_reference(from, to, ref_type, None)
+    }
+
+    /// As [`Self::add_reference`], naming the field or slot that holds it.
+    pub fn add_labeled_reference(
+        &mut self,
+        from: ObjectId,
+        to: ObjectId,
+        ref_type: ReferenceType,
+        label: impl Into<String>,
+    ) -> GCResult<()> {
+        self.insert_reference(from, to, ref_type, Some(label.into()))
+    }
+
+    fn insert_reference(
+        &mut self,
+        from: ObjectId,
+        to: ObjectId,
+        ref_type: ReferenceType,
+        label: Option<String>,
+    ) -> GCResult<()> {
+        if let Some(&id) = [from, to]
+            .iter()
+            .find(|obj_id| !self.objects.contains_key(obj_id))
+        {
+            return Err(GCError::NotTracked { id });
         }
 
-        let reference = Reference {
+        push_reference(
+            Arc::make_mut(&mut self.references).entry(from).or_default(),
+            Arc::make_mut(&mut self.reverse_references)
+                .entry(to)
+                .or_default(),
             from,
             to,
-            reference_type: ref_type,
-        };
-
-        self.references.entry(from).or_default().push(reference);
+            ref_type,
+            label,
+        );
 
-        self.reverse_references.entry(to).or_default().push(from);
+        for reachable in self.reachability.get_mut().sets.values_mut() {
+            if reachable.contains(&from) && reachable.insert(to) {
+                reach(&self.references, reachable, VecDeque::from([to]));
+            }
+        }
 
         Ok(())
     }
 
+    /// Drop one occurrence of a reference from `from` to `to`, the one added
+    /// last. The reference goes once its count reaches zero.
     pub fn remove_reference(&mut self, from: ObjectId, to: ObjectId) -> GCResult<()> {
-        if let Some(refs) = self.references.get_mut(&from) {
-            refs.retain(|r| r.to != to);
+        // Checked first so a no-op doesn't copy a map a snapshot holds.
+        if !self.references.contains_key(&from) {
+            return Ok(());
         }
-
-        if let Some(reverse_refs) = self.reverse_references.get_mut(&to) {
-            reverse_refs.retain(|&id| id != from);
+        let refs = Arc::make_mut(&mut self.references).entry(from).or_default();
+        let referrers = Arc::make_mut(&mut self.reverse_references)
+            .entry(to)
+            .or_default();
+        if pop_reference(refs, referrers, from, to) == Some(false) {
+            self.reachability
+                .get_mut()
+                .sets
+                .retain(|_, reachable| !(reachable.contains(&from) && reachable.contains(&to)));
         }
 
         Ok(())
     }
 
+    /// How many times `from` refers to `to`, counting every kind.
+    pub fn edge_count(&self, from: &ObjectId, to: &ObjectId) -> usize {
+        self.references
+            .get(from)
+            .into_iter()
+            .flatten()
+            .filter(|r| r.to == *to)
+            .map(|r| r.count)
+            .sum()
+    }
+
+    /// Each object referring to `obj_id`, once however many times it does.
     pub fn get_referrers(&self, obj_id: &ObjectId) -> Vec<&PyObject> {
         self.reverse_references
             .get(obj_id)
-            .map(|refs| refs.iter().filter_map(|id| self.objects.get(id)).collect())
+            .map(|refs| refs.keys().filter_map(|id| self.objects.get(id)).collect())
             .unwrap_or_default()
     }
 
+    /// How many references point at `obj_id`, each counted as many times as
+    /// it is held.
+    pub fn referrer_count(&self, obj_id: &ObjectId) -> usize {
+        self.reverse_references
+            .get(obj_id)
+            .map_or(0, |referrers| referrers.values().sum())
+    }
+
+    /// Check that the referrers recorded for each object match the
+    /// references made to it, returning every pair that disagrees.
+    pub fn verify_consistency(&self) -> Vec<IntegrityViolation> {
+        let mut forward: HashMap<(ObjectId, ObjectId), usize> = HashMap::new();
+        for reference in self.references.values().flatten() {
+            *forward.entry((reference.from, reference.to)).or_default() += reference.count;
+        }
+        let mut pairs: HashSet<(ObjectId, ObjectId)> = forward.keys().copied().collect();
+        for (to, referrers) in self.reverse_references.iter() {
+            pairs.extend(referrers.keys().map(|from| (*from, *to)));
+        }
+
+        let mut pairs: Vec<(ObjectId, ObjectId)> = pairs.into_iter().collect();
+        pairs.sort_by_key(|(from, to)| (from.as_u64(), to.as_u64()));
+        pairs
+            .into_iter()
+            .filter_map(|(from, to)| {
+                let forward = forward.get(&(from, to)).copied().unwrap_or(0);
+                let reverse = self
+                    .reverse_references
+                    .get(&to)
+                    .and_then(|referrers| referrers.get(&from))
+                    .copied()
+                    .unwrap_or(0);
+                (forward != reverse).then_some(IntegrityViolation::ReferrerMismatch {
+                    from,
+                    to,
+                    forward,
+                    reverse,
+                })
+            })
+            .collect()
+    }
+
+    /// The label of the first reference from `from` to `to` that has one.
+    pub fn reference_label(&self, from: &ObjectId, to: &ObjectId) -> Option<&str> {
+        self.references
+            .get(from)?
+            .iter()
+            .filter(|r| r.to == *to)
+            .find_map(|r| r.label.as_deref())
+    }
+
     pub fn get_references(&self, obj_id: &ObjectId) -> Vec<&PyObject> {
         self.references
             .get(obj_id)
@@ -109,27 +528,72 @@ impl ObjectGraph {
             .unwrap_or_default()
     }
 
+    /// Everything reachable from `roots`, the roots included. The answer is
+    /// cached per root set until a change to the graph could alter it.
     pub fn find_reachable(&self, roots: &[ObjectId]) -> HashSet<ObjectId> {
-        let mut reachable = HashSet::new();
-        let mut queue = VecDeque::new();
+        self.with_reachable(roots, HashSet::clone)
+    }
 
-        for root_id in roots {
-            reachable.insert(*root_id);
-            queue.push_back(*root_id);
+    /// Whether `obj_id` is reachable from `roots`, answered from the cache
+    /// without copying the reachable set out.
+    pub fn is_reachable_from(&self, roots: &[ObjectId], obj_id: &ObjectId) -> bool {
+        self.with_reachable(roots, |reachable| reachable.contains(obj_id))
+    }
+
+    fn with_reachable<R>(&self, roots: &[ObjectId], f: impl FnOnce(&HashSet<ObjectId>) -> R) -> R {
+        let key = ReachabilityCache::key(roots);
+        let mut cache = self.reachability.lock();
+        if let Some(reachable) = cache.sets.get(&key) {
+            return f(reachable);
         }
 
-        while let Some(current_id) = queue.pop_front() {
-            if let Some(refs) = self.references.get(&current_id) {
-                for reference in refs {
-                    if !reachable.contains(&reference.to) {
-                        reachable.insert(reference.to);
-                        queue.push_back(reference.to);
-                    }
-                }
+        let mut reachable: HashSet<ObjectId> = key.iter().copied().collect();
+        reach(
+            &self.references,
+            &mut reachable,
+            key.iter().copied().collect(),
+        );
+        let answer = f(&reachable);
+        if cache.sets.len() >= REACHABILITY_CACHE_SIZE {
+            cache.sets.clear();
+        }
+        cache.sets.insert(key, reachable);
+        answer
+    }
+
+    /// Objects only the graph's own strong references keep alive, found by
+    /// trial deletion as the collector does: take each object's refcount,
+    /// subtract every strong reference to it as many times as it is held,
+    /// and keep whatever is reachable from an object with counts left over.
+    pub fn find_garbage(&self) -> HashSet<ObjectId> {
+        let mut gc_refs: HashMap<ObjectId, usize> = self
+            .objects
+            .values()
+            .map(|obj| (obj.id(), obj.get_refcount()))
+            .collect();
+        for reference in self.references.values().flatten() {
+            if reference.reference_type != ReferenceType::Weak
+                && let Some(refs) = gc_refs.get_mut(&reference.to)
+            {
+                *refs = refs.saturating_sub(reference.count);
             }
         }
 
-        reachable
+        let mut live: HashSet<ObjectId> = HashSet::new();
+        let mut worklist: Vec<ObjectId> = gc_refs
+            .iter()
+            .filter(|&(_, &refs)| refs > 0)
+            .map(|(obj_id, _)| *obj_id)
+            .collect();
+        while let Some(obj_id) = worklist.pop() {
+            if live.insert(obj_id) {
+                worklist.extend(self.successors(&obj_id));
+            }
+        }
+        gc_refs
+            .into_keys()
+            .filter(|obj_id| !live.contains(obj_id))
+            .collect()
     }
 
     pub fn find_unreachable(&self, roots: &[ObjectId]) -> HashSet<ObjectId> {
@@ -178,11 +642,11 @@ impl ObjectGraph {
 
                 if !visited.contains(&next_id) {
                     self.dfs_cycle_detection(next_id, visited, rec_stack, path, cycles);
-                } else if rec_stack.contains(&next_id) {
-                    if let Some(cycle_start) = path.iter().position(|&id| id == next_id) {
-                        let cycle: Vec<ObjectId> = path[cycle_start..].to_vec();
-                        cycles.push(cycle);
-                    }
+                } else if rec_stack.contains(&next_id)
+                    && let Some(cycle_start) = path.iter().position(|&id| id == next_id)
+                {
+                    let cycle: Vec<ObjectId> = path[cycle_start..].to_vec();
+                    cycles.push(cycle);
                 }
             }
         }
@@ -191,12 +655,111 @@ impl ObjectGraph {
         path.pop();
     }
 
+    pub fn strongly_connected_components(&self) -> Vec<Vec<ObjectId>> {
+        let nodes: Vec<ObjectId> = self.objects.keys().copied().collect();
+        strongly_connected_components(&nodes, |obj_id| self.successors(obj_id))
+    }
+
+    pub fn find_cycles(&self) -> Vec<Vec<ObjectId>> {
+        let nodes: Vec<ObjectId> = self.objects.keys().copied().collect();
+        cyclic_components(&nodes, |obj_id| self.successors(obj_id))
+    }
+
+    fn successors(&self, obj_id: &ObjectId) -> Vec<ObjectId> {
+        self.references
+            .get(obj_id)
+            .map(|refs| {
+                refs.iter()
+                    .filter(|r| r.reference_type != ReferenceType::Weak)
+                    .map(|r| r.to)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The objects in `ids` that are in this graph, with the references
+    /// between them. Ids the graph doesn't hold are skipped.
+    pub fn subgraph(&self, ids: impl IntoIterator<Item = ObjectId>) -> ObjectGraph {
+        let mut graph = ObjectGraph::new();
+        for obj_id in ids {
+            if let Some(obj) = self.objects.get(&obj_id) {
+                graph.add_object(obj.clone());
+            }
+        }
+        let kept: Vec<ObjectId> = graph.objects.keys().copied().collect();
+        for from in kept {
+            for reference in self.references.get(&from).into_iter().flatten() {
+                if let Some(referrers) =
+                    Arc::make_mut(&mut graph.reverse_references).get_mut(&reference.to)
+                {
+                    *referrers.entry(from).or_default() += reference.count;
+                    Arc::make_mut(&mut graph.references)
+                        .entry(from)
+                        .or_default()
+                        .push(reference.clone());
+                }
+            }
+        }
+        graph
+    }
+
+    /// The subgraph of the objects `predicate` accepts.
+    pub fn filter_by_predicate(&self, predicate: impl Fn(&PyObject) -> bool) -> ObjectGraph {
+        self.subgraph(
+            self.objects
+                .values()
+                .filter(|obj| predicate(obj))
+                .map(|obj| obj.id()),
+        )
+    }
+
+    /// The subgraph of the objects whose type name is `type_name`.
+    pub fn filter_by_type(&self, type_name: &str) -> ObjectGraph {
+        self.filter_by_predicate(|obj| obj.name() == type_name)
+    }
+
+    /// The objects at most `depth` references away from `roots`, the roots
+    /// included. Like [`Self::find_reachable`], weak references count.
+    pub fn neighbors_within(&self, roots: &[ObjectId], depth: usize) -> HashSet<ObjectId> {
+        let mut seen: HashSet<ObjectId> = roots.iter().copied().collect();
+        let mut frontier: Vec<ObjectId> = seen.iter().copied().collect();
+        for _ in 0..depth {
+            let mut next = Vec::new();
+            for obj_id in frontier {
+                for reference in self.references.get(&obj_id).into_iter().flatten() {
+                    if seen.insert(reference.to) {
+                        next.push(reference.to);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+        seen
+    }
+
+    /// How many objects fall under each key `key` gives.
+    pub fn count_by<K: Eq + Hash>(&self, key: impl Fn(&PyObject) -> K) -> HashMap<K, usize> {
+        let mut counts = HashMap::new();
+        for obj in self.objects.values() {
+            *counts.entry(key(obj)).or_default() += 1;
+        }
+        counts
+    }
+
     pub fn object_count(&self) -> usize {
         self.objects.len()
     }
 
+    /// Every reference in the graph, each counted as many times as it is held.
     pub fn reference_count(&self) -> usize {
-        self.references.values().map(|refs| refs.len()).sum()
+        self.references
+            .values()
+            .flatten()
+            .map(|reference| reference.count)
+            .sum()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -204,9 +767,10 @@ impl ObjectGraph {
     }
 
     pub fn clear(&mut self) {
-        self.objects.clear();
-        self.references.clear();
-        self.reverse_references.clear();
+        self.objects = Arc::default();
+        self.references = Arc::default();
+        self.reverse_references = Arc::default();
+        self.reachability.get_mut().sets.clear();
     }
 
     pub fn get_object(&self, obj_id: &ObjectId) -> Option<&PyObject> {
@@ -214,7 +778,7 @@ impl ObjectGraph {
     }
 
     pub fn get_object_mut(&mut self, obj_id: &ObjectId) -> Option<&mut PyObject> {
-        self.objects.get_mut(obj_id)
+        Arc::make_mut(&mut self.objects).get_mut(obj_id)
     }
 
     pub fn get_all_objects(&self) -> &HashMap<ObjectId, PyObject> {
@@ -228,10 +792,508 @@ impl Default for ObjectGraph {
     }
 }
 
+/// An [`ObjectGraph`] frozen at the moment [`ObjectGraph::snapshot`] was
+/// called, for dumps and leak reports that must describe one point in time
+/// while the live graph keeps changing. A snapshot shares the graph's maps
+/// instead of copying them: the live graph copies a map only when it changes
+/// it while a snapshot still holds it, so taking or cloning a snapshot costs
+/// a few reference counts. Every read-only query of [`ObjectGraph`] works on
+/// a snapshot. Objects themselves are shared handles, so what is frozen is
+/// who refers to whom, not each object's own state.
+#[derive(Debug)]
+pub struct GraphSnapshot {
+    graph: ObjectGraph,
+}
+
+impl GraphSnapshot {
+    /// A mutable graph starting from this snapshot, sharing its maps until
+    /// either side changes them.
+    pub fn to_graph(&self) -> ObjectGraph {
+        self.graph.share()
+    }
+}
+
+impl Clone for GraphSnapshot {
+    fn clone(&self) -> Self {
+        Self {
+            graph: self.graph.share(),
+        }
+    }
+}
+
+impl Deref for GraphSnapshot {
+    type Target = ObjectGraph;
+
+    fn deref(&self) -> &ObjectGraph {
+        &self.graph
+    }
+}
+
+/// Read access shared by [`ObjectGraph`] and [`SharedObjectGraph`], so
+/// inspection code can take either. Objects and ids come back by value, as
+/// a locked graph cannot lend out references into itself.
+pub trait ReferenceGraph {
+    fn object(&self, obj_id: &ObjectId) -> Option<PyObject>;
+
+    fn object_ids(&self) -> Vec<ObjectId>;
+
+    /// What `obj_id` refers to, once per distinct reference.
+    fn referent_ids(&self, obj_id: &ObjectId) -> Vec<ObjectId>;
+
+    /// Who refers to `obj_id`, once each.
+    fn referrer_ids(&self, obj_id: &ObjectId) -> Vec<ObjectId>;
+
+    fn edge_count(&self, from: &ObjectId, to: &ObjectId) -> usize;
+
+    fn object_count(&self) -> usize;
+
+    fn reference_count(&self) -> usize;
+
+    /// Everything reachable from `roots`, the roots included.
+    fn find_reachable(&self, roots: &[ObjectId]) -> HashSet<ObjectId> {
+        let mut reachable: HashSet<ObjectId> = roots.iter().copied().collect();
+        let mut queue: VecDeque<ObjectId> = roots.iter().copied().collect();
+        while let Some(obj_id) = queue.pop_front() {
+            for referent in self.referent_ids(&obj_id) {
+                if reachable.insert(referent) {
+                    queue.push_back(referent);
+                }
+            }
+        }
+        reachable
+    }
+
+    fn is_reachable_from(&self, roots: &[ObjectId], obj_id: &ObjectId) -> bool {
+        self.find_reachable(roots).contains(obj_id)
+    }
+}
+
+impl ReferenceGraph for ObjectGraph {
+    fn object(&self, obj_id: &ObjectId) -> Option<PyObject> {
+        self.objects.get(obj_id).cloned()
+    }
+
+    fn object_ids(&self) -> Vec<ObjectId> {
+        self.objects.keys().copied().collect()
+    }
+
+    fn referent_ids(&self, obj_id: &ObjectId) -> Vec<ObjectId> {
+        self.references
+            .get(obj_id)
+            .into_iter()
+            .flatten()
+            .map(|r| r.to)
+            .collect()
+    }
+
+    fn referrer_ids(&self, obj_id: &ObjectId) -> Vec<ObjectId> {
+        self.reverse_references
+            .get(obj_id)
+            .map(|referrers| referrers.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn edge_count(&self, from: &ObjectId, to: &ObjectId) -> usize {
+        ObjectGraph::edge_count(self, from, to)
+    }
+
+    fn object_count(&self) -> usize {
+        ObjectGraph::object_count(self)
+    }
+
+    fn reference_count(&self) -> usize {
+        ObjectGraph::reference_count(self)
+    }
+
+    fn find_reachable(&self, roots: &[ObjectId]) -> HashSet<ObjectId> {
+        ObjectGraph::find_reachable(self, roots)
+    }
+
+    fn is_reachable_from(&self, roots: &[ObjectId], obj_id: &ObjectId) -> bool {
+        ObjectGraph::is_reachable_from(self, roots, obj_id)
+    }
+}
+
+/// How many locks a [`SharedObjectGraph`] spreads its objects over.
+const GRAPH_SHARDS: usize = 16;
+
+/// The objects of one shard, the references they make and who refers to
+/// them, laid out as in [`ObjectGraph`].
+#[derive(Debug, Default)]
+struct Shard {
+    objects: HashMap<ObjectId, PyObject>,
+    references: HashMap<ObjectId, Vec<Reference>>,
+    reverse_references: HashMap<ObjectId, IndexMap<ObjectId, usize>>,
+}
+
+/// The two halves of an edge: the referrer's objects and references, and
+/// the referent's objects and referrers. Both may be in the same shard.
+struct EdgeEnds<'a> {
+    from_objects: &'a HashMap<ObjectId, PyObject>,
+    references: &'a mut HashMap<ObjectId, Vec<Reference>>,
+    to_objects: &'a HashMap<ObjectId, PyObject>,
+    reverse_references: &'a mut HashMap<ObjectId, IndexMap<ObjectId, usize>>,
+}
+
+/// An object graph mutators and readers can share between threads. Objects
+/// are spread over shards, each behind its own lock: changing an edge locks
+/// only the shards of its two ends, and reads lock one shard at a time, so
+/// threads working on different objects rarely wait on each other. A walk
+/// such as [`ReferenceGraph::find_reachable`] sees each object as it is when
+/// the walk reaches it; [`Self::to_graph`] takes a consistent copy.
+#[derive(Debug)]
+pub struct SharedObjectGraph {
+    shards: Vec<RwLock<Shard>>,
+}
+
+impl SharedObjectGraph {
+    pub fn new() -> Self {
+        Self {
+            shards: (0..GRAPH_SHARDS).map(|_| RwLock::default()).collect(),
+        }
+    }
+
+    fn shard_of(obj_id: &ObjectId) -> usize {
+        (obj_id.as_u64() % GRAPH_SHARDS as u64) as usize
+    }
+
+    fn shard(&self, obj_id: &ObjectId) -> &RwLock<Shard> {
+        &self.shards[Self::shard_of(obj_id)]
+    }
+
+    pub fn add_object(&self, obj: PyObject) {
+        let obj_id = obj.id();
+        let mut shard = self.shard(&obj_id).write();
+        shard.objects.insert(obj_id, obj);
+        shard.references.entry(obj_id).or_default();
+        shard.reverse_references.entry(obj_id).or_default();
+    }
+
+    /// Remove `obj_id` and every reference to or from it. This locks every
+    /// shard, as the object's neighbours may be anywhere.
+    pub fn remove_object(&self, obj_id: &ObjectId) -> Option<PyObject> {
+        let mut shards: Vec<_> = self.shards.iter().map(|shard| shard.write()).collect();
+        let own = &mut shards[Self::shard_of(obj_id)];
+        let obj = own.objects.remove(obj_id)?;
+        let referrers = own.reverse_references.remove(obj_id).unwrap_or_default();
+        let references = own.references.remove(obj_id).unwrap_or_default();
+
+        for from_id in referrers.into_keys() {
+            if let Some(from_refs) = shards[Self::shard_of(&from_id)]
+                .references
+                .get_mut(&from_id)
+            {
+                from_refs.retain(|r| r.to != *obj_id);
+            }
+        }
+        for reference in references {
+            if let Some(referrers) = shards[Self::shard_of(&reference.to)]
+                .reverse_references
+                .get_mut(&reference.to)
+            {
+                referrers.swap_remove(obj_id);
+            }
+        }
+        Some(obj)
+    }
+
+    pub fn add_reference(
+        &self,
+        from: ObjectId,
+        to: ObjectId,
+        ref_type: ReferenceType,
+    ) -> GCResult<()> {
+        self.insert_reference(from, to, ref_type, None)
+    }
+
+    /// As [`Self::add_reference`], naming the field or slot that holds it.
+    pub fn add_labeled_reference(
+        &self,
+        from: ObjectId,
+        to: ObjectId,
+        ref_type: ReferenceType,
+        label: impl Into<String>,
+    ) -> GCResult<()> {
+        self.insert_reference(from, to, ref_type, Some(label.into()))
+    }
+
+    fn insert_reference(
+        &self,
+        from: ObjectId,
+        to: ObjectId,
+        ref_type: ReferenceType,
+        label: Option<String>,
+    ) -> GCResult<()> {
+        self.edit_edge(&from, &to, |ends| {
+            if !ends.from_objects.contains_key(&from) {
+                return Err(GCError::NotTracked { id: from });
+            }
+            if !ends.to_objects.contains_key(&to) {
+                return Err(GCError::NotTracked { id: to });
+            }
+            push_reference(
+                ends.references.entry(from).or_default(),
+                ends.reverse_references.entry(to).or_default(),
+                from,
+                to,
+                ref_type,
+                label,
+            );
+            Ok(())
+        })
+    }
+
+    /// Drop one occurrence of a reference from `from` to `to`, as
+    /// [`ObjectGraph::remove_reference`] does.
+    pub fn remove_reference(&self, from: ObjectId, to: ObjectId) -> GCResult<()> {
+        self.edit_edge(&from, &to, |ends| {
+            if let Some(refs) = ends.references.get_mut(&from) {
+                pop_reference(
+                    refs,
+                    ends.reverse_references.entry(to).or_default(),
+                    from,
+                    to,
+                );
+            }
+            Ok(())
+        })
+    }
+
+    /// Run `edit` with the shards of `from` and `to` locked, taking the
+    /// locks in shard order so two edits never wait on each other in turn.
+    fn edit_edge<R>(
+        &self,
+        from: &ObjectId,
+        to: &ObjectId,
+        edit: impl FnOnce(EdgeEnds<'_>) -> R,
+    ) -> R {
+        let (from_index, to_index) = (Self::shard_of(from), Self::shard_of(to));
+        if from_index == to_index {
+            let mut shard = self.shards[from_index].write();
+            let shard = &mut *shard;
+            return edit(EdgeEnds {
+                from_objects: &shard.objects,
+                references: &mut shard.references,
+                to_objects: &shard.objects,
+                reverse_references: &mut shard.reverse_references,
+            });
+        }
+
+        let (mut from_shard, mut to_shard) = if from_index < to_index {
+            let from_shard = self.shards[from_index].write();
+            (from_shard, self.shards[to_index].write())
+        } else {
+            let to_shard = self.shards[to_index].write();
+            (self.shards[from_index].write(), to_shard)
+        };
+        let (from_shard, to_shard) = (&mut *from_shard, &mut *to_shard);
+        edit(EdgeEnds {
+            from_objects: &from_shard.objects,
+            references: &mut from_shard.references,
+            to_objects: &to_shard.objects,
+            reverse_references: &mut to_shard.reverse_references,
+        })
+    }
+
+    /// A copy of the whole graph as one consistent [`ObjectGraph`], for the
+    /// analyses only it offers. Every shard is read-locked while copying.
+    pub fn to_graph(&self) -> ObjectGraph {
+        let shards: Vec<_> = self.shards.iter().map(|shard| shard.read()).collect();
+        let mut objects = HashMap::new();
+        let mut references = HashMap::new();
+        let mut reverse_references = HashMap::new();
+        for shard in &shards {
+            objects.extend(
+                shard
+                    .objects
+                    .iter()
+                    .map(|(obj_id, obj)| (*obj_id, obj.clone())),
+            );
+            references.extend(
+                shard
+                    .references
+                    .iter()
+                    .map(|(obj_id, refs)| (*obj_id, refs.clone())),
+            );
+            reverse_references.extend(
+                shard
+                    .reverse_references
+                    .iter()
+                    .map(|(obj_id, referrers)| (*obj_id, referrers.clone())),
+            );
+        }
+        ObjectGraph {
+            objects: Arc::new(objects),
+            references: Arc::new(references),
+            reverse_references: Arc::new(reverse_references),
+            reachability: Mutex::default(),
+        }
+    }
+}
+
+impl Default for SharedObjectGraph {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReferenceGraph for SharedObjectGraph {
+    fn object(&self, obj_id: &ObjectId) -> Option<PyObject> {
+        self.shard(obj_id).read().objects.get(obj_id).cloned()
+    }
+
+    fn object_ids(&self) -> Vec<ObjectId> {
+        self.shards
+            .iter()
+            .flat_map(|shard| shard.read().objects.keys().copied().collect::<Vec<_>>())
+            .collect()
+    }
+
+    fn referent_ids(&self, obj_id: &ObjectId) -> Vec<ObjectId> {
+        self.shard(obj_id)
+            .read()
+            .references
+            .get(obj_id)
+            .into_iter()
+            .flatten()
+            .map(|r| r.to)
+            .collect()
+    }
+
+    fn referrer_ids(&self, obj_id: &ObjectId) -> Vec<ObjectId> {
+        self.shard(obj_id)
+            .read()
+            .reverse_references
+            .get(obj_id)
+            .map(|referrers| referrers.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    fn edge_count(&self, from: &ObjectId, to: &ObjectId) -> usize {
+        self.shard(from)
+            .read()
+            .references
+            .get(from)
+            .into_iter()
+            .flatten()
+            .filter(|r| r.to == *to)
+            .map(|r| r.count)
+            .sum()
+    }
+
+    fn object_count(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.read().objects.len())
+            .sum()
+    }
+
+    fn reference_count(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| {
+                shard
+                    .read()
+                    .references
+                    .values()
+                    .flatten()
+                    .map(|r| r.count)
+                    .sum::<usize>()
+            })
+            .sum()
+    }
+}
+
+/// Tarjan's algorithm, iterative so deep chains do not overflow the stack.
+pub fn strongly_connected_components<N, F>(nodes: &[N], successors: F) -> Vec<Vec<N>>
+where
+    N: Copy + Eq + Hash,
+    F: Fn(&N) -> Vec<N>,
+{
+    let mut index_of: HashMap<N, usize> = HashMap::new();
+    let mut lowlink: HashMap<N, usize> = HashMap::new();
+    let mut on_stack: HashSet<N> = HashSet::new();
+    let mut stack: Vec<N> = Vec::new();
+    let mut components = Vec::new();
+    let mut next_index = 0;
+
+    for &root in nodes {
+        if index_of.contains_key(&root) {
+            continue;
+        }
+
+        let mut work: Vec<(N, Vec<N>, usize)> = Vec::new();
+        index_of.insert(root, next_index);
+        lowlink.insert(root, next_index);
+        next_index += 1;
+        stack.push(root);
+        on_stack.insert(root);
+        work.push((root, successors(&root), 0));
+
+        while let Some((node, children, position)) = work.last_mut() {
+            let node = *node;
+            if let Some(&child) = children.get(*position) {
+                *position += 1;
+                match index_of.get(&child).copied() {
+                    None => {
+                        index_of.insert(child, next_index);
+                        lowlink.insert(child, next_index);
+                        next_index += 1;
+                        stack.push(child);
+                        on_stack.insert(child);
+                        work.push((child, successors(&child), 0));
+                    }
+                    Some(child_index) if on_stack.contains(&child) => {
+                        let low = lowlink[&node].min(child_index);
+                        lowlink.insert(node, low);
+                    }
+                    Some(_) => {}
+                }
+                continue;
+            }
+
+            work.pop();
+            if let Some((parent, _, _)) = work.last() {
+                let low = lowlink[parent].min(lowlink[&node]);
+                lowlink.insert(*parent, low);
+            }
+
+            if lowlink[&node] == index_of[&node] {
+                let mut component = Vec::new();
+                while let Some(member) = stack.pop() {
+                    on_stack.remove(&member);
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+
+    components
+}
+
+/// Strongly connected components that form at least one cycle: more than one
+/// member, or a single member that references itself.
+pub fn cyclic_components<N, F>(nodes: &[N], successors: F) -> Vec<Vec<N>>
+where
+    N: Copy + Eq + Hash,
+    F: Fn(&N) -> Vec<N>,
+{
+    strongly_connected_components(nodes, &successors)
+        .into_iter()
+        .filter(|component| {
+            component.len() > 1 || successors(&component[0]).contains(&component[0])
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::object::{ObjectData, PyObject};
+    use crate::object::{CustomData, ObjectData, PyObject};
 
     #[test]
     fn test_object_graph_creation() {
@@ -245,7 +1307,7 @@ mod tests {
         let mut graph = ObjectGraph::new();
 
         let obj = PyObject::new("test".to_string(), ObjectData::Integer(42));
-        let obj_id = obj.id;
+        let obj_id = obj.id();
 
         graph.add_object(obj);
         assert_eq!(graph.object_count(), 1);
@@ -263,8 +1325,8 @@ mod tests {
         let obj1 = PyObject::new("obj1".to_string(), ObjectData::Integer(1));
         let obj2 = PyObject::new("obj2".to_string(), ObjectData::Integer(2));
 
-        let id1 = obj1.id;
-        let id2 = obj2.id;
+        let id1 = obj1.id();
+        let id2 = obj2.id();
 
         graph.add_object(obj1);
         graph.add_object(obj2);
@@ -274,7 +1336,200 @@ mod tests {
 
         let referrers = graph.get_referrers(&id2);
         assert_eq!(referrers.len(), 1);
-        assert_eq!(referrers[0].id, id1);
+        assert_eq!(referrers[0].id(), id1);
+    }
+
+    #[test]
+    fn test_from_edges_matches_one_at_a_time_insertion() {
+        let objects: Vec<PyObject> = (0..4)
+            .map(|i| PyObject::new(format!("obj{i}"), ObjectData::Integer(i)))
+            .collect();
+        let ids: Vec<ObjectId> = objects.iter().map(|obj| obj.id()).collect();
+        let edges = [
+            (ids[0], ids[1], ReferenceType::Direct),
+            (ids[1], ids[0], ReferenceType::Direct),
+            (ids[1], ids[2], ReferenceType::Weak),
+            (ids[0], ids[1], ReferenceType::Direct),
+        ];
+
+        let graph = ObjectGraph::from_edges(objects.clone(), edges).unwrap();
+        let mut expected = ObjectGraph::new();
+        for obj in objects.clone() {
+            expected.add_object(obj);
+        }
+        for (from, to, reference_type) in edges {
+            expected.add_reference(from, to, reference_type).unwrap();
+        }
+
+        assert_eq!(graph.object_count(), 4);
+        assert_eq!(graph.reference_count(), expected.reference_count());
+        for obj_id in &ids {
+            let ids_of = |objs: Vec<&PyObject>| objs.iter().map(|obj| obj.id()).collect::<Vec<_>>();
+            assert_eq!(
+                ids_of(graph.get_references(obj_id)),
+                ids_of(expected.get_references(obj_id))
+            );
+            assert_eq!(
+                ids_of(graph.get_referrers(obj_id)),
+                ids_of(expected.get_referrers(obj_id))
+            );
+        }
+        let mut cycles = graph.find_cycles();
+        assert_eq!(cycles.len(), 1);
+        let mut members = vec![ids[0], ids[1]];
+        cycles[0].sort_by_key(|id| id.as_u64());
+        members.sort_by_key(|id| id.as_u64());
+        assert_eq!(cycles[0], members);
+        assert_eq!(graph.find_reachable(&[ids[3]]).len(), 1);
+
+        let stranger = PyObject::new("stranger".to_string(), ObjectData::None);
+        assert!(matches!(
+            ObjectGraph::from_edges(objects, [(ids[0], stranger.id(), ReferenceType::Direct)]),
+            Err(GCError::NotTracked { id }) if id == stranger.id()
+        ));
+    }
+
+    #[test]
+    fn test_references_carry_labels() {
+        #[derive(Debug)]
+        struct Slots;
+        impl Trace for Slots {
+            fn trace(&self, data: &CustomData, visit: &mut dyn FnMut(ObjectId)) {
+                self.trace_labeled(data, &mut |child_id, _| visit(child_id));
+            }
+
+            fn trace_labeled(
+                &self,
+                data: &CustomData,
+                visit: &mut dyn FnMut(ObjectId, Option<&str>),
+            ) {
+                if let Some(&(parent, child)) = data.downcast_ref::<(ObjectId, ObjectId)>() {
+                    visit(parent, Some("parent"));
+                    visit(child, None);
+                }
+            }
+        }
+
+        let parent = PyObject::new("parent".to_string(), ObjectData::None);
+        let child = PyObject::new("child".to_string(), ObjectData::None);
+        let node = PyObject::new(
+            "node".to_string(),
+            ObjectData::Custom(CustomData::new((parent.id(), child.id()))),
+        );
+        assert_eq!(
+            edge_label(&node, Some(&Slots), &parent.id()).as_deref(),
+            Some("parent")
+        );
+        assert_eq!(edge_label(&node, Some(&Slots), &child.id()), None);
+        assert_eq!(edge_label(&node, None, &parent.id()), None);
+
+        let mut graph = ObjectGraph::from_edges([parent.clone(), node.clone()], []).unwrap();
+        graph
+            .add_labeled_reference(node.id(), parent.id(), ReferenceType::Direct, "parent")
+            .unwrap();
+        graph
+            .add_reference(parent.id(), node.id(), ReferenceType::Weak)
+            .unwrap();
+        assert_eq!(
+            graph.reference_label(&node.id(), &parent.id()),
+            Some("parent")
+        );
+        assert_eq!(graph.reference_label(&parent.id(), &node.id()), None);
+        let sub = graph.subgraph([node.id(), parent.id()]);
+        assert_eq!(
+            sub.reference_label(&node.id(), &parent.id()),
+            Some("parent")
+        );
+    }
+
+    #[test]
+    fn test_repeated_references_count_toward_trial_deletion() {
+        // `list` holds `item` twice and `item` holds `list` back: a cycle
+        // whose counts come entirely from inside the graph.
+        let list = PyObject::new("list".to_string(), ObjectData::None);
+        let item = PyObject::new("item".to_string(), ObjectData::None);
+        item.set_refcount(2);
+        let (list_id, item_id) = (list.id(), item.id());
+        let mut graph = ObjectGraph::from_edges(
+            [list.clone(), item.clone()],
+            [
+                (list_id, item_id, ReferenceType::Direct),
+                (item_id, list_id, ReferenceType::Direct),
+                (list_id, item_id, ReferenceType::Direct),
+            ],
+        )
+        .unwrap();
+        assert_eq!(graph.edge_count(&list_id, &item_id), 2);
+        assert_eq!(graph.reference_count(), 3);
+        assert_eq!(graph.get_references(&list_id).len(), 1);
+        assert_eq!(graph.find_garbage(), HashSet::from([list_id, item_id]));
+
+        // With one occurrence gone, the count it held looks external.
+        graph.remove_reference(list_id, item_id).unwrap();
+        assert_eq!(graph.edge_count(&list_id, &item_id), 1);
+        assert!(graph.find_garbage().is_empty());
+        graph
+            .add_reference(list_id, item_id, ReferenceType::Direct)
+            .unwrap();
+        assert_eq!(graph.find_garbage().len(), 2);
+
+        // A weak reference never accounts for a count.
+        graph
+            .add_reference(item_id, list_id, ReferenceType::Weak)
+            .unwrap();
+        assert_eq!(graph.edge_count(&item_id, &list_id), 2);
+        assert_eq!(graph.find_garbage().len(), 2);
+
+        graph.remove_reference(list_id, item_id).unwrap();
+        graph.remove_reference(list_id, item_id).unwrap();
+        assert_eq!(graph.edge_count(&list_id, &item_id), 0);
+        assert!(graph.get_referrers(&item_id).is_empty());
+        assert!(!graph.is_reachable_from(&[list_id], &item_id));
+    }
+
+    #[test]
+    fn test_referrers_stay_in_step_with_references() {
+        let objects: Vec<PyObject> = (0..3)
+            .map(|i| PyObject::new(format!("obj{i}"), ObjectData::Integer(i)))
+            .collect();
+        let ids: Vec<ObjectId> = objects.iter().map(|obj| obj.id()).collect();
+        let mut graph = ObjectGraph::from_edges(objects, []).unwrap();
+        for (from, to) in [(0, 2), (0, 2), (1, 2), (2, 0)] {
+            graph
+                .add_reference(ids[from], ids[to], ReferenceType::Direct)
+                .unwrap();
+        }
+        graph
+            .add_labeled_reference(ids[0], ids[2], ReferenceType::Direct, "slot")
+            .unwrap();
+        assert_eq!(graph.referrer_count(&ids[2]), 4);
+        assert_eq!(graph.get_referrers(&ids[2]).len(), 2);
+        assert!(graph.verify_consistency().is_empty());
+
+        graph.remove_reference(ids[0], ids[2]).unwrap();
+        assert_eq!(graph.referrer_count(&ids[2]), 3);
+        assert_eq!(graph.reference_label(&ids[0], &ids[2]), None);
+
+        // Removing an object drops it from the referrers of what it held.
+        graph.remove_object(&ids[0]);
+        assert_eq!(graph.referrer_count(&ids[2]), 1);
+        assert!(graph.get_references(&ids[2]).is_empty());
+        assert!(graph.verify_consistency().is_empty());
+
+        Arc::make_mut(&mut graph.reverse_references)
+            .get_mut(&ids[2])
+            .unwrap()
+            .insert(ids[1], 3);
+        let violations = graph.verify_consistency();
+        assert_eq!(violations.len(), 1);
+        assert!(matches!(
+            violations[0],
+            IntegrityViolation::ReferrerMismatch {
+                forward: 1,
+                reverse: 3,
+                ..
+            }
+        ));
     }
 
     #[test]
@@ -285,9 +1540,9 @@ mod tests {
         let obj2 = PyObject::new("obj2".to_string(), ObjectData::Integer(2));
         let obj3 = PyObject::new("obj3".to_string(), ObjectData::Integer(3));
 
-        let id1 = obj1.id;
-        let id2 = obj2.id;
-        let id3 = obj3.id;
+        let id1 = obj1.id();
+        let id2 = obj2.id();
+        let id3 = obj3.id();
 
         graph.add_object(obj1);
         graph.add_object(obj2);
@@ -307,6 +1562,92 @@ mod tests {
         assert!(reachable.contains(&id3));
     }
 
+    #[test]
+    fn test_query_combinators_compose() {
+        let objects: Vec<PyObject> = (0..6)
+            .map(|i| {
+                let name = if i % 2 == 0 { "Node" } else { "Leaf" };
+                PyObject::new(name.to_string(), ObjectData::Integer(i))
+            })
+            .collect();
+        let ids: Vec<ObjectId> = objects.iter().map(|obj| obj.id()).collect();
+        // A chain 0 -> 1 -> 2 -> 3 -> 4, with 2 -> 0 closing a cycle and 5
+        // off on its own.
+        let edges = [(0, 1), (1, 2), (2, 3), (3, 4), (2, 0)]
+            .map(|(from, to)| (ids[from], ids[to], ReferenceType::Direct));
+        let graph = ObjectGraph::from_edges(objects, edges).unwrap();
+
+        let counts = graph.count_by(|obj| obj.name().to_string());
+        assert_eq!((counts["Node"], counts["Leaf"]), (3, 3));
+
+        let near = graph.neighbors_within(&[ids[0]], 2);
+        assert_eq!(near, HashSet::from([ids[0], ids[1], ids[2]]));
+        assert_eq!(
+            graph.neighbors_within(&[ids[0]], 0),
+            HashSet::from([ids[0]])
+        );
+        assert_eq!(graph.neighbors_within(&[ids[0]], 10).len(), 5);
+
+        // Only edges with both ends kept survive.
+        let sub = graph.subgraph(near.into_iter().chain([ids[5]]));
+        assert_eq!((sub.object_count(), sub.reference_count()), (4, 3));
+        assert_eq!(sub.find_cycles().len(), 1);
+        assert!(
+            sub.get_references(&ids[2])
+                .iter()
+                .all(|obj| obj.id() == ids[0])
+        );
+        assert!(sub.get_referrers(&ids[3]).is_empty());
+
+        let nodes = graph.filter_by_type("Node");
+        assert_eq!((nodes.object_count(), nodes.reference_count()), (3, 1));
+        let first_two =
+            sub.filter_by_predicate(|obj| matches!(*obj.data(), ObjectData::Integer(i) if i < 2));
+        assert_eq!(
+            (first_two.object_count(), first_two.reference_count()),
+            (2, 1)
+        );
+        assert_eq!(first_two.count_by(|obj| obj.name().to_string()).len(), 2);
+    }
+
+    #[test]
+    fn test_reachability_is_cached_until_the_graph_changes() {
+        let objects: Vec<PyObject> = (0..5)
+            .map(|i| PyObject::new(format!("obj{i}"), ObjectData::Integer(i)))
+            .collect();
+        let ids: Vec<ObjectId> = objects.iter().map(|obj| obj.id()).collect();
+        let edges =
+            [(0, 1), (1, 2), (3, 4)].map(|(from, to)| (ids[from], ids[to], ReferenceType::Direct));
+        let mut graph = ObjectGraph::from_edges(objects, edges).unwrap();
+        let cached = |graph: &ObjectGraph| graph.reachability.lock().sets.len();
+
+        assert_eq!(graph.find_reachable(&[ids[0]]).len(), 3);
+        assert!(graph.is_reachable_from(&[ids[0], ids[0]], &ids[2]));
+        assert!(!graph.is_reachable_from(&[ids[3]], &ids[0]));
+        assert_eq!(cached(&graph), 2);
+
+        // A new edge extends the sets holding its source in place.
+        graph
+            .add_reference(ids[2], ids[3], ReferenceType::Direct)
+            .unwrap();
+        assert_eq!(cached(&graph), 2);
+        assert!(graph.is_reachable_from(&[ids[0]], &ids[4]));
+
+        // Removals drop only the sets they could shrink.
+        graph.remove_reference(ids[1], ids[2]).unwrap();
+        assert_eq!(cached(&graph), 1);
+        assert_eq!(
+            graph.find_reachable(&[ids[0]]),
+            HashSet::from([ids[0], ids[1]])
+        );
+        graph.remove_object(&ids[4]);
+        assert_eq!(cached(&graph), 1);
+        assert!(!graph.is_reachable_from(&[ids[3]], &ids[4]));
+
+        graph.clear();
+        assert_eq!(cached(&graph), 0);
+    }
+
     #[test]
     fn test_cycle_detection() {
         let mut graph = ObjectGraph::new();
@@ -314,8 +1655,8 @@ mod tests {
         let obj1 = PyObject::new("obj1".to_string(), ObjectData::Integer(1));
         let obj2 = PyObject::new("obj2".to_string(), ObjectData::Integer(2));
 
-        let id1 = obj1.id;
-        let id2 = obj2.id;
+        let id1 = obj1.id();
+        let id2 = obj2.id();
 
         graph.add_object(obj1);
         graph.add_object(obj2);
@@ -331,4 +1672,131 @@ mod tests {
         assert_eq!(cycles.len(), 1);
         assert_eq!(cycles[0].len(), 2);
     }
+
+    #[test]
+    fn test_find_cycles_scc() {
+        let mut graph = ObjectGraph::new();
+
+        let objects: Vec<PyObject> = (0..5)
+            .map(|i| PyObject::new(format!("obj{i}"), ObjectData::Integer(i)))
+            .collect();
+        let ids: Vec<ObjectId> = objects.iter().map(|obj| obj.id()).collect();
+        for obj in objects {
+            graph.add_object(obj);
+        }
+
+        for (from, to) in [(0, 1), (1, 2), (2, 0), (2, 3), (4, 4)] {
+            graph
+                .add_reference(ids[from], ids[to], ReferenceType::Direct)
+                .unwrap();
+        }
+        graph
+            .add_reference(ids[3], ids[2], ReferenceType::Weak)
+            .unwrap();
+
+        assert_eq!(graph.strongly_connected_components().len(), 3);
+
+        let mut cycles = graph.find_cycles();
+        cycles.sort_by_key(|cycle| cycle.len());
+        assert_eq!(cycles.len(), 2);
+        assert_eq!(cycles[0], vec![ids[4]]);
+
+        let mut members = cycles[1].clone();
+        let mut expected = vec![ids[0], ids[1], ids[2]];
+        members.sort_by_key(|id| id.as_u64());
+        expected.sort_by_key(|id| id.as_u64());
+        assert_eq!(members, expected);
+    }
+
+    #[test]
+    fn test_shared_graph_takes_edges_from_many_threads() {
+        let shared = SharedObjectGraph::new();
+        let objects: Vec<PyObject> = (0..64)
+            .map(|i| PyObject::new(format!("obj{i}"), ObjectData::Integer(i)))
+            .collect();
+        objects
+            .iter()
+            .for_each(|obj| shared.add_object(obj.clone()));
+
+        std::thread::scope(|scope| {
+            for thread in 0..4 {
+                let (shared, objects) = (&shared, &objects);
+                scope.spawn(move || {
+                    for i in 0..objects.len() {
+                        let to = &objects[(i + thread + 1) % objects.len()];
+                        shared
+                            .add_reference(objects[i].id(), to.id(), ReferenceType::Direct)
+                            .unwrap();
+                        shared
+                            .add_reference(objects[i].id(), objects[0].id(), ReferenceType::Direct)
+                            .unwrap();
+                    }
+                });
+            }
+        });
+        shared
+            .remove_reference(objects[1].id(), objects[0].id())
+            .unwrap();
+
+        assert_eq!(shared.reference_count(), 64 * 4 * 2 - 1);
+        assert_eq!(shared.edge_count(&objects[1].id(), &objects[0].id()), 3);
+        assert_eq!(shared.referrer_ids(&objects[0].id()).len(), 64);
+        assert_eq!(shared.find_reachable(&[objects[5].id()]).len(), 64);
+
+        let graph = shared.to_graph();
+        assert!(graph.verify_consistency().is_empty());
+        assert_eq!(graph.reference_count(), shared.reference_count());
+
+        let removed = shared.remove_object(&objects[0].id()).unwrap();
+        assert_eq!(removed.id(), objects[0].id());
+        assert_eq!(ReferenceGraph::object_count(&shared), 63);
+        assert!(
+            shared
+                .referent_ids(&objects[1].id())
+                .iter()
+                .all(|id| *id != removed.id())
+        );
+        assert!(shared.to_graph().verify_consistency().is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_keeps_its_view_while_the_graph_changes() {
+        let objects: Vec<PyObject> = (0..3)
+            .map(|i| PyObject::new(format!("obj{i}"), ObjectData::Integer(i)))
+            .collect();
+        let ids: Vec<ObjectId> = objects.iter().map(PyObject::id).collect();
+        let mut graph = ObjectGraph::new();
+        objects.iter().for_each(|obj| graph.add_object(obj.clone()));
+        graph
+            .add_reference(ids[0], ids[1], ReferenceType::Direct)
+            .unwrap();
+        graph
+            .add_reference(ids[1], ids[0], ReferenceType::Direct)
+            .unwrap();
+
+        let snapshot = graph.snapshot();
+        let copy = snapshot.clone();
+        assert!(Arc::ptr_eq(&copy.graph.references, &graph.references));
+
+        graph
+            .add_reference(ids[1], ids[2], ReferenceType::Direct)
+            .unwrap();
+        graph.remove_object(&ids[0]);
+        assert!(!Arc::ptr_eq(&snapshot.graph.references, &graph.references));
+        assert_eq!(graph.object_count(), 2);
+        assert_eq!(graph.reference_count(), 1);
+
+        assert_eq!(snapshot.object_count(), 3);
+        assert_eq!(snapshot.reference_count(), 2);
+        assert_eq!(snapshot.find_cycles().len(), 1);
+        assert!(snapshot.is_reachable_from(&[ids[1]], &ids[0]));
+        assert!(!snapshot.is_reachable_from(&[ids[1]], &ids[2]));
+        assert!(snapshot.verify_consistency().is_empty());
+        assert_eq!(copy.get_referrers(&ids[0])[0].id(), ids[1]);
+
+        let mut fork = snapshot.to_graph();
+        fork.remove_reference(ids[0], ids[1]).unwrap();
+        assert_eq!(fork.reference_count(), 1);
+        assert_eq!(snapshot.reference_count(), 2);
+    }
 }
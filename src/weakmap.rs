@@ -0,0 +1,254 @@
+//! Maps that hold objects weakly: an entry goes away once the collector
+//! frees its object, as `weakref.WeakValueDictionary` and
+//! `WeakKeyDictionary` drop entries from their weakref callbacks. Entries are
+//! dropped by collection watches, so they go whether the object dies in a
+//! collection or by its refcount reaching zero.
+//!
+//! Neither map takes a reference on the objects it holds. An object must be
+//! tracked to go in, and one untracked while in a map stays there until it
+//! is removed.
+
+use crate::GCResult;
+use crate::gc::{GarbageCollector, WatchCallback, WatchId, WatchKind};
+use crate::object::{ObjectId, PyObject};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Weak};
+
+/// Keys mapped to objects that the map doesn't keep alive.
+#[derive(Debug)]
+pub struct WeakValueHashMap<K> {
+    gc: Arc<GarbageCollector>,
+    entries: Arc<Mutex<HashMap<K, (PyObject, WatchId)>>>,
+}
+
+impl<K: Eq + Hash + Clone + Send + Sync + 'static> WeakValueHashMap<K> {
+    pub fn new(gc: Arc<GarbageCollector>) -> Self {
+        Self {
+            gc,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Map `key` to `value`, returning the object it mapped to before. Fails
+    /// with `NotTracked` if the collector doesn't track `value`.
+    pub fn insert(&self, key: K, value: &PyObject) -> GCResult<Option<PyObject>> {
+        // Hold the entries across the watch, so a callback for an object
+        // freed meanwhile waits for its entry and removes it.
+        let mut entries = self.entries.lock();
+        let watch = self.gc.watch(
+            &value.id(),
+            WatchKind::Collection,
+            evict_value(Arc::downgrade(&self.entries), key.clone()),
+        )?;
+        let replaced = entries.insert(key, (value.clone(), watch));
+        Ok(replaced.map(|(obj, watch)| {
+            self.gc.unwatch(watch);
+            obj
+        }))
+    }
+
+    pub fn get(&self, key: &K) -> Option<PyObject> {
+        self.entries.lock().get(key).map(|(obj, _)| obj.clone())
+    }
+
+    pub fn remove(&self, key: &K) -> Option<PyObject> {
+        let (obj, watch) = self.entries.lock().remove(key)?;
+        self.gc.unwatch(watch);
+        Some(obj)
+    }
+
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.entries.lock().contains_key(key)
+    }
+
+    pub fn keys(&self) -> Vec<K> {
+        self.entries.lock().keys().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    pub fn clear(&self) {
+        for (_, (_, watch)) in self.entries.lock().drain() {
+            self.gc.unwatch(watch);
+        }
+    }
+}
+
+impl<K> Drop for WeakValueHashMap<K> {
+    fn drop(&mut self) {
+        for (_, watch) in self.entries.lock().values() {
+            self.gc.unwatch(*watch);
+        }
+    }
+}
+
+/// Drops `key` once its object is freed, unless the key has been mapped to
+/// another object since.
+fn evict_value<K: Eq + Hash + Send + Sync + 'static>(
+    entries: Weak<Mutex<HashMap<K, (PyObject, WatchId)>>>,
+    key: K,
+) -> WatchCallback {
+    Arc::new(move |obj_id, _| {
+        if let Some(entries) = entries.upgrade() {
+            let mut entries = entries.lock();
+            if entries.get(&key).is_some_and(|(obj, _)| obj.id() == obj_id) {
+                entries.remove(&key);
+            }
+        }
+    })
+}
+
+/// Values keyed by objects that the map doesn't keep alive.
+#[derive(Debug)]
+pub struct WeakKeyHashMap<V> {
+    gc: Arc<GarbageCollector>,
+    entries: Arc<Mutex<HashMap<ObjectId, (V, WatchId)>>>,
+}
+
+impl<V: Send + 'static> WeakKeyHashMap<V> {
+    pub fn new(gc: Arc<GarbageCollector>) -> Self {
+        Self {
+            gc,
+            entries: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Map `key` to `value`, returning the value it mapped to before. Fails
+    /// with `NotTracked` if the collector doesn't track `key`.
+    pub fn insert(&self, key: &PyObject, value: V) -> GCResult<Option<V>> {
+        let mut entries = self.entries.lock();
+        if let Some((old, _)) = entries.get_mut(&key.id()) {
+            return Ok(Some(std::mem::replace(old, value)));
+        }
+        let watch = self.gc.watch(
+            &key.id(),
+            WatchKind::Collection,
+            evict_key(Arc::downgrade(&self.entries)),
+        )?;
+        entries.insert(key.id(), (value, watch));
+        Ok(None)
+    }
+
+    pub fn get(&self, key: &ObjectId) -> Option<V>
+    where
+        V: Clone,
+    {
+        self.entries.lock().get(key).map(|(value, _)| value.clone())
+    }
+
+    pub fn remove(&self, key: &ObjectId) -> Option<V> {
+        let (value, watch) = self.entries.lock().remove(key)?;
+        self.gc.unwatch(watch);
+        Some(value)
+    }
+
+    pub fn contains_key(&self, key: &ObjectId) -> bool {
+        self.entries.lock().contains_key(key)
+    }
+
+    pub fn keys(&self) -> Vec<ObjectId> {
+        self.entries.lock().keys().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.lock().is_empty()
+    }
+
+    pub fn clear(&self) {
+        for (_, (_, watch)) in self.entries.lock().drain() {
+            self.gc.unwatch(watch);
+        }
+    }
+}
+
+impl<V> Drop for WeakKeyHashMap<V> {
+    fn drop(&mut self) {
+        for (_, watch) in self.entries.lock().values() {
+            self.gc.unwatch(*watch);
+        }
+    }
+}
+
+fn evict_key<V: Send + 'static>(
+    entries: Weak<Mutex<HashMap<ObjectId, (V, WatchId)>>>,
+) -> WatchCallback {
+    Arc::new(move |obj_id, _| {
+        if let Some(entries) = entries.upgrade() {
+            entries.lock().remove(&obj_id);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::GCError;
+    use crate::object::{CustomData, ObjectData};
+
+    fn held(name: &str) -> PyObject {
+        PyObject::new(name.to_string(), ObjectData::Custom(CustomData::new(())))
+    }
+
+    #[test]
+    fn test_entries_vanish_when_objects_are_freed() {
+        let gc = Arc::new(GarbageCollector::new());
+        let values = WeakValueHashMap::new(gc.clone());
+        let keys = WeakKeyHashMap::new(gc.clone());
+
+        let a = PyObject::new("a".to_string(), ObjectData::List(Vec::new()));
+        let b = PyObject::new("b".to_string(), ObjectData::List(vec![a.clone()]));
+        a.set_data(ObjectData::List(vec![b.clone()]));
+        let (kept, leaf, moved) = (held("kept"), held("leaf"), held("moved"));
+        for obj in [&a, &b, &kept, &leaf, &moved] {
+            gc.track(obj.clone()).unwrap();
+        }
+
+        values.insert("cycle", &a).unwrap();
+        values.insert("kept", &kept).unwrap();
+        values.insert("leaf", &leaf).unwrap();
+        values.insert("slot", &moved).unwrap();
+        assert_eq!(values.insert("slot", &kept).unwrap(), Some(moved.clone()));
+        keys.insert(&b, 1).unwrap();
+        keys.insert(&kept, 2).unwrap();
+        assert_eq!(keys.insert(&leaf, 3).unwrap(), None);
+        assert_eq!(keys.insert(&leaf, 4).unwrap(), Some(3));
+
+        // Freed by its refcount, then by a collection.
+        gc.decref(&leaf.id()).unwrap();
+        assert!(!values.contains_key(&"leaf"));
+        assert!(!keys.contains_key(&leaf.id()));
+        gc.collect().unwrap();
+        assert_eq!(values.get(&"cycle"), None);
+        assert_eq!(keys.get(&b.id()), None);
+
+        // "slot" moved on, so freeing what it held before leaves it be.
+        gc.decref(&moved.id()).unwrap();
+        let mut names = values.keys();
+        names.sort();
+        assert_eq!(names, vec!["kept", "slot"]);
+        assert_eq!(values.get(&"slot"), Some(kept.clone()));
+        assert_eq!(keys.keys(), vec![kept.id()]);
+
+        let untracked = held("untracked");
+        assert!(matches!(
+            values.insert("untracked", &untracked),
+            Err(GCError::NotTracked { .. })
+        ));
+        assert_eq!(values.remove(&"kept"), Some(kept.clone()));
+        assert_eq!(keys.remove(&kept.id()), Some(2));
+        assert!(keys.is_empty());
+        assert_eq!(values.len(), 1);
+    }
+}
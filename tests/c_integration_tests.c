@@ -32,10 +32,27 @@ static void test_gc_thresholds(void);
 static void test_gc_error_handling(void);
 static void test_gc_cleanup(void);
 
+typedef struct {
+    intptr_t ob_refcnt;
+    void* ob_type;
+    intptr_t ob_size;
+    const char* tp_name;
+} mock_type_t;
+
+typedef struct {
+    intptr_t ob_refcnt;
+    void* ob_type;
+} mock_object_head_t;
+
+static mock_type_t mock_type = {1, NULL, 0, "mock_object"};
+
 static void* create_mock_object(size_t size) {
     void* obj = malloc(size);
     if (obj) {
         memset(obj, 0xAA, size);
+        mock_object_head_t* head = (mock_object_head_t*)obj;
+        head->ob_refcnt = 1;
+        head->ob_type = &mock_type;
     }
     return obj;
 }
@@ -2,6 +2,26 @@
 #include <stdlib.h>
 #include <string.h>
 #include "../include/python_gc.h"
+typedef struct {
+    intptr_t ob_refcnt;
+    void* ob_type;
+    intptr_t ob_size;
+    const char* tp_name;
+} mock_type_t;
+typedef struct {
+    intptr_t ob_refcnt;
+    void* ob_type;
+} mock_object_head_t;
+static mock_type_t mock_type = {1, NULL, 0, "mock_object"};
+static void* create_mock_object(size_t size) {
+    void* obj = calloc(1, size);
+    if (obj) {
+        mock_object_head_t* head = (mock_object_head_t*)obj;
+        head->ob_refcnt = 1;
+        head->ob_type = &mock_type;
+    }
+    return obj;
+}
 #define TEST_ASSERT(condition, message) do { \
     if (condition) { \
         printf("✓ %s\n", message); \
@@ -16,9 +36,9 @@ void test_production_object_tracking() {
     TEST_ASSERT(result == GC_SUCCESS, "GC initialization should succeed");
     result = py_gc_clear_registry();
     TEST_ASSERT(result == GC_SUCCESS, "Registry clearing should succeed");
-    void* obj1 = malloc(64);
-    void* obj2 = malloc(128);
-    void* obj3 = malloc(256);
+    void* obj1 = create_mock_object(64);
+    void* obj2 = create_mock_object(128);
+    void* obj3 = create_mock_object(256);
     TEST_ASSERT(obj1 != NULL && obj2 != NULL && obj3 != NULL, 
                 "Test object creation should succeed");
     int tracked = py_gc_is_tracked(obj1);
@@ -76,7 +96,7 @@ void test_memory_management() {
     TEST_ASSERT(result == GC_SUCCESS, "Registry clearing should succeed");
     void* objects[20];
     for (int i = 0; i < 20; i++) {
-        objects[i] = malloc(64 + i * 8);
+        objects[i] = create_mock_object(64 + i * 8);
         TEST_ASSERT(objects[i] != NULL, "Object creation should succeed");
         printf("   Tracking object %d at %p\n", i, objects[i]);
         result = py_gc_track(objects[i]);
@@ -127,14 +147,20 @@ void test_error_handling() {
     char buffer[256];
     result = py_gc_get_tracked_info(NULL, buffer, sizeof(buffer));
     TEST_ASSERT(result == GC_ERROR_INTERNAL, "Getting info for NULL pointer should fail");
-    void* obj = malloc(64);
+    void* obj = create_mock_object(64);
     TEST_ASSERT(obj != NULL, "Test object creation should succeed");
     result = py_gc_get_tracked_info(obj, buffer, sizeof(buffer));
     TEST_ASSERT(result == GC_ERROR_NOT_TRACKED, "Getting info for untracked object should fail");
     result = py_gc_get_tracked_info(obj, NULL, 256);
-    TEST_ASSERT(result == GC_ERROR_INTERNAL, "Getting info with NULL buffer should fail");
-    result = py_gc_get_tracked_info(obj, buffer, 0);
-    TEST_ASSERT(result == GC_ERROR_INTERNAL, "Getting info with zero buffer size should fail");
+    TEST_ASSERT(result == GC_ERROR_NOT_TRACKED, "Size query for untracked object should fail");
+    result = py_gc_track(obj);
+    TEST_ASSERT(result == GC_SUCCESS, "Object tracking should succeed");
+    result = py_gc_get_tracked_info(obj, NULL, 0);
+    TEST_ASSERT(result == GC_ERROR_BUFFER_TOO_SMALL, "Size query with NULL buffer should report required size");
+    TEST_ASSERT(py_gc_get_required_buffer_size() > 1, "Required buffer size should be reported");
+    result = py_gc_get_tracked_info(obj, buffer, 4);
+    TEST_ASSERT(result == GC_ERROR_BUFFER_TOO_SMALL, "Truncated info should be signaled");
+    TEST_ASSERT(strlen(buffer) == 3, "Truncated info should stay NUL-terminated");
     free(obj);
     result = py_gc_cleanup();
     TEST_ASSERT(result == GC_SUCCESS, "GC cleanup should succeed");
@@ -0,0 +1,267 @@
+//! End-to-end tests of the FFI layer against an embedded CPython. The real
+//! C-API is registered as the Python API backend, real objects are tracked
+//! by pointer, and the lists the FFI layer builds are handed back to Python
+//! code, which asserts on refcounts and weakref callbacks.
+//!
+//! The FFI layer only borrows object pointers: a collection decides which
+//! objects are garbage, and the embedder releases them. These tests play the
+//! embedder by clearing what the collector reports and checking that CPython
+//! frees exactly those objects.
+//!
+//! Run with `cargo test --features cpython-tests --test python_integration`.
+
+use parking_lot::Mutex;
+use python_gc::ffi::*;
+use python_gc::gc::DEBUG_SAVEALL;
+use python_gc::python_api::GCPythonApi;
+use std::ffi::{CString, c_char, c_int, c_void};
+use std::sync::Once;
+
+unsafe extern "C" {
+    fn Py_InitializeEx(initsigs: c_int);
+    fn PyEval_SaveThread() -> *mut c_void;
+    fn PyGILState_Ensure() -> c_int;
+    fn PyGILState_Release(state: c_int);
+    fn PyRun_SimpleString(command: *const c_char) -> c_int;
+    fn PyImport_AddModule(name: *const c_char) -> *mut c_void;
+    fn PyObject_GetAttrString(obj: *mut c_void, name: *const c_char) -> *mut c_void;
+    fn PyObject_SetAttrString(obj: *mut c_void, name: *const c_char, value: *mut c_void) -> c_int;
+    fn PyList_New(size: isize) -> *mut c_void;
+    fn PyList_SetItem(list: *mut c_void, index: isize, item: *mut c_void) -> c_int;
+    fn PyList_GetItem(list: *mut c_void, index: isize) -> *mut c_void;
+    fn PyList_Size(list: *mut c_void) -> isize;
+    fn Py_IncRef(obj: *mut c_void);
+    fn Py_DecRef(obj: *mut c_void);
+    fn _PyObject_GC_New(type_obj: *mut c_void) -> *mut c_void;
+    fn PyObject_GC_Del(obj: *mut c_void);
+}
+
+extern "C" fn list_new(size: isize) -> *mut c_void {
+    unsafe { PyList_New(size) }
+}
+
+extern "C" fn list_set_item(list: *mut c_void, index: isize, item: *mut c_void) -> c_int {
+    unsafe { PyList_SetItem(list, index, item) }
+}
+
+extern "C" fn list_get_item(list: *mut c_void, index: isize) -> *mut c_void {
+    unsafe { PyList_GetItem(list, index) }
+}
+
+extern "C" fn list_size(list: *mut c_void) -> isize {
+    unsafe { PyList_Size(list) }
+}
+
+extern "C" fn incref(obj: *mut c_void) {
+    unsafe { Py_IncRef(obj) }
+}
+
+extern "C" fn decref(obj: *mut c_void) {
+    unsafe { Py_DecRef(obj) }
+}
+
+extern "C" fn gc_new(type_obj: *mut c_void) -> *mut c_void {
+    unsafe { _PyObject_GC_New(type_obj) }
+}
+
+extern "C" fn gc_del(obj: *mut c_void) {
+    unsafe { PyObject_GC_Del(obj) }
+}
+
+extern "C" fn gil_ensure() -> c_int {
+    unsafe { PyGILState_Ensure() }
+}
+
+extern "C" fn gil_release(state: c_int) {
+    unsafe { PyGILState_Release(state) }
+}
+
+static INIT: Once = Once::new();
+static TEST_LOCK: Mutex<()> = Mutex::new(());
+
+/// Holds the GIL and exclusive use of the FFI collector for one test.
+struct Python {
+    gil: c_int,
+    main: *mut c_void,
+}
+
+impl Python {
+    fn acquire() -> (parking_lot::MutexGuard<'static, ()>, Self) {
+        INIT.call_once(|| unsafe {
+            Py_InitializeEx(0);
+            PyEval_SaveThread();
+        });
+        let guard = TEST_LOCK.lock();
+        let gil = unsafe { PyGILState_Ensure() };
+        let main = unsafe { PyImport_AddModule(c"__main__".as_ptr()) };
+
+        let api = GCPythonApi {
+            list_new: Some(list_new),
+            list_set_item: Some(list_set_item),
+            list_get_item: Some(list_get_item),
+            list_size: Some(list_size),
+            incref: Some(incref),
+            decref: Some(decref),
+            gc_new: Some(gc_new),
+            gc_del: Some(gc_del),
+        };
+        let hooks = GCPythonHooks {
+            gil_ensure: Some(gil_ensure),
+            gil_release: Some(gil_release),
+        };
+        assert_eq!(py_gc_init() as i32, GCReturnCode::Success as i32);
+        unsafe {
+            assert_eq!(
+                py_gc_set_python_api(&api) as i32,
+                GCReturnCode::Success as i32
+            );
+            assert_eq!(
+                py_gc_set_python_hooks(&hooks) as i32,
+                GCReturnCode::Success as i32
+            );
+        }
+        (guard, Self { gil, main })
+    }
+
+    /// Run `code` in `__main__`, failing the test if it raises.
+    fn run(&self, code: &str) {
+        let code = CString::new(code).unwrap();
+        assert_eq!(unsafe { PyRun_SimpleString(code.as_ptr()) }, 0);
+    }
+
+    /// A new reference to the `__main__` global `name`.
+    fn global(&self, name: &str) -> *mut c_void {
+        let name = CString::new(name).unwrap();
+        let obj = unsafe { PyObject_GetAttrString(self.main, name.as_ptr()) };
+        assert!(!obj.is_null());
+        obj
+    }
+
+    /// Bind `obj` to the `__main__` global `name`, consuming the reference.
+    fn set_global(&self, name: &str, obj: *mut c_void) {
+        assert!(!obj.is_null());
+        let name = CString::new(name).unwrap();
+        unsafe {
+            assert_eq!(PyObject_SetAttrString(self.main, name.as_ptr(), obj), 0);
+            Py_DecRef(obj);
+        }
+    }
+}
+
+impl Drop for Python {
+    fn drop(&mut self) {
+        py_gc_cleanup();
+        unsafe {
+            py_gc_set_python_hooks(std::ptr::null());
+            py_gc_set_python_api(std::ptr::null());
+            PyGILState_Release(self.gil);
+        }
+    }
+}
+
+const CYCLE: &str = "\
+import sys, weakref
+class Node(list): pass
+freed = []
+a, b = Node(), Node()
+a.append(b)
+b.append(a)
+watch = [weakref.ref(a, lambda _: freed.append('a')), weakref.ref(b, lambda _: freed.append('b'))]
+";
+
+#[test]
+fn test_collected_cycle_is_handed_back_and_released() {
+    let (_guard, python) = Python::acquire();
+    python.run(CYCLE);
+    let (a, b) = (python.global("a"), python.global("b"));
+
+    for obj in [a, b] {
+        assert_eq!(py_gc_track_python(obj) as i32, GCReturnCode::Success as i32);
+    }
+    assert_eq!(
+        py_gc_add_reference(a, b) as i32,
+        GCReturnCode::Success as i32
+    );
+    assert_eq!(
+        py_gc_add_reference(b, a) as i32,
+        GCReturnCode::Success as i32
+    );
+    let mut type_name = [0 as c_char; 32];
+    unsafe {
+        py_gc_get_object_type_name(a, type_name.as_mut_ptr(), type_name.len());
+        assert_eq!(
+            std::ffi::CStr::from_ptr(type_name.as_ptr()).to_str(),
+            Ok("Node")
+        );
+    }
+
+    // Drop every outside reference: the two lists now only hold each other.
+    python.run("del a, b");
+    unsafe {
+        Py_DecRef(a);
+        Py_DecRef(b);
+    }
+    python.run("assert freed == []");
+    for obj in [a, b] {
+        let result = unsafe { py_gc_set_refcount(obj, 0) };
+        assert_eq!(result as i32, GCReturnCode::Success as i32);
+    }
+
+    assert_eq!(
+        py_gc_set_debug_flags(DEBUG_SAVEALL as c_int) as i32,
+        GCReturnCode::Success as i32
+    );
+    let mut collected = 0;
+    unsafe {
+        assert_eq!(
+            py_gc_collect_ex(&mut collected) as i32,
+            GCReturnCode::Success as i32
+        );
+    }
+    assert_eq!(collected, 2);
+    assert_eq!((py_gc_is_tracked(a), py_gc_is_tracked(b)), (0, 0));
+
+    python.set_global("garbage", unsafe { py_gc_get_saveall_garbage() });
+    assert_eq!(
+        py_gc_clear_saveall_garbage() as i32,
+        GCReturnCode::Success as i32
+    );
+    python.run(
+        "assert sorted(id(obj) for obj in garbage) == sorted(id(ref()) for ref in watch)\n\
+         for obj in garbage: obj.clear()\n\
+         del garbage, obj\n\
+         assert sorted(freed) == ['a', 'b'], freed",
+    );
+}
+
+#[test]
+fn test_garbage_list_holds_one_reference_per_object() {
+    let (_guard, python) = Python::acquire();
+    python.run(
+        "import sys\nstuck = {'self': None}\nstuck['self'] = stuck\nbase = sys.getrefcount(stuck)",
+    );
+    let stuck = python.global("stuck");
+    assert_eq!(
+        py_gc_track_python(stuck) as i32,
+        GCReturnCode::Success as i32
+    );
+    assert_eq!(
+        py_gc_mark_uncollectable(stuck) as i32,
+        GCReturnCode::Success as i32
+    );
+    unsafe { Py_DecRef(stuck) };
+
+    python.set_global("garbage", unsafe { py_gc_get_garbage() });
+    python.run(
+        "assert len(garbage) == 1 and garbage[0] is stuck\n\
+         assert sys.getrefcount(stuck) == base + 1\n\
+         del garbage\n\
+         assert sys.getrefcount(stuck) == base",
+    );
+
+    assert_eq!(
+        py_gc_untrack_python(stuck) as i32,
+        GCReturnCode::Success as i32
+    );
+    python.run("assert sys.getrefcount(stuck) == base\ndel stuck['self'], stuck");
+}